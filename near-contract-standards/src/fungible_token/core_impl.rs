@@ -1,11 +1,11 @@
 use crate::fungible_token::core::FungibleTokenCore;
-use crate::fungible_token::resolver::FungibleTokenResolver;
+use crate::fungible_token::resolver::{FungibleTokenResolver, TransferCallResolver};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::LookupMap;
 use near_sdk::json_types::U128;
 use near_sdk::{
     assert_one_yocto, env, ext_contract, log, require, AccountId, Balance, Gas, IntoStorageKey,
-    PromiseOrValue, PromiseResult, StorageUsage,
+    PromiseOrValue, StorageUsage,
 };
 
 const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(5_000_000_000_000);
@@ -209,31 +209,19 @@ impl FungibleToken {
         receiver_id: AccountId,
         amount: U128,
     ) -> (u128, u128) {
-        let amount: Balance = amount.into();
-
-        // Get the unused amount from the `ft_on_transfer` call result.
-        let unused_amount = match env::promise_result(0) {
-            PromiseResult::NotReady => env::abort(),
-            PromiseResult::Successful(value) => {
-                if let Ok(unused_amount) = near_sdk::serde_json::from_slice::<U128>(&value) {
-                    std::cmp::min(amount, unused_amount.0)
-                } else {
-                    amount
-                }
-            }
-            PromiseResult::Failed => amount,
-        };
+        let resolver = TransferCallResolver::new(amount);
+        let amount = resolver.amount();
 
-        if unused_amount > 0 {
+        if resolver.unused_amount() > 0 {
             let receiver_balance = self.accounts.get(&receiver_id).unwrap_or(0);
             if receiver_balance > 0 {
-                let refund_amount = std::cmp::min(receiver_balance, unused_amount);
+                let refund_amount = resolver.refund_amount(receiver_balance);
                 self.accounts.insert(&receiver_id, &(receiver_balance - refund_amount));
 
                 if let Some(sender_balance) = self.accounts.get(sender_id) {
                     self.accounts.insert(sender_id, &(sender_balance + refund_amount));
                     log!("Refund {} from {} to {}", refund_amount, receiver_id, sender_id);
-                    return (amount - refund_amount, 0);
+                    return (resolver.used_amount(refund_amount), 0);
                 } else {
                     // Sender's account was deleted, so we need to burn tokens.
                     self.total_supply -= refund_amount;
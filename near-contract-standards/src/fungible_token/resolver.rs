@@ -1,4 +1,4 @@
-use near_sdk::{json_types::U128, AccountId};
+use near_sdk::{env, json_types::U128, AccountId, Balance, PromiseResult};
 
 pub trait FungibleTokenResolver {
     fn ft_resolve_transfer(
@@ -8,3 +8,113 @@ pub trait FungibleTokenResolver {
         amount: U128,
     ) -> U128;
 }
+
+/// Helper for implementing [`FungibleTokenResolver::ft_resolve_transfer`], which reads the
+/// amount of tokens that were not used by the receiver from the result of `ft_on_transfer`.
+///
+/// The unused amount is clamped to the amount that was transferred, and a failed call or a result
+/// that cannot be parsed is treated as none of the tokens being used, so a receiver can never
+/// cause more tokens to be refunded than were transferred. The amount that can be refunded is
+/// further limited by the balance the receiver has left through [`refund_amount`].
+///
+/// [`refund_amount`]: Self::refund_amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferCallResolver {
+    amount: Balance,
+    unused_amount: Balance,
+}
+
+impl TransferCallResolver {
+    /// Reads the result of the `ft_on_transfer` call for a transfer of `amount` tokens. This must
+    /// be called from the callback of the `ft_on_transfer` promise.
+    pub fn new(amount: U128) -> Self {
+        Self::from_promise_result(amount, env::promise_result(0))
+    }
+
+    /// Reads the unused amount of a transfer of `amount` tokens from the given `ft_on_transfer`
+    /// promise result.
+    pub fn from_promise_result(amount: U128, result: PromiseResult) -> Self {
+        let amount: Balance = amount.into();
+        let unused_amount = match result {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(value) => {
+                if let Ok(unused_amount) = near_sdk::serde_json::from_slice::<U128>(&value) {
+                    std::cmp::min(amount, unused_amount.0)
+                } else {
+                    amount
+                }
+            }
+            PromiseResult::Failed => amount,
+        };
+        Self { amount, unused_amount }
+    }
+
+    /// Amount of tokens that were transferred.
+    pub fn amount(&self) -> Balance {
+        self.amount
+    }
+
+    /// Amount of tokens the receiver did not use, which is never more than [`amount`].
+    ///
+    /// [`amount`]: Self::amount
+    pub fn unused_amount(&self) -> Balance {
+        self.unused_amount
+    }
+
+    /// Amount of tokens to move back from the receiver to the sender, which is the unused amount
+    /// limited by the current balance of the receiver.
+    pub fn refund_amount(&self, receiver_balance: Balance) -> Balance {
+        std::cmp::min(receiver_balance, self.unused_amount)
+    }
+
+    /// Amount of tokens that were used by the receiver after refunding `refund_amount`, which is
+    /// the value to return from `ft_resolve_transfer`.
+    pub fn used_amount(&self, refund_amount: Balance) -> Balance {
+        self.amount.saturating_sub(refund_amount)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::TransferCallResolver;
+    use near_sdk::PromiseResult;
+
+    fn resolve(amount: u128, result: PromiseResult) -> TransferCallResolver {
+        TransferCallResolver::from_promise_result(amount.into(), result)
+    }
+
+    #[test]
+    fn unused_amount_from_result() {
+        let resolver = resolve(100, PromiseResult::Successful(br#""30""#.to_vec()));
+        assert_eq!(resolver.amount(), 100);
+        assert_eq!(resolver.unused_amount(), 30);
+        assert_eq!(resolver.refund_amount(1000), 30);
+        assert_eq!(resolver.refund_amount(10), 10);
+        assert_eq!(resolver.used_amount(10), 90);
+    }
+
+    #[test]
+    fn unused_amount_is_clamped() {
+        let resolver = resolve(100, PromiseResult::Successful(br#""1000""#.to_vec()));
+        assert_eq!(resolver.unused_amount(), 100);
+        assert_eq!(resolver.refund_amount(1000), 100);
+        assert_eq!(resolver.used_amount(100), 0);
+    }
+
+    #[test]
+    fn unparseable_result_refunds_everything() {
+        for value in [&b""[..], b"30", b"\"-1\"", b"{\"amount\":\"30\"}"] {
+            let resolver = resolve(100, PromiseResult::Successful(value.to_vec()));
+            assert_eq!(resolver.unused_amount(), 100);
+        }
+    }
+
+    #[test]
+    fn failed_promise_refunds_everything() {
+        let resolver = resolve(100, PromiseResult::Failed);
+        assert_eq!(resolver.unused_amount(), 100);
+        assert_eq!(resolver.refund_amount(60), 60);
+        assert_eq!(resolver.used_amount(60), 40);
+    }
+}
@@ -0,0 +1,257 @@
+use near_sdk::env;
+use near_sdk::json_types::Base64VecU8;
+use std::fmt;
+
+/// Length of an Arweave transaction ID, which is a base64url encoded 32 byte hash.
+const ARWEAVE_ID_LEN: usize = 43;
+
+/// URI of content on decentralized, content-addressed storage, which NEP-177 recommends for the
+/// `media` and `reference` fields of token metadata.
+///
+/// URIs can be parsed from the `ipfs://` and `ar://` schemes, from HTTP gateway URLs such as
+/// `https://ipfs.io/ipfs/<cid>`, `https://<cid>.ipfs.dweb.link` and `https://arweave.net/<id>`,
+/// or from a bare CID or Arweave ID, as used with the `base_uri` of the contract metadata.
+/// Surrounding whitespace is ignored, as is the case of the scheme and host. The
+/// [`Display`](fmt::Display) implementation normalizes them to the `ipfs://` and `ar://` schemes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentUri {
+    /// Content on IPFS, with the CID of the content and an optional path within it.
+    Ipfs { cid: String, path: String },
+    /// Content on Arweave, with the transaction ID and an optional path within it.
+    Arweave { id: String, path: String },
+}
+
+impl ContentUri {
+    /// Parses a content-addressed URI, returning [`None`] if it does not point to IPFS or Arweave
+    /// content.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let uri = uri.trim();
+        if let Some(rest) = strip_prefix_ignore_case(uri, "ipfs://") {
+            let (cid, path) = split_path(rest.strip_prefix("ipfs/").unwrap_or(rest));
+            return Self::ipfs(cid, path);
+        }
+        if let Some(rest) = strip_prefix_ignore_case(uri, "ar://") {
+            let (id, path) = split_path(rest);
+            return Self::arweave(id, path);
+        }
+        let rest = match strip_prefix_ignore_case(uri, "https://")
+            .or_else(|| strip_prefix_ignore_case(uri, "http://"))
+        {
+            Some(rest) => rest,
+            None => {
+                // Bare CID or Arweave ID, which is relative to a base URI.
+                let (id, path) = split_path(uri);
+                return Self::ipfs(id, path).or_else(|| Self::arweave(id, path));
+            }
+        };
+
+        let (host, rest) = split_path(rest);
+        let host = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+        if let Some(ipfs_path) = rest.strip_prefix("/ipfs/") {
+            // Path gateway, such as `https://ipfs.io/ipfs/<cid>`.
+            let (cid, path) = split_path(ipfs_path);
+            Self::ipfs(cid, path)
+        } else if let Some((cid, _)) = host.split_once(".ipfs.") {
+            // Subdomain gateway, such as `https://<cid>.ipfs.dweb.link`.
+            Self::ipfs(cid, rest)
+        } else if host == "arweave.net" || host.ends_with(".arweave.net") {
+            let (id, path) = split_path(rest.trim_start_matches('/'));
+            Self::arweave(id, path)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an HTTP URL to the content through `gateway`, such as `https://ipfs.io` for IPFS or
+    /// `https://arweave.net` for Arweave content.
+    pub fn to_gateway_url(&self, gateway: &str) -> String {
+        let gateway = gateway.trim_end_matches('/');
+        match self {
+            Self::Ipfs { cid, path } => format!("{}/ipfs/{}{}", gateway, cid, path),
+            Self::Arweave { id, path } => format!("{}/{}{}", gateway, id, path),
+        }
+    }
+
+    fn ipfs(cid: &str, path: &str) -> Option<Self> {
+        is_valid_cid(cid).then(|| Self::Ipfs { cid: cid.to_string(), path: path.to_string() })
+    }
+
+    fn arweave(id: &str, path: &str) -> Option<Self> {
+        let is_valid = id.len() == ARWEAVE_ID_LEN
+            && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        is_valid.then(|| Self::Arweave { id: id.to_string(), path: path.to_string() })
+    }
+}
+
+impl fmt::Display for ContentUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ipfs { cid, path } => write!(f, "ipfs://{}{}", cid, path),
+            Self::Arweave { id, path } => write!(f, "ar://{}{}", id, path),
+        }
+    }
+}
+
+/// Strips `prefix` from `s` ignoring ASCII case, as URI schemes are case-insensitive.
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
+
+/// Splits off the first segment of a path, keeping the separator with the rest of the path.
+fn split_path(s: &str) -> (&str, &str) {
+    match s.find(&['/', '?', '#'][..]) {
+        Some(idx) => s.split_at(idx),
+        None => (s, ""),
+    }
+}
+
+/// Checks the format of an IPFS CID, either a base58 encoded CIDv0 or a base32 encoded CIDv1.
+fn is_valid_cid(cid: &str) -> bool {
+    if cid.len() == 46 && cid.starts_with("Qm") {
+        bs58_alphabet(cid)
+    } else if let Some(encoded) = cid.strip_prefix('b') {
+        cid.len() >= 59
+            && encoded.bytes().all(|b| b.is_ascii_lowercase() || (b'2'..=b'7').contains(&b))
+    } else {
+        false
+    }
+}
+
+fn bs58_alphabet(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_alphanumeric() && !matches!(b, b'0' | b'O' | b'I' | b'l'))
+}
+
+/// Returns `true` if `uri` points to content on IPFS or Arweave, see [`ContentUri::parse`].
+pub fn is_content_addressed(uri: &str) -> bool {
+    ContentUri::parse(uri).is_some()
+}
+
+/// Normalizes a content-addressed URI to the `ipfs://` or `ar://` scheme, so that the same content
+/// is referenced in the same way regardless of the gateway it was uploaded through. Returns
+/// [`None`] if the URI does not point to IPFS or Arweave content.
+pub fn normalize_content_uri(uri: &str) -> Option<String> {
+    ContentUri::parse(uri).map(|uri| uri.to_string())
+}
+
+/// Returns `true` if `hash`, such as the `media_hash` or `reference_hash` of token metadata, is
+/// the given sha256 hash of the content.
+pub fn hash_matches(hash: &Base64VecU8, content_hash: &[u8; 32]) -> bool {
+    hash.0 == content_hash
+}
+
+/// Returns `true` if `hash`, such as the `media_hash` or `reference_hash` of token metadata, is
+/// the sha256 hash of `content`.
+pub fn hash_matches_content(hash: &Base64VecU8, content: &[u8]) -> bool {
+    hash_matches(hash, &env::sha256_array(content))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID_V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+    const CID_V1: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+    const ARWEAVE_ID: &str = "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U";
+
+    fn ipfs(cid: &str, path: &str) -> Option<ContentUri> {
+        Some(ContentUri::Ipfs { cid: cid.to_string(), path: path.to_string() })
+    }
+
+    #[test]
+    fn parse_ipfs() {
+        assert_eq!(ContentUri::parse(&format!("ipfs://{}", CID_V0)), ipfs(CID_V0, ""));
+        assert_eq!(
+            ContentUri::parse(&format!("ipfs://ipfs/{}/1.json", CID_V1)),
+            ipfs(CID_V1, "/1.json")
+        );
+        assert_eq!(ContentUri::parse(CID_V0), ipfs(CID_V0, ""));
+        assert_eq!(
+            ContentUri::parse(&format!("https://ipfs.io/ipfs/{}/image.png?x=1", CID_V0)),
+            ipfs(CID_V0, "/image.png?x=1")
+        );
+        assert_eq!(
+            ContentUri::parse(&format!("https://{}.ipfs.dweb.link/1.json", CID_V1)),
+            ipfs(CID_V1, "/1.json")
+        );
+        assert_eq!(
+            ContentUri::parse(&format!("http://localhost:8080/ipfs/{}", CID_V1)),
+            ipfs(CID_V1, "")
+        );
+    }
+
+    #[test]
+    fn parse_arweave() {
+        let arweave = Some(ContentUri::Arweave { id: ARWEAVE_ID.to_string(), path: String::new() });
+        assert_eq!(ContentUri::parse(&format!("ar://{}", ARWEAVE_ID)), arweave);
+        assert_eq!(ContentUri::parse(&format!("https://arweave.net/{}", ARWEAVE_ID)), arweave);
+        assert_eq!(ContentUri::parse(ARWEAVE_ID), arweave);
+        assert_eq!(
+            ContentUri::parse(&format!("ar://{}", ARWEAVE_ID))
+                .unwrap()
+                .to_gateway_url("https://arweave.net/"),
+            format!("https://arweave.net/{}", ARWEAVE_ID)
+        );
+    }
+
+    #[test]
+    fn parse_malformed() {
+        for uri in [
+            "",
+            "ipfs://",
+            "ipfs://not-a-cid",
+            &format!("ipfs://{}", &CID_V0[1..]),
+            &format!("ar://{}", &ARWEAVE_ID[1..]),
+            &format!("ar://{}!", &ARWEAVE_ID[1..]),
+            &format!("https://example.com/{}", CID_V0),
+            &format!("https://example.com/ipfs/{}x", CID_V0),
+            &format!("ftp://ipfs.io/ipfs/{}", CID_V0),
+            "https://",
+        ] {
+            assert_eq!(ContentUri::parse(uri), None, "{}", uri);
+            assert!(!is_content_addressed(uri));
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        let normalized = Some(format!("ipfs://{}/1.json", CID_V1));
+        for uri in [
+            format!("ipfs://{}/1.json", CID_V1),
+            format!("  IPFS://{}/1.json\n", CID_V1),
+            format!("HTTPS://IPFS.IO/ipfs/{}/1.json", CID_V1),
+            format!("https://{}.IPFS.dweb.link/1.json", CID_V1.to_ascii_uppercase()),
+        ] {
+            assert_eq!(normalize_content_uri(&uri), normalized, "{}", uri);
+        }
+        assert_eq!(
+            normalize_content_uri(&format!(" https://Arweave.net/{} ", ARWEAVE_ID)),
+            Some(format!("ar://{}", ARWEAVE_ID))
+        );
+        assert_eq!(normalize_content_uri("https://example.com/image.png"), None);
+    }
+
+    #[test]
+    fn hashes() {
+        let content = b"token media";
+        let content_hash = env::sha256_array(content);
+        let hash = Base64VecU8::from(content_hash.to_vec());
+        assert!(hash_matches(&hash, &content_hash));
+        assert!(hash_matches_content(&hash, content));
+
+        assert!(!hash_matches_content(&hash, b"other media"));
+        assert!(!hash_matches(&hash, &env::sha256_array(b"other media")));
+
+        // A base64 encoded hash of the wrong length never matches.
+        let short: Base64VecU8 =
+            near_sdk::serde_json::from_str("\"AAAAAAAAAAAAAAAAAAAAAA==\"").unwrap();
+        assert_eq!(short.0.len(), 16);
+        assert!(!hash_matches(&short, &[0; 32]));
+        let mut long = content_hash.to_vec();
+        long.push(0);
+        assert!(!hash_matches_content(&Base64VecU8::from(long), content));
+    }
+}
@@ -1,3 +1,4 @@
+use super::content::{is_content_addressed, normalize_content_uri};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::json_types::Base64VecU8;
 use near_sdk::require;
@@ -67,4 +68,28 @@ impl TokenMetadata {
             require!(reference_hash.0.len() == 32, "Reference hash has to be 32 bytes");
         }
     }
+
+    /// Asserts that the `media` and `reference` URIs point to content-addressed storage on IPFS
+    /// or Arweave, as recommended by NEP-177.
+    pub fn assert_content_addressed(&self) {
+        if let Some(media) = &self.media {
+            require!(is_content_addressed(media), "Media has to be stored on IPFS or Arweave");
+        }
+        if let Some(reference) = &self.reference {
+            require!(
+                is_content_addressed(reference),
+                "Reference has to be stored on IPFS or Arweave"
+            );
+        }
+    }
+
+    /// Normalizes the `media` and `reference` URIs which point to IPFS or Arweave content to the
+    /// `ipfs://` and `ar://` schemes, see [`normalize_content_uri`]. Other URIs are kept as is.
+    pub fn normalize_content_uris(&mut self) {
+        for uri in self.media.iter_mut().chain(self.reference.iter_mut()) {
+            if let Some(normalized) = normalize_content_uri(uri) {
+                *uri = normalized;
+            }
+        }
+    }
 }
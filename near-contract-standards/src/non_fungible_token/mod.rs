@@ -1,5 +1,7 @@
 /// The [approval management standard](https://nomicon.io/Standards/NonFungibleToken/ApprovalManagement.html) for NFTs.
 pub mod approval;
+/// Validation of content URIs and hashes referenced by NFT metadata.
+pub mod content;
 /// The [core non-fungible token standard](https://nomicon.io/Standards/NonFungibleToken/Core.html). This can be though of as the base standard, with the others being extension standards.
 pub mod core;
 /// Common implementation of the [core non-fungible token standard](https://nomicon.io/Standards/NonFungibleToken/Core.html).
@@ -1,11 +1,101 @@
 use proc_macro2::TokenStream as TokenStream2;
 
 use crate::core_impl::info_extractor::{
-    ArgInfo, AttrSigInfo, BindgenArgType, InputStructType, SerializerType,
+    ArgInfo, AttrSigInfo, BindgenArgType, InputStructType, MethodType, SerializerType,
 };
-use quote::quote;
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::ReturnType;
 
 impl AttrSigInfo {
+    /// Create a compile time check that the serialized input arguments and result of the method
+    /// always fit within the runtime limits, if the method has the `#[check_size]` attribute.
+    /// Each check selects one of two constants by whether the size can exceed the limit, and the
+    /// constant used when it can is deprecated, which warns with a message naming the method and
+    /// the limit.
+    /// # Example
+    /// ```ignore
+    /// const _: () = {
+    ///     let _ = {
+    ///         struct SizeCheck<const EXCEEDS_LIMIT: bool>;
+    ///         #[allow(dead_code)]
+    ///         impl SizeCheck<false> {
+    ///             const CHECK: () = ();
+    ///         }
+    ///         #[allow(dead_code)]
+    ///         impl SizeCheck<true> {
+    ///             #[deprecated(note = "...")]
+    ///             const CHECK: () = ();
+    ///         }
+    ///         SizeCheck::<{ near_sdk::exceeds_limit(
+    ///             near_sdk::max_json_object_size(&[("arg0", <u64 as near_sdk::MaxSerializedSize>::MAX_JSON_SIZE)]),
+    ///             near_sdk::MAX_ARGUMENTS_LENGTH,
+    ///         ) }>::CHECK
+    ///     };
+    /// };
+    /// ```
+    pub fn size_check(&self) -> TokenStream2 {
+        if !self.check_size {
+            return TokenStream2::new();
+        }
+        let mut checks = TokenStream2::new();
+        let args: Vec<_> = self.input_args().collect();
+        if !args.is_empty() {
+            let size = match &self.input_serializer {
+                SerializerType::JSON => {
+                    let fields = args.iter().map(|ArgInfo { ty, ident, .. }| {
+                        let ident_str = ident.to_string();
+                        quote! { (#ident_str, <#ty as near_sdk::MaxSerializedSize>::MAX_JSON_SIZE) }
+                    });
+                    quote! { near_sdk::max_json_object_size(&[#(#fields),*]) }
+                }
+                SerializerType::Borsh => {
+                    let fields = args.iter().map(|ArgInfo { ty, .. }| {
+                        quote! { <#ty as near_sdk::MaxSerializedSize>::MAX_BORSH_SIZE }
+                    });
+                    quote! { near_sdk::max_size_sum(&[#(#fields),*]) }
+                }
+            };
+            let note = format!(
+                "the serialized arguments of `{}` can exceed the limit of 4 MiB \
+                (`near_sdk::MAX_ARGUMENTS_LENGTH`)",
+                self.ident
+            );
+            checks.extend(limit_check(
+                self.original_sig.inputs.span(),
+                quote! { near_sdk::exceeds_limit(#size, near_sdk::MAX_ARGUMENTS_LENGTH) },
+                &note,
+            ));
+        }
+        let is_init = matches!(self.method_type, MethodType::Init | MethodType::InitIgnoreState);
+        if let (ReturnType::Type(_, ty), false) = (&self.returns, is_init) {
+            let size = match &self.result_serializer {
+                SerializerType::JSON => {
+                    quote! { <#ty as near_sdk::MaxSerializedSize>::MAX_JSON_SIZE }
+                }
+                SerializerType::Borsh => {
+                    quote! { <#ty as near_sdk::MaxSerializedSize>::MAX_BORSH_SIZE }
+                }
+            };
+            let note = format!(
+                "the serialized result of `{}` can exceed the limit of 4 MiB \
+                (`near_sdk::MAX_RETURN_DATA_LENGTH`)",
+                self.ident
+            );
+            checks.extend(limit_check(
+                ty.span(),
+                quote! { near_sdk::exceeds_limit(#size, near_sdk::MAX_RETURN_DATA_LENGTH) },
+                &note,
+            ));
+        }
+        quote! {
+            const _: () = {
+                #checks
+            };
+        }
+    }
+
     /// Create struct representing input arguments.
     /// * input_struct_type represents whether the input structure will be used for serialization
     ///     (e.g. for a promise input) or deserialization (e.g. for a method input).
@@ -243,3 +333,22 @@ pub fn deserialize_data(ty: &SerializerType) -> TokenStream2 {
         },
     }
 }
+
+/// Statement which uses a deprecated constant with `note` if `exceeds` evaluates to `true`.
+fn limit_check(span: Span, exceeds: TokenStream2, note: &str) -> TokenStream2 {
+    quote_spanned! {span=>
+        let _ = {
+            struct SizeCheck<const EXCEEDS_LIMIT: bool>;
+            #[allow(dead_code)]
+            impl SizeCheck<false> {
+                const CHECK: () = ();
+            }
+            #[allow(dead_code)]
+            impl SizeCheck<true> {
+                #[deprecated(note = #note)]
+                const CHECK: () = ();
+            }
+            SizeCheck::<{ #exceeds }>::CHECK
+        };
+    }
+}
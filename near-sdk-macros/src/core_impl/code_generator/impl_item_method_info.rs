@@ -42,6 +42,7 @@ impl ImplItemMethodInfo {
 
         let callback_deser = attr_signature_info.callback_deserialization();
         let callback_vec_deser = attr_signature_info.callback_vec_deserialization();
+        let size_check = attr_signature_info.size_check();
 
         let arg_list = attr_signature_info.arg_list();
         let AttrSigInfo {
@@ -162,6 +163,7 @@ impl ImplItemMethodInfo {
             }
         });
         quote! {
+            #size_check
             #non_bindgen_attrs
             #[cfg(target_arch = "wasm32")]
             #[no_mangle]
@@ -18,6 +18,8 @@ pub struct AttrSigInfo {
     pub is_payable: bool,
     /// Whether method can accept calls from self (current account)
     pub is_private: bool,
+    /// Whether to check that the serialized arguments and result always fit the runtime limits.
+    pub check_size: bool,
     /// The serializer that we use for `env::input()`.
     pub input_serializer: SerializerType,
     /// The serializer that we use for the return type.
@@ -61,6 +63,7 @@ impl AttrSigInfo {
         let mut method_type = MethodType::Regular;
         let mut is_payable = false;
         let mut is_private = false;
+        let mut check_size = false;
         // By the default we serialize the result with JSON.
         let mut result_serializer = SerializerType::JSON;
 
@@ -83,6 +86,9 @@ impl AttrSigInfo {
                 "private" => {
                     is_private = true;
                 }
+                "check_size" => {
+                    check_size = true;
+                }
                 "result_serializer" => {
                     let serializer: SerializerAttr = syn::parse2(attr.tokens.clone())?;
                     result_serializer = serializer.serializer_type;
@@ -136,6 +142,7 @@ impl AttrSigInfo {
             method_type,
             is_payable,
             is_private,
+            check_size,
             result_serializer,
             receiver,
             returns,
@@ -7,7 +7,7 @@ use near_crypto::{InMemorySigner, KeyType, PublicKey, Signer};
 use near_pool::{types::PoolIterator, TransactionPool};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::errors::RuntimeError;
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::profile::ProfileData;
 use near_primitives::receipt::Receipt;
 use near_primitives::runtime::config::RuntimeConfig;
@@ -92,6 +92,31 @@ impl GenesisConfig {
         });
         signer
     }
+
+    /// Adds an account with the given contract deployed and `state` as the initial contract
+    /// storage, so that methods can be called on existing state without initializing the
+    /// contract through transactions first.
+    pub fn add_contract(
+        &mut self,
+        account_id: &str,
+        wasm_bytes: &[u8],
+        balance: Balance,
+        state: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    ) {
+        self.state_records.push(StateRecord::Account {
+            account_id: account_id.to_string(),
+            account: account_new(balance, hash(wasm_bytes)),
+        });
+        self.state_records.push(StateRecord::Contract {
+            account_id: account_id.to_string(),
+            code: wasm_bytes.to_vec(),
+        });
+        self.state_records.extend(state.into_iter().map(|(data_key, value)| StateRecord::Data {
+            account_id: account_id.to_string(),
+            data_key,
+            value,
+        }));
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -361,6 +361,38 @@ pub fn init_simulator(genesis_config: Option<GenesisConfig>) -> UserAccount {
     UserAccount::new(&Rc::new(RefCell::new(runtime)), root_account_id, signer)
 }
 
+/// Runs a single method of the compiled contract `wasm_bytes` in-process, with `state` as the
+/// initial contract storage. The call is made by the root account with [`DEFAULT_GAS`] attached,
+/// and the returned result includes the gas burnt by the method, as charged by the runtime.
+///
+/// This sits between unit tests with a mocked blockchain, which do not execute the compiled
+/// contract, and full simulation tests, which have to set up the contract state through calls.
+///
+/// # Examples
+///
+/// ```
+/// # lazy_static_include::lazy_static_include_bytes! {
+/// #    TOKEN_WASM_BYTES => "../examples/fungible-token/res/fungible_token.wasm",
+/// # }
+/// use near_sdk_sim::run_method;
+///
+/// // The contract is not initialized, so the method fails, after burning some gas.
+/// let res = run_method(&TOKEN_WASM_BYTES, vec![], "ft_total_supply", b"{}");
+/// assert!(!res.is_ok());
+/// assert!(res.gas_burnt().0 > 0);
+/// ```
+pub fn run_method(
+    wasm_bytes: &[u8],
+    state: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    method: &str,
+    args: &[u8],
+) -> ExecutionResult {
+    let contract_id = AccountId::new_unchecked("contract".to_string());
+    let mut genesis = GenesisConfig::default();
+    genesis.add_contract(contract_id.as_str(), wasm_bytes, STORAGE_AMOUNT, state);
+    init_simulator(Some(genesis)).call(contract_id, method, args, DEFAULT_GAS, 0)
+}
+
 /// Deploys a contract. Will either deploy or deploy and initialize a contract.
 /// Returns a `ContractAccount<T>` where `T` is the first argument.
 ///
@@ -18,4 +18,6 @@ fn compilation_tests() {
     t.pass("compilation_tests/cond_compilation.rs");
     t.compile_fail("compilation_tests/payable_view.rs");
     t.pass("compilation_tests/borsh_storage_key.rs");
+    t.pass("compilation_tests/check_size.rs");
+    t.compile_fail("compilation_tests/check_size_unbounded.rs");
 }
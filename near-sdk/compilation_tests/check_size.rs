@@ -0,0 +1,29 @@
+//! Methods with arguments and results that always fit the runtime limits.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{near_bindgen, AccountId};
+
+#[near_bindgen]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+struct Ledger {
+    last: u128,
+}
+
+#[near_bindgen]
+impl Ledger {
+    #[check_size]
+    pub fn transfer(&mut self, receiver_id: AccountId, amount: U128) -> Option<[u64; 4]> {
+        let _ = receiver_id;
+        self.last = amount.0;
+        None
+    }
+
+    #[check_size]
+    #[result_serializer(borsh)]
+    pub fn last(&self, #[serializer(borsh)] offsets: (u8, [u32; 16])) -> u128 {
+        self.last + offsets.0 as u128
+    }
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+//! Arguments and results of unbounded size fail the size check, which warns and is denied here.
+
+#![deny(deprecated)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::near_bindgen;
+
+#[near_bindgen]
+#[derive(Default, BorshDeserialize, BorshSerialize)]
+struct Registry {
+    names: Vec<String>,
+}
+
+#[near_bindgen]
+impl Registry {
+    #[check_size]
+    pub fn names(&self) -> Vec<String> {
+        self.names.clone()
+    }
+
+    #[check_size]
+    pub fn add_names(&mut self, names: Vec<String>) {
+        self.names.extend(names);
+    }
+}
+
+fn main() {}
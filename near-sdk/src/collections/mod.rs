@@ -59,6 +59,9 @@ pub use lazy_option::LazyOption;
 mod tree_map;
 pub use tree_map::TreeMap;
 
+mod reverse;
+pub use reverse::Reverse;
+
 pub const ERR_INCONSISTENT_STATE: &str = "The collection is an inconsistent state. Did previous smart contract execution terminate unexpectedly?";
 pub const ERR_ELEMENT_SERIALIZATION: &str = "Cannot serialize element with Borsh.";
 pub const ERR_ELEMENT_DESERIALIZATION: &str = "Cannot deserialize element with Borsh.";
@@ -0,0 +1,92 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use core::cmp::Ordering;
+
+/// Key wrapper that reverses the ordering of the wrapped value, for use with ordered collections
+/// such as [`TreeMap`](crate::collections::TreeMap) to keep entries in descending order.
+///
+/// This is equivalent to [`std::cmp::Reverse`], but also implements Borsh serialization. The
+/// serialized form is the same as the inner value, so only the iteration order of a collection
+/// changes by wrapping its keys. The order of an existing tree is determined when keys are
+/// inserted though, so the key type of a collection with existing entries must not be changed to
+/// or from `Reverse`.
+///
+/// # Examples
+/// ```
+/// use near_sdk::collections::{Reverse, TreeMap};
+///
+/// // Bids are kept best (highest price) first, asks lowest price first.
+/// let mut bids: TreeMap<Reverse<u128>, u64> = TreeMap::new(b"b");
+/// let mut asks: TreeMap<u128, u64> = TreeMap::new(b"a");
+///
+/// bids.insert(&Reverse(100), &5);
+/// bids.insert(&Reverse(105), &2);
+/// asks.insert(&110, &3);
+/// asks.insert(&108, &1);
+///
+/// assert_eq!(bids.min(), Some(Reverse(105)));
+/// assert_eq!(asks.min(), Some(108));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Reverse<T>(pub T);
+
+impl<T: BorshSerialize> BorshSerialize for Reverse<T> {
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.0, writer)
+    }
+}
+
+impl<T: BorshDeserialize> BorshDeserialize for Reverse<T> {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self(BorshDeserialize::deserialize(buf)?))
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for Reverse<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        other.0.partial_cmp(&self.0)
+    }
+}
+
+impl<T: Ord> Ord for Reverse<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl<T> From<T> for Reverse<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::Reverse;
+    use crate::collections::TreeMap;
+    use crate::test_utils::next_trie_id;
+
+    #[test]
+    fn reverse_tree_map_order() {
+        let mut map: TreeMap<Reverse<u32>, u32> = TreeMap::new(next_trie_id());
+        for k in [3, 1, 4, 5, 9, 2, 6] {
+            map.insert(&Reverse(k), &(k * 10));
+        }
+
+        let keys: Vec<u32> = map.iter().map(|(k, _)| k.0).collect();
+        assert_eq!(keys, [9, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(map.min(), Some(Reverse(9)));
+        assert_eq!(map.max(), Some(Reverse(1)));
+        assert_eq!(map.ceil_key(&Reverse(7)), Some(Reverse(6)));
+        assert_eq!(map.floor_key(&Reverse(7)), Some(Reverse(9)));
+
+        // Serialized form is the same as the wrapped value.
+        assert_eq!(
+            borsh::BorshSerialize::try_to_vec(&Reverse(7u32)).unwrap(),
+            borsh::BorshSerialize::try_to_vec(&7u32).unwrap()
+        );
+    }
+}
@@ -14,6 +14,9 @@ use crate::{env, IntoStorageKey};
 /// - `above`/`below`:          O(log(N))
 /// - `range` of K elements:    O(Klog(N))
 ///
+/// Entries are ordered by the [`Ord`] implementation of `K`. To keep entries in descending
+/// order, wrap keys in [`Reverse`](crate::collections::Reverse).
+///
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct TreeMap<K, V> {
     root: u64,
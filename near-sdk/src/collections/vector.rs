@@ -108,6 +108,26 @@ impl<T> Vector<T> {
         }
     }
 
+    /// Removes the serialized element at `index` from storage without moving the other elements,
+    /// and empties the vector when the last element is removed. This is used to move elements out
+    /// of the vector in order, during which the elements before `index` must not be read.
+    #[cfg(feature = "unstable")]
+    pub(crate) fn take_raw(&mut self, index: u64) -> Vec<u8> {
+        if index >= self.len {
+            env::panic_str(ERR_INDEX_OUT_OF_BOUNDS)
+        }
+        let lookup_key = self.index_to_lookup_key(index);
+        let raw_value = if env::storage_remove(&lookup_key) {
+            expect_consistent_state(env::storage_get_evicted())
+        } else {
+            env::panic_str(ERR_INCONSISTENT_STATE)
+        };
+        if index + 1 == self.len {
+            self.len = 0;
+        }
+        raw_value
+    }
+
     /// Inserts a serialized element at `index`, returns a serialized evicted element.
     ///
     /// # Panics
@@ -0,0 +1,176 @@
+//! Tasks which wait for an external event before they continue, such as the response of an
+//! off-chain service, built on yielded promises (NEP-519).
+//!
+//! A [`DeferredTask`] is created with a callback of the current contract, which is called once
+//! the task is [triggered](DeferredTask::trigger) with a payload, [cancelled](DeferredTask::cancel),
+//! or times out after a number of blocks set by the protocol. The task is kept in the state of the
+//! contract to trigger or cancel it from a later call, and the callback reads how the task ended
+//! with [`DeferredTask::outcome`].
+//!
+//! # Examples
+//! ```
+//! use near_sdk::deferred::{DeferredOutcome, DeferredTask};
+//! use near_sdk::Gas;
+//!
+//! // Request some data and wait for it.
+//! let (task, promise) = DeferredTask::create("on_data", b"{}", Gas(10_000_000_000_000));
+//! // Store `task` in the state of the contract and return `promise`.
+//! # drop(promise);
+//!
+//! // In a later call, submit the data, which calls `on_data`.
+//! assert!(task.clone().trigger(b"42"));
+//! // The task can only be triggered or cancelled once.
+//! assert!(!task.cancel());
+//!
+//! // In `on_data`, `DeferredTask::outcome()` returns `DeferredOutcome::Triggered(b"42".to_vec())`.
+//! ```
+
+use crate::{env, DataId, Gas, Promise, PromiseResult};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Tag of the payload of a cancelled task.
+const CANCELLED: u8 = 0;
+/// Tag before the payload of a triggered task.
+const TRIGGERED: u8 = 1;
+
+/// Callback of the current contract which waits until it is triggered or cancelled.
+///
+/// The task only holds the ID of the data its yielded promise waits for, so it can be stored in
+/// the state of the contract.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeferredTask {
+    data_id: DataId,
+}
+
+/// How a [`DeferredTask`] ended, as seen by its callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeferredOutcome {
+    /// The task was triggered with the payload.
+    Triggered(Vec<u8>),
+    /// The task was cancelled.
+    Cancelled,
+    /// The task was neither triggered nor cancelled before it timed out.
+    TimedOut,
+}
+
+impl DeferredTask {
+    /// Creates a task which calls `callback` of the current account with `arguments` once it is
+    /// triggered, cancelled or timed out, with `gas` attached to the call.
+    ///
+    /// Returns the task and its promise. Returning the promise from the current function makes the
+    /// result of the callback the result of the current call.
+    pub fn create(callback: &str, arguments: &[u8], gas: Gas) -> (Self, Promise) {
+        let (promise, data_id) = Promise::yield_call(callback, arguments, gas);
+        (Self { data_id }, promise)
+    }
+
+    /// Returns the ID of the data the yielded promise of the task waits for.
+    pub fn data_id(&self) -> &DataId {
+        &self.data_id
+    }
+
+    /// Calls the callback of the task with `payload`. Returns `false` if the task was already
+    /// triggered or cancelled, or timed out.
+    pub fn trigger(self, payload: &[u8]) -> bool {
+        Promise::resume(&self.data_id, &[&[TRIGGERED][..], payload].concat())
+    }
+
+    /// Calls the callback of the task without a payload. Returns `false` if the task was already
+    /// triggered or cancelled, or timed out.
+    pub fn cancel(self) -> bool {
+        Promise::resume(&self.data_id, &[CANCELLED])
+    }
+
+    /// Returns how the task ended, which can only be called by the callback of the task.
+    pub fn outcome() -> DeferredOutcome {
+        if env::promise_results_count() != 1 {
+            env::panic_str("Expected the result of a deferred task");
+        }
+        DeferredOutcome::from_promise_result(env::promise_result(0))
+    }
+}
+
+impl DeferredOutcome {
+    /// Reads the outcome from the result of the yielded promise of a task.
+    pub fn from_promise_result(result: PromiseResult) -> Self {
+        match result {
+            PromiseResult::Successful(payload) => match payload.split_first() {
+                Some((&TRIGGERED, payload)) => Self::Triggered(payload.to_vec()),
+                Some((&CANCELLED, [])) => Self::Cancelled,
+                _ => env::panic_str("Expected the result of a deferred task"),
+            },
+            PromiseResult::Failed => Self::TimedOut,
+            PromiseResult::NotReady => env::abort(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::{DeferredOutcome, DeferredTask};
+    use crate::mock::{VmAction, YieldedPromise};
+    use crate::test_utils::{get_created_receipts, get_yielded_promises, VMContextBuilder};
+    use crate::{testing_env, Gas, PromiseResult, RuntimeFeesConfig, VMConfig};
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[test]
+    fn trigger_and_cancel() {
+        let (triggered, promise) = DeferredTask::create("on_data", b"{}", Gas(5));
+        drop(promise);
+        let (cancelled, _) = DeferredTask::create("on_data", b"{}", Gas(5));
+        let (pending, _) = DeferredTask::create("on_data", b"{}", Gas(5));
+        assert_eq!(
+            get_created_receipts()[0].actions,
+            [VmAction::FunctionCall {
+                function_name: "on_data".to_string(),
+                args: b"{}".to_vec(),
+                gas: Gas(5),
+                deposit: 0
+            }]
+        );
+
+        // Tasks are stored and triggered in later calls.
+        let stored = triggered.try_to_vec().unwrap();
+        testing_env!(VMContextBuilder::new().build());
+        let task = DeferredTask::try_from_slice(&stored).unwrap();
+        assert_eq!(task, triggered);
+        let task_id = *task.data_id();
+        assert!(task.trigger(b"42"));
+        assert!(cancelled.clone().cancel());
+        assert!(!cancelled.clone().trigger(b"42"));
+        assert!(!triggered.cancel());
+        assert_eq!(
+            get_yielded_promises(),
+            [
+                YieldedPromise { data_id: task_id, payload: Some(b"\x0142".to_vec()) },
+                YieldedPromise { data_id: *cancelled.data_id(), payload: Some(vec![0]) },
+                YieldedPromise { data_id: *pending.data_id(), payload: None },
+            ]
+        );
+    }
+
+    #[test]
+    fn outcome() {
+        let outcome = |result| DeferredOutcome::from_promise_result(result);
+        assert_eq!(
+            outcome(PromiseResult::Successful(b"\x0142".to_vec())),
+            DeferredOutcome::Triggered(b"42".to_vec())
+        );
+        assert_eq!(
+            outcome(PromiseResult::Successful(vec![1])),
+            DeferredOutcome::Triggered(Vec::new())
+        );
+        assert_eq!(outcome(PromiseResult::Successful(vec![0])), DeferredOutcome::Cancelled);
+        assert_eq!(outcome(PromiseResult::Failed), DeferredOutcome::TimedOut);
+
+        testing_env!(
+            VMContextBuilder::new().build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![0])],
+        );
+        assert_eq!(DeferredTask::outcome(), DeferredOutcome::Cancelled);
+    }
+}
@@ -3,6 +3,7 @@
 //! whenever possible. In case of cross-contract calls prefer using even higher-level API available
 //! through `callback_args`, `callback_args_vec`, `ext_contract`, `Promise`, and `PromiseOrValue`.
 
+use std::cell::RefCell;
 use std::mem::size_of;
 use std::panic as std_panic;
 use std::{convert::TryFrom, mem::MaybeUninit};
@@ -12,10 +13,15 @@ use crate::mock::MockedBlockchain;
 use crate::types::{
     AccountId, Balance, BlockHeight, Gas, PromiseIndex, PromiseResult, PublicKey, StorageUsage,
 };
+#[cfg(feature = "bls12381")]
+use crate::types::{Bls12381G1Point, Bls12381G2Point};
+#[cfg(feature = "unstable")]
+use crate::types::{DataId, Ed25519PublicKey, Ed25519Signature, Secp256k1PublicKey};
 use near_sys as sys;
 
 const REGISTER_EXPECTED_ERR: &str =
     "Register was expected to have data because we just wrote it into it.";
+const ERR_INPUT_EXPECTED: &str = "Expected input to the function call.";
 
 /// Register used internally for atomic operations. This register is safe to use by the user,
 /// since it only needs to be untouched while methods of `Environment` execute, which is guaranteed
@@ -129,6 +135,65 @@ pub fn register_len(register_id: u64) -> Option<u64> {
     }
 }
 
+/// Reads the content of the `register_id` into `buffer`, replacing its content and reusing its
+/// allocation. Returns `false`, leaving `buffer` empty, if the register is not used.
+pub fn read_register_into(register_id: u64, buffer: &mut Vec<u8>) -> bool {
+    buffer.clear();
+    let len = match register_len(register_id) {
+        Some(len) => len as usize,
+        None => return false,
+    };
+    buffer.resize(len, 0);
+    unsafe { sys::read_register(register_id, buffer.as_mut_ptr() as _) };
+    true
+}
+
+/// Reads the content of the `register_id`, which has to be `N` bytes long, into an array. If
+/// register is not used returns `None`.
+///
+/// # Panics
+///
+/// Panics if the length of the register is not `N`.
+pub fn read_register_array<const N: usize>(register_id: u64) -> Option<[u8; N]> {
+    let len = register_len(register_id)?;
+    if len != N as u64 {
+        panic_str(&format!("Expected register of length {}, found length {}", N, len));
+    }
+    let mut array = [0; N];
+    unsafe { sys::read_register(register_id, array.as_mut_ptr() as _) };
+    Some(array)
+}
+
+thread_local! {
+    /// Buffer the registers are read into by [`read_register_typed`], which is kept to not
+    /// allocate a new buffer for every read.
+    static REGISTER_BUFFER: RefCell<Vec<u8>> = Default::default();
+}
+
+/// Deserializes the content of the `register_id` from Borsh. If register is not used returns
+/// `None`.
+///
+/// The content is copied into a buffer which is reused by every call, so that reading a register
+/// only allocates when it is larger than all the registers read before, unlike deserializing the
+/// result of [`read_register`].
+///
+/// # Panics
+///
+/// Panics if the content of the register cannot be deserialized as `T`. The panic message
+/// includes the deserialization error.
+pub fn read_register_typed<T: borsh::BorshDeserialize>(register_id: u64) -> Option<T> {
+    REGISTER_BUFFER.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        if !read_register_into(register_id, &mut buffer) {
+            return None;
+        }
+        let value = T::try_from_slice(&buffer).unwrap_or_else(|e| {
+            panic_str(&format!("Failed to deserialize register from Borsh: {}", e))
+        });
+        Some(value)
+    })
+}
+
 // ###############
 // # Context API #
 // ###############
@@ -167,6 +232,39 @@ pub fn input() -> Option<Vec<u8>> {
     try_method_into_register!(input)
 }
 
+/// Deserializes the input to the contract call from JSON. This is what methods of a
+/// `#[near_bindgen]` contract do by default, and can be used for methods that read their input
+/// manually, such as a fallback method that forwards calls.
+///
+/// # Panics
+///
+/// Panics if no input was provided or if it cannot be deserialized as `T`. The panic message
+/// includes the deserialization error.
+pub fn input_json<T: serde::de::DeserializeOwned>() -> T {
+    let input = input().unwrap_or_else(|| panic_str(ERR_INPUT_EXPECTED));
+    serde_json::from_slice(&input)
+        .unwrap_or_else(|e| panic_str(&format!("Failed to deserialize input from JSON: {}", e)))
+}
+
+/// Deserializes the input to the contract call from JSON.
+#[deprecated(since = "4.0.0", note = "Use input_json instead")]
+pub fn input_as<T: serde::de::DeserializeOwned>() -> T {
+    input_json()
+}
+
+/// Deserializes the input to the contract call from Borsh. See [`input_json`] for deserializing
+/// JSON input.
+///
+/// # Panics
+///
+/// Panics if no input was provided or if it cannot be deserialized as `T`. The panic message
+/// includes the deserialization error.
+pub fn input_borsh<T: borsh::BorshDeserialize>() -> T {
+    let input = input().unwrap_or_else(|| panic_str(ERR_INPUT_EXPECTED));
+    T::try_from_slice(&input)
+        .unwrap_or_else(|e| panic_str(&format!("Failed to deserialize input from Borsh: {}", e)))
+}
+
 /// Current block index.
 #[deprecated(since = "4.0.0", note = "Use block_height instead")]
 pub fn block_index() -> BlockHeight {
@@ -252,6 +350,11 @@ pub fn keccak512(value: &[u8]) -> Vec<u8> {
     keccak512_array(value).to_vec()
 }
 
+/// Hashes the random sequence of bytes using ripemd160.
+pub fn ripemd160(value: &[u8]) -> Vec<u8> {
+    ripemd160_array(value).to_vec()
+}
+
 /// Hashes the bytes using the SHA-256 hash function. This returns a 32 byte hash.
 pub fn sha256_array(value: &[u8]) -> [u8; 32] {
     //* SAFETY: sha256 syscall will always generate 32 bytes inside of the atomic op register
@@ -328,6 +431,208 @@ pub fn ecrecover(
     }
 }
 
+/// Recovers the secp256k1 public key which signed a 32-byte message `hash` with the 64-byte
+/// `signature` and the recovery id `v`.
+///
+/// `v` is `0` or `1`, and the Ethereum values `27` and `28` are also accepted. If
+/// `malleability_flag` is `true`, signatures with an `s` value in the upper half of the curve
+/// order are rejected, as Ethereum does for transactions. Returns [`None`] if the key can't be
+/// recovered. The Ethereum address of the signer is given by
+/// [`Secp256k1PublicKey::eth_address`].
+///
+/// # Examples
+/// ```
+/// use near_sdk::env;
+///
+/// assert_eq!(env::ecrecover_typed(&[0; 32], &[0; 64], 0, false), None);
+/// ```
+#[cfg(feature = "unstable")]
+pub fn ecrecover_typed(
+    hash: &[u8; 32],
+    signature: &[u8; 64],
+    v: u8,
+    malleability_flag: bool,
+) -> Option<Secp256k1PublicKey> {
+    let v = if v >= 27 { v - 27 } else { v };
+    ecrecover(hash, signature, v, malleability_flag).map(Secp256k1PublicKey::from)
+}
+
+/// Verifies that `signature` is a valid ed25519 signature of `message` by the owner of
+/// `public_key`.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{env, Ed25519PublicKey, Ed25519Signature};
+///
+/// let public_key: Ed25519PublicKey = "6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+/// let signature = Ed25519Signature::from([0; 64]);
+/// assert!(!env::ed25519_verify(&signature, b"message", &public_key));
+/// ```
+#[cfg(feature = "unstable")]
+pub fn ed25519_verify(
+    signature: &Ed25519Signature,
+    message: &[u8],
+    public_key: &Ed25519PublicKey,
+) -> bool {
+    let signature = signature.as_bytes();
+    let public_key = public_key.as_bytes();
+    unsafe {
+        sys::ed25519_verify(
+            signature.len() as _,
+            signature.as_ptr() as _,
+            message.len() as _,
+            message.as_ptr() as _,
+            public_key.len() as _,
+            public_key.as_ptr() as _,
+        ) == 1
+    }
+}
+
+/// Calls a BLS12-381 host function which writes its result to a register. Returns [`None`] if
+/// the input was rejected.
+#[cfg(feature = "bls12381")]
+fn bls12381_into_register(
+    host_fn: unsafe extern "C" fn(u64, u64, u64) -> u64,
+    input: &[u8],
+) -> Option<Vec<u8>> {
+    if unsafe { host_fn(input.len() as _, input.as_ptr() as _, ATOMIC_OP_REGISTER) } != 0 {
+        return None;
+    }
+    Some(expect_register(read_register(ATOMIC_OP_REGISTER)))
+}
+
+/// Decodes the concatenated points written by a BLS12-381 host function. Returns [`None`] if
+/// the length of `bytes` is not a multiple of `len`.
+#[cfg(feature = "bls12381")]
+fn bls12381_points<P>(bytes: &[u8], len: usize) -> Option<Vec<P>>
+where
+    P: for<'a> TryFrom<&'a [u8]>,
+{
+    let points = bytes.chunks_exact(len);
+    if !points.remainder().is_empty() {
+        return None;
+    }
+    points.map(|point| P::try_from(point).ok()).collect()
+}
+
+/// Encodes the input of the BLS12-381 sum host functions, where each point is preceded by `1` if
+/// it is negated and `0` otherwise.
+#[cfg(feature = "bls12381")]
+fn bls12381_sum_input<P: AsRef<[u8]>>(points: &[(bool, P)]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (negate, point) in points {
+        input.push(*negate as u8);
+        input.extend_from_slice(point.as_ref());
+    }
+    input
+}
+
+/// Encodes the input of the BLS12-381 multiexp and pairing host functions, which is the
+/// concatenation of the pairs.
+#[cfg(feature = "bls12381")]
+fn bls12381_pairs_input<A: AsRef<[u8]>, B: AsRef<[u8]>>(pairs: &[(A, B)]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (a, b) in pairs {
+        input.extend_from_slice(a.as_ref());
+        input.extend_from_slice(b.as_ref());
+    }
+    input
+}
+
+/// Decodes the single point written by a BLS12-381 host function.
+#[cfg(feature = "bls12381")]
+fn bls12381_point<P>(bytes: Vec<u8>) -> P
+where
+    P: for<'a> TryFrom<&'a [u8]>,
+{
+    P::try_from(&bytes).unwrap_or_else(|_| abort())
+}
+
+/// Decodes the points written by a BLS12-381 host function.
+#[cfg(feature = "bls12381")]
+fn bls12381_expect_points<P>(bytes: Vec<u8>, len: usize) -> Vec<P>
+where
+    P: for<'a> TryFrom<&'a [u8]>,
+{
+    bls12381_points(&bytes, len).unwrap_or_else(|| abort())
+}
+
+/// Returns the sum of G1 points of the BLS12-381 curve, where each point is negated if it is
+/// paired with `true`. Returns [`None`] if any of the points is not on the curve.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_p1_sum(points: &[(bool, Bls12381G1Point)]) -> Option<Bls12381G1Point> {
+    bls12381_into_register(sys::bls12381_p1_sum, &bls12381_sum_input(points)).map(bls12381_point)
+}
+
+/// Returns the sum of G2 points of the BLS12-381 curve, where each point is negated if it is
+/// paired with `true`. Returns [`None`] if any of the points is not on the curve.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_p2_sum(points: &[(bool, Bls12381G2Point)]) -> Option<Bls12381G2Point> {
+    bls12381_into_register(sys::bls12381_p2_sum, &bls12381_sum_input(points)).map(bls12381_point)
+}
+
+/// Returns the sum of G1 points of the BLS12-381 curve multiplied by scalars, which are
+/// encoded as 32 little-endian bytes. Returns [`None`] if any of the points is not in G1.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_g1_multiexp(terms: &[(Bls12381G1Point, [u8; 32])]) -> Option<Bls12381G1Point> {
+    bls12381_into_register(sys::bls12381_g1_multiexp, &bls12381_pairs_input(terms))
+        .map(bls12381_point)
+}
+
+/// Returns the sum of G2 points of the BLS12-381 curve multiplied by scalars, which are
+/// encoded as 32 little-endian bytes. Returns [`None`] if any of the points is not in G2.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_g2_multiexp(terms: &[(Bls12381G2Point, [u8; 32])]) -> Option<Bls12381G2Point> {
+    bls12381_into_register(sys::bls12381_g2_multiexp, &bls12381_pairs_input(terms))
+        .map(bls12381_point)
+}
+
+/// Maps elements of the Fp field, encoded as 48 big-endian bytes, to G1 points of the
+/// BLS12-381 curve, as used for hashing to the curve. Returns [`None`] if any of the elements
+/// is not in the field.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_map_fp_to_g1(elements: &[[u8; 48]]) -> Option<Vec<Bls12381G1Point>> {
+    bls12381_into_register(sys::bls12381_map_fp_to_g1, &elements.concat())
+        .map(|bytes| bls12381_expect_points(bytes, Bls12381G1Point::LEN))
+}
+
+/// Maps elements of the Fp2 field, encoded as the 48 big-endian bytes of `c1` followed by the
+/// ones of `c0`, to G2 points of the BLS12-381 curve, as used for hashing to the curve.
+/// Returns [`None`] if any of the elements is not in the field.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_map_fp2_to_g2(elements: &[[u8; 96]]) -> Option<Vec<Bls12381G2Point>> {
+    bls12381_into_register(sys::bls12381_map_fp2_to_g2, &elements.concat())
+        .map(|bytes| bls12381_expect_points(bytes, Bls12381G2Point::LEN))
+}
+
+/// Decompresses G1 points of the BLS12-381 curve from their 48-byte compressed encoding, as
+/// used for BLS public keys. Returns [`None`] if any of the points is invalid.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_p1_decompress(points: &[[u8; 48]]) -> Option<Vec<Bls12381G1Point>> {
+    bls12381_into_register(sys::bls12381_p1_decompress, &points.concat())
+        .map(|bytes| bls12381_expect_points(bytes, Bls12381G1Point::LEN))
+}
+
+/// Decompresses G2 points of the BLS12-381 curve from their 96-byte compressed encoding, as
+/// used for BLS signatures. Returns [`None`] if any of the points is invalid.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_p2_decompress(points: &[[u8; 96]]) -> Option<Vec<Bls12381G2Point>> {
+    bls12381_into_register(sys::bls12381_p2_decompress, &points.concat())
+        .map(|bytes| bls12381_expect_points(bytes, Bls12381G2Point::LEN))
+}
+
+/// Returns `true` if the product of the pairings of the pairs of points of the BLS12-381 curve
+/// is the identity, which is how BLS signatures are verified. Returns `false` if it is not, or if
+/// any of the points is not in its group.
+///
+/// A signature `sig` of a message hashed to `h` by a key `pk` is valid if the pairings of
+/// `(-g1, sig)` and `(pk, h)` multiply to the identity, where `g1` is the generator of G1.
+#[cfg(feature = "bls12381")]
+pub fn bls12381_pairing_check(pairs: &[(Bls12381G1Point, Bls12381G2Point)]) -> bool {
+    let input = bls12381_pairs_input(pairs);
+    unsafe { sys::bls12381_pairing_check(input.len() as _, input.as_ptr() as _) == 0 }
+}
+
 // ################
 // # Promises API #
 // ################
@@ -540,6 +845,55 @@ pub fn promise_return(promise_idx: PromiseIndex) {
     unsafe { sys::promise_return(promise_idx) }
 }
 
+/// Creates a promise which calls `function_name` of the current account with `arguments` once it
+/// is resumed with [`promise_yield_resume`], or once it times out after a number of blocks set
+/// by the protocol. Returns the index of the promise and the ID of the data it waits for.
+///
+/// The called function receives the payload of [`promise_yield_resume`] as the result of its
+/// only promise, or [`PromiseResult::Failed`] if the promise timed out. `gas` is attached to the
+/// call, and `gas_weight` is the share of the unused gas of the current call which is attached
+/// in addition to it, like for other function calls.
+///
+/// See [`Promise::yield_call`](crate::Promise::yield_call) for a higher-level API.
+#[cfg(feature = "unstable")]
+pub fn promise_yield_create(
+    function_name: &str,
+    arguments: &[u8],
+    gas: Gas,
+    gas_weight: u64,
+) -> (PromiseIndex, DataId) {
+    unsafe {
+        let promise_index = sys::promise_yield_create(
+            function_name.len() as _,
+            function_name.as_ptr() as _,
+            arguments.len() as _,
+            arguments.as_ptr() as _,
+            gas.0,
+            gas_weight,
+            ATOMIC_OP_REGISTER,
+        );
+        (promise_index, read_register_fixed_32(ATOMIC_OP_REGISTER))
+    }
+}
+
+/// Resumes the promise created with [`promise_yield_create`] which waits for `data_id`, passing
+/// `payload` to the function it calls. This can be called by any function of the contract which
+/// created the promise, in a later receipt.
+///
+/// Returns `false` if there is no promise waiting for `data_id`, because it was already resumed
+/// or it timed out.
+#[cfg(feature = "unstable")]
+pub fn promise_yield_resume(data_id: &DataId, payload: &[u8]) -> bool {
+    unsafe {
+        sys::promise_yield_resume(
+            data_id.len() as _,
+            data_id.as_ptr() as _,
+            payload.len() as _,
+            payload.as_ptr() as _,
+        ) == 1
+    }
+}
+
 // ###############
 // # Validator API #
 // ###############
@@ -565,9 +919,24 @@ pub fn validator_total_stake() -> Balance {
 // # Miscellaneous API #
 // #####################
 /// Sets the blob of data as the return value of the contract.
+///
+/// Panics with a descriptive message if the value is too large to be returned, see
+/// [`assert_return_size`].
 pub fn value_return(value: &[u8]) {
+    assert_return_size(value);
     unsafe { sys::value_return(value.len() as _, value.as_ptr() as _) }
 }
+/// Panics if `value` is longer than [`MAX_RETURN_DATA_LENGTH`](crate::MAX_RETURN_DATA_LENGTH),
+/// which would otherwise fail the function call with an opaque runtime error when it is returned.
+pub fn assert_return_size(value: &[u8]) {
+    if value.len() > crate::MAX_RETURN_DATA_LENGTH {
+        panic_str(&format!(
+            "Return value of {} bytes exceeds the maximum length of {} bytes",
+            value.len(),
+            crate::MAX_RETURN_DATA_LENGTH
+        ))
+    }
+}
 /// Terminates the execution of the program with the UTF-8 encoded message.
 /// [`panic_str`] should be used as the bytes are required to be UTF-8
 #[deprecated(since = "4.0.0", note = "Use env::panic_str to panic with a message.")]
@@ -604,6 +973,66 @@ pub fn log_str(message: &str) {
     unsafe { sys::log_utf8(message.len() as _, message.as_ptr() as _) }
 }
 
+/// Logs an event in the format of the [NEP-297] standard, which is the `EVENT_JSON:` prefix
+/// followed by a JSON object with the `standard`, `version` and `event` names and the `data` of
+/// the event. Indexers read events from the logs of receipts.
+///
+/// See [`emit_event_raw`] for data which is already serialized to JSON.
+///
+/// # Panics
+///
+/// Panics if `data` can't be serialized to JSON.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{env, test_utils::get_logs};
+/// use serde_json::json;
+///
+/// env::emit_event("nep171", "1.0.0", "nft_mint", &[json!({"owner_id": "alice.near"})]);
+/// assert_eq!(
+///     get_logs(),
+///     [r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[{"owner_id":"alice.near"}]}"#]
+/// );
+/// ```
+///
+/// [NEP-297]: https://nomicon.io/Standards/EventsFormat
+pub fn emit_event<T>(standard: &str, version: &str, event: &str, data: &T)
+where
+    T: serde::Serialize + ?Sized,
+{
+    let data = serde_json::to_string(data)
+        .unwrap_or_else(|e| panic_str(&format!("Failed to serialize event data to JSON: {}", e)));
+    emit_event_raw(standard, version, event, &data);
+}
+
+/// Logs an event in the format of the [NEP-297] standard like [`emit_event`], with `data` which
+/// is already serialized to JSON. `data` is inserted in the event as it is, so it must be valid
+/// JSON for the event to be read by indexers.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{env, test_utils::get_logs};
+///
+/// env::emit_event_raw("nep141", "1.0.0", "ft_burn", r#"[{"amount":"5"}]"#);
+/// assert_eq!(
+///     get_logs(),
+///     [r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_burn","data":[{"amount":"5"}]}"#]
+/// );
+/// ```
+///
+/// [NEP-297]: https://nomicon.io/Standards/EventsFormat
+pub fn emit_event_raw(standard: &str, version: &str, event: &str, data: &str) {
+    // Serializing the names as JSON strings escapes them.
+    let name = |name: &str| serde_json::to_string(name).unwrap_or_else(|_| abort());
+    log_str(&format!(
+        "EVENT_JSON:{{\"standard\":{},\"version\":{},\"event\":{},\"data\":{}}}",
+        name(standard),
+        name(version),
+        name(event),
+        data
+    ));
+}
+
 /// Log the UTF-8 encodable message.
 #[deprecated(since = "4.0.0", note = "Use env::log_str for logging messages.")]
 pub fn log(message: &[u8]) {
@@ -662,6 +1091,31 @@ pub fn storage_has_key(key: &[u8]) -> bool {
         _ => abort(),
     }
 }
+/// Writes all key-value pairs of `entries` into storage, in order, replacing existing values.
+/// This is the same as calling [`storage_write`] for every entry, for contracts writing raw
+/// storage in bulk, such as migrations. After the batch, [`storage_get_evicted`] returns the value
+/// evicted by the last entry which replaced one.
+///
+/// # Examples
+/// ```
+/// use near_sdk::env;
+///
+/// env::storage_write_batch(&[(b"a", b"1"), (b"b", b"2")]);
+/// assert_eq!(
+///     env::storage_read_batch(&[&b"a"[..], b"b", b"c"]),
+///     [Some(b"1".to_vec()), Some(b"2".to_vec()), None]
+/// );
+/// ```
+pub fn storage_write_batch<K: AsRef<[u8]>, V: AsRef<[u8]>>(entries: &[(K, V)]) {
+    for (key, value) in entries {
+        storage_write(key.as_ref(), value.as_ref());
+    }
+}
+/// Reads the values stored under each of `keys`, returning them in the same order. This is the
+/// same as calling [`storage_read`] for every key.
+pub fn storage_read_batch<K: AsRef<[u8]>>(keys: &[K]) -> Vec<Option<Vec<u8>>> {
+    keys.iter().map(|key| storage_read(key.as_ref())).collect()
+}
 
 // ############################################
 // # Saving and loading of the contract state #
@@ -734,10 +1188,75 @@ mod tests {
     use crate::test_utils::test_env;
     use hex::FromHex;
     use serde::de::Error;
-    use serde::{Deserialize, Deserializer};
+    use serde::{Deserialize, Deserializer, Serialize};
     use serde_json::from_slice;
     use std::fmt::Display;
 
+    #[test]
+    fn storage_batch() {
+        storage_write(b"b", b"old");
+        storage_write_batch(&[(&b"a"[..], &b"1"[..]), (b"b", b"2"), (b"a", b"3")]);
+        assert_eq!(storage_get_evicted(), Some(b"1".to_vec()));
+        assert_eq!(
+            storage_read_batch(&[b"a", b"b", b"c"]),
+            [Some(b"3".to_vec()), Some(b"2".to_vec()), None]
+        );
+        storage_write_batch::<&[u8], &[u8]>(&[]);
+        assert!(storage_read_batch::<&[u8]>(&[]).is_empty());
+    }
+
+    #[test]
+    fn read_register_typed() {
+        let mut context = crate::test_utils::VMContextBuilder::new();
+        context.context.input = borsh::BorshSerialize::try_to_vec(&(5u32, true)).unwrap();
+        crate::testing_env!(context.build());
+        let register_id = 3;
+        unsafe { sys::input(register_id) };
+
+        assert_eq!(register_len(register_id), Some(5));
+        assert_eq!(super::read_register_typed::<(u32, bool)>(register_id), Some((5, true)));
+        assert_eq!(read_register_array::<5>(register_id), Some([5, 0, 0, 0, 1]));
+        let mut buffer = Vec::with_capacity(16);
+        assert!(read_register_into(register_id, &mut buffer));
+        assert_eq!(buffer, [5, 0, 0, 0, 1]);
+        assert!(buffer.capacity() >= 16);
+
+        // The reused buffer shrinks and grows with the register.
+        unsafe { sys::sha256(1, [7u8].as_ptr() as _, register_id + 2) };
+        let hash = super::read_register_typed::<[u8; 32]>(register_id + 2).unwrap();
+        assert_eq!(hash, sha256_array(&[7]));
+        assert_eq!(super::read_register_typed::<(u32, bool)>(register_id), Some((5, true)));
+
+        assert_eq!(super::read_register_typed::<u8>(register_id + 1), None);
+        assert_eq!(read_register_array::<5>(register_id + 1), None);
+        assert!(!read_register_into(register_id + 1, &mut buffer));
+        assert!(buffer.is_empty());
+    }
+
+    #[cfg(feature = "bls12381")]
+    #[test]
+    fn bls12381_encoding() {
+        use crate::types::{Bls12381G1Point, Bls12381G2Point};
+
+        let p = Bls12381G1Point::from([1; 96]);
+        let q = Bls12381G1Point::from([2; 96]);
+        let input = bls12381_sum_input(&[(false, p), (true, q)]);
+        assert_eq!(input, [&[0][..], &[1; 96], &[1], &[2; 96]].concat());
+
+        let input = bls12381_pairs_input(&[(p, [3u8; 32]), (q, [4; 32])]);
+        assert_eq!(input, [&[1; 96][..], &[3; 32], &[2; 96], &[4; 32]].concat());
+        let g2 = Bls12381G2Point::from([5; 192]);
+        assert_eq!(bls12381_pairs_input(&[(p, g2)]), [&[1; 96][..], &[5; 192]].concat());
+        assert!(bls12381_sum_input::<Bls12381G1Point>(&[]).is_empty());
+
+        let output = [[1; 96], [2; 96]].concat();
+        assert_eq!(bls12381_points(&output, Bls12381G1Point::LEN), Some(vec![p, q]));
+        assert_eq!(bls12381_points::<Bls12381G1Point>(&[], Bls12381G1Point::LEN), Some(vec![]));
+        assert_eq!(bls12381_points::<Bls12381G1Point>(&output[1..], Bls12381G1Point::LEN), None);
+        assert_eq!(bls12381_points::<Bls12381G2Point>(&output[..96], Bls12381G2Point::LEN), None);
+        assert_eq!(bls12381_point::<Bls12381G2Point>([5; 192].to_vec()), g2);
+    }
+
     #[test]
     fn test_is_valid_account_id_strings() {
         // Valid
@@ -874,6 +1393,28 @@ mod tests {
             .and_then(|v| v)
     }
 
+    #[test]
+    fn test_input_deserialization() {
+        use crate::test_utils::VMContextBuilder;
+        use crate::testing_env;
+
+        #[derive(Deserialize, borsh::BorshDeserialize, borsh::BorshSerialize, PartialEq, Debug)]
+        struct Args {
+            a: u32,
+            b: String,
+        }
+
+        let mut context = VMContextBuilder::new();
+        context.context.input = br#"{"a": 1, "b": "two"}"#.to_vec();
+        testing_env!(context.build());
+        assert_eq!(input_json::<Args>(), Args { a: 1, b: "two".to_string() });
+
+        let args = Args { a: 3, b: "four".to_string() };
+        context.context.input = borsh::BorshSerialize::try_to_vec(&args).unwrap();
+        testing_env!(context.build());
+        assert_eq!(input_borsh::<Args>(), args);
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     #[cfg(feature = "unstable")]
     #[test]
@@ -885,4 +1426,123 @@ mod tests {
             assert_eq!(super::ecrecover(&m, &sig, v, mc), res);
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_ecrecover_typed() {
+        use near_crypto::{KeyType, SecretKey, Signature};
+
+        let secret_key = SecretKey::from_seed(KeyType::SECP256K1, "alice");
+        let public_key = secret_key.public_key();
+        let hash = super::keccak256_array(b"order");
+        let signature = match secret_key.sign(&hash) {
+            Signature::SECP256K1(signature) => <[u8; 65]>::from(signature),
+            _ => unreachable!(),
+        };
+        let mut rs = [0; 64];
+        rs.copy_from_slice(&signature[..64]);
+        let v = signature[64];
+
+        let recovered = super::ecrecover_typed(&hash, &rs, v, true).unwrap();
+        assert_eq!(recovered.as_bytes()[..], *public_key.key_data());
+        assert_eq!(super::ecrecover_typed(&hash, &rs, v + 27, true), Some(recovered));
+        assert_ne!(super::ecrecover_typed(&hash, &rs, 1 - v, true), Some(recovered));
+        assert_ne!(super::ecrecover_typed(&[0; 32], &rs, v, true), Some(recovered));
+
+        let pk = crate::PublicKey::from(recovered);
+        assert_eq!(
+            crate::AccountId::from_secp256k1_pubkey(&pk).unwrap().as_str(),
+            recovered.eth_address_hex()
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_ed25519_verify() {
+        use near_crypto::{KeyType, SecretKey, Signature};
+
+        let secret_key = SecretKey::from_seed(KeyType::ED25519, "alice");
+        let public_key = match secret_key.public_key() {
+            near_crypto::PublicKey::ED25519(key) => Ed25519PublicKey::from(key.0),
+            _ => unreachable!(),
+        };
+        let signature = match secret_key.sign(b"order") {
+            Signature::ED25519(signature) => Ed25519Signature::from(signature.to_bytes()),
+            _ => unreachable!(),
+        };
+        assert!(super::ed25519_verify(&signature, b"order", &public_key));
+        assert!(!super::ed25519_verify(&signature, b"orders", &public_key));
+
+        let mut forged = *signature.as_bytes();
+        forged[0] ^= 1;
+        assert!(!super::ed25519_verify(&forged.into(), b"order", &public_key));
+        let other = SecretKey::from_seed(KeyType::ED25519, "bob").public_key();
+        let other = Ed25519PublicKey::try_from(other.key_data()).unwrap();
+        assert!(!super::ed25519_verify(&signature, b"order", &other));
+    }
+
+    #[test]
+    fn emit_event_escapes_names() {
+        #[derive(Serialize)]
+        #[serde(crate = "crate::serde")]
+        struct Transfer<'a> {
+            old_owner_id: &'a str,
+            token_ids: &'a [&'a str],
+        }
+
+        emit_event(
+            "nep171",
+            "1.0.0",
+            "nft_\"transfer\"",
+            &[Transfer { old_owner_id: "alice.near", token_ids: &["1"] }],
+        );
+        emit_event("nep171", "1.0.0", "nft_burn", &());
+        let logs = crate::test_utils::get_logs();
+        assert_eq!(
+            logs[0],
+            r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_\"transfer\"","data":[{"old_owner_id":"alice.near","token_ids":["1"]}]}"#
+        );
+        let event: serde_json::Value =
+            serde_json::from_str(&logs[1]["EVENT_JSON:".len()..]).unwrap();
+        assert_eq!(event["data"], serde_json::Value::Null);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_promise_yield() {
+        use crate::mock::{VmAction, YieldedPromise};
+        use crate::test_utils::{get_created_receipts, get_yielded_promises, VMContextBuilder};
+        use crate::testing_env;
+
+        let (index, data_id) = promise_yield_create("on_response", b"{}", Gas(5), 0);
+        let (_, other_id) = promise_yield_create("on_response", b"{}", Gas(5), 0);
+        assert_ne!(data_id, other_id);
+        let receipt = &get_created_receipts()[index as usize];
+        assert_eq!(receipt.receiver_id, current_account_id());
+        assert_eq!(
+            receipt.actions,
+            [VmAction::FunctionCall {
+                function_name: "on_response".to_string(),
+                args: b"{}".to_vec(),
+                gas: Gas(5),
+                deposit: 0
+            }]
+        );
+
+        // Yielded promises are resumed in later receipts.
+        testing_env!(VMContextBuilder::new().build());
+        assert!(promise_yield_resume(&data_id, b"payload"));
+        assert!(!promise_yield_resume(&data_id, b"payload"));
+        assert!(!promise_yield_resume(&[0; 32], b"payload"));
+        assert_eq!(
+            get_yielded_promises(),
+            [
+                YieldedPromise { data_id, payload: Some(b"payload".to_vec()) },
+                YieldedPromise { data_id: other_id, payload: None }
+            ]
+        );
+    }
 }
@@ -1,3 +1,7 @@
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
 use crate::env;
 
 mod private {
@@ -6,12 +10,14 @@ mod private {
 
     impl Sealed for super::Sha256 {}
     impl Sealed for super::Keccak256 {}
+    impl Sealed for super::Keccak512 {}
+    impl Sealed for super::Ripemd160 {}
 }
 
 /// Cryptographic hashes that can be used within the SDK as a hashing function.
 pub trait CryptoHasher: self::private::Sealed {
     /// Output type of the hashing function.
-    type Digest;
+    type Digest: AsRef<[u8]>;
 
     /// Hashes raw bytes and returns the `Digest` output.
     fn hash(ingest: &[u8]) -> Self::Digest;
@@ -42,3 +48,93 @@ impl CryptoHasher for Keccak256 {
         env::keccak256_array(ingest)
     }
 }
+
+/// Keccak512 hash helper which hashes through a syscall. This type satisfies the [`CryptoHasher`]
+/// trait, and can be used as the hasher of [`LookupMap`](crate::store::LookupMap) and
+/// [`LookupSet`](crate::store::LookupSet).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Keccak512 {}
+
+impl CryptoHasher for Keccak512 {
+    type Digest = Keccak512Digest;
+
+    fn hash(ingest: &[u8]) -> Self::Digest {
+        Keccak512Digest(env::keccak512_array(ingest))
+    }
+}
+
+/// Ripemd160 hash helper which hashes through a syscall. This type satisfies the
+/// [`CryptoHasher`] trait, and can be used as the hasher of
+/// [`LookupMap`](crate::store::LookupMap) and [`LookupSet`](crate::store::LookupSet).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Ripemd160 {}
+
+impl CryptoHasher for Ripemd160 {
+    type Digest = Ripemd160Digest;
+
+    fn hash(ingest: &[u8]) -> Self::Digest {
+        Ripemd160Digest(env::ripemd160_array(ingest))
+    }
+}
+
+/// Output of the [`Keccak512`] hasher. It is displayed as lowercase hex.
+///
+/// # Examples
+/// ```
+/// use near_sdk::crypto_hash::{CryptoHasher, Keccak512};
+///
+/// let digest = Keccak512::hash(b"some value");
+/// assert_eq!(digest.to_string().len(), 128);
+/// assert_eq!(digest.as_bytes()[..], near_sdk::env::keccak512(b"some value")[..]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, BorshSerialize, BorshDeserialize)]
+pub struct Keccak512Digest([u8; 64]);
+
+/// Output of the [`Ripemd160`] hasher. It is displayed as lowercase hex.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize,
+)]
+pub struct Ripemd160Digest([u8; 20]);
+
+macro_rules! impl_digest {
+    ($name:ident, $len:expr) => {
+        impl $name {
+            /// Returns the bytes of the digest.
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            /// Returns the base58 encoding of the digest.
+            pub fn to_base58(&self) -> String {
+                bs58::encode(&self.0).into_string()
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl From<$name> for [u8; $len] {
+            fn from(digest: $name) -> Self {
+                digest.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+        }
+    };
+}
+
+impl_digest!(Keccak512Digest, 64);
+impl_digest!(Ripemd160Digest, 20);
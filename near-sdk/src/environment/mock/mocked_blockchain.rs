@@ -83,6 +83,11 @@ impl MockedBlockchain {
         std::mem::take(&mut self.logic_fixture.ext.fake_trie)
     }
 
+    /// Replaces the storage of the blockchain, returning the previous storage.
+    pub fn set_storage(&mut self, storage: HashMap<Vec<u8>, Vec<u8>>) -> HashMap<Vec<u8>, Vec<u8>> {
+        std::mem::replace(&mut self.logic_fixture.ext.fake_trie, storage)
+    }
+
     pub fn created_receipts(&self) -> &Vec<Receipt> {
         &self.logic_fixture.ext.receipts
     }
@@ -209,6 +214,89 @@ mod mock_chain {
         })
     }
     #[no_mangle]
+    extern "C" fn ed25519_verify(
+        sig_len: u64,
+        sig_ptr: u64,
+        msg_len: u64,
+        msg_ptr: u64,
+        pub_key_len: u64,
+        pub_key_ptr: u64,
+    ) -> u64 {
+        use ed25519_dalek::Verifier;
+        use std::convert::TryFrom;
+
+        // Not implemented by this version of `VMLogic`, so the signature is verified here. The
+        // pointers are addresses in the memory of the test, like for the mocked memory.
+        let read = |len, ptr| unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) };
+        let (signature, message, public_key) =
+            (read(sig_len, sig_ptr), read(msg_len, msg_ptr), read(pub_key_len, pub_key_ptr));
+        let signature = match ed25519_dalek::Signature::try_from(signature) {
+            Ok(signature) => signature,
+            Err(_) => return 0,
+        };
+        match ed25519_dalek::PublicKey::from_bytes(public_key) {
+            Ok(public_key) => public_key.verify(message, &signature).is_ok() as u64,
+            Err(_) => 0,
+        }
+    }
+    #[no_mangle]
+    extern "C" fn promise_yield_create(
+        function_name_len: u64,
+        function_name_ptr: u64,
+        arguments_len: u64,
+        arguments_ptr: u64,
+        gas: u64,
+        _gas_weight: u64,
+        register_id: u64,
+    ) -> u64 {
+        use crate::mock::{with_yielded_promises, YieldedPromise};
+
+        // Not implemented by this version of `VMLogic`, so the promise is created as a function
+        // call to the current account, which is recorded with the yielded promises to be resumed.
+        let account_id = crate::env::current_account_id();
+        let account_id = account_id.as_str();
+        let promise_index = with_mock_interface(|b| {
+            b.promise_batch_create(account_id.len() as _, account_id.as_ptr() as _)
+        });
+        let amount = 0u128.to_le_bytes();
+        with_mock_interface(|b| {
+            b.promise_batch_action_function_call(
+                promise_index,
+                function_name_len,
+                function_name_ptr,
+                arguments_len,
+                arguments_ptr,
+                amount.as_ptr() as _,
+                gas,
+            )
+        });
+        let count = with_yielded_promises(|promises| promises.len() as u64);
+        let data_id = crate::env::sha256_array(&[b"yield", &count.to_le_bytes()[..]].concat());
+        with_yielded_promises(|promises| promises.push(YieldedPromise { data_id, payload: None }));
+        with_mock_interface(|b| b.write_register(register_id, 32, data_id.as_ptr() as _));
+        promise_index
+    }
+    #[no_mangle]
+    extern "C" fn promise_yield_resume(
+        data_id_len: u64,
+        data_id_ptr: u64,
+        payload_len: u64,
+        payload_ptr: u64,
+    ) -> u32 {
+        // The pointers are addresses in the memory of the test, like for the mocked memory.
+        let read = |len, ptr| unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) };
+        let (data_id, payload) = (read(data_id_len, data_id_ptr), read(payload_len, payload_ptr));
+        crate::mock::with_yielded_promises(|promises| {
+            match promises.iter_mut().find(|p| p.data_id[..] == *data_id && p.payload.is_none()) {
+                Some(promise) => {
+                    promise.payload = Some(payload.to_vec());
+                    1
+                }
+                None => 0,
+            }
+        })
+    }
+    #[no_mangle]
     extern "C" fn value_return(value_len: u64, value_ptr: u64) {
         with_mock_interface(|b| b.value_return(value_len, value_ptr))
     }
@@ -457,4 +545,71 @@ mod mock_chain {
     extern "C" fn validator_total_stake(stake_ptr: u64) {
         with_mock_interface(|b| b.validator_total_stake(stake_ptr))
     }
+
+    // BLS12-381 host functions are not implemented by this version of `VMLogic`.
+    #[cfg(feature = "bls12381")]
+    fn bls12381_unsupported(name: &str) -> ! {
+        panic!("{} is not supported by the mocked blockchain", name)
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_p1_sum(_value_len: u64, _value_ptr: u64, _register_id: u64) -> u64 {
+        bls12381_unsupported("bls12381_p1_sum")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_p2_sum(_value_len: u64, _value_ptr: u64, _register_id: u64) -> u64 {
+        bls12381_unsupported("bls12381_p2_sum")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_g1_multiexp(_value_len: u64, _value_ptr: u64, _register_id: u64) -> u64 {
+        bls12381_unsupported("bls12381_g1_multiexp")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_g2_multiexp(_value_len: u64, _value_ptr: u64, _register_id: u64) -> u64 {
+        bls12381_unsupported("bls12381_g2_multiexp")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_map_fp_to_g1(
+        _value_len: u64,
+        _value_ptr: u64,
+        _register_id: u64,
+    ) -> u64 {
+        bls12381_unsupported("bls12381_map_fp_to_g1")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_map_fp2_to_g2(
+        _value_len: u64,
+        _value_ptr: u64,
+        _register_id: u64,
+    ) -> u64 {
+        bls12381_unsupported("bls12381_map_fp2_to_g2")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_p1_decompress(
+        _value_len: u64,
+        _value_ptr: u64,
+        _register_id: u64,
+    ) -> u64 {
+        bls12381_unsupported("bls12381_p1_decompress")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_p2_decompress(
+        _value_len: u64,
+        _value_ptr: u64,
+        _register_id: u64,
+    ) -> u64 {
+        bls12381_unsupported("bls12381_p2_decompress")
+    }
+    #[cfg(feature = "bls12381")]
+    #[no_mangle]
+    extern "C" fn bls12381_pairing_check(_value_len: u64, _value_ptr: u64) -> u64 {
+        bls12381_unsupported("bls12381_pairing_check")
+    }
 }
@@ -5,7 +5,7 @@ mod receipt;
 pub(crate) use self::external::SdkExternal;
 pub use self::mocked_blockchain::MockedBlockchain;
 pub use self::receipt::{Receipt, VmAction};
-use crate::AccountId;
+use crate::{AccountId, DataId};
 use core::cell::RefCell;
 use near_primitives_core::account::id::ParseAccountError;
 
@@ -14,6 +14,25 @@ thread_local! {
     /// `testing_env` for interacting with the real and fake blockchains.
     static BLOCKCHAIN_INTERFACE: RefCell<MockedBlockchain>
          = RefCell::new(MockedBlockchain::default());
+    /// Promises created with `env::promise_yield_create`, which are kept when the blockchain
+    /// interface is replaced, as they can be resumed in later receipts.
+    static YIELDED_PROMISES: RefCell<Vec<YieldedPromise>> = Default::default();
+}
+
+/// Promise created with `env::promise_yield_create` in unit tests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct YieldedPromise {
+    /// ID of the data the promise waits for.
+    pub data_id: DataId,
+    /// Payload the promise was resumed with, or `None` if it was not resumed.
+    pub payload: Option<Vec<u8>>,
+}
+
+pub(crate) fn with_yielded_promises<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut Vec<YieldedPromise>) -> R,
+{
+    YIELDED_PROMISES.with(|promises| f(&mut promises.borrow_mut()))
 }
 
 /// Perform function on a mutable reference to the [`MockedBlockchain`]. This can only be used
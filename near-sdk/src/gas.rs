@@ -0,0 +1,122 @@
+//! Measurement of the gas burned by parts of a contract call.
+
+use std::cell::Cell;
+
+use crate::{env, Gas};
+
+thread_local! {
+    /// Number of [`GasGuard`]s which were started and not dropped, used to indent nested scopes.
+    static DEPTH: Cell<usize> = Default::default();
+}
+
+/// Guard which logs the gas burned between its creation with [`GasGuard::start`] and its drop,
+/// as measured by [`env::used_gas`].
+///
+/// Guards started while another guard is alive are nested scopes of it, and their logs are
+/// indented by two spaces per level. The log is written in the format `{label}: {gas} gas`, and
+/// the gas burned by writing the logs of nested scopes is included in the gas of the outer scope.
+///
+/// # Examples
+/// ```
+/// use near_sdk::gas::GasGuard;
+/// use near_sdk::test_utils::get_logs;
+///
+/// fn transfer() {
+///     let _guard = GasGuard::start("transfer");
+///     {
+///         let _guard = GasGuard::start("storage");
+///         near_sdk::env::storage_write(b"balance", &5u128.to_le_bytes());
+///     }
+/// }
+///
+/// transfer();
+/// let logs = get_logs();
+/// assert!(logs[0].starts_with("  storage: "));
+/// assert!(logs[1].starts_with("transfer: "));
+/// ```
+#[derive(Debug)]
+#[must_use = "the gas is measured until the guard is dropped"]
+pub struct GasGuard {
+    label: String,
+    start: Gas,
+    depth: usize,
+    finished: bool,
+}
+
+impl GasGuard {
+    /// Starts measuring the gas burned until the returned guard is dropped.
+    pub fn start(label: impl Into<String>) -> Self {
+        let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        Self { label: label.into(), start: env::used_gas(), depth, finished: false }
+    }
+
+    /// Returns the gas burned since the guard was started.
+    pub fn burned(&self) -> Gas {
+        env::used_gas() - self.start
+    }
+
+    /// Returns the label the guard was started with.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Stops the measurement and logs the gas burned, returning it. This is the same as dropping
+    /// the guard, except for returning the gas.
+    pub fn finish(mut self) -> Gas {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Gas {
+        let burned = self.burned();
+        self.finished = true;
+        DEPTH.with(|depth| depth.set(self.depth));
+        env::log_str(&format!(
+            "{:indent$}{}: {} gas",
+            "",
+            self.label,
+            burned.0,
+            indent = self.depth * 2
+        ));
+        burned
+    }
+}
+
+impl Drop for GasGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.stop();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::GasGuard;
+    use crate::env;
+    use crate::test_utils::get_logs;
+
+    #[test]
+    fn logs_nested_scopes() {
+        let outer = GasGuard::start("outer");
+        let first = GasGuard::start("first");
+        env::storage_write(b"a", &[1; 100]);
+        let first_gas = first.finish();
+        {
+            let _second = GasGuard::start("second".to_string());
+            let _third = GasGuard::start("third");
+        }
+        let inner_gas = outer.burned();
+        drop(outer);
+        let _after = GasGuard::start("after");
+
+        let logs = get_logs();
+        assert_eq!(logs[0], format!("  first: {} gas", first_gas.0));
+        assert!(logs[1].starts_with("    third: "));
+        assert!(logs[2].starts_with("  second: "));
+        let outer_gas: u64 =
+            logs[3].strip_prefix("outer: ").unwrap().strip_suffix(" gas").unwrap().parse().unwrap();
+        assert!(first_gas.0 > 0 && outer_gas >= inner_gas.0 && inner_gas.0 > first_gas.0);
+        assert_eq!(super::DEPTH.with(|depth| depth.get()), 1);
+    }
+}
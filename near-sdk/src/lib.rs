@@ -16,6 +16,12 @@ pub mod store;
 #[cfg(feature = "unstable")]
 pub use environment::hash as crypto_hash;
 
+#[cfg(feature = "unstable")]
+pub mod permit;
+
+#[cfg(feature = "unstable")]
+pub mod deferred;
+
 pub mod collections;
 mod environment;
 pub use environment::env;
@@ -26,11 +32,20 @@ pub use near_sys as sys;
 mod promise;
 pub use promise::{Promise, PromiseOrValue};
 
+mod tx;
+pub use tx::{Tx, TxAccessKey, TxAction, TxPermission};
+
 mod metadata;
 pub use metadata::{Metadata, MethodMetadata};
 
 pub mod json_types;
 
+pub mod log;
+
+pub mod gas;
+
+pub mod rand;
+
 mod types;
 pub use crate::types::*;
 
@@ -0,0 +1,207 @@
+//! Logging macros with levels, which can be removed at compile time.
+//!
+//! The [`error!`], [`warn!`], [`info!`], [`debug!`] and [`trace!`] macros format their arguments
+//! like [`log!`](crate::log!), and log the message through [`env::log_str`] prefixed with its
+//! level, for example `INFO: transferred 5`.
+//!
+//! Levels above the maximum level are removed at compile time, so their messages and formatting
+//! code are not included in the contract. The maximum level is set with the
+//! `log-max-level-{off,error,warn,info,debug}` features of `near-sdk`, and the
+//! `log-release-max-level-{off,error,warn,info,debug}` features set it for builds without debug
+//! assertions, such as release builds. If none of the features are enabled, all levels are kept.
+//! For example, a contract keeping only warnings and errors in release builds depends on
+//! `near-sdk` with:
+//!
+//! ```toml
+//! near-sdk = { version = "4", features = ["log-release-max-level-warn"] }
+//! ```
+//!
+//! With the `log-json` feature, messages are logged as JSON objects instead, for example
+//! `{"level":"info","message":"transferred 5"}`.
+//!
+//! # Examples
+//! ```
+//! use near_sdk::log::{debug, info};
+//!
+//! let amount = 5;
+//! info!("transferred {}", amount);
+//! debug!("amount = {}", amount);
+//! ```
+
+use std::fmt;
+
+use crate::env;
+
+/// Level of a log message, from the most to the least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// Returns the name of the level in uppercase, which prefixes the messages of the level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Maximum level kept when debug assertions are enabled, as a [`Level`] discriminant, where `0`
+/// disables logging.
+const MAX_LEVEL: u8 = if cfg!(feature = "log-max-level-off") {
+    0
+} else if cfg!(feature = "log-max-level-error") {
+    Level::Error as u8
+} else if cfg!(feature = "log-max-level-warn") {
+    Level::Warn as u8
+} else if cfg!(feature = "log-max-level-info") {
+    Level::Info as u8
+} else if cfg!(feature = "log-max-level-debug") {
+    Level::Debug as u8
+} else {
+    Level::Trace as u8
+};
+
+/// Maximum level kept when debug assertions are disabled.
+const RELEASE_MAX_LEVEL: u8 = if cfg!(feature = "log-release-max-level-off") {
+    0
+} else if cfg!(feature = "log-release-max-level-error") {
+    Level::Error as u8
+} else if cfg!(feature = "log-release-max-level-warn") {
+    Level::Warn as u8
+} else if cfg!(feature = "log-release-max-level-info") {
+    Level::Info as u8
+} else if cfg!(feature = "log-release-max-level-debug") {
+    Level::Debug as u8
+} else {
+    MAX_LEVEL
+};
+
+/// Returns `true` if messages of `level` are kept. `debug_assertions` is passed by the macros, as
+/// it has to be checked in the crate which logs.
+#[doc(hidden)]
+pub const fn __enabled(level: Level, debug_assertions: bool) -> bool {
+    let max_level = if debug_assertions { MAX_LEVEL } else { RELEASE_MAX_LEVEL };
+    level as u8 <= max_level
+}
+
+/// Logs a formatted message of `level`. The formatting is done here rather than in the macros,
+/// to not repeat it at every call site.
+#[doc(hidden)]
+pub fn __log(level: Level, args: fmt::Arguments) {
+    if cfg!(feature = "log-json") {
+        let message = serde_json::json!({
+            "level": level.as_str().to_ascii_lowercase(),
+            "message": args.to_string(),
+        });
+        env::log_str(&message.to_string());
+    } else {
+        env::log_str(&format!("{}: {}", level, args));
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_with_level {
+    ($level:ident, $($arg:tt)+) => {{
+        const ENABLED: bool =
+            $crate::log::__enabled($crate::log::Level::$level, cfg!(debug_assertions));
+        if ENABLED {
+            $crate::log::__log($crate::log::Level::$level, format_args!($($arg)+));
+        }
+    }};
+}
+
+/// Logs a message of the [`Error`](Level::Error) level. See the [module documentation](crate::log).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_error {
+    ($($arg:tt)+) => {
+        $crate::__log_with_level!(Error, $($arg)+)
+    };
+}
+
+/// Logs a message of the [`Warn`](Level::Warn) level. See the [module documentation](crate::log).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_warn {
+    ($($arg:tt)+) => {
+        $crate::__log_with_level!(Warn, $($arg)+)
+    };
+}
+
+/// Logs a message of the [`Info`](Level::Info) level. See the [module documentation](crate::log).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_info {
+    ($($arg:tt)+) => {
+        $crate::__log_with_level!(Info, $($arg)+)
+    };
+}
+
+/// Logs a message of the [`Debug`](Level::Debug) level. See the [module documentation](crate::log).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_debug {
+    ($($arg:tt)+) => {
+        $crate::__log_with_level!(Debug, $($arg)+)
+    };
+}
+
+/// Logs a message of the [`Trace`](Level::Trace) level. See the [module documentation](crate::log).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_trace {
+    ($($arg:tt)+) => {
+        $crate::__log_with_level!(Trace, $($arg)+)
+    };
+}
+
+pub use crate::__log_debug as debug;
+pub use crate::__log_error as error;
+pub use crate::__log_info as info;
+pub use crate::__log_trace as trace;
+pub use crate::__log_warn as warn;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::{debug, error, info, trace, warn, Level};
+    use crate::test_utils::get_logs;
+
+    #[test]
+    fn logs_with_level() {
+        let amount = 5;
+        error!("failed");
+        warn!("amount is {}", amount);
+        info!("amount = {amount}", amount = amount);
+        debug!("{}", "debug");
+        trace!("trace");
+        assert_eq!(
+            get_logs(),
+            [
+                "ERROR: failed",
+                "WARN: amount is 5",
+                "INFO: amount = 5",
+                "DEBUG: debug",
+                "TRACE: trace"
+            ]
+        );
+        assert!(Level::Error < Level::Trace);
+    }
+}
@@ -0,0 +1,270 @@
+//! Signed authorizations of actions, which can be submitted to a contract by a third party on
+//! behalf of the signer, such as approving a transfer allowance without the signer sending a
+//! transaction.
+//!
+//! A [`Permit`] is signed off-chain with an ED25519 or SECP256K1 key over its
+//! [`hash`](Permit::hash), which binds it to the contract it is submitted to. The contract
+//! verifies it with [`PermitNonces`], which keeps the nonce of every signer in storage so each
+//! permit can only be used once.
+//!
+//! ED25519 signatures are verified with [`env::ed25519_verify`], and SECP256K1 signatures with
+//! [`env::ecrecover`].
+
+use crate::json_types::{Base64VecU8, U64};
+use crate::store::LookupMap;
+use crate::{
+    env, AccountId, CurveType, Ed25519PublicKey, Ed25519Signature, IntoStorageKey, PublicKey,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Prefix of the signed message, to separate permits from other data signed with the same key.
+const PERMIT_DOMAIN: &str = "near-sdk:permit";
+
+/// Authorization of `action`, signed by `signer`, which expires at `expires_at`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde")]
+pub struct Permit<A> {
+    /// ED25519 or SECP256K1 public key of the signer.
+    pub signer: PublicKey,
+    /// Nonce of the signer, which has to be the next nonce of [`PermitNonces::next_nonce`].
+    pub nonce: U64,
+    /// Block timestamp in nanoseconds from which the permit can no longer be used.
+    pub expires_at: U64,
+    /// Action authorized by the signer.
+    pub action: A,
+}
+
+impl<A: BorshSerialize> Permit<A> {
+    /// Returns the keccak256 hash of the permit for the contract `contract_id`, which is signed by
+    /// the signer.
+    pub fn hash(&self, contract_id: &AccountId) -> [u8; 32] {
+        let message = (
+            PERMIT_DOMAIN,
+            contract_id,
+            &self.signer,
+            self.nonce.0,
+            self.expires_at.0,
+            &self.action,
+        )
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize the permit"));
+        env::keccak256_array(&message)
+    }
+}
+
+/// [`Permit`] with the signature of its hash by the signer, which is the 64 byte ED25519
+/// signature, or the 65 byte recoverable SECP256K1 signature where the last byte is the recovery
+/// ID.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde")]
+pub struct SignedPermit<A> {
+    pub permit: Permit<A>,
+    pub signature: Base64VecU8,
+}
+
+/// Reason a [`SignedPermit`] could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PermitError {
+    /// The permit has expired.
+    Expired,
+    /// The nonce of the permit is not the next nonce of the signer, either because the permit
+    /// was already used or because earlier permits have not been used yet.
+    InvalidNonce { expected: u64, found: u64 },
+    /// The signature is not valid for the permit and signer.
+    InvalidSignature,
+}
+
+impl fmt::Display for PermitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expired => write!(f, "permit has expired"),
+            Self::InvalidNonce { expected, found } => {
+                write!(f, "invalid permit nonce, expected {} found {}", expected, found)
+            }
+            Self::InvalidSignature => write!(f, "invalid permit signature"),
+        }
+    }
+}
+
+impl std::error::Error for PermitError {}
+
+/// Nonces of the signers of permits, which protect against permits being used more than once.
+///
+/// # Examples
+/// ```
+/// use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+/// use near_sdk::permit::{PermitNonces, SignedPermit};
+/// use near_sdk::{env, AccountId};
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct Allowance {
+///     spender: AccountId,
+///     amount: u128,
+/// }
+///
+/// fn approve_with_permit(nonces: &mut PermitNonces, signed: SignedPermit<Allowance>) {
+///     nonces.consume(&signed).unwrap_or_else(|e| env::panic_str(&e.to_string()));
+///     let Allowance { spender, amount } = signed.permit.action;
+///     // Approve `amount` for `spender` on behalf of `signed.permit.signer`.
+/// }
+/// ```
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct PermitNonces {
+    nonces: LookupMap<PublicKey, u64>,
+}
+
+impl PermitNonces {
+    /// Creates the nonces with the storage `prefix`, which has to be unique within the contract.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { nonces: LookupMap::new(prefix) }
+    }
+
+    /// Returns the nonce the next permit of `signer` has to use.
+    pub fn next_nonce(&self, signer: &PublicKey) -> u64 {
+        self.nonces.get(signer).copied().unwrap_or_default()
+    }
+
+    /// Checks that the permit can be used, without using it.
+    pub fn verify<A: BorshSerialize>(&self, signed: &SignedPermit<A>) -> Result<(), PermitError> {
+        let permit = &signed.permit;
+        if env::block_timestamp() >= permit.expires_at.0 {
+            return Err(PermitError::Expired);
+        }
+        let expected = self.next_nonce(&permit.signer);
+        if permit.nonce.0 != expected {
+            return Err(PermitError::InvalidNonce { expected, found: permit.nonce.0 });
+        }
+
+        let signature = &signed.signature.0;
+        let hash = permit.hash(&env::current_account_id());
+        let is_valid = match permit.signer.curve_type() {
+            CurveType::ED25519 => {
+                match (
+                    Ed25519Signature::try_from(&signature[..]),
+                    Ed25519PublicKey::try_from(&permit.signer),
+                ) {
+                    (Ok(signature), Ok(public_key)) => {
+                        env::ed25519_verify(&signature, &hash, &public_key)
+                    }
+                    _ => false,
+                }
+            }
+            CurveType::SECP256K1 => {
+                signature.len() == 65
+                    && matches!(
+                        env::ecrecover(&hash, &signature[..64], signature[64], true),
+                        Some(key) if key[..] == permit.signer.as_bytes()[1..]
+                    )
+            }
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(PermitError::InvalidSignature)
+        }
+    }
+
+    /// Checks that the permit can be used, and increments the nonce of the signer so that it
+    /// cannot be used again.
+    pub fn consume<A: BorshSerialize>(
+        &mut self,
+        signed: &SignedPermit<A>,
+    ) -> Result<(), PermitError> {
+        self.verify(signed)?;
+        let signer = signed.permit.signer.clone();
+        *self.nonces.entry(signer).or_default() += 1;
+        Ok(())
+    }
+
+    /// Writes any changes to the nonces to storage. This is done automatically when the nonces
+    /// are dropped.
+    pub fn flush(&mut self) {
+        self.nonces.flush()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::VMContextBuilder;
+    use crate::testing_env;
+    use near_crypto::{KeyType, SecretKey, Signature};
+
+    fn sign(key: &SecretKey, nonce: u64, expires_at: u64, action: &str) -> SignedPermit<String> {
+        let curve_type = match key.key_type() {
+            KeyType::ED25519 => CurveType::ED25519,
+            KeyType::SECP256K1 => CurveType::SECP256K1,
+        };
+        let signer =
+            PublicKey::try_from([&[curve_type as u8][..], key.public_key().key_data()].concat())
+                .unwrap();
+        let permit = Permit {
+            signer,
+            nonce: nonce.into(),
+            expires_at: expires_at.into(),
+            action: action.to_string(),
+        };
+        let signature = match key.sign(&permit.hash(&env::current_account_id())) {
+            Signature::ED25519(signature) => signature.to_bytes().to_vec(),
+            Signature::SECP256K1(signature) => <[u8; 65]>::from(signature).to_vec(),
+        };
+        SignedPermit { permit, signature: signature.into() }
+    }
+
+    #[test]
+    fn consume_ed25519_permits() {
+        consume_permits(KeyType::ED25519);
+    }
+
+    #[test]
+    fn consume_secp256k1_permits() {
+        consume_permits(KeyType::SECP256K1);
+    }
+
+    fn consume_permits(key_type: KeyType) {
+        testing_env!(VMContextBuilder::new().block_timestamp(100).build());
+        let key = SecretKey::from_seed(key_type, "signer");
+        let other = SecretKey::from_seed(key_type, "other");
+        let mut nonces = PermitNonces::new(b"p");
+
+        let first = sign(&key, 0, 200, "first");
+        assert_eq!(nonces.consume(&first), Ok(()));
+        assert_eq!(
+            nonces.consume(&first),
+            Err(PermitError::InvalidNonce { expected: 1, found: 0 })
+        );
+        assert_eq!(nonces.next_nonce(&first.permit.signer), 1);
+
+        assert_eq!(nonces.verify(&sign(&key, 1, 100, "expired")), Err(PermitError::Expired));
+
+        // Signed by another key or for another action.
+        let mut forged = sign(&other, 1, 200, "second");
+        forged.permit.signer = first.permit.signer.clone();
+        assert_eq!(nonces.verify(&forged), Err(PermitError::InvalidSignature));
+        let mut changed = sign(&key, 1, 200, "second");
+        changed.permit.action = "changed".to_string();
+        assert_eq!(nonces.verify(&changed), Err(PermitError::InvalidSignature));
+
+        // Permits are bound to the contract they were signed for.
+        let second = sign(&key, 1, 200, "second");
+        testing_env!(VMContextBuilder::new()
+            .current_account_id("other.near".parse().unwrap())
+            .block_timestamp(100)
+            .build());
+        assert_eq!(nonces.verify(&second), Err(PermitError::InvalidSignature));
+
+        // A signature of the wrong length.
+        let mut truncated = sign(&key, 1, 200, "second");
+        assert_eq!(nonces.verify(&truncated), Ok(()));
+        truncated.signature.0.pop();
+        assert_eq!(nonces.verify(&truncated), Err(PermitError::InvalidSignature));
+    }
+}
@@ -4,6 +4,8 @@ use std::collections::HashMap;
 use std::io::{Error, Write};
 use std::rc::Rc;
 
+#[cfg(feature = "unstable")]
+use crate::DataId;
 use crate::{AccountId, Balance, Gas, PromiseIndex, PublicKey};
 
 enum PromiseAction {
@@ -206,6 +208,10 @@ impl BorshSchema for Promise {
 enum PromiseSubtype {
     Single(Rc<PromiseSingle>),
     Joint(Rc<PromiseJoint>),
+    /// Promise created with [`Promise::yield_call`], which is created eagerly to return its data
+    /// ID.
+    #[cfg(feature = "unstable")]
+    Yielded(PromiseIndex),
 }
 
 impl Promise {
@@ -228,6 +234,10 @@ impl Promise {
             PromiseSubtype::Joint(_) => {
                 crate::env::panic_str("Cannot add action to a joint promise.")
             }
+            #[cfg(feature = "unstable")]
+            PromiseSubtype::Yielded(_) => {
+                crate::env::panic_str("Cannot add action to a yielded promise.")
+            }
         }
         self
     }
@@ -314,6 +324,95 @@ impl Promise {
         self.add_action(PromiseAction::DeleteAccount { beneficiary_id })
     }
 
+    /// Deletes the current account and transfers its balance to `beneficiary_id`, with guards
+    /// against the mistakes that make deleting an account irreversibly lose funds.
+    ///
+    /// The call panics unless:
+    /// - `is_owner` returns `true` for the predecessor of the function call, which is the hook for
+    ///   the contract's own access control.
+    /// - `confirmation` is the ID of the current account, which has to be passed explicitly, for
+    ///   example as an argument to the function call, so the account cannot be deleted by mistake.
+    /// - `beneficiary_id` is not the current account, which would burn the balance.
+    ///
+    /// If the beneficiary account does not exist, the balance is still lost, which cannot be
+    /// checked within the function call.
+    ///
+    /// # Examples
+    /// ```
+    /// use near_sdk::{env, AccountId, Promise};
+    ///
+    /// # let owner_id = env::predecessor_account_id();
+    /// let beneficiary_id: AccountId = "treasury.near".parse().unwrap();
+    /// Promise::delete_current_account(beneficiary_id, "alice.near", |caller| caller == &owner_id);
+    /// ```
+    pub fn delete_current_account<F>(
+        beneficiary_id: AccountId,
+        confirmation: &str,
+        is_owner: F,
+    ) -> Self
+    where
+        F: FnOnce(&AccountId) -> bool,
+    {
+        let current_account_id = crate::env::current_account_id();
+        crate::require!(
+            is_owner(&crate::env::predecessor_account_id()),
+            "Only the owner can delete the account"
+        );
+        crate::require!(
+            confirmation == current_account_id.as_str(),
+            "Confirmation does not match the current account ID"
+        );
+        crate::require!(
+            beneficiary_id != current_account_id,
+            "Beneficiary of the deleted account cannot be the account itself"
+        );
+        Self::new(current_account_id).delete_account(beneficiary_id)
+    }
+
+    /// Creates a promise which calls `function_name` of the current account with `arguments` once
+    /// it is resumed with [`Promise::resume`], for example when an off-chain service submits the
+    /// response to a request. Returns the promise and the ID of the data it waits for, which has to
+    /// be kept to resume it. `gas` is attached to the call.
+    ///
+    /// The called function receives the payload passed to [`Promise::resume`] as the result of its
+    /// only promise, or [`PromiseResult::Failed`](crate::PromiseResult::Failed) if the promise
+    /// timed out before it was resumed. Returning the promise makes the result of the called
+    /// function the result of the current call. See [`env::promise_yield_create`] for details.
+    ///
+    /// Unlike other promises, the promise is created immediately, so actions can't be added to it
+    /// and it can't be scheduled after another promise.
+    ///
+    /// # Examples
+    /// ```
+    /// use near_sdk::{Gas, Promise};
+    ///
+    /// let (promise, data_id) = Promise::yield_call("on_response", b"{}", Gas(10_000_000_000_000));
+    /// // Store `data_id` so the request can be resumed, then return the promise.
+    /// # drop(promise);
+    /// assert!(Promise::resume(&data_id, b"\"response\""));
+    /// assert!(!Promise::resume(&data_id, b"\"response\""));
+    /// ```
+    ///
+    /// [`env::promise_yield_create`]: crate::env::promise_yield_create
+    #[cfg(feature = "unstable")]
+    pub fn yield_call(function_name: &str, arguments: &[u8], gas: Gas) -> (Self, DataId) {
+        let (promise_index, data_id) =
+            crate::env::promise_yield_create(function_name, arguments, gas, 0);
+        let promise = Self {
+            subtype: PromiseSubtype::Yielded(promise_index),
+            should_return: RefCell::new(false),
+        };
+        (promise, data_id)
+    }
+
+    /// Resumes the promise created with [`Promise::yield_call`] which waits for `data_id`, passing
+    /// `payload` to the function it calls. Returns `false` if there is no promise waiting for
+    /// `data_id`, because it was already resumed or it timed out.
+    #[cfg(feature = "unstable")]
+    pub fn resume(data_id: &DataId, payload: &[u8]) -> bool {
+        crate::env::promise_yield_resume(data_id, payload)
+    }
+
     /// Merge this promise with another promise, so that we can schedule execution of another
     /// smart contract right after all merged promises finish.
     ///
@@ -355,6 +454,8 @@ impl Promise {
         match &mut other.subtype {
             PromiseSubtype::Single(x) => *x.after.borrow_mut() = Some(self),
             PromiseSubtype::Joint(_) => crate::env::panic_str("Cannot callback joint promise."),
+            #[cfg(feature = "unstable")]
+            PromiseSubtype::Yielded(_) => crate::env::panic_str("Cannot callback yielded promise."),
         }
         other
     }
@@ -396,6 +497,8 @@ impl Promise {
         let res = match &self.subtype {
             PromiseSubtype::Single(x) => x.construct_recursively(),
             PromiseSubtype::Joint(x) => x.construct_recursively(),
+            #[cfg(feature = "unstable")]
+            PromiseSubtype::Yielded(promise_index) => *promise_index,
         };
         if *self.should_return.borrow() {
             crate::env::promise_return(res);
@@ -468,3 +571,52 @@ impl<T: borsh::BorshSerialize> borsh::BorshSerialize for PromiseOrValue<T> {
         }
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::Promise;
+    use crate::mock::VmAction;
+    use crate::test_utils::test_env::{alice, bob, carol};
+    use crate::test_utils::{get_created_receipts, VMContextBuilder};
+    use crate::testing_env;
+
+    fn setup() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(alice())
+            .predecessor_account_id(bob())
+            .build());
+    }
+
+    #[test]
+    fn delete_current_account() {
+        setup();
+        Promise::delete_current_account(carol(), "alice.near", |caller| caller == &bob());
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, alice());
+        assert_eq!(receipts[0].actions, [VmAction::DeleteAccount { beneficiary_id: carol() }]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Beneficiary of the deleted account cannot be the account itself")]
+    fn delete_current_account_to_itself() {
+        setup();
+        Promise::delete_current_account(alice(), "alice.near", |caller| caller == &bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Confirmation does not match the current account ID")]
+    fn delete_current_account_wrong_confirmation() {
+        setup();
+        Promise::delete_current_account(carol(), "bob.near", |caller| caller == &bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can delete the account")]
+    fn delete_current_account_not_owner() {
+        setup();
+        Promise::delete_current_account(carol(), "alice.near", |caller| caller == &carol());
+    }
+}
@@ -0,0 +1,210 @@
+//! Deterministic random number generation for contracts, seeded from [`env::random_seed`].
+//!
+//! [`ContractRng`] expands the random seed of the current execution into a stream of random
+//! numbers with sha256, and samples ranges without the bias of reducing a random number modulo
+//! the size of the range.
+//!
+//! # Security
+//!
+//! The random seed is derived by the protocol from the block and the receipt being executed, so
+//! it can't be predicted by users before their transaction is included in a block, and every
+//! validator computes the same numbers when executing the receipt. It is not secret though:
+//!
+//! - The block producer knows the seed before publishing the block, and can choose not to
+//!   publish a block with an unfavorable outcome. Contracts where the value of an outcome
+//!   exceeds the reward of producing a block should use a commit-reveal scheme instead.
+//! - The numbers are visible to anyone after the receipt is executed, and the seed is the same
+//!   for every [`ContractRng`] created in the same function call, unless a different nonce is
+//!   mixed in with [`ContractRng::with_nonce`].
+//! - As contract state and execution are public, random numbers must not be used to generate
+//!   secrets such as keys.
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::env;
+
+/// Deterministic random number generator seeded from [`env::random_seed`]. See the
+/// [module documentation](self) for its security model.
+///
+/// # Examples
+/// ```
+/// use near_sdk::rand::ContractRng;
+///
+/// let mut rng = ContractRng::new();
+/// let roll = rng.gen_range(1..=6);
+/// assert!((1..=6).contains(&roll));
+///
+/// let mut tickets = vec!["alice.near", "bob.near", "carol.near"];
+/// rng.shuffle(&mut tickets);
+/// let winner = rng.pick(&tickets).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContractRng {
+    seed: [u8; 32],
+    /// Index of the next block of random bytes, which is the hash of the seed and the index.
+    counter: u64,
+    block: [u8; 32],
+    /// Number of bytes of `block` which were used.
+    used: usize,
+}
+
+impl ContractRng {
+    /// Creates a generator seeded from the random seed of the current execution.
+    pub fn new() -> Self {
+        Self::with_nonce(&[])
+    }
+
+    /// Creates a generator seeded from the random seed of the current execution and `nonce`.
+    /// Generators created with different nonces produce independent numbers in the same
+    /// execution, for example when the nonce is the ID of a raffle.
+    pub fn with_nonce(nonce: &[u8]) -> Self {
+        Self::from_seed(env::sha256_array(&[&env::random_seed()[..], nonce].concat()))
+    }
+
+    /// Creates a generator from a fixed seed, which produces the same numbers in every
+    /// execution.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self { seed, counter: 0, block: [0; 32], used: 32 }
+    }
+
+    /// Fills `dest` with random bytes.
+    pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut filled = 0;
+        while filled < dest.len() {
+            if self.used == self.block.len() {
+                self.block =
+                    env::sha256_array(&[&self.seed[..], &self.counter.to_le_bytes()].concat());
+                self.counter += 1;
+                self.used = 0;
+            }
+            let len = (dest.len() - filled).min(self.block.len() - self.used);
+            dest[filled..filled + len].copy_from_slice(&self.block[self.used..self.used + len]);
+            filled += len;
+            self.used += len;
+        }
+    }
+
+    /// Returns a random `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    /// Returns a random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Returns a number sampled uniformly from `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn gen_range<R: RangeBounds<u64>>(&mut self, range: R) -> u64 {
+        let low = match range.start_bound() {
+            Bound::Included(&low) => Some(low),
+            Bound::Excluded(&low) => low.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let high = match range.end_bound() {
+            Bound::Included(&high) => Some(high),
+            Bound::Excluded(&high) => high.checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        match (low, high) {
+            (Some(low), Some(high)) if low <= high => low + self.gen_below(high - low),
+            _ => env::panic_str("Cannot sample from an empty range"),
+        }
+    }
+
+    /// Returns a number sampled uniformly from `0..=max`, rejecting the numbers which would
+    /// make smaller results more likely.
+    fn gen_below(&mut self, max: u64) -> u64 {
+        if max == u64::MAX {
+            return self.next_u64();
+        }
+        let len = max + 1;
+        // The number of values of `u64` above the largest multiple of `len`.
+        let excess = (u64::MAX % len + 1) % len;
+        loop {
+            let value = self.next_u64();
+            if value <= u64::MAX - excess {
+                return value % len;
+            }
+        }
+    }
+
+    /// Shuffles `slice` in place, with every order being equally likely.
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            slice.swap(i, self.gen_below(i as u64) as usize);
+        }
+    }
+
+    /// Returns a random element of `slice`, or [`None`] if it is empty.
+    pub fn pick<'a, T>(&mut self, slice: &'a [T]) -> Option<&'a T> {
+        if slice.is_empty() {
+            return None;
+        }
+        slice.get(self.gen_below(slice.len() as u64 - 1) as usize)
+    }
+}
+
+impl Default for ContractRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::ContractRng;
+    use crate::test_utils::VMContextBuilder;
+    use crate::testing_env;
+
+    #[test]
+    fn seeded_from_random_seed() {
+        testing_env!(VMContextBuilder::new().random_seed(vec![1; 32]).build());
+        let first = ContractRng::new().next_u64();
+        assert_eq!(ContractRng::new().next_u64(), first);
+        assert_ne!(ContractRng::with_nonce(b"raffle").next_u64(), first);
+
+        testing_env!(VMContextBuilder::new().random_seed(vec![2; 32]).build());
+        assert_ne!(ContractRng::new().next_u64(), first);
+
+        let mut rng = ContractRng::from_seed([3; 32]);
+        let mut bytes = [0; 100];
+        rng.fill_bytes(&mut bytes[..5]);
+        rng.fill_bytes(&mut bytes[5..]);
+        let mut same = [0; 100];
+        ContractRng::from_seed([3; 32]).fill_bytes(&mut same);
+        assert_eq!(bytes[..], same[..]);
+    }
+
+    #[test]
+    fn samples_uniformly() {
+        let mut rng = ContractRng::from_seed([0; 32]);
+        let mut counts = [0u32; 6];
+        for _ in 0..6000 {
+            counts[rng.gen_range(1..=6) as usize - 1] += 1;
+        }
+        assert!(counts.iter().all(|&count| (850..1150).contains(&count)), "{:?}", counts);
+
+        assert_eq!(rng.gen_range(7..8), 7);
+        assert!(rng.gen_range(u64::MAX - 1..) >= u64::MAX - 1);
+        rng.gen_range(..);
+
+        let mut values: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut values);
+        assert_ne!(values, (0..50).collect::<Vec<_>>());
+        values.sort_unstable();
+        assert_eq!(values, (0..50).collect::<Vec<_>>());
+
+        assert_eq!(rng.pick::<u8>(&[]), None);
+        assert_eq!(rng.pick(&[4]), Some(&4));
+    }
+}
@@ -0,0 +1,309 @@
+//! A priority queue implemented with a binary heap stored in a [`Vector`].
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{vec, Vector, ERR_INCONSISTENT_STATE};
+use crate::{env, IntoStorageKey};
+
+/// Ordering of the elements of a [`BinaryHeap`], which determines the element at the top of the
+/// heap.
+pub trait HeapOrder {
+    /// Returns `true` if `a` belongs closer to the top of the heap than `b`.
+    fn is_above<T: Ord>(a: &T, b: &T) -> bool;
+}
+
+/// Orders a [`BinaryHeap`] with the greatest element at the top.
+pub enum Max {}
+
+impl HeapOrder for Max {
+    fn is_above<T: Ord>(a: &T, b: &T) -> bool {
+        a > b
+    }
+}
+
+/// Orders a [`BinaryHeap`] with the least element at the top.
+pub enum Min {}
+
+impl HeapOrder for Min {
+    fn is_above<T: Ord>(a: &T, b: &T) -> bool {
+        a < b
+    }
+}
+
+/// A priority queue implemented with a binary heap, which keeps its elements in a [`Vector`].
+///
+/// The element at the top of the heap is the greatest element by default, or the least element
+/// when the order parameter is [`Min`]. Pushing and popping an element reads and writes
+/// `O(log n)` elements, and peeking reads a single element.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::binary_heap::Min;
+/// use near_sdk::store::BinaryHeap;
+///
+/// let mut heap: BinaryHeap<u32> = BinaryHeap::new(b"h");
+/// heap.push(3);
+/// heap.push(7);
+/// heap.push(5);
+/// assert_eq!(heap.peek(), Some(&7));
+/// assert_eq!(heap.pop(), Some(7));
+/// assert_eq!(heap.len(), 2);
+///
+/// // Jobs ordered by their deadline, with the earliest deadline at the top.
+/// let mut jobs = BinaryHeap::<(u64, String), Min>::new(b"j");
+/// jobs.push((20, "b".to_string()));
+/// jobs.push((10, "a".to_string()));
+/// assert_eq!(jobs.pop(), Some((10, "a".to_string())));
+/// ```
+pub struct BinaryHeap<T, O = Max>
+where
+    T: BorshSerialize,
+{
+    data: Vector<T>,
+    order: PhantomData<O>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T, O> BorshSerialize for BinaryHeap<T, O>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.data, writer)
+    }
+}
+
+impl<T, O> BorshDeserialize for BinaryHeap<T, O>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self { data: BorshDeserialize::deserialize(buf)?, order: PhantomData })
+    }
+}
+
+impl_borsh_schema! {
+    BinaryHeap<T, O> {
+        data: Vector<T>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+impl<T, O> fmt::Debug for BinaryHeap<T, O>
+where
+    T: BorshSerialize + BorshDeserialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinaryHeap").field("data", &self.data).finish()
+    }
+}
+
+impl<T, O> BinaryHeap<T, O>
+where
+    T: BorshSerialize,
+{
+    /// Create a new heap which uses the given prefix for the storage keys of its elements.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { data: Vector::new(prefix), order: PhantomData }
+    }
+
+    /// Returns the number of elements in the heap.
+    pub fn len(&self) -> u32 {
+        self.data.len()
+    }
+
+    /// Returns `true` if the heap contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Removes all elements from the heap.
+    pub fn clear(&mut self) {
+        self.data.clear()
+    }
+
+    /// Flushes the cache and writes all modified elements to storage.
+    pub fn flush(&mut self) {
+        self.data.flush()
+    }
+}
+
+impl<T, O> BinaryHeap<T, O>
+where
+    T: BorshSerialize + BorshDeserialize + Ord,
+    O: HeapOrder,
+{
+    /// Returns the element at the top of the heap, or [`None`] if it is empty.
+    pub fn peek(&self) -> Option<&T> {
+        self.data.get(0)
+    }
+
+    /// Pushes an element onto the heap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::BinaryHeap;
+    ///
+    /// let mut heap: BinaryHeap<u32> = BinaryHeap::new(b"h");
+    /// heap.push(3);
+    /// heap.push(5);
+    /// heap.push(1);
+    ///
+    /// assert_eq!(heap.len(), 3);
+    /// assert_eq!(heap.peek(), Some(&5));
+    /// ```
+    pub fn push(&mut self, item: T) {
+        self.data.push(item);
+        self.sift_up(self.len() - 1);
+    }
+
+    /// Removes the element at the top of the heap and returns it, or [`None`] if it is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::binary_heap::Min;
+    /// use near_sdk::store::BinaryHeap;
+    ///
+    /// let mut heap = BinaryHeap::<u32, Min>::new(b"h");
+    /// heap.extend([3, 1, 2]);
+    ///
+    /// assert_eq!(heap.pop(), Some(1));
+    /// assert_eq!(heap.pop(), Some(2));
+    /// assert_eq!(heap.pop(), Some(3));
+    /// assert_eq!(heap.pop(), None);
+    /// ```
+    pub fn pop(&mut self) -> Option<T> {
+        let last = self.len().checked_sub(1)?;
+        self.data.swap(0, last);
+        let item = self.data.pop();
+        self.sift_down(0);
+        item
+    }
+
+    /// Returns an iterator over the elements of the heap, in arbitrary order.
+    pub fn iter(&self) -> vec::Iter<T> {
+        self.data.iter()
+    }
+
+    fn get(&self, index: u32) -> &T {
+        self.data.get(index).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+    }
+
+    /// Moves the element at `index` up until its parent is above it.
+    fn sift_up(&mut self, mut index: u32) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if !O::is_above(self.get(index), self.get(parent)) {
+                break;
+            }
+            self.data.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    /// Moves the element at `index` down until it is above both of its children.
+    fn sift_down(&mut self, mut index: u32) {
+        let len = self.len();
+        loop {
+            let mut child = 2 * index + 1;
+            if child >= len {
+                break;
+            }
+            if child + 1 < len && O::is_above(self.get(child + 1), self.get(child)) {
+                child += 1;
+            }
+            if !O::is_above(self.get(child), self.get(index)) {
+                break;
+            }
+            self.data.swap(index, child);
+            index = child;
+        }
+    }
+}
+
+impl<T, O> Extend<T> for BinaryHeap<T, O>
+where
+    T: BorshSerialize + BorshDeserialize + Ord,
+    O: HeapOrder,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::{BinaryHeap, Min};
+
+    #[test]
+    fn max_heap() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut heap: BinaryHeap<u16> = BinaryHeap::new(b"h");
+        let mut baseline = std::collections::BinaryHeap::new();
+        for _ in 0..500 {
+            if rng.gen_bool(0.6) {
+                let value = rng.gen::<u16>();
+                heap.push(value);
+                baseline.push(value);
+            } else {
+                assert_eq!(heap.pop(), baseline.pop());
+            }
+            assert_eq!(heap.peek(), baseline.peek());
+            assert_eq!(heap.len() as usize, baseline.len());
+
+            if rng.gen_bool(0.05) {
+                heap.flush();
+                heap = BinaryHeap::try_from_slice(&heap.try_to_vec().unwrap()).unwrap();
+            }
+        }
+        while let Some(value) = baseline.pop() {
+            assert_eq!(heap.pop(), Some(value));
+        }
+        assert!(heap.is_empty());
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn min_heap() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(1);
+        let mut heap = BinaryHeap::<_, Min>::new(b"h");
+        let mut baseline = std::collections::BinaryHeap::new();
+        let mut values: Vec<u8> = (0..200).map(|_| rng.gen()).collect();
+        heap.extend(values.iter().copied());
+        baseline.extend(values.iter().copied().map(Reverse));
+
+        let mut iterated: Vec<_> = heap.iter().copied().collect();
+        iterated.sort_unstable();
+        values.sort_unstable();
+        assert_eq!(iterated, values);
+
+        while let Some(Reverse(value)) = baseline.pop() {
+            assert_eq!(heap.pop(), Some(value));
+        }
+        assert!(heap.is_empty());
+    }
+}
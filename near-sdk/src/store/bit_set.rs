@@ -0,0 +1,231 @@
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::IndexMap;
+use crate::IntoStorageKey;
+
+/// Number of bits stored in each storage value.
+const WORD_BITS: u32 = u64::BITS;
+
+/// A lazily loaded set of `u32` indices, stored as bits packed into 64-bit words.
+///
+/// Each group of 64 consecutive indices is stored as a single storage value, so setting or
+/// checking flags for indices which are close together only reads and writes a few values.
+/// Words in which no bit is set are removed from storage. This makes [`BitSet`] much cheaper
+/// than a [`LookupSet<u32>`](crate::store::LookupSet) for dense flags keyed by small integer ids,
+/// but it should not be used for sparse indices spread over a large range.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::BitSet;
+///
+/// let mut claimed = BitSet::new(b"c");
+/// assert!(!claimed.set(3));
+/// assert!(claimed.set(3));
+/// claimed.set(70);
+///
+/// assert!(claimed.get(3));
+/// assert!(!claimed.get(4));
+/// assert_eq!(claimed.count_ones_in_range(0..100), 2);
+///
+/// assert!(claimed.unset(3));
+/// assert_eq!(claimed.count_ones_in_range(..=70), 1);
+/// ```
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct BitSet {
+    words: IndexMap<u64>,
+}
+
+impl_borsh_schema! {
+    BitSet {
+        prefix: Box<[u8]>,
+    }
+}
+
+impl Drop for BitSet {
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitSet").field("prefix", &self.words.prefix).finish()
+    }
+}
+
+impl BitSet {
+    /// Create a new bit set with no bits set. Uses the given prefix for the storage keys of the
+    /// words of the set.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { words: IndexMap::new(prefix) }
+    }
+
+    fn word(&self, word: u32) -> u64 {
+        self.words.get(word).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if the bit at `index` is set.
+    pub fn get(&self, index: u32) -> bool {
+        self.word(index / WORD_BITS) & (1 << (index % WORD_BITS)) != 0
+    }
+
+    /// Sets the bit at `index`, returning `true` if it was already set.
+    pub fn set(&mut self, index: u32) -> bool {
+        self.update(index, true)
+    }
+
+    /// Clears the bit at `index`, returning `true` if it was set.
+    pub fn unset(&mut self, index: u32) -> bool {
+        self.update(index, false)
+    }
+
+    fn update(&mut self, index: u32, value: bool) -> bool {
+        let mask = 1 << (index % WORD_BITS);
+        let entry = self.words.get_mut_inner(index / WORD_BITS);
+        let word = entry.value().unwrap_or(0);
+        let updated = if value { word | mask } else { word & !mask };
+        if updated != word {
+            // Words without any bits set are removed from storage.
+            *entry.value_mut() = Some(updated).filter(|&w| w != 0);
+        }
+        word & mask != 0
+    }
+
+    /// Returns the number of bits set in the given range of indices. One storage value is read
+    /// for every 64 indices in the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::BitSet;
+    ///
+    /// let mut set = BitSet::new(b"s");
+    /// for i in (0..200).step_by(3) {
+    ///     set.set(i);
+    /// }
+    /// assert_eq!(set.count_ones_in_range(0..10), 4);
+    /// assert_eq!(set.count_ones_in_range(60..=129), 24);
+    /// ```
+    pub fn count_ones_in_range<R>(&self, range: R) -> u32
+    where
+        R: RangeBounds<u32>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => u64::from(s),
+            Bound::Excluded(&s) => u64::from(s) + 1,
+            Bound::Unbounded => 0,
+        };
+        // Exclusive end, which can be one past `u32::MAX`.
+        let end = match range.end_bound() {
+            Bound::Included(&e) => u64::from(e) + 1,
+            Bound::Excluded(&e) => u64::from(e),
+            Bound::Unbounded => u64::from(u32::MAX) + 1,
+        };
+        if start >= end {
+            return 0;
+        }
+
+        let bits = u64::from(WORD_BITS);
+        let (first, last) = (start / bits, (end - 1) / bits);
+        (first..=last)
+            .map(|w| {
+                let mut word = self.word(w as u32);
+                if w == first {
+                    word &= u64::MAX << (start % bits);
+                }
+                if w == last {
+                    word &= u64::MAX >> (bits - 1 - (end - 1) % bits);
+                }
+                word.count_ones()
+            })
+            .sum()
+    }
+
+    /// Flushes the intermediate values of the set before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified words to storage but keep all cached words in
+    /// memory.
+    pub fn flush(&mut self) {
+        self.words.flush()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::BitSet;
+
+    #[test]
+    fn set_get_count() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut set = BitSet::new(b"b");
+        let mut baseline = BTreeSet::new();
+        for _ in 0..1000 {
+            let index = rng.gen_range(0..1000);
+            if rng.gen_bool(0.7) {
+                assert_eq!(set.set(index), !baseline.insert(index));
+            } else {
+                assert_eq!(set.unset(index), baseline.remove(&index));
+            }
+            if rng.gen_bool(0.05) {
+                set.flush();
+                set = BitSet::try_from_slice(&set.try_to_vec().unwrap()).unwrap();
+            }
+        }
+        for index in 0..1100 {
+            assert_eq!(set.get(index), baseline.contains(&index));
+        }
+        for _ in 0..100 {
+            let start = rng.gen_range(0..1100);
+            let end = rng.gen_range(start..1100);
+            assert_eq!(
+                set.count_ones_in_range(start..end) as usize,
+                baseline.range(start..end).count()
+            );
+            assert_eq!(
+                set.count_ones_in_range(start..=end) as usize,
+                baseline.range(start..=end).count()
+            );
+        }
+        assert_eq!(set.count_ones_in_range(..1100) as usize, baseline.len());
+    }
+
+    #[test]
+    fn range_edges() {
+        let mut set = BitSet::new(b"b");
+        set.set(0);
+        set.set(63);
+        set.set(64);
+        set.set(u32::MAX);
+        assert_eq!(set.count_ones_in_range(0..64), 2);
+        assert_eq!(set.count_ones_in_range(63..=64), 2);
+        assert_eq!(set.count_ones_in_range(64..64), 0);
+        assert_eq!(set.count_ones_in_range(u32::MAX - 10..), 1);
+        assert_eq!(set.count_ones_in_range(u32::MAX..=u32::MAX), 1);
+        assert!(set.get(u32::MAX));
+    }
+
+    #[test]
+    fn empty_words_removed() {
+        let mut set = BitSet::new(b"b");
+        for i in 0..200 {
+            set.set(i);
+        }
+        set.flush();
+        for i in 0..200 {
+            assert!(set.unset(i));
+        }
+        set.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
@@ -0,0 +1,430 @@
+//! A vector which stores multiple elements in each storage value. See [`ChunkedVector`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+use super::{IndexMap, ERR_INCONSISTENT_STATE};
+use crate::{env, IntoStorageKey};
+
+const ERR_INDEX_OUT_OF_BOUNDS: &str = "Index out of bounds";
+const ERR_EMPTY_CHUNK: &str = "Chunks must hold at least one element";
+
+/// A vector which packs `N` consecutive elements into each storage value, for vectors of small
+/// elements such as ids or amounts.
+///
+/// Every storage value of a [`Vector`](super::Vector) has its own key, which takes more storage
+/// and gas than an element of a few bytes. Storing elements in chunks of `N` amortizes the cost
+/// of the keys, at the cost of reading and writing the whole chunk when one of its elements is
+/// accessed. Elements are still accessed by index, and only the chunks containing the accessed
+/// elements are loaded.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::ChunkedVector;
+///
+/// let mut ids: ChunkedVector<u64, 32> = ChunkedVector::new(b"i");
+/// ids.extend(0..100);
+///
+/// // Elements 0 to 31 are stored in one value.
+/// assert_eq!(ids[40], 40);
+/// ids[40] = 7;
+/// assert_eq!(ids.get(40), Some(&7));
+///
+/// assert_eq!(ids.pop(), Some(99));
+/// assert_eq!(ids.len(), 99);
+/// assert!(ids.iter().take(3).eq([0, 1, 2].iter()));
+/// ```
+pub struct ChunkedVector<T, const N: usize>
+where
+    T: BorshSerialize,
+{
+    len: u32,
+    chunks: IndexMap<Vec<T>>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T, const N: usize> BorshSerialize for ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.len, writer)?;
+        BorshSerialize::serialize(&self.chunks, writer)?;
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> BorshDeserialize for ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            len: BorshDeserialize::deserialize(buf)?,
+            chunks: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl<T, const N: usize> BorshSchema for ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    fn add_definitions_recursively(
+        definitions: &mut HashMap<borsh::schema::Declaration, borsh::schema::Definition>,
+    ) {
+        let fields = borsh::schema::Fields::NamedFields(vec![
+            ("len".to_string(), u32::declaration()),
+            ("prefix".to_string(), <Box<[u8]>>::declaration()),
+        ]);
+        Self::add_definition(
+            Self::declaration(),
+            borsh::schema::Definition::Struct { fields },
+            definitions,
+        );
+        u32::add_definitions_recursively(definitions);
+        <Box<[u8]>>::add_definitions_recursively(definitions);
+    }
+
+    fn declaration() -> borsh::schema::Declaration {
+        "ChunkedVector".to_string()
+    }
+}
+
+impl<T, const N: usize> Drop for ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChunkedVector")
+            .field("len", &self.len)
+            .field("chunk_len", &N)
+            .field("prefix", &self.chunks.prefix)
+            .finish()
+    }
+}
+
+impl<T, const N: usize> ChunkedVector<T, N>
+where
+    T: BorshSerialize,
+{
+    /// Create new vector with zero elements. Prefixes storage accesss with the prefix provided.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        if N == 0 {
+            env::panic_str(ERR_EMPTY_CHUNK);
+        }
+        Self { len: 0, chunks: IndexMap::new(prefix) }
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the index of the chunk holding the element at `index`, and the position of the
+    /// element in the chunk.
+    fn position(index: u32) -> (u32, usize) {
+        let index = index as usize;
+        ((index / N) as u32, index % N)
+    }
+
+    /// Removes all elements from the collection. This will remove the storage values of all
+    /// chunks of the vector.
+    pub fn clear(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        let (last, _) = Self::position(self.len - 1);
+        for chunk in 0..=last {
+            self.chunks.set(chunk, None);
+        }
+        self.len = 0;
+    }
+
+    /// Flushes the cache and writes all modified chunks to storage.
+    pub fn flush(&mut self) {
+        self.chunks.flush();
+    }
+}
+
+impl<T, const N: usize> ChunkedVector<T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Returns the element at `index` or `None` if it is out of bounds. This loads the chunk
+    /// holding the element from storage.
+    pub fn get(&self, index: u32) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let (chunk, offset) = Self::position(index);
+        let element = self.chunks.get(chunk).and_then(|chunk| chunk.get(offset));
+        Some(element.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Returns a mutable reference to the element at `index` or `None` if it is out of bounds.
+    /// The whole chunk holding the element is written to storage when the vector is flushed.
+    pub fn get_mut(&mut self, index: u32) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let (chunk, offset) = Self::position(index);
+        let element = self.chunks.get_mut(chunk).and_then(|chunk| chunk.get_mut(offset));
+        Some(element.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Sets the element at `index` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: u32, value: T) {
+        *self.get_mut(index).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS)) = value;
+    }
+
+    /// Appends an element to the back of the collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if new length exceeds `u32::MAX`
+    pub fn push(&mut self, element: T) {
+        let (chunk, offset) = Self::position(self.len);
+        self.len =
+            self.len.checked_add(1).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS));
+        if offset == 0 {
+            let mut elements = Vec::with_capacity(N);
+            elements.push(element);
+            self.chunks.set(chunk, Some(elements));
+        } else {
+            self.chunks
+                .get_mut(chunk)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+                .push(element);
+        }
+    }
+
+    /// Removes the last element from the vector and returns it, or `None` if it is empty. The
+    /// storage value of the last chunk is removed once it holds no more elements.
+    pub fn pop(&mut self) -> Option<T> {
+        let index = self.len.checked_sub(1)?;
+        let (chunk, offset) = Self::position(index);
+        let element = if offset == 0 {
+            self.chunks.remove(chunk).and_then(|mut elements| elements.pop())
+        } else {
+            self.chunks.get_mut(chunk).and_then(|elements| elements.pop())
+        };
+        self.len = index;
+        Some(element.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Removes the element at `index` and returns it, replacing it with the last element of the
+    /// vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn swap_remove(&mut self, index: u32) -> T {
+        if index >= self.len {
+            env::panic_str(ERR_INDEX_OUT_OF_BOUNDS);
+        }
+        let last = self.pop().unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        if index == self.len {
+            return last;
+        }
+        core::mem::replace(
+            self.get_mut(index).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)),
+            last,
+        )
+    }
+
+    /// Returns an iterator over the elements of the vector, which loads each chunk once.
+    pub fn iter(&self) -> Iter<T, N> {
+        Iter { vec: self, range: 0..self.len }
+    }
+}
+
+impl<T, const N: usize> Extend<T> for ChunkedVector<T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for element in iter {
+            self.push(element)
+        }
+    }
+}
+
+impl<T, const N: usize> core::ops::Index<u32> for ChunkedVector<T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Output = T;
+
+    fn index(&self, index: u32) -> &Self::Output {
+        self.get(index).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS))
+    }
+}
+
+impl<T, const N: usize> core::ops::IndexMut<u32> for ChunkedVector<T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn index_mut(&mut self, index: u32) -> &mut Self::Output {
+        self.get_mut(index).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS))
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ChunkedVector<T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over references to each element in a [`ChunkedVector`].
+pub struct Iter<'a, T, const N: usize>
+where
+    T: BorshSerialize,
+{
+    vec: &'a ChunkedVector<T, N>,
+    range: Range<u32>,
+}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.range.next()?;
+        self.vec.get(index)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let index = self.range.nth(n)?;
+        self.vec.get(index)
+    }
+}
+
+impl<'a, T, const N: usize> ExactSizeIterator for Iter<'a, T, N> where
+    T: BorshSerialize + BorshDeserialize
+{
+}
+impl<'a, T, const N: usize> FusedIterator for Iter<'a, T, N> where
+    T: BorshSerialize + BorshDeserialize
+{
+}
+
+impl<'a, T, const N: usize> DoubleEndedIterator for Iter<'a, T, N>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.range.next_back()?;
+        self.vec.get(index)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::ChunkedVector;
+    use crate::test_utils::test_env::setup_free;
+
+    #[test]
+    fn matches_vec() {
+        setup_free();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(4);
+        let mut vec: ChunkedVector<u64, 5> = ChunkedVector::new(b"c");
+        let mut baseline = Vec::new();
+        for _ in 0..500 {
+            match rng.gen_range(0..6) {
+                0 | 1 => {
+                    let value = rng.gen();
+                    vec.push(value);
+                    baseline.push(value);
+                }
+                2 => assert_eq!(vec.pop(), baseline.pop()),
+                3 if !baseline.is_empty() => {
+                    let index = rng.gen_range(0..baseline.len());
+                    let value = rng.gen();
+                    vec.set(index as u32, value);
+                    baseline[index] = value;
+                }
+                4 if !baseline.is_empty() => {
+                    let index = rng.gen_range(0..baseline.len());
+                    assert_eq!(vec.swap_remove(index as u32), baseline.swap_remove(index));
+                }
+                _ => {
+                    // Reload the vector from storage.
+                    let serialized = vec.try_to_vec().unwrap();
+                    drop(vec);
+                    vec = ChunkedVector::try_from_slice(&serialized).unwrap();
+                }
+            }
+            assert_eq!(vec.len() as usize, baseline.len());
+        }
+        assert!(vec.iter().eq(baseline.iter()));
+        assert!(vec.iter().rev().eq(baseline.iter().rev()));
+
+        vec.clear();
+        vec.flush();
+        assert!(crate::mock::with_mocked_blockchain(|m| m.take_storage()).is_empty());
+    }
+
+    #[test]
+    fn chunks_in_storage() {
+        let mut vec: ChunkedVector<u8, 4> = ChunkedVector::new(b"c");
+        vec.extend(0..6);
+        vec.flush();
+        let storage = crate::mock::with_mocked_blockchain(|m| m.take_storage());
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage[&b"c\x00\x00\x00\x00".to_vec()], vec![4, 0, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(storage[&b"c\x01\x00\x00\x00".to_vec()], vec![2, 0, 0, 0, 4, 5]);
+    }
+}
@@ -0,0 +1,77 @@
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+use crate::{env, Gas};
+
+const ERR_NOT_COMPLETE: &str = "Collection has not been completely copied";
+
+/// A copy of a store collection into a new storage prefix. The copy is done in gas-bounded
+/// chunks through the `resume` method and can be continued over multiple function calls by
+/// keeping this type in the contract state in between calls.
+///
+/// This `struct` is created by the `clone_to` method on iterable store collections, such as
+/// [`Vector::clone_to`](crate::store::Vector::clone_to). The source collection must not be
+/// modified until the copy is complete.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::Vector;
+/// use near_sdk::Gas;
+///
+/// let mut vec = Vector::new(b"v");
+/// vec.extend([1u8, 2, 3]);
+///
+/// let mut copy = vec.clone_to(b"c");
+/// // Stops copying once 10 TGas has been used, and can be resumed in a later function call.
+/// while !copy.resume(&vec, Gas(10_000_000_000_000)) {}
+///
+/// let shadow = copy.into_inner();
+/// assert!(Iterator::eq(shadow.iter(), vec.iter()));
+/// ```
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct CloneTo<C> {
+    target: C,
+    /// Position in the source collection of the next element to copy.
+    cursor: u32,
+    complete: bool,
+}
+
+impl<C> CloneTo<C> {
+    pub(crate) fn new(target: C) -> Self {
+        Self { target, cursor: 0, complete: false }
+    }
+
+    /// Copies the elements at positions `cursor..len` of the source collection through `copy`,
+    /// until all elements are copied or [`env::used_gas`] exceeds `gas_limit`. At least one
+    /// element is copied on every call.
+    pub(crate) fn copy_until<F>(&mut self, len: u32, gas_limit: Gas, mut copy: F) -> bool
+    where
+        F: FnMut(&mut C, u32),
+    {
+        while self.cursor < len {
+            copy(&mut self.target, self.cursor);
+            self.cursor += 1;
+            if env::used_gas() >= gas_limit {
+                break;
+            }
+        }
+        self.complete = self.cursor >= len;
+        self.complete
+    }
+
+    /// Returns `true` if all elements of the source collection have been copied.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the copied collection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the copy has not been completed.
+    pub fn into_inner(self) -> C {
+        if !self.complete {
+            env::panic_str(ERR_NOT_COMPLETE)
+        }
+        self.target
+    }
+}
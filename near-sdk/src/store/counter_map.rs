@@ -0,0 +1,240 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::LookupMap;
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+const ERR_OVERFLOW: &str = "Counter overflow";
+
+/// A lazily loaded storage map from keys to `u128` counters, such as token balances.
+///
+/// Every key has a counter which starts at `0`, and counters are updated in place with checked
+/// arithmetic which returns the new value, without reading, modifying and writing back the value
+/// by hand. Counters which are `0` are not kept in storage.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::CounterMap;
+///
+/// let mut balances = CounterMap::new(b"b");
+/// assert_eq!(balances.add("alice.near", 100), 100);
+///
+/// // Transfer 30 from alice to bob, if alice has enough.
+/// assert_eq!(balances.checked_sub("alice.near", 30), Some(70));
+/// assert_eq!(balances.add("bob.near", 30), 30);
+///
+/// // Insufficient balance leaves the counter unchanged.
+/// assert_eq!(balances.checked_sub("bob.near", 50), None);
+/// assert_eq!(balances.get("bob.near"), 30);
+/// assert_eq!(balances.saturating_sub("bob.near", 50), 0);
+/// assert_eq!(balances.get("carol.near"), 0);
+/// ```
+pub struct CounterMap<K, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    counters: LookupMap<K, u128, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, H> BorshSerialize for CounterMap<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.counters, writer)
+    }
+}
+
+impl<K, H> BorshDeserialize for CounterMap<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self { counters: BorshDeserialize::deserialize(buf)? })
+    }
+}
+
+impl_borsh_schema! {
+    CounterMap<K, H> {
+        counters: LookupMap<K, u128, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, H> fmt::Debug for CounterMap<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CounterMap").field("counters", &self.counters).finish()
+    }
+}
+
+impl<K> CounterMap<K, Sha256>
+where
+    K: BorshSerialize + Ord,
+{
+    /// Create a new map where all counters are `0`. Uses the given prefix for the storage keys
+    /// of the counters.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, H> CounterMap<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`CounterMap`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { counters: LookupMap::with_hasher(prefix) }
+    }
+
+    /// Returns the counter of `key`, which is `0` if it has not been changed.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> u128
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.counters.get(key).copied().unwrap_or(0)
+    }
+
+    /// Sets the counter of `key` to `value`, without reading the previous value.
+    pub fn set(&mut self, key: K, value: u128) {
+        self.counters.set(key, Some(value).filter(|&v| v != 0));
+    }
+
+    /// Adds `delta` to the counter of `key` and returns the new value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the counter overflows.
+    pub fn add<Q: ?Sized>(&mut self, key: &Q, delta: u128) -> u128
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.update(key, |v| v.checked_add(delta)).unwrap_or_else(|| env::panic_str(ERR_OVERFLOW))
+    }
+
+    /// Subtracts `delta` from the counter of `key` and returns the new value, or returns [`None`]
+    /// and leaves the counter unchanged if it is less than `delta`.
+    pub fn checked_sub<Q: ?Sized>(&mut self, key: &Q, delta: u128) -> Option<u128>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.update(key, |v| v.checked_sub(delta))
+    }
+
+    /// Subtracts `delta` from the counter of `key`, stopping at `0`, and returns the new value.
+    pub fn saturating_sub<Q: ?Sized>(&mut self, key: &Q, delta: u128) -> u128
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.update(key, |v| Some(v.saturating_sub(delta))).unwrap_or_else(|| env::abort())
+    }
+
+    /// Applies `f` to the counter of `key`, storing and returning the new value if `f` returns
+    /// [`Some`], otherwise leaving the counter unchanged.
+    fn update<Q: ?Sized, F>(&mut self, key: &Q, f: F) -> Option<u128>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+        F: FnOnce(u128) -> Option<u128>,
+    {
+        let entry = self.counters.get_mut_inner(key);
+        let current = entry.value().unwrap_or(0);
+        let updated = f(current)?;
+        if updated != current {
+            // Counters which are zero are removed from storage.
+            *entry.value_mut() = Some(updated).filter(|&v| v != 0);
+        }
+        Some(updated)
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.counters.flush()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::CounterMap;
+
+    #[test]
+    fn arithmetic() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut map = CounterMap::new(b"c");
+        let mut baseline: HashMap<u8, u128> = HashMap::new();
+        for _ in 0..1000 {
+            let key = rng.gen::<u8>() % 16;
+            let delta = rng.gen_range(0..100);
+            let current = baseline.get(&key).copied().unwrap_or(0);
+            match rng.gen_range(0..4) {
+                0 => {
+                    assert_eq!(map.add(&key, delta), current + delta);
+                    baseline.insert(key, current + delta);
+                }
+                1 => {
+                    let expected = current.checked_sub(delta);
+                    assert_eq!(map.checked_sub(&key, delta), expected);
+                    baseline.insert(key, expected.unwrap_or(current));
+                }
+                2 => {
+                    assert_eq!(map.saturating_sub(&key, delta), current.saturating_sub(delta));
+                    baseline.insert(key, current.saturating_sub(delta));
+                }
+                _ => {
+                    map.flush();
+                    map = CounterMap::try_from_slice(&map.try_to_vec().unwrap()).unwrap();
+                }
+            }
+        }
+        for key in 0..16 {
+            assert_eq!(map.get(&key), baseline.get(&key).copied().unwrap_or(0));
+        }
+
+        // Counters at zero are removed from storage.
+        for key in 0..16 {
+            map.set(key, 0);
+        }
+        map.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
@@ -0,0 +1,336 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{LookupMap, TreeMap};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+/// A value of an [`ExpiringMap`] with the block timestamp at which it expires.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct Entry<V> {
+    value: V,
+    expires_at: u64,
+}
+
+impl<V> Entry<V> {
+    fn is_live(&self) -> bool {
+        env::block_timestamp() < self.expires_at
+    }
+}
+
+/// A lazily loaded storage map whose entries expire at a given block timestamp.
+///
+/// An entry is expired once [`env::block_timestamp`] reaches its expiry, after which the map
+/// behaves as if the key was not in it. Expired entries keep taking storage until they are
+/// overwritten or removed, or until they are deleted by [`purge_expired`], which removes a
+/// bounded number of them in the order they expire, so that it can be called on every change of
+/// a contract at a fixed gas cost.
+///
+/// The entries are stored in a [`LookupMap`], and the keys are also stored in a [`TreeMap`]
+/// ordered by expiry, which is used to find the expired entries.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::ExpiringMap;
+/// use near_sdk::test_utils::VMContextBuilder;
+/// use near_sdk::testing_env;
+///
+/// let mut offers = ExpiringMap::new(b"o");
+/// testing_env!(VMContextBuilder::new().block_timestamp(100).build());
+/// offers.insert("alice.near".to_string(), 10u128, 200);
+/// offers.insert("bob.near".to_string(), 20, 300);
+/// assert_eq!(offers.get("alice.near"), Some(&10));
+///
+/// testing_env!(VMContextBuilder::new().block_timestamp(250).build());
+/// assert_eq!(offers.get("alice.near"), None);
+/// assert_eq!(offers.get("bob.near"), Some(&20));
+///
+/// assert_eq!(offers.len(), 2);
+/// assert_eq!(offers.purge_expired(10), 1);
+/// assert_eq!(offers.len(), 1);
+/// ```
+///
+/// [`purge_expired`]: Self::purge_expired
+pub struct ExpiringMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    entries: LookupMap<K, Entry<V>, H>,
+    expirations: TreeMap<(u64, K), (), H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for ExpiringMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.entries, writer)?;
+        BorshSerialize::serialize(&self.expirations, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for ExpiringMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            entries: BorshDeserialize::deserialize(buf)?,
+            expirations: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    ExpiringMap<K, V, H> {
+        entries: LookupMap<K, Entry<V>, H>,
+        expirations: TreeMap<(u64, K), (), H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> fmt::Debug for ExpiringMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpiringMap")
+            .field("entries", &self.entries)
+            .field("expirations", &self.expirations)
+            .finish()
+    }
+}
+
+impl<K, V> ExpiringMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create a new empty map. Use `prefix` as a unique identifier.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> ExpiringMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize an [`ExpiringMap`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut entries_key = prefix.into_storage_key();
+        let expirations_key = [entries_key.as_slice(), b"x"].concat();
+        entries_key.push(b'e');
+        Self {
+            entries: LookupMap::with_hasher(entries_key),
+            expirations: TreeMap::with_hasher(expirations_key),
+        }
+    }
+
+    /// Returns the number of entries in the map, including the expired entries which were not
+    /// removed yet.
+    pub fn len(&self) -> u32 {
+        self.expirations.len()
+    }
+
+    /// Returns `true` if the map contains no entries, including expired ones.
+    pub fn is_empty(&self) -> bool {
+        self.expirations.is_empty()
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.entries.flush();
+        self.expirations.flush();
+    }
+}
+
+impl<K, V, H> ExpiringMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns a reference to the value of the key, or [`None`] if the key is not in the map or
+    /// its entry has expired.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.entries.get(k).filter(|entry| entry.is_live()).map(|entry| &entry.value)
+    }
+
+    /// Returns a mutable reference to the value of the key, or [`None`] if the key is not in
+    /// the map or its entry has expired.
+    pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.entries.get_mut(k).filter(|entry| entry.is_live()).map(|entry| &mut entry.value)
+    }
+
+    /// Returns the block timestamp at which the entry of the key expires, or [`None`] if the key
+    /// is not in the map or its entry has expired.
+    pub fn expires_at<Q: ?Sized>(&self, k: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.entries.get(k).filter(|entry| entry.is_live()).map(|entry| entry.expires_at)
+    }
+
+    /// Returns `true` if the map contains an entry for the key which has not expired.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.get(k).is_some()
+    }
+
+    /// Inserts a key-value pair which expires at the block timestamp `expires_at`, replacing
+    /// the value and expiry of the key if it is already in the map.
+    ///
+    /// Returns the previous value of the key, or [`None`] if the key was not in the map or its
+    /// entry had expired.
+    pub fn insert(&mut self, k: K, value: V, expires_at: u64) -> Option<V> {
+        self.expirations.insert((expires_at, k.clone()), ());
+        let previous = self.entries.insert(k.clone(), Entry { value, expires_at })?;
+        if previous.expires_at != expires_at {
+            self.expirations.remove(&(previous.expires_at, k));
+        }
+        Some(previous).filter(Entry::is_live).map(|entry| entry.value)
+    }
+
+    /// Removes the key from the map, and returns its value, or [`None`] if the key was not in
+    /// the map or its entry had expired. The entry is removed from storage either way.
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let entry = self.entries.remove(k)?;
+        self.expirations.remove(&(entry.expires_at, k.to_owned()));
+        Some(entry).filter(Entry::is_live).map(|entry| entry.value)
+    }
+
+    /// Removes up to `max` expired entries from storage, in the order they expired, and returns
+    /// the number of entries removed. Fewer than `max` entries are removed only if there are no
+    /// expired entries left.
+    pub fn purge_expired(&mut self, max: u32) -> u32 {
+        let now = env::block_timestamp();
+        let mut purged = 0;
+        while purged < max {
+            let (expires_at, key) = match self.expirations.min_key() {
+                Some((expires_at, _)) if *expires_at > now => break,
+                Some(key) => key.clone(),
+                None => break,
+            };
+            self.expirations.remove(&(expires_at, key.clone()));
+            self.entries.remove(&key);
+            purged += 1;
+        }
+        purged
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::ExpiringMap;
+    use crate::test_utils::VMContextBuilder;
+    use crate::testing_env;
+
+    #[test]
+    fn matches_btree_map() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(9);
+        let mut map = ExpiringMap::new(b"e");
+        let mut baseline: BTreeMap<u8, (u32, u64)> = BTreeMap::new();
+        let mut now = 0;
+        for _ in 0..500 {
+            let key = rng.gen_range(0..30);
+            let live = |entry: Option<(u32, u64)>, now| entry.filter(|e| now < e.1).map(|e| e.0);
+            match rng.gen_range(0..6) {
+                0 | 1 => {
+                    let value = rng.gen();
+                    let expires_at = now + rng.gen_range(0..20);
+                    let previous = baseline.insert(key, (value, expires_at));
+                    assert_eq!(map.insert(key, value, expires_at), live(previous, now));
+                }
+                2 => assert_eq!(map.remove(&key), live(baseline.remove(&key), now)),
+                3 => {
+                    now += rng.gen_range(0..5);
+                    testing_env!(VMContextBuilder::new().block_timestamp(now).build());
+                }
+                4 => {
+                    // Purging removes the entries which expired first.
+                    let mut expired: Vec<_> =
+                        baseline.iter().filter(|e| e.1 .1 <= now).map(|(&k, e)| (e.1, k)).collect();
+                    expired.sort_unstable();
+                    let max = rng.gen_range(0..5);
+                    expired.truncate(max as usize);
+                    assert_eq!(map.purge_expired(max), expired.len() as u32);
+                    for (_, k) in expired {
+                        baseline.remove(&k);
+                    }
+                }
+                _ => {
+                    let serialized = map.try_to_vec().unwrap();
+                    drop(map);
+                    map = ExpiringMap::try_from_slice(&serialized).unwrap();
+                }
+            }
+            assert_eq!(map.get(&key).copied(), live(baseline.get(&key).copied(), now));
+            assert_eq!(map.expires_at(&key), baseline.get(&key).filter(|e| now < e.1).map(|e| e.1));
+            assert_eq!(map.len() as usize, baseline.len());
+        }
+
+        testing_env!(VMContextBuilder::new().block_timestamp(u64::MAX).build());
+        map.purge_expired(u32::MAX);
+        assert!(map.is_empty());
+        map.flush();
+        // The nodes of the expiry index are left as vacant slots, which are reused by later inserts.
+        let storage = crate::mock::with_mocked_blockchain(|m| m.take_storage());
+        assert!(storage.keys().all(|key| !key.starts_with(b"ee")));
+    }
+}
@@ -117,6 +117,65 @@ where
     }
 }
 
+/// An iterator over copies of the elements in the storage bucket, which are not cached. This
+/// only yields the occupied entries.
+pub struct IterUncached<'a, T>
+where
+    T: BorshDeserialize + BorshSerialize,
+{
+    /// Values iterator which contains empty and filled cells.
+    values: vec::IterUncached<'a, Slot<T>>,
+    /// Amount of valid elements left to iterate.
+    elements_left: u32,
+}
+
+impl<'a, T> IterUncached<'a, T>
+where
+    T: BorshDeserialize + BorshSerialize + Clone,
+{
+    pub(super) fn new(bucket: &'a FreeList<T>) -> Self {
+        Self { values: bucket.elements.iter_uncached(), elements_left: bucket.occupied_count }
+    }
+}
+
+impl<'a, T> Iterator for IterUncached<'a, T>
+where
+    T: BorshDeserialize + BorshSerialize + Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.elements_left == 0 {
+            return None;
+        }
+        loop {
+            match self.values.next() {
+                Some(Slot::Empty { .. }) => continue,
+                Some(Slot::Occupied(value)) => {
+                    decrement_count(&mut self.elements_left);
+                    return Some(value);
+                }
+                None => env::panic_str(ERR_INCONSISTENT_STATE),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let elements_left = self.elements_left as usize;
+        (elements_left, Some(elements_left))
+    }
+
+    fn count(self) -> usize {
+        self.elements_left as usize
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterUncached<'a, T> where
+    T: BorshSerialize + BorshDeserialize + Clone
+{
+}
+impl<'a, T> FusedIterator for IterUncached<'a, T> where T: BorshSerialize + BorshDeserialize + Clone {}
+
 /// An iterator over elements in the storage bucket. This only yields the occupied entries.
 pub struct IterMut<'a, T>
 where
@@ -1,20 +1,44 @@
 mod iter;
-pub use self::iter::{Drain, Iter, IterMut};
+pub use self::iter::{Drain, Iter, IterMut, IterUncached};
 
 use super::{Vector, ERR_INCONSISTENT_STATE};
 use crate::{env, IntoStorageKey};
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 
 use std::{fmt, mem};
 
-/// Index for value within a bucket.
-#[derive(BorshSerialize, BorshDeserialize, Debug, Hash, PartialEq, Eq, Clone, Copy)]
-pub struct FreeListIndex(u32);
+/// Index for value within a [`FreeList`]. The index of a value stays the same until the value is
+/// removed, or moved by [`FreeList::defrag`] or [`FreeList::defrag_bounded`].
+#[derive(
+    BorshSerialize, BorshDeserialize, BorshSchema, Debug, Hash, PartialEq, Eq, Clone, Copy,
+)]
+pub struct FreeListIndex(pub(crate) u32);
 
 /// Unordered container of values. This is similar to [`Vector`] except that values are not
 /// re-arranged on removal, keeping the indices consistent. When an element is removed, it will
 /// be replaced with an empty cell which will be populated on the next insertion.
+///
+/// This can be used to store values that are referenced by a stable handle, the
+/// [`FreeListIndex`] returned from [`insert`](FreeList::insert), without having to keep a
+/// counter and a map from the handles to the values.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::FreeList;
+///
+/// let mut list = FreeList::new(b"f");
+/// let a = list.insert("a".to_string());
+/// let b = list.insert("b".to_string());
+///
+/// assert_eq!(list.remove(a), Some("a".to_string()));
+/// assert_eq!(list.get(b), Some(&"b".to_string()));
+///
+/// // The empty cell of the removed value is reused, the index of `b` is unchanged.
+/// let c = list.insert("c".to_string());
+/// assert_eq!(c, a);
+/// assert!(Iterator::eq(list.iter(), ["c", "b"].iter()));
+/// ```
 pub struct FreeList<T>
 where
     T: BorshSerialize,
@@ -54,7 +78,17 @@ where
     }
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Debug)]
+impl_borsh_schema! {
+    FreeList<T> {
+        first_free: Option<FreeListIndex>,
+        occupied_count: u32,
+        elements: Vector<Slot<T>>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
 enum Slot<T> {
     /// Represents a filled cell of a value in the collection.
     Occupied(T),
@@ -104,6 +138,7 @@ impl<T> FreeList<T>
 where
     T: BorshSerialize,
 {
+    /// Create a new free list which uses the given prefix for the storage keys of its cells.
     pub fn new<S: IntoStorageKey>(prefix: S) -> Self {
         Self { first_free: None, occupied_count: 0, elements: Vector::new(prefix) }
     }
@@ -115,6 +150,14 @@ where
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    /// Returns the amount of cells in the bucket, including the empty cells.
+    pub fn capacity(&self) -> u32 {
+        self.elements.len()
+    }
+    /// Returns the prefix of the storage keys of the cells.
+    pub(crate) fn prefix(&self) -> &[u8] {
+        self.elements.prefix()
+    }
 
     /// Flushes cached changes to storage. This retains any cached values in memory.
     pub fn flush(&mut self) {
@@ -205,6 +248,15 @@ where
         Iter::new(self)
     }
 
+    /// Generates iterator for copies of each value in the bucket, which are read from storage
+    /// without being cached.
+    pub fn iter_uncached(&self) -> IterUncached<T>
+    where
+        T: Clone,
+    {
+        IterUncached::new(self)
+    }
+
     /// Generates iterator for exclusive references to each value in the bucket.
     pub fn iter_mut(&mut self) -> IterMut<T> {
         IterMut::new(self)
@@ -220,6 +272,206 @@ where
     pub fn drain(&mut self) -> Drain<T> {
         Drain::new(self)
     }
+
+    /// Retains only the elements specified by the predicate. In other words, removes all
+    /// elements `e` such that `f(&e)` returns `false`.
+    ///
+    /// Removed cells are left empty and are not compacted, use [`FreeList::defrag`] after to
+    /// remove the gaps from the underlying storage.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        for i in 0..self.elements.len() {
+            let keep = match self.elements.get(i) {
+                Some(Slot::Occupied(value)) => f(value),
+                Some(Slot::Empty { .. }) => true,
+                None => env::panic_str(ERR_INCONSISTENT_STATE),
+            };
+            if !keep {
+                self.remove(FreeListIndex(i));
+            }
+        }
+    }
+
+    /// Removes all empty cells from the underlying storage by moving elements from the back of
+    /// the list into the empty cells. The `on_move` callback is called for every element that
+    /// is moved with the new index of that element, so that any external references to the
+    /// index can be updated.
+    pub fn defrag<F>(&mut self, mut on_move: F)
+    where
+        F: FnMut(&T, FreeListIndex),
+    {
+        let mut i = 0;
+        while i < self.elements.len() {
+            if let Some(Slot::Occupied(_)) = self.elements.get(i) {
+                i += 1;
+                continue;
+            }
+
+            // Cell at `i` is empty, fill it with the last element. If the last element is empty,
+            // it is dropped and the cell at `i` is checked again on the next iteration.
+            let last =
+                self.elements.pop().unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if let Slot::Occupied(value) = last {
+                let new_index = FreeListIndex(i);
+                on_move(&value, new_index);
+                self.elements.replace(i, Slot::Occupied(value));
+                i += 1;
+            }
+        }
+
+        // All empty cells have been removed.
+        self.first_free = None;
+    }
+
+    /// Moves up to `max_moves` elements from the back of the list into the empty cells with the
+    /// lowest indices, and removes the empty cells at the back. The `on_move` callback is called
+    /// for every element that is moved with its old and new index. Returns `true` if no empty
+    /// cells are left, otherwise the defragmentation can be continued with another call.
+    ///
+    /// All empty cells are read to find the lowest ones, but only the moved elements and the
+    /// empty cells which are removed or relinked are written.
+    pub fn defrag_bounded<F>(&mut self, max_moves: u32, mut on_move: F) -> bool
+    where
+        F: FnMut(&T, FreeListIndex, FreeListIndex),
+    {
+        // Empty cells in the order of the free list.
+        let mut chain = Vec::new();
+        let mut next = self.first_free;
+        while let Some(FreeListIndex(i)) = next {
+            chain.push(i);
+            next = match self.elements.get(i) {
+                Some(Slot::Empty { next_free }) => *next_free,
+                _ => env::panic_str(ERR_INCONSISTENT_STATE),
+            };
+        }
+        let mut holes = chain.clone();
+        holes.sort_unstable();
+
+        // Walk back from the end of the list, assigning the occupied cells to the lowest empty
+        // cells, to find the length after the moves. The first `filled` empty cells are filled.
+        let mut filled = 0;
+        let mut new_len = self.elements.len();
+        while let Some(i) = new_len.checked_sub(1) {
+            if holes.binary_search(&i).is_ok() {
+                if filled > 0 && i <= holes[filled - 1] {
+                    // Cell will be filled by an element moved from the back.
+                    break;
+                }
+            } else if filled as u32 != max_moves && matches!(holes.get(filled), Some(&h) if h < i) {
+                filled += 1;
+            } else {
+                break;
+            }
+            new_len = i;
+        }
+        let (filled, left) = holes.split_at(filled);
+
+        let mut targets = filled.iter();
+        while self.elements.len() > new_len {
+            let from = self.elements.len() - 1;
+            match self.elements.pop() {
+                Some(Slot::Occupied(value)) => {
+                    let to =
+                        *targets.next().unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+                    on_move(&value, FreeListIndex(from), FreeListIndex(to));
+                    self.elements.replace(to, Slot::Occupied(value));
+                }
+                Some(Slot::Empty { .. }) => {}
+                None => env::panic_str(ERR_INCONSISTENT_STATE),
+            }
+        }
+
+        // Relink the empty cells which are left, keeping their order in the free list.
+        let left: Vec<_> =
+            chain.into_iter().filter(|i| i < &new_len && left.binary_search(i).is_ok()).collect();
+        self.first_free = left.first().map(|&i| FreeListIndex(i));
+        for (n, &i) in left.iter().enumerate() {
+            let next_free = left.get(n + 1).map(|&i| FreeListIndex(i));
+            match self.elements.get(i) {
+                Some(Slot::Empty { next_free: prev }) if *prev == next_free => {}
+                Some(Slot::Empty { .. }) => {
+                    self.elements.replace(i, Slot::Empty { next_free });
+                }
+                _ => env::panic_str(ERR_INCONSISTENT_STATE),
+            }
+        }
+        left.is_empty()
+    }
+
+    /// Moves up to `max_moves` elements towards the front of the list into the empty cells,
+    /// keeping the order of the elements, and removes the empty cells at the back. The `on_move`
+    /// callback is called for every element that is moved with its old and new index. Returns
+    /// `true` if no empty cells are left, otherwise the compaction can be continued with another
+    /// call.
+    ///
+    /// Unlike [`FreeList::defrag_bounded`], every element after the first empty cell is moved,
+    /// so this writes more cells but the elements are iterated in the same order afterwards.
+    pub fn compact<F>(&mut self, max_moves: u32, mut on_move: F) -> bool
+    where
+        F: FnMut(&T, FreeListIndex, FreeListIndex),
+    {
+        let mut holes = Vec::new();
+        let mut next = self.first_free;
+        while let Some(FreeListIndex(i)) = next {
+            holes.push(i);
+            next = match self.elements.get(i) {
+                Some(Slot::Empty { next_free }) => *next_free,
+                _ => env::panic_str(ERR_INCONSISTENT_STATE),
+            };
+        }
+        holes.sort_unstable();
+        let first = match holes.first() {
+            Some(&first) => first,
+            None => return true,
+        };
+
+        // Shift the elements after the first empty cell down, the cells in `to..from` are empty.
+        let (mut to, mut from) = (first, first + 1);
+        let mut moves = 0;
+        while from < self.elements.len() {
+            if let Some(Slot::Occupied(_)) = self.elements.get(from) {
+                if moves == max_moves {
+                    break;
+                }
+                self.elements.swap(to, from);
+                match self.elements.get(to) {
+                    Some(Slot::Occupied(value)) => {
+                        on_move(value, FreeListIndex(from), FreeListIndex(to))
+                    }
+                    _ => env::panic_str(ERR_INCONSISTENT_STATE),
+                }
+                to += 1;
+                moves += 1;
+            }
+            from += 1;
+        }
+
+        if from == self.elements.len() {
+            while self.elements.len() > to {
+                self.elements.pop();
+            }
+            self.first_free = None;
+            return true;
+        }
+
+        // Relink the empty cells which are left in ascending order.
+        let start = holes.partition_point(|&i| i < from);
+        let left: Vec<_> = (to..from).chain(holes[start..].iter().copied()).collect();
+        self.first_free = left.first().map(|&i| FreeListIndex(i));
+        for (n, &i) in left.iter().enumerate() {
+            let next_free = left.get(n + 1).map(|&i| FreeListIndex(i));
+            match self.elements.get(i) {
+                Some(Slot::Empty { next_free: prev }) if *prev == next_free => {}
+                Some(Slot::Empty { .. }) => {
+                    self.elements.replace(i, Slot::Empty { next_free });
+                }
+                _ => env::panic_str(ERR_INCONSISTENT_STATE),
+            }
+        }
+        false
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -346,6 +598,87 @@ mod tests {
         crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
     }
 
+    #[test]
+    fn retain_defrag() {
+        let mut bucket = FreeList::new(b"b");
+        bucket.extend(0u8..10);
+
+        bucket.retain(|v| v % 3 == 0);
+        assert_eq!(bucket.len(), 4);
+        assert_eq!(bucket.elements.len(), 10);
+        assert!(Iterator::eq(bucket.iter(), [0, 3, 6, 9].iter()));
+
+        let mut moved = Vec::new();
+        bucket.defrag(|v, idx| moved.push((*v, idx)));
+        assert_eq!(moved, [(9, FreeListIndex(1)), (6, FreeListIndex(2))]);
+        assert_eq!(bucket.elements.len(), 4);
+        assert!(bucket.first_free.is_none());
+        assert!(Iterator::eq(bucket.iter(), [0, 9, 6, 3].iter()));
+
+        // Inserting after defrag pushes to the end
+        assert_eq!(bucket.insert(10), FreeListIndex(4));
+    }
+
+    #[test]
+    fn defrag_bounded() {
+        let mut bucket = FreeList::new(b"b");
+        bucket.extend(0u8..20);
+        for i in [1, 18, 5, 12, 3, 19, 7] {
+            bucket.remove(FreeListIndex(i));
+        }
+
+        let mut moved = Vec::new();
+        assert!(!bucket.defrag_bounded(2, |v, from, to| moved.push((*v, from, to))));
+        assert_eq!(
+            moved,
+            [(17, FreeListIndex(17), FreeListIndex(1)), (16, FreeListIndex(16), FreeListIndex(3))]
+        );
+        assert_eq!(bucket.elements.len(), 16);
+        assert_eq!(bucket.len(), 13);
+
+        // Remaining empty cells are still reused.
+        assert_eq!(bucket.insert(20), FreeListIndex(7));
+        assert_eq!(bucket.insert(21), FreeListIndex(12));
+        assert_eq!(bucket.insert(22), FreeListIndex(5));
+        assert!(bucket.defrag_bounded(0, |_, _, _| panic!()));
+        bucket.remove(FreeListIndex(0));
+        bucket.remove(FreeListIndex(15));
+
+        assert!(bucket.defrag_bounded(10, |_, _, _| {}));
+        assert_eq!(bucket.elements.len(), 14);
+        assert!(bucket.first_free.is_none());
+        let mut values: Vec<_> = bucket.iter().copied().collect();
+        values.sort_unstable();
+        assert_eq!(values, [2, 4, 6, 8, 9, 10, 11, 13, 14, 16, 17, 20, 21, 22]);
+    }
+
+    #[test]
+    fn compact() {
+        let mut bucket = FreeList::new(b"b");
+        bucket.extend(0u8..10);
+        for i in [7, 1, 9, 3] {
+            bucket.remove(FreeListIndex(i));
+        }
+
+        let mut moved = Vec::new();
+        assert!(!bucket.compact(2, |v, from, to| moved.push((*v, from, to))));
+        assert_eq!(
+            moved,
+            [(2, FreeListIndex(2), FreeListIndex(1)), (4, FreeListIndex(4), FreeListIndex(2))]
+        );
+        assert!(Iterator::eq(bucket.iter(), [0, 2, 4, 5, 6, 8].iter()));
+
+        // Remaining empty cells are reused from the front.
+        assert_eq!(bucket.insert(10), FreeListIndex(3));
+        assert_eq!(bucket.insert(11), FreeListIndex(4));
+        bucket.remove(FreeListIndex(3));
+        assert!(bucket.compact(u32::MAX, |_, _, _| {}));
+        assert!(bucket.compact(0, |_, _, _| panic!()));
+        assert_eq!(bucket.elements.len(), 7);
+        assert!(bucket.first_free.is_none());
+        assert!(Iterator::eq(bucket.iter(), [0, 2, 4, 11, 5, 6, 8].iter()));
+    }
+
     #[derive(Arbitrary, Debug)]
     enum Op {
         Insert(u8),
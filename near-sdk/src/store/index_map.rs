@@ -42,6 +42,13 @@ where
         buf.extend_from_slice(&index.to_le_bytes());
     }
 
+    /// Returns the storage key of the element at `index`.
+    pub(crate) fn element_key(&self, index: u32) -> Vec<u8> {
+        let mut key = Vec::with_capacity(self.prefix.len() + 4);
+        Self::index_to_lookup_key(&self.prefix, index, &mut key);
+        key
+    }
+
     /// Flushes the cache and writes all modified values to storage.
     pub fn flush(&mut self) {
         let mut buf = Vec::new();
@@ -107,6 +114,21 @@ where
         entry.value().as_ref()
     }
 
+    /// Returns a copy of the element at `index`, which is read from storage without being cached
+    /// unless it is cached already.
+    pub(crate) fn get_uncached(&self, index: u32) -> Option<T>
+    where
+        T: Clone,
+    {
+        let cached = self.cache.map_value_ref(&index, |v| v.get().map(|e| e.value().clone()));
+        match cached.flatten() {
+            Some(value) => value,
+            None => env::storage_read(&self.element_key(index))
+                .as_deref()
+                .map(Self::deserialize_element),
+        }
+    }
+
     /// Returns a mutable reference to the element at the `index` provided.
     pub(crate) fn get_mut_inner(&mut self, index: u32) -> &mut CacheEntry<T> {
         let prefix = &self.prefix;
@@ -145,7 +167,6 @@ where
     }
 
     /// Removes value at index and returns existing value.
-    #[allow(dead_code)]
     pub fn remove(&mut self, index: u32) -> Option<T> {
         self.get_mut_inner(index).replace(None)
     }
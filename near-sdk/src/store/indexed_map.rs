@@ -0,0 +1,417 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::LookupMap;
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+const ERR_ELEMENT_SERIALIZATION: &str = "Cannot serialize element";
+
+/// A key of a value in one of the secondary indexes of an [`IndexedMap`].
+///
+/// The key is stored as the [`BorshSerialize`] bytes of the indexed field, so the type used to
+/// create an [`IndexKey`] in [`Indexed::index_keys`] must serialize the same way as the type used
+/// to query the index with [`IndexedMap::get_by_index`].
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IndexKey {
+    index: u8,
+    key: Vec<u8>,
+}
+
+impl IndexKey {
+    /// Creates the key of a value in the secondary index with the given number.
+    pub fn new<I>(index: u8, key: &I) -> Self
+    where
+        I: BorshSerialize + ?Sized,
+    {
+        let key = key.try_to_vec().unwrap_or_else(|_| env::panic_str(ERR_ELEMENT_SERIALIZATION));
+        Self { index, key }
+    }
+}
+
+/// Values that can be stored in an [`IndexedMap`], which defines the secondary indexes a value
+/// is listed under.
+pub trait Indexed {
+    /// Returns the keys of this value in the secondary indexes of the map. A value can have any
+    /// number of keys in each index, including none.
+    ///
+    /// This must only depend on the value, and return the same keys every time it is called.
+    fn index_keys(&self) -> Vec<IndexKey>;
+}
+
+/// A non-iterable storage map, similar to [`LookupMap`], which also keeps secondary indexes of
+/// the keys of the map by fields of the values.
+///
+/// Secondary indexes are defined by implementing [`Indexed`] for the value type, and are kept in
+/// sync with the map on every [`insert`] and [`remove`]. The keys of all entries with the same
+/// index key are stored together in a single storage value, so each index key should only be
+/// shared by a limited number of entries.
+///
+/// Values can only be modified by replacing them with [`insert`], since changes through a
+/// mutable reference would not update the indexes.
+///
+/// # Examples
+/// ```
+/// use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+/// use near_sdk::store::indexed_map::{IndexKey, Indexed, IndexedMap};
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct Offer {
+///     owner: String,
+///     expires_at: u64,
+/// }
+///
+/// const BY_OWNER: u8 = 0;
+/// const BY_EXPIRY: u8 = 1;
+///
+/// impl Indexed for Offer {
+///     fn index_keys(&self) -> Vec<IndexKey> {
+///         vec![IndexKey::new(BY_OWNER, &self.owner), IndexKey::new(BY_EXPIRY, &self.expires_at)]
+///     }
+/// }
+///
+/// let mut offers = IndexedMap::new(b"o");
+/// offers.insert(1u64, Offer { owner: "alice".to_string(), expires_at: 10 });
+/// offers.insert(2u64, Offer { owner: "bob".to_string(), expires_at: 10 });
+/// offers.insert(3u64, Offer { owner: "alice".to_string(), expires_at: 20 });
+///
+/// assert_eq!(offers.get_by_index(BY_OWNER, "alice"), &[1, 3]);
+/// assert_eq!(offers.get_by_index(BY_EXPIRY, &10u64), &[1, 2]);
+///
+/// offers.remove(&1);
+/// assert_eq!(offers.get_by_index(BY_OWNER, "alice"), &[3]);
+/// assert_eq!(offers.get_by_index(BY_EXPIRY, &10u64), &[2]);
+/// ```
+///
+/// [`insert`]: Self::insert
+/// [`remove`]: Self::remove
+pub struct IndexedMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    values: LookupMap<K, V, H>,
+    index: LookupMap<IndexKey, Vec<K>, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for IndexedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.values, writer)?;
+        BorshSerialize::serialize(&self.index, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for IndexedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            values: BorshDeserialize::deserialize(buf)?,
+            index: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    IndexedMap<K, V, H> {
+        values: LookupMap<K, V, H>,
+        index: LookupMap<IndexKey, Vec<K>, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> fmt::Debug for IndexedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedMap")
+            .field("values", &self.values)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<K, V> IndexedMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create a new map. Use `prefix` as a unique prefix for the keys of the map and its
+    /// secondary indexes.
+    #[inline]
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> IndexedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`IndexedMap`] with a custom hash function.
+    ///
+    /// # Example
+    /// ```
+    /// use near_sdk::crypto_hash::Keccak256;
+    /// use near_sdk::store::IndexedMap;
+    ///
+    /// let map = IndexedMap::<String, String, Keccak256>::with_hasher(b"m");
+    /// ```
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut values_key = prefix.into_storage_key();
+        let index_key = [values_key.as_slice(), b"i"].concat();
+        values_key.push(b'v');
+        Self {
+            values: LookupMap::with_hasher(values_key),
+            index: LookupMap::with_hasher(index_key),
+        }
+    }
+
+    /// Flushes the intermediate values of the map and its indexes before this is called when the
+    /// structure is [`Drop`]ed. This will write all modified values to storage but keep all
+    /// cached values in memory.
+    pub fn flush(&mut self) {
+        self.values.flush();
+        self.index.flush();
+    }
+}
+
+impl<K, V, H> IndexedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Indexed,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match those for
+    /// the key type.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.values.get(k)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match those for
+    /// the key type.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.values.contains_key(k)
+    }
+
+    /// Returns the keys of all entries listed under `key` in the secondary index with the given
+    /// number, in the order they were added to the index.
+    ///
+    /// `key` must serialize the same way as the [`IndexKey`]s returned by [`Indexed::index_keys`].
+    pub fn get_by_index<I>(&self, index: u8, key: &I) -> &[K]
+    where
+        I: BorshSerialize + ?Sized,
+    {
+        self.index.get(&IndexKey::new(index, key)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Inserts a key-value pair into the map, and updates the secondary indexes to list the key
+    /// under the index keys of the new value.
+    ///
+    /// If the map did not have this key present, [`None`] is returned. If the map did have this
+    /// key present, the value is updated, and the old value is returned.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        let index_keys = v.index_keys();
+        let old = self.values.insert(k.clone(), v);
+        if let Some(old) = &old {
+            self.unindex(&k, old.index_keys());
+        }
+        for index_key in index_keys {
+            let keys = self.index.entry(index_key).or_default();
+            if !keys.contains(&k) {
+                keys.push(k.clone());
+            }
+        }
+        old
+    }
+
+    /// Removes a key from the map and its secondary indexes, returning the value at the key if
+    /// the key was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match those for
+    /// the key type.
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let old = self.values.remove(k)?;
+        self.unindex(&k.to_owned(), old.index_keys());
+        Some(old)
+    }
+
+    /// Removes `k` from the entries of the secondary indexes under `index_keys`.
+    fn unindex(&mut self, k: &K, index_keys: Vec<IndexKey>) {
+        for index_key in index_keys {
+            let entry = self.index.get_mut_inner(&index_key);
+            if let Some(keys) = entry.value_mut() {
+                keys.retain(|key| key != k);
+                if keys.is_empty() {
+                    entry.replace(None);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for IndexedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Indexed,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::{IndexKey, Indexed, IndexedMap};
+    use crate::test_utils::test_env::setup_free;
+    use arbitrary::{Arbitrary, Unstructured};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::RngCore;
+    use rand::SeedableRng;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(BorshSerialize, BorshDeserialize, Arbitrary, Debug, Clone, PartialEq)]
+    struct Entry {
+        owner: u8,
+        tags: Vec<u8>,
+    }
+
+    impl Indexed for Entry {
+        fn index_keys(&self) -> Vec<IndexKey> {
+            let mut keys = vec![IndexKey::new(0, &self.owner)];
+            keys.extend(self.tags.iter().map(|tag| IndexKey::new(1, tag)));
+            keys
+        }
+    }
+
+    #[derive(Arbitrary, Debug)]
+    enum Op {
+        Insert(u8, Entry),
+        Remove(u8),
+        Flush,
+        Restore,
+    }
+
+    #[test]
+    fn arbitrary() {
+        setup_free();
+
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut buf = vec![0; 4096];
+        for _ in 0..256 {
+            // Clear storage in-between runs
+            crate::mock::with_mocked_blockchain(|b| b.take_storage());
+            rng.fill_bytes(&mut buf);
+
+            let mut map = IndexedMap::new(b"m");
+            let mut baseline = HashMap::new();
+            let u = Unstructured::new(&buf);
+            if let Ok(ops) = Vec::<Op>::arbitrary_take_rest(u) {
+                for op in ops {
+                    match op {
+                        Op::Insert(k, v) => {
+                            let r1 = map.insert(k, v.clone());
+                            let r2 = baseline.insert(k, v);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Remove(k) => {
+                            let r1 = map.remove(&k);
+                            let r2 = baseline.remove(&k);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Flush => {
+                            map.flush();
+                        }
+                        Op::Restore => {
+                            let serialized = map.try_to_vec().unwrap();
+                            map = IndexedMap::deserialize(&mut serialized.as_slice()).unwrap();
+                        }
+                    }
+                }
+            }
+
+            // Secondary indexes match the values of the map.
+            let mut by_owner: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+            let mut by_tag: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+            for (k, v) in &baseline {
+                by_owner.entry(v.owner).or_default().push(*k);
+                for tag in &v.tags {
+                    let keys = by_tag.entry(*tag).or_default();
+                    if !keys.contains(k) {
+                        keys.push(*k);
+                    }
+                }
+            }
+            for i in 0..=u8::MAX {
+                let mut owned = map.get_by_index(0, &i).to_vec();
+                owned.sort_unstable();
+                let mut expected = by_owner.remove(&i).unwrap_or_default();
+                expected.sort_unstable();
+                assert_eq!(owned, expected);
+
+                let mut tagged = map.get_by_index(1, &i).to_vec();
+                tagged.sort_unstable();
+                let mut expected = by_tag.remove(&i).unwrap_or_default();
+                expected.sort_unstable();
+                assert_eq!(tagged, expected);
+            }
+        }
+    }
+}
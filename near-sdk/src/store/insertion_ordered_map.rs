@@ -0,0 +1,584 @@
+//! A map which iterates over its entries in the order the keys were inserted.
+
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+use std::{fmt, mem};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{vec, LookupMap, Vector, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+/// A lazily loaded storage map, similar to [`UnorderedMap`](crate::store::UnorderedMap), which
+/// iterates over its entries in the order their keys were first inserted.
+///
+/// [`UnorderedMap`](crate::store::UnorderedMap) reuses the position of a removed key for the next
+/// inserted key, so the iteration order changes after removals. This map instead leaves an empty
+/// slot in place of a removed key and always adds new keys at the end, so the order of the
+/// remaining keys never changes. Replacing the value of a key which is in the map keeps its
+/// position.
+///
+/// Empty slots are still read when iterating over the map. After many removals, they can be
+/// removed with [`compact_bounded`], which moves the remaining keys towards the front without
+/// changing their order.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::InsertionOrderedMap;
+///
+/// let mut listings = InsertionOrderedMap::new(b"l");
+/// listings.insert("c".to_string(), 3u32);
+/// listings.insert("a".to_string(), 1);
+/// listings.insert("b".to_string(), 2);
+/// listings.remove("c");
+/// listings.insert("d".to_string(), 4);
+///
+/// let keys: Vec<&str> = listings.keys().map(String::as_str).collect();
+/// assert_eq!(keys, ["a", "b", "d"]);
+///
+/// assert!(listings.compact_bounded(10));
+/// assert_eq!(listings.iter().next(), Some((&"a".to_string(), &1)));
+/// ```
+///
+/// [`compact_bounded`]: Self::compact_bounded
+pub struct InsertionOrderedMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Number of keys in the map.
+    len: u32,
+    /// Position before which there are no empty slots.
+    dense_until: u32,
+    /// Position where compaction continues. All slots from `dense_until` up to this position are
+    /// empty.
+    scan_from: u32,
+    /// Keys in insertion order, where removed keys leave an empty slot.
+    keys: Vector<Option<K>>,
+    values: LookupMap<K, ValueAndIndex<V>, H>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct ValueAndIndex<V> {
+    value: V,
+    key_index: u32,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.len, writer)?;
+        BorshSerialize::serialize(&self.dense_until, writer)?;
+        BorshSerialize::serialize(&self.scan_from, writer)?;
+        BorshSerialize::serialize(&self.keys, writer)?;
+        BorshSerialize::serialize(&self.values, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            len: BorshDeserialize::deserialize(buf)?,
+            dense_until: BorshDeserialize::deserialize(buf)?,
+            scan_from: BorshDeserialize::deserialize(buf)?,
+            keys: BorshDeserialize::deserialize(buf)?,
+            values: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    InsertionOrderedMap<K, V, H> {
+        len: u32,
+        dense_until: u32,
+        scan_from: u32,
+        keys: Vector<Option<K>>,
+        values: LookupMap<K, ValueAndIndex<V>, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> fmt::Debug for InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InsertionOrderedMap")
+            .field("len", &self.len)
+            .field("keys", &self.keys)
+            .field("values", &self.values)
+            .finish()
+    }
+}
+
+impl<K, V> InsertionOrderedMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create a new empty map which uses the given prefix for the storage keys of its entries.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize an [`InsertionOrderedMap`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut vec_key = prefix.into_storage_key();
+        let map_key = [vec_key.as_slice(), b"m"].concat();
+        vec_key.push(b'v');
+        Self {
+            len: 0,
+            dense_until: 0,
+            scan_from: 0,
+            keys: Vector::new(vec_key),
+            values: LookupMap::with_hasher(map_key),
+        }
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the map contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of empty slots left by removed keys, which are read when iterating over
+    /// the map until they are removed with [`compact_bounded`](Self::compact_bounded).
+    pub fn empty_slots(&self) -> u32 {
+        self.keys.len() - self.len
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.keys.flush();
+        self.values.flush();
+    }
+}
+
+impl<K, V, H> InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.values.get(k).map(|v| &v.value)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.values.get_mut(k).map(|v| &mut v.value)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.values.contains_key(k)
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, the key is added after all other keys and
+    /// [`None`] is returned. If the map did have this key present, the value is updated without
+    /// changing the position of the key, and the old value is returned.
+    pub fn insert(&mut self, k: K, value: V) -> Option<V>
+    where
+        K: Clone,
+    {
+        let entry = self.values.get_mut_inner(&k);
+        if let Some(existing) = entry.value_mut() {
+            return Some(mem::replace(&mut existing.value, value));
+        }
+
+        let key_index = self.keys.len();
+        self.keys.push(Some(k));
+        if self.dense_until == key_index {
+            // There are no empty slots, so compaction does not need to visit the new key.
+            self.dense_until += 1;
+            self.scan_from += 1;
+        }
+        entry.replace(Some(ValueAndIndex { value, key_index }));
+        self.len += 1;
+        None
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in
+    /// the map. The positions of the other keys do not change.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let ValueAndIndex { value, key_index } = self.values.remove(k)?;
+        self.keys.set(key_index, None);
+        self.len -= 1;
+        if key_index < self.dense_until {
+            // Compaction restarts from the new empty slot. Slots which were emptied by an earlier
+            // compaction are found again when it reaches them.
+            self.dense_until = key_index;
+            self.scan_from = key_index + 1;
+        }
+        Some(value)
+    }
+
+    /// Removes empty slots left by removed keys, reading or writing at most `max_steps` slots.
+    /// Returns `true` once the map has no empty slots, otherwise compaction can be continued with
+    /// another call.
+    ///
+    /// Keys after an empty slot are moved towards the front in order, so the iteration order
+    /// does not change, and the map can be used as normal between calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::InsertionOrderedMap;
+    ///
+    /// let mut map = InsertionOrderedMap::new(b"m");
+    /// for i in 0u32..100 {
+    ///     map.insert(i, ());
+    /// }
+    /// for i in (0..100).step_by(2) {
+    ///     map.remove(&i);
+    /// }
+    /// assert_eq!(map.empty_slots(), 50);
+    ///
+    /// while !map.compact_bounded(30) {}
+    /// assert_eq!(map.empty_slots(), 0);
+    /// assert!(map.keys().copied().eq((1..100).step_by(2)));
+    /// ```
+    pub fn compact_bounded(&mut self, max_steps: u32) -> bool
+    where
+        K: Clone,
+    {
+        let mut steps = 0;
+        while steps < max_steps && self.scan_from < self.keys.len() {
+            let index = self.scan_from;
+            if !matches!(self.keys.get(index), Some(Some(_))) {
+                // Empty slot, which is left behind to be removed at the end.
+            } else if index == self.dense_until {
+                self.dense_until += 1;
+            } else {
+                let key = self.keys.replace(index, None).unwrap_or_else(|| env::abort());
+                match self.values.get_mut(&key) {
+                    Some(entry) => entry.key_index = self.dense_until,
+                    None => env::panic_str(ERR_INCONSISTENT_STATE),
+                }
+                self.keys.set(self.dense_until, Some(key));
+                self.dense_until += 1;
+            }
+            self.scan_from += 1;
+            steps += 1;
+        }
+        if self.scan_from == self.keys.len() {
+            // All slots after `dense_until` are empty.
+            while steps < max_steps && self.keys.len() > self.dense_until {
+                self.keys.pop();
+                steps += 1;
+            }
+            self.scan_from = self.keys.len();
+        }
+        self.keys.len() == self.len
+    }
+
+    /// An iterator visiting all key-value pairs in the order the keys were inserted.
+    /// The iterator element type is `(&'a K, &'a V)`.
+    pub fn iter(&self) -> Iter<K, V, H> {
+        Iter { keys: self.keys(), values: &self.values }
+    }
+
+    /// An iterator visiting all keys in the order they were inserted.
+    /// The iterator element type is `&'a K`.
+    pub fn keys(&self) -> Keys<K> {
+        Keys { inner: self.keys.iter(), remaining: self.len }
+    }
+
+    /// An iterator visiting all values in the order their keys were inserted.
+    /// The iterator element type is `&'a V`.
+    pub fn values(&self) -> Values<K, V, H> {
+        Values { inner: self.iter() }
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K, V, H> IntoIterator for &'a InsertionOrderedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the keys of an [`InsertionOrderedMap`] in insertion order.
+///
+/// This `struct` is created by the `keys` method on [`InsertionOrderedMap`].
+pub struct Keys<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize,
+{
+    /// Slots of the keys, which contain empty slots of removed keys.
+    inner: vec::Iter<'a, Option<K>>,
+    /// Number of keys which have not been yielded, after which the remaining slots are empty.
+    remaining: u32,
+}
+
+impl<'a, K> Iterator for Keys<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let key = self.inner.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining as usize, Some(self.remaining as usize))
+    }
+}
+
+impl<'a, K> ExactSizeIterator for Keys<'a, K> where K: BorshSerialize + BorshDeserialize {}
+impl<'a, K> FusedIterator for Keys<'a, K> where K: BorshSerialize + BorshDeserialize {}
+
+/// An iterator over the entries of an [`InsertionOrderedMap`] in insertion order.
+///
+/// This `struct` is created by the `iter` method on [`InsertionOrderedMap`].
+pub struct Iter<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    keys: Keys<'a, K>,
+    values: &'a LookupMap<K, ValueAndIndex<V>, H>,
+}
+
+impl<'a, K, V, H> Iterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        let entry = self.values.get(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        Some((key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+impl<'a, K, V, H> FusedIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// An iterator over the values of an [`InsertionOrderedMap`] in the insertion order of their
+/// keys.
+///
+/// This `struct` is created by the `values` method on [`InsertionOrderedMap`].
+pub struct Values<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    inner: Iter<'a, K, V, H>,
+}
+
+impl<'a, K, V, H> Iterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+impl<'a, K, V, H> FusedIterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::InsertionOrderedMap;
+    use crate::test_utils::test_env::setup_free;
+
+    #[test]
+    fn insertion_order() {
+        setup_free();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut map = InsertionOrderedMap::new(b"m");
+        let mut baseline: Vec<(u32, u64)> = Vec::new();
+        for _ in 0..2000 {
+            let key = rng.gen_range(0..100);
+            match rng.gen_range(0..10) {
+                0..=4 => {
+                    let value = rng.gen::<u64>();
+                    let expected = match baseline.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, v)) => Some(std::mem::replace(v, value)),
+                        None => {
+                            baseline.push((key, value));
+                            None
+                        }
+                    };
+                    assert_eq!(map.insert(key, value), expected);
+                }
+                5..=7 => {
+                    let expected =
+                        baseline.iter().position(|(k, _)| *k == key).map(|i| baseline.remove(i).1);
+                    assert_eq!(map.remove(&key), expected);
+                }
+                8 => {
+                    map.compact_bounded(rng.gen_range(0..20));
+                }
+                _ => {
+                    map.flush();
+                    map = InsertionOrderedMap::try_from_slice(&map.try_to_vec().unwrap()).unwrap();
+                }
+            }
+            assert_eq!(map.len() as usize, baseline.len());
+            assert_eq!(map.iter().len(), baseline.len());
+            assert!(map.iter().map(|(k, v)| (*k, *v)).eq(baseline.iter().copied()));
+        }
+
+        while !map.compact_bounded(7) {}
+        assert_eq!(map.empty_slots(), 0);
+        assert!(map.iter().map(|(k, v)| (*k, *v)).eq(baseline.iter().copied()));
+        for (k, v) in &baseline {
+            assert_eq!(map.get(k), Some(v));
+        }
+    }
+}
@@ -72,6 +72,14 @@ where
     cache: OnceCell<CacheEntry<T>>,
 }
 
+impl_borsh_schema! {
+    Lazy<T> {
+        storage_key: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize,
+}
+
 impl<T> Lazy<T>
 where
     T: BorshSerialize,
@@ -113,6 +121,74 @@ where
             }
         }
     }
+
+    /// Returns `true` if the value has been modified since it was loaded from or last written to
+    /// storage, meaning it will be written to storage on [`flush`](Self::flush) or [`Drop`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Lazy;
+    ///
+    /// let mut a = Lazy::new(b"a", 8u32);
+    /// assert!(a.is_dirty());
+    /// a.flush();
+    /// assert!(!a.is_dirty());
+    /// *a += 1;
+    /// assert!(a.is_dirty());
+    /// ```
+    pub fn is_dirty(&self) -> bool {
+        matches!(self.cache.get(), Some(v) if v.is_modified())
+    }
+
+    /// Marks the cached value as matching the value in storage, so that it is not written on
+    /// [`flush`](Self::flush) or [`Drop`]. This can be used to skip writing a value which was
+    /// accessed mutably but not changed.
+    ///
+    /// Any changes made to the cached value since it was loaded are not written to storage, but
+    /// the changed value is kept in memory until [`reload`](Self::reload) is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Lazy;
+    ///
+    /// let mut a = Lazy::new(b"a", 8u32);
+    /// a.flush();
+    ///
+    /// // Mutable access marks the value as modified, even if it is unchanged.
+    /// let _ = a.get_mut();
+    /// assert!(a.is_dirty());
+    /// a.set_clean();
+    /// assert!(!a.is_dirty());
+    /// ```
+    pub fn set_clean(&mut self) {
+        if let Some(v) = self.cache.get_mut() {
+            v.replace_state(EntryState::Cached);
+        }
+    }
+
+    /// Discards the cached value, so that the value is read from storage again the next time it
+    /// is accessed. This can be used when the value in storage may have been changed outside of
+    /// this instance, such as by a re-entrant call before a callback.
+    ///
+    /// Any changes to the value which have not been [`flush`](Self::flush)ed are lost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Lazy;
+    ///
+    /// let mut a = Lazy::new(b"a", 8u32);
+    /// a.flush();
+    ///
+    /// *a = 9;
+    /// a.reload();
+    /// assert_eq!(*a, 8);
+    /// ```
+    pub fn reload(&mut self) {
+        self.cache = OnceCell::new();
+    }
 }
 
 impl<T> Lazy<T>
@@ -178,4 +254,35 @@ mod tests {
         // be checked for equality.
         assert_eq!(lazy_loaded, b);
     }
+
+    #[test]
+    pub fn test_dirty_tracking() {
+        let mut a = Lazy::new(b"a", 8u32);
+        assert!(a.is_dirty());
+        a.flush();
+        assert!(!a.is_dirty());
+
+        // A clean value is not written to storage.
+        *a = 9;
+        assert!(a.is_dirty());
+        a.set_clean();
+        assert!(!a.is_dirty());
+        a.flush();
+        assert_eq!(*a, 9);
+        assert_eq!(u32::try_from_slice(&env::storage_read(b"a").unwrap()).unwrap(), 8);
+
+        // Reloading reads the value written to storage by another instance.
+        let mut b = Lazy::new(b"a", 10u32);
+        b.flush();
+        a.reload();
+        assert!(a.cache.get().is_none());
+        assert!(!a.is_dirty());
+        assert_eq!(*a, 10);
+
+        // Reloading discards unflushed changes.
+        a.set(11);
+        a.reload();
+        drop(a);
+        assert_eq!(u32::try_from_slice(&env::storage_read(b"a").unwrap()).unwrap(), 10);
+    }
 }
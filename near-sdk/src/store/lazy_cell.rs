@@ -0,0 +1,216 @@
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use once_cell::unsync::OnceCell;
+
+use crate::env;
+
+const ERR_VALUE_DESERIALIZATION: &str = "Cannot deserialize value with Borsh";
+
+/// A value which is only deserialized when it is first accessed, to be used as the value type
+/// of a collection.
+///
+/// Collections deserialize each element they load, so iterating over an
+/// [`UnorderedMap`](super::UnorderedMap) or a [`Vector`](super::Vector) with large values pays
+/// for deserializing every value, even if only a few of them are used. Wrapping the values in a
+/// [`LazyCell`] only copies their bytes when they are loaded, and each value is deserialized the
+/// first time [`get`](Self::get) or [`get_mut`](Self::get_mut) is called on it.
+///
+/// A [`LazyCell`] is serialized as the Borsh serialization of its value, prefixed by its length
+/// as a `u32`, so it can't be used to read values which were stored without the wrapper. A value
+/// which was not accessed mutably is written back as the bytes it was loaded from.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::{LazyCell, UnorderedMap};
+///
+/// let mut profiles: UnorderedMap<String, LazyCell<Vec<u64>>> = UnorderedMap::new(b"p");
+/// profiles.insert("alice.near".to_string(), LazyCell::new(vec![1; 1000]));
+/// profiles.insert("bob.near".to_string(), LazyCell::new(vec![2; 1000]));
+/// profiles.flush();
+///
+/// // Only the value which is used is deserialized.
+/// for (account, profile) in profiles.iter() {
+///     if account == "bob.near" {
+///         assert_eq!(profile.get()[0], 2);
+///     }
+/// }
+/// ```
+pub struct LazyCell<T> {
+    /// Serialized value, if it is known to match the value.
+    bytes: Option<Vec<u8>>,
+    value: OnceCell<T>,
+}
+
+impl<T> BorshSerialize for LazyCell<T>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        match (&self.bytes, self.value.get()) {
+            (Some(bytes), _) => BorshSerialize::serialize(bytes, writer),
+            (None, Some(value)) => BorshSerialize::serialize(&value.try_to_vec()?, writer),
+            (None, None) => env::abort(),
+        }
+    }
+}
+
+impl<T> BorshDeserialize for LazyCell<T> {
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self { bytes: Some(BorshDeserialize::deserialize(buf)?), value: OnceCell::new() })
+    }
+}
+
+impl_borsh_schema! {
+    LazyCell<T> {
+        bytes: Vec<u8>,
+    }
+}
+
+impl<T> fmt::Debug for LazyCell<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("LazyCell").field(value).finish(),
+            None => f.debug_tuple("LazyCell").field(&format_args!("<not loaded>")).finish(),
+        }
+    }
+}
+
+impl<T> LazyCell<T> {
+    /// Wraps a value, which is serialized when the cell is.
+    pub fn new(value: T) -> Self {
+        Self { bytes: None, value: OnceCell::from(value) }
+    }
+
+    /// Returns `true` if the value has been deserialized, or if the cell was created from a
+    /// value.
+    pub fn is_loaded(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Replaces the value. This does not deserialize the current value.
+    pub fn set(&mut self, value: T) {
+        self.bytes = None;
+        self.value = OnceCell::from(value);
+    }
+}
+
+impl<T> LazyCell<T>
+where
+    T: BorshDeserialize,
+{
+    /// Returns a reference to the value, deserializing it if this is the first access.
+    pub fn get(&self) -> &T {
+        self.value.get_or_init(|| {
+            let bytes = self.bytes.as_deref().unwrap_or_else(|| env::abort());
+            T::try_from_slice(bytes).unwrap_or_else(|_| env::panic_str(ERR_VALUE_DESERIALIZATION))
+        })
+    }
+
+    /// Returns a mutable reference to the value, deserializing it if this is the first access.
+    /// The value is serialized again when the cell is.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.get();
+        self.bytes = None;
+        self.value.get_mut().unwrap_or_else(|| env::abort())
+    }
+
+    /// Returns the value, deserializing it if it was not accessed.
+    pub fn into_inner(self) -> T {
+        self.get();
+        self.value.into_inner().unwrap_or_else(|| env::abort())
+    }
+}
+
+impl<T> From<T> for LazyCell<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> core::ops::Deref for LazyCell<T>
+where
+    T: BorshDeserialize,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        Self::get(self)
+    }
+}
+
+impl<T> core::ops::DerefMut for LazyCell<T>
+where
+    T: BorshDeserialize,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Self::get_mut(self)
+    }
+}
+
+impl<T> PartialEq for LazyCell<T>
+where
+    T: PartialEq + BorshDeserialize,
+{
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(self.get(), other.get())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use super::LazyCell;
+    use crate::store::{UnorderedMap, Vector};
+
+    #[test]
+    fn serialized_as_bytes_of_value() {
+        let value = vec!["a".to_string(), "bc".to_string()];
+        let serialized = LazyCell::new(value.clone()).try_to_vec().unwrap();
+        assert_eq!(serialized, value.try_to_vec().unwrap().try_to_vec().unwrap());
+
+        let mut cell = LazyCell::<Vec<String>>::try_from_slice(&serialized).unwrap();
+        assert!(!cell.is_loaded());
+        assert_eq!(cell.try_to_vec().unwrap(), serialized);
+        assert_eq!(*cell.get(), value);
+
+        cell.get_mut().push("d".to_string());
+        let cell = LazyCell::<Vec<String>>::try_from_slice(&cell.try_to_vec().unwrap()).unwrap();
+        assert_eq!(cell.into_inner(), ["a", "bc", "d"]);
+    }
+
+    #[test]
+    fn loaded_on_access() {
+        let mut map = UnorderedMap::new(b"m");
+        let mut vec = Vector::new(b"v");
+        for i in 0..10u32 {
+            map.insert(i, LazyCell::new(vec![i; 100]));
+            vec.push(LazyCell::new(i.to_string()));
+        }
+        let (map_bytes, vec_bytes) = (map.try_to_vec().unwrap(), vec.try_to_vec().unwrap());
+        drop(map);
+        drop(vec);
+
+        let mut map = UnorderedMap::<u32, LazyCell<Vec<u32>>>::try_from_slice(&map_bytes).unwrap();
+        let vec = Vector::<LazyCell<String>>::try_from_slice(&vec_bytes).unwrap();
+        assert!(map.values().all(|cell| !cell.is_loaded()));
+        assert!(vec.iter().all(|cell| !cell.is_loaded()));
+        assert_eq!(map[&3].get()[99], 3);
+        assert_eq!(map.values().filter(|cell| cell.is_loaded()).count(), 1);
+        assert_eq!(*vec[7], "7");
+
+        map.get_mut(&5).unwrap().set(vec![0]);
+        drop(map);
+        let map = UnorderedMap::<u32, LazyCell<Vec<u32>>>::try_from_slice(&map_bytes).unwrap();
+        assert_eq!(*map[&5], [0]);
+        assert_eq!(map[&4].len(), 100);
+    }
+}
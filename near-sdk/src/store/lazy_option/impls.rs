@@ -1,4 +1,6 @@
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde-collections")]
+use serde::{Serialize, Serializer};
 
 use super::LazyOption;
 
@@ -43,3 +45,17 @@ where
         }
     }
 }
+
+/// Serializes the value as an optional value, which loads it from storage.
+#[cfg(feature = "serde-collections")]
+impl<T> Serialize for LazyOption<T>
+where
+    T: BorshSerialize + BorshDeserialize + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Serialize::serialize(self.get(), serializer)
+    }
+}
@@ -29,6 +29,13 @@ use crate::IntoStorageKey;
 /// let old_str = a.replace("new new value".to_owned());
 /// assert_eq!(old_str, Some("new value".to_owned()));
 /// assert_eq!(a.get(), &Some("new new value".to_owned()));
+///
+/// // Using Option::take, which removes the value from storage when flushed:
+/// assert_eq!(a.take(), Some("new new value".to_owned()));
+///
+/// // Using Option::get_or_insert_with:
+/// a.get_or_insert_with(|| "default".to_owned()).push_str(" value");
+/// assert_eq!(a.get(), &Some("default value".to_owned()));
 /// ```
 /// [`Deref`]: std::ops::Deref
 #[derive(BorshSerialize, BorshDeserialize)]
@@ -44,6 +51,14 @@ where
     cache: OnceCell<CacheEntry<T>>,
 }
 
+impl_borsh_schema! {
+    LazyOption<T> {
+        storage_key: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize,
+}
+
 impl<T> LazyOption<T>
 where
     T: BorshSerialize,
@@ -118,6 +133,33 @@ where
         let entry = self.cache.get_mut().unwrap_or_else(|| env::abort());
         entry.value_mut()
     }
+
+    /// Maps the contained value in place by applying `f` to it, if the option is [`Some`]. The
+    /// value is moved into `f`, so this does not require cloning the value to update it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LazyOption;
+    ///
+    /// let mut a = LazyOption::new(b"a", Some(vec![1u8, 2, 3]));
+    /// a.map_in_place(|v| v.into_iter().map(|x| x * 2).collect());
+    /// assert_eq!(a.get(), &Some(vec![2, 4, 6]));
+    ///
+    /// let mut b = LazyOption::<Vec<u8>>::new(b"b", None);
+    /// b.map_in_place(|_| unreachable!());
+    /// assert!(b.is_none());
+    /// ```
+    pub fn map_in_place<F>(&mut self, f: F)
+    where
+        F: FnOnce(T) -> T,
+    {
+        // Only load the value mutably when it exists, to not mark a `None` value as modified.
+        if self.get().is_some() {
+            let value = self.get_mut();
+            *value = value.take().map(f);
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -161,6 +203,27 @@ mod tests {
         assert!(!env::storage_has_key(b"a"));
     }
 
+    #[test]
+    pub fn test_map_in_place() {
+        let mut a = LazyOption::new(b"a", Some(2u32));
+        a.map_in_place(|v| v * 10);
+        assert_eq!(a.get(), &Some(20));
+        a.flush();
+        assert_eq!(u32::try_from_slice(&env::storage_read(b"a").unwrap()).unwrap(), 20);
+
+        // Mapping a value loaded from storage writes the mapped value on drop.
+        let serialized = a.try_to_vec().unwrap();
+        drop(a);
+        let mut a = LazyOption::<u32>::try_from_slice(&serialized).unwrap();
+        a.map_in_place(|v| v + 1);
+        drop(a);
+        assert_eq!(u32::try_from_slice(&env::storage_read(b"a").unwrap()).unwrap(), 21);
+
+        let mut b = LazyOption::<u32>::new(b"b", None);
+        b.map_in_place(|_| unreachable!());
+        assert!(b.is_none());
+    }
+
     #[test]
     pub fn test_debug() {
         let mut lazy_option = LazyOption::new(b"m", None);
@@ -177,4 +240,13 @@ mod tests {
             assert_eq!(format!("{:?}", lazy_option), "LazyOption { storage_key: [109] }");
         }
     }
+
+    #[cfg(feature = "serde-collections")]
+    #[test]
+    pub fn test_serialize_json() {
+        let mut lazy_option = LazyOption::new(b"m", None);
+        assert_eq!(serde_json::to_string(&lazy_option).unwrap(), "null");
+        lazy_option.set(Some(5u8));
+        assert_eq!(serde_json::to_string(&lazy_option).unwrap(), "5");
+    }
 }
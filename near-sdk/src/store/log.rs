@@ -0,0 +1,287 @@
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{LookupMap, ERR_INCONSISTENT_STATE};
+use crate::{env, IntoStorageKey};
+
+/// A lazily loaded append-only log, which assigns each entry a sequence number that increases
+/// by one for every appended entry.
+///
+/// Old entries can be removed with [`prune_before`], which removes a bounded number of entries
+/// on each call so that pruning a large number of entries can be spread over multiple function
+/// calls. The sequence numbers of the remaining entries do not change.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::Log;
+///
+/// let mut events = Log::new(b"e");
+/// for i in 0u32..5 {
+///     events.append(format!("event {}", i));
+/// }
+/// assert_eq!(events.get(3), Some(&"event 3".to_string()));
+///
+/// let recent: Vec<(u64, &String)> = events.range(3..).collect();
+/// assert_eq!(recent, [(3, &"event 3".to_string()), (4, &"event 4".to_string())]);
+///
+/// // Remove the entries before sequence number 2, at most 10 at a time.
+/// assert!(events.prune_before(2, 10));
+/// assert_eq!(events.get(1), None);
+/// assert_eq!(events.first_seq(), 2);
+/// assert_eq!(events.len(), 3);
+/// ```
+///
+/// [`prune_before`]: Self::prune_before
+pub struct Log<T>
+where
+    T: BorshSerialize,
+{
+    /// Sequence number of the first entry which has not been pruned.
+    start: u64,
+    /// Sequence number of the next appended entry.
+    end: u64,
+    entries: LookupMap<u64, T>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T> BorshSerialize for Log<T>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.start, writer)?;
+        BorshSerialize::serialize(&self.end, writer)?;
+        BorshSerialize::serialize(&self.entries, writer)?;
+        Ok(())
+    }
+}
+
+impl<T> BorshDeserialize for Log<T>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            start: BorshDeserialize::deserialize(buf)?,
+            end: BorshDeserialize::deserialize(buf)?,
+            entries: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    Log<T> {
+        start: u64,
+        end: u64,
+        entries: LookupMap<u64, T>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+impl<T> fmt::Debug for Log<T>
+where
+    T: BorshSerialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Log")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+impl<T> Log<T>
+where
+    T: BorshSerialize,
+{
+    /// Create a new empty log which uses the given prefix for the storage keys of its entries.
+    /// The first appended entry has sequence number `0`.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { start: 0, end: 0, entries: LookupMap::new(prefix) }
+    }
+
+    /// Returns the number of entries in the log which have not been pruned.
+    pub fn len(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the log contains no entries, either because none were appended or
+    /// because all were pruned.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns the sequence number of the first entry which has not been pruned.
+    pub fn first_seq(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the sequence number that the next appended entry will have.
+    pub fn next_seq(&self) -> u64 {
+        self.end
+    }
+
+    /// Appends an entry to the log, returning its sequence number.
+    pub fn append(&mut self, entry: T) -> u64 {
+        let seq = self.end;
+        self.entries.set(seq, Some(entry));
+        self.end += 1;
+        seq
+    }
+
+    /// Removes the entries with a sequence number less than `seq`, removing at most
+    /// `max_removals` entries. Returns `true` if all entries before `seq` are removed, otherwise
+    /// pruning can be continued with another call. Removed entries are not read from storage.
+    pub fn prune_before(&mut self, seq: u64, max_removals: u32) -> bool {
+        let target = seq.min(self.end);
+        let stop = target.min(self.start.saturating_add(u64::from(max_removals)));
+        while self.start < stop {
+            self.entries.set(self.start, None);
+            self.start += 1;
+        }
+        self.start >= target
+    }
+
+    /// Flushes the cache and writes all modified entries to storage.
+    pub fn flush(&mut self) {
+        self.entries.flush()
+    }
+}
+
+impl<T> Log<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Returns the entry with sequence number `seq`, or [`None`] if it was pruned or has not
+    /// been appended yet.
+    pub fn get(&self, seq: u64) -> Option<&T> {
+        if seq < self.start || seq >= self.end {
+            return None;
+        }
+        Some(self.entries.get(&seq).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Returns an iterator over the entries with a sequence number in `range`, along with their
+    /// sequence numbers. Entries which were pruned are skipped, and only the yielded entries are
+    /// read from storage.
+    pub fn range<R>(&self, range: R) -> Range<T>
+    where
+        R: RangeBounds<u64>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e.saturating_add(1),
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => u64::MAX,
+        };
+        let start = start.max(self.start);
+        let end = end.min(self.end).max(start);
+        Range { log: self, seqs: start..end }
+    }
+}
+
+/// An iterator over a range of entries of a [`Log`] and their sequence numbers.
+///
+/// This `struct` is created by the [`range`](Log::range) method on [`Log`].
+pub struct Range<'a, T>
+where
+    T: BorshSerialize,
+{
+    log: &'a Log<T>,
+    seqs: std::ops::Range<u64>,
+}
+
+impl<'a, T> Iterator for Range<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = (u64, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let seq = self.seqs.next()?;
+        Some((seq, self.log.get(seq).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let seq = self.seqs.nth(n)?;
+        Some((seq, self.log.get(seq).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.seqs.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Range<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let seq = self.seqs.next_back()?;
+        Some((seq, self.log.get(seq).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))))
+    }
+}
+
+impl<'a, T> FusedIterator for Range<'a, T> where T: BorshSerialize + BorshDeserialize {}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use super::Log;
+
+    #[test]
+    fn append_range_prune() {
+        let mut log = Log::new(b"l");
+        for i in 0u64..50 {
+            assert_eq!(log.append(i * 2), i);
+        }
+        assert_eq!(log.len(), 50);
+        assert_eq!(log.get(49), Some(&98));
+        assert_eq!(log.get(50), None);
+
+        let range: Vec<_> = log.range(10..15).map(|(seq, v)| (seq, *v)).collect();
+        assert_eq!(range, [(10, 20), (11, 22), (12, 24), (13, 26), (14, 28)]);
+        assert_eq!(log.range(45..=u64::MAX).next_back(), Some((49, &98)));
+        assert_eq!(log.range(60..).count(), 0);
+
+        // Pruning in bounded batches.
+        assert!(!log.prune_before(30, 20));
+        assert_eq!(log.first_seq(), 20);
+        assert!(log.prune_before(30, 20));
+        assert_eq!(log.first_seq(), 30);
+        assert!(log.prune_before(10, 5));
+        assert_eq!(log.get(29), None);
+        assert_eq!(log.range(..32).map(|(seq, _)| seq).collect::<Vec<_>>(), [30, 31]);
+
+        log.flush();
+        let mut log = Log::<u64>::try_from_slice(&log.try_to_vec().unwrap()).unwrap();
+        assert_eq!(log.len(), 20);
+        assert_eq!(log.get(30), Some(&60));
+        assert_eq!(log.append(0), 50);
+
+        // Pruning past the end removes all entries, and keeps the next sequence number.
+        assert!(log.prune_before(u64::MAX, u32::MAX));
+        assert!(log.is_empty());
+        assert_eq!(log.next_seq(), 51);
+        log.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
@@ -9,7 +9,7 @@ impl<K, V, H> Extend<(K, V)> for LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn extend<I>(&mut self, iter: I)
     where
@@ -25,7 +25,7 @@ impl<K, V, H, Q: ?Sized> core::ops::Index<&Q> for LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord + Clone + Borrow<Q>,
     V: BorshSerialize + BorshDeserialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
     Q: BorshSerialize + ToOwned<Owned = K>,
 {
     type Output = V;
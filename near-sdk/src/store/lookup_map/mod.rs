@@ -1,5 +1,6 @@
 mod entry;
 mod impls;
+mod tracked;
 
 use std::borrow::Borrow;
 use std::fmt;
@@ -13,13 +14,12 @@ use crate::utils::{EntryState, StableMap};
 use crate::{env, CacheEntry, IntoStorageKey};
 
 pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use tracked::TrackedLookupMap;
 
 const ERR_ELEMENT_DESERIALIZATION: &str = "Cannot deserialize element";
 const ERR_ELEMENT_SERIALIZATION: &str = "Cannot serialize element";
 const ERR_NOT_EXIST: &str = "Key does not exist in map";
 
-type LookupKey = [u8; 32];
-
 /// A non-iterable, lazily loaded storage map that stores its content directly on the storage trie.
 ///
 /// This map stores the values under a hash of the map's `prefix` and [`BorshSerialize`] of the key
@@ -82,25 +82,35 @@ pub struct LookupMap<K, V, H = Sha256>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     prefix: Box<[u8]>,
     /// Cache for loads and intermediate changes to the underlying vector.
     /// The cached entries are wrapped in a [`Box`] to avoid existing pointers from being
     /// invalidated.
     #[borsh_skip]
-    cache: StableMap<K, EntryAndHash<V>>,
+    cache: StableMap<K, EntryAndHash<V, H::Digest>>,
 
     #[borsh_skip]
     hasher: PhantomData<H>,
 }
 
-struct EntryAndHash<V> {
+impl_borsh_schema! {
+    LookupMap<K, V, H> {
+        prefix: Box<[u8]>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher,
+}
+
+struct EntryAndHash<V, D> {
     value: OnceCell<CacheEntry<V>>,
-    hash: OnceCell<[u8; 32]>,
+    hash: OnceCell<D>,
 }
 
-impl<V> Default for EntryAndHash<V> {
+impl<V, D> Default for EntryAndHash<V, D> {
     fn default() -> Self {
         Self { value: Default::default(), hash: Default::default() }
     }
@@ -110,7 +120,7 @@ impl<K, V, H> Drop for LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn drop(&mut self) {
         self.flush()
@@ -121,7 +131,7 @@ impl<K, V, H> fmt::Debug for LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LookupMap").field("prefix", &self.prefix).finish()
@@ -146,7 +156,7 @@ impl<K, V, H> LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     /// Initialize a [`LookupMap`] with a custom hash function.
     ///
@@ -184,7 +194,35 @@ where
         }
     }
 
-    fn lookup_key<Q: ?Sized>(prefix: &[u8], key: &Q, buffer: &mut Vec<u8>) -> LookupKey
+    /// Returns the storage key of the value for the given key, which is the hash of the prefix
+    /// of the map followed by the serialized key. This allows tools which read the state of the
+    /// contract, such as state viewers and indexers, to find the value of a key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but [`BorshSerialize`] on the
+    /// borrowed form *must* match that of the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::env;
+    /// use near_sdk::store::LookupMap;
+    ///
+    /// let mut map: LookupMap<String, u8> = LookupMap::new(b"m");
+    /// map.insert("a".to_string(), 1);
+    /// map.flush();
+    ///
+    /// assert_eq!(map.raw_key_for("a"), env::sha256(b"m\x01\0\0\0a"));
+    /// assert_eq!(env::storage_read(&map.raw_key_for("a")), Some(vec![1]));
+    /// ```
+    pub fn raw_key_for<Q: ?Sized>(&self, k: &Q) -> Vec<u8>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize,
+    {
+        Self::lookup_key(&self.prefix, k, &mut Vec::new()).as_ref().to_vec()
+    }
+
+    fn lookup_key<Q: ?Sized>(prefix: &[u8], key: &Q, buffer: &mut Vec<u8>) -> H::Digest
     where
         Q: BorshSerialize,
         K: Borrow<Q>,
@@ -201,19 +239,19 @@ impl<K, V, H> LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize + BorshDeserialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn deserialize_element(bytes: &[u8]) -> V {
         V::try_from_slice(bytes).unwrap_or_else(|_| env::panic_str(ERR_ELEMENT_DESERIALIZATION))
     }
 
-    fn load_element<Q: ?Sized>(prefix: &[u8], key: &Q) -> (LookupKey, Option<V>)
+    fn load_element<Q: ?Sized>(prefix: &[u8], key: &Q) -> (H::Digest, Option<V>)
     where
         Q: BorshSerialize,
         K: Borrow<Q>,
     {
         let key = Self::lookup_key(prefix, key, &mut Vec::new());
-        let storage_bytes = env::storage_read(&key);
+        let storage_bytes = env::storage_read(key.as_ref());
         (key, storage_bytes.as_deref().map(Self::deserialize_element))
     }
 
@@ -237,6 +275,49 @@ where
         entry.value().as_ref()
     }
 
+    /// Returns references to the values corresponding to each of the given keys, in the same
+    /// order as the keys. Keys which are already cached are not read from storage again, and
+    /// duplicate keys are only read once.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LookupMap;
+    ///
+    /// let mut balances: LookupMap<String, u128> = LookupMap::new(b"b");
+    /// balances.insert("alice".to_string(), 10);
+    /// balances.insert("bob".to_string(), 20);
+    ///
+    /// assert_eq!(balances.get_many(["alice", "carol", "bob"]), [Some(&10), None, Some(&20)]);
+    /// ```
+    pub fn get_many<'a, Q: ?Sized + 'a, I>(&self, keys: I) -> Vec<Option<&V>>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+        I: IntoIterator<Item = &'a Q>,
+    {
+        keys.into_iter().map(|k| self.get(k)).collect()
+    }
+
+    /// Returns a copy of the value corresponding to the key, which is read from storage without
+    /// being cached unless it is cached already.
+    pub(crate) fn get_uncached<Q: ?Sized>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + Ord,
+        V: Clone,
+    {
+        let cached = self.cache.map_value_ref(k, |v| v.value.get().map(|e| e.value().clone()));
+        match cached.flatten() {
+            Some(value) => value,
+            None => Self::load_element(&self.prefix, k).1,
+        }
+    }
+
     pub(crate) fn get_mut_inner<Q: ?Sized>(&mut self, k: &Q) -> &mut CacheEntry<V>
     where
         K: Borrow<Q>,
@@ -301,7 +382,7 @@ where
 
         // Value is not in cache, check if storage has value for given key.
         let storage_key = Self::lookup_key(&self.prefix, k, &mut Vec::new());
-        let contains = env::storage_has_key(&storage_key);
+        let contains = env::storage_has_key(storage_key.as_ref());
 
         if !contains {
             // If value not in cache and not in storage, can set a cached `None`
@@ -326,6 +407,35 @@ where
         self.get_mut_inner(k).replace(None)
     }
 
+    /// Removes a key from the map without reading the previous value from storage.
+    ///
+    /// This is the same as calling [`set`](Self::set) with [`None`], and avoids the cost of
+    /// reading and deserializing a large value which is only discarded. Use
+    /// [`remove`](Self::remove) if you need the previous value.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LookupMap;
+    ///
+    /// let mut map: LookupMap<String, Vec<u8>> = LookupMap::new(b"m");
+    /// map.insert("blob".to_string(), vec![0; 1024]);
+    ///
+    /// map.remove_no_return("blob");
+    /// assert!(!map.contains_key("blob"));
+    /// ```
+    pub fn remove_no_return<Q: ?Sized>(&mut self, k: &Q)
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.set(k.to_owned(), None);
+    }
+
     /// Gets the given key's corresponding entry in the map for in-place manipulation.
     /// ```
     /// use near_sdk::store::LookupMap;
@@ -361,8 +471,17 @@ impl<K, V, H> LookupMap<K, V, H>
 where
     K: BorshSerialize + Ord,
     V: BorshSerialize,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
+    /// Returns the keys of the values which were inserted, modified or removed since the map was
+    /// last flushed.
+    pub(crate) fn modified_keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.cache.keys_where(|v| matches!(v.value.get(), Some(entry) if entry.is_modified()))
+    }
+
     /// Flushes the intermediate values of the map before this is called when the structure is
     /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
     /// in memory.
@@ -381,11 +500,11 @@ where
                             buf.clear();
                             BorshSerialize::serialize(modified, &mut buf)
                                 .unwrap_or_else(|_| env::panic_str(ERR_ELEMENT_SERIALIZATION));
-                            env::storage_write(key, &buf);
+                            env::storage_write(key.as_ref(), &buf);
                         }
                         None => {
                             // Element was removed, clear the storage for the value
-                            env::storage_remove(key);
+                            env::storage_remove(key.as_ref());
                         }
                     }
 
@@ -402,7 +521,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::LookupMap;
-    use crate::crypto_hash::Keccak256;
+    use crate::crypto_hash::{Keccak256, Keccak512, Ripemd160};
     use crate::env;
     use crate::test_utils::test_env::setup_free;
     use arbitrary::{Arbitrary, Unstructured};
@@ -526,6 +645,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remove_no_return() {
+        let mut map = LookupMap::new(b"m");
+        for k in 0u64..20 {
+            map.insert(k, vec![k; 8]);
+        }
+        map.flush();
+
+        let mut map = LookupMap::<u64, Vec<u64>>::new(b"m");
+        for k in (0u64..20).filter(|k| k % 2 == 0) {
+            map.remove_no_return(&k);
+        }
+        for k in 0u64..20 {
+            assert_eq!(map.contains_key(&k), k % 2 != 0);
+        }
+        map.flush();
+        let map = LookupMap::<u64, Vec<u64>>::new(b"m");
+        for k in 0u64..20 {
+            assert_eq!(map.get(&k).is_some(), k % 2 != 0);
+        }
+    }
+
+    #[test]
+    fn test_get_many() {
+        let mut map = LookupMap::new(b"m");
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(5);
+        let mut key_to_value = HashMap::new();
+        for _ in 0..100 {
+            let key = rng.gen::<u64>() % 200;
+            let value = rng.gen::<u64>();
+            key_to_value.insert(key, value);
+            map.insert(key, value);
+        }
+        map.flush();
+        let map = LookupMap::<u64, u64>::new(b"m");
+        let keys: Vec<u64> = (0..50).map(|_| rng.gen::<u64>() % 200).collect();
+        let expected: Vec<_> = keys.iter().map(|k| key_to_value.get(k)).collect();
+        assert_eq!(map.get_many(&keys), expected);
+        assert_eq!(map.get_many(&keys), expected);
+        assert!(map.get_many(&[]).is_empty());
+    }
+
     #[test]
     fn test_extend() {
         let mut map = LookupMap::new(b"m");
@@ -574,6 +735,25 @@ mod tests {
         assert_eq!(dup_map[&5], 8);
     }
 
+    #[test]
+    fn non_32_byte_hashers() {
+        let mut keccak = LookupMap::<_, _, Keccak512>::with_hasher(b"k");
+        let mut ripemd = LookupMap::<_, _, Ripemd160>::with_hasher(b"r");
+        keccak.insert(1u8, 2u8);
+        ripemd.insert(1u8, 3u8);
+        keccak.flush();
+        ripemd.flush();
+
+        assert_eq!(keccak.raw_key_for(&1), env::keccak512(b"k\x01"));
+        assert_eq!(ripemd.raw_key_for(&1), env::ripemd160(b"r\x01"));
+        assert_eq!(env::storage_read(&env::keccak512(b"k\x01")), Some(vec![2]));
+        assert_eq!(env::storage_read(&env::ripemd160(b"r\x01")), Some(vec![3]));
+
+        let keccak = LookupMap::<u8, u8, Keccak512>::with_hasher(b"k");
+        assert_eq!(keccak.get(&1), Some(&2));
+        assert!(!keccak.contains_key(&2));
+    }
+
     #[derive(Arbitrary, Debug)]
     enum Op {
         Insert(u8, u8),
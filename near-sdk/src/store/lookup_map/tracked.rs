@@ -0,0 +1,378 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::LookupMap;
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::env;
+use crate::store::free_list::{FreeList, FreeListIndex};
+use crate::store::ERR_INCONSISTENT_STATE;
+use crate::IntoStorageKey;
+
+/// A [`LookupMap`] which also keeps an index of its keys, so that it can be iterated over.
+///
+/// The values are stored exactly as in a [`LookupMap`] with the same prefix, and the index is
+/// stored separately, so a [`LookupMap`] can start tracking its keys with
+/// [`LookupMap::into_key_tracking`], and a [`TrackedLookupMap`] can be turned back into a plain
+/// [`LookupMap`] with [`into_lookup_map`](Self::into_lookup_map), without moving any values.
+///
+/// Keys which were inserted before the map started tracking its keys can still be read, but
+/// they are not iterated over or counted by [`len`](Self::len) until they are added to the index
+/// with [`track_key`](Self::track_key).
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::LookupMap;
+///
+/// let mut balances = LookupMap::with_key_tracking(b"b");
+/// balances.insert("alice.near".to_string(), 10u128);
+/// balances.insert("bob.near".to_string(), 20);
+/// assert_eq!(balances.len(), 2);
+/// assert_eq!(balances.iter().map(|(_, balance)| balance).sum::<u128>(), 30);
+///
+/// // The values stay where a `LookupMap` with the same prefix reads them.
+/// let balances = balances.into_lookup_map();
+/// assert_eq!(balances["bob.near"], 20);
+/// ```
+pub struct TrackedLookupMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    map: LookupMap<K, V, H>,
+    keys: FreeList<K>,
+    /// Positions of the keys in `keys`, used to remove a key from the index.
+    positions: LookupMap<K, FreeListIndex, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for TrackedLookupMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.map, writer)?;
+        BorshSerialize::serialize(&self.keys, writer)?;
+        BorshSerialize::serialize(&self.positions, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for TrackedLookupMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            map: BorshDeserialize::deserialize(buf)?,
+            keys: BorshDeserialize::deserialize(buf)?,
+            positions: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    TrackedLookupMap<K, V, H> {
+        map: LookupMap<K, V, H>,
+        keys: FreeList<K>,
+        positions: LookupMap<K, FreeListIndex, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> fmt::Debug for TrackedLookupMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedLookupMap")
+            .field("map", &self.map)
+            .field("keys", &self.keys)
+            .field("positions", &self.positions)
+            .finish()
+    }
+}
+
+impl<K, V> LookupMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create a new map which keeps an index of its keys. See [`TrackedLookupMap`].
+    pub fn with_key_tracking<S>(prefix: S) -> TrackedLookupMap<K, V, Sha256>
+    where
+        S: IntoStorageKey,
+    {
+        TrackedLookupMap::new(prefix)
+    }
+}
+
+impl<K, V, H> LookupMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Turns the map into a [`TrackedLookupMap`] which keeps an index of its keys, and adds the
+    /// given keys which are in the map to the index. Other keys of the map can be added to the
+    /// index later with [`TrackedLookupMap::track_key`], so that large maps can be indexed in
+    /// batches.
+    ///
+    /// # Examples
+    /// ```
+    /// use near_sdk::store::LookupMap;
+    ///
+    /// let mut map = LookupMap::new(b"m");
+    /// map.insert(1u8, "a".to_string());
+    /// map.insert(2, "b".to_string());
+    ///
+    /// let mut map = map.into_key_tracking([1, 3].iter().copied());
+    /// assert!(map.keys().eq([1].iter()));
+    /// assert!(map.track_key(2));
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn into_key_tracking<I>(self, keys: I) -> TrackedLookupMap<K, V, H>
+    where
+        K: BorshDeserialize + Clone,
+        V: BorshDeserialize,
+        I: IntoIterator<Item = K>,
+    {
+        let (keys_prefix, positions_prefix) =
+            TrackedLookupMap::<K, V, H>::index_prefixes(&self.prefix);
+        let mut map = TrackedLookupMap {
+            map: self,
+            keys: FreeList::new(keys_prefix),
+            positions: LookupMap::with_hasher(positions_prefix),
+        };
+        for key in keys {
+            map.track_key(key);
+        }
+        map
+    }
+}
+
+impl<K, V> TrackedLookupMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create a new map which keeps an index of its keys. Use `prefix` as a unique prefix for
+    /// the keys of the values, which are the same as those of a [`LookupMap`] with this prefix.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> TrackedLookupMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`TrackedLookupMap`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let map = LookupMap::with_hasher(prefix);
+        let (keys_prefix, positions_prefix) = Self::index_prefixes(&map.prefix);
+        Self {
+            map,
+            keys: FreeList::new(keys_prefix),
+            positions: LookupMap::with_hasher(positions_prefix),
+        }
+    }
+
+    fn index_prefixes(prefix: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        ([prefix, b"k"].concat(), [prefix, b"p"].concat())
+    }
+
+    /// Returns the number of tracked keys in the map.
+    pub fn len(&self) -> u32 {
+        self.keys.len()
+    }
+
+    /// Returns `true` if the map has no tracked keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.map.flush();
+        self.keys.flush();
+        self.positions.flush();
+    }
+}
+
+impl<K, V, H> TrackedLookupMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.map.get(k)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.map.get_mut(k)
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.map.contains_key(k)
+    }
+
+    /// Inserts a key-value pair into the map, and adds the key to the index if it is not in
+    /// it. Returns the previous value of the key, if there was one.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        if self.positions.get(&k).is_none() {
+            let position = self.keys.insert(k.clone());
+            self.positions.set(k.clone(), Some(position));
+        }
+        self.map.insert(k, v)
+    }
+
+    /// Removes a key from the map and from the index, returning its value if the key was in
+    /// the map.
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        if let Some(position) = self.positions.remove(k) {
+            self.keys.remove(position);
+        }
+        self.map.remove(k)
+    }
+
+    /// Adds a key which was inserted before the map started tracking its keys to the index.
+    /// Returns `true` if the key was added, and `false` if it is not in the map or is already
+    /// tracked.
+    pub fn track_key(&mut self, k: K) -> bool {
+        if self.positions.get(&k).is_some() || !self.map.contains_key(&k) {
+            return false;
+        }
+        let position = self.keys.insert(k.clone());
+        self.positions.set(k, Some(position));
+        true
+    }
+
+    /// An iterator visiting all tracked keys in arbitrary order.
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> {
+        self.keys.iter()
+    }
+
+    /// An iterator visiting all tracked key-value pairs in arbitrary order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, &V)> {
+        self.keys.iter().map(move |key| {
+            let value = self.map.get(key);
+            (key, value.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+        })
+    }
+
+    /// Removes the index of the keys from storage, and returns the map as a [`LookupMap`]
+    /// which reads the same values. This removes a storage entry for each tracked key.
+    pub fn into_lookup_map(self) -> LookupMap<K, V, H> {
+        let Self { map, mut keys, mut positions } = self;
+        for key in keys.drain() {
+            positions.set(key, None);
+        }
+        map
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::TrackedLookupMap;
+    use crate::store::LookupMap;
+
+    #[test]
+    fn matches_btree_map() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(4);
+        let mut map = LookupMap::with_key_tracking(b"t");
+        let mut baseline = BTreeMap::new();
+        for _ in 0..300 {
+            let key = rng.gen_range(0..40u8);
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let value: u32 = rng.gen();
+                    assert_eq!(map.insert(key, value), baseline.insert(key, value));
+                }
+                2 => assert_eq!(map.remove(&key), baseline.remove(&key)),
+                _ => {
+                    let serialized = map.try_to_vec().unwrap();
+                    drop(map);
+                    map = TrackedLookupMap::try_from_slice(&serialized).unwrap();
+                }
+            }
+            assert_eq!(map.len() as usize, baseline.len());
+        }
+        let entries: BTreeMap<_, _> = map.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(entries, baseline);
+
+        // Only the values are left in storage once the index is removed.
+        let plain = map.into_lookup_map();
+        drop(plain);
+        crate::mock::with_mocked_blockchain(|m| {
+            let storage = m.take_storage();
+            assert_eq!(storage.len(), baseline.len());
+            m.set_storage(storage);
+        });
+
+        let plain: LookupMap<u8, u32> = LookupMap::new(b"t");
+        let mut map = plain.into_key_tracking(0..20);
+        assert!(map.keys().all(|&k| k < 20));
+        assert!(!map.track_key(100));
+        for &key in baseline.keys() {
+            map.track_key(key);
+        }
+        let entries: BTreeMap<_, _> = map.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(entries, baseline);
+    }
+}
@@ -5,7 +5,7 @@ use borsh::BorshSerialize;
 impl<T, H> Extend<T> for LookupSet<T, H>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn extend<I>(&mut self, iter: I)
     where
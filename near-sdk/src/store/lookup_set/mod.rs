@@ -10,13 +10,11 @@ use std::marker::PhantomData;
 
 const ERR_ELEMENT_SERIALIZATION: &str = "Cannot serialize element";
 
-type LookupKey = [u8; 32];
-
 #[derive(BorshSerialize, BorshDeserialize)]
 pub struct LookupSet<T, H = Sha256>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     prefix: Box<[u8]>,
 
@@ -28,6 +26,15 @@ where
     hasher: PhantomData<H>,
 }
 
+impl_borsh_schema! {
+    LookupSet<T, H> {
+        prefix: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize + Ord,
+        H: CryptoHasher,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub(crate) enum EntryState {
     /// The element is cached as freshly inserted, but not necessarily absent from the trie
@@ -43,7 +50,7 @@ pub(crate) enum EntryState {
 impl<T, H> Drop for LookupSet<T, H>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn drop(&mut self) {
         self.flush()
@@ -53,7 +60,7 @@ where
 impl<T, H> fmt::Debug for LookupSet<T, H>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("LookupSet").field("prefix", &self.prefix).finish()
@@ -76,9 +83,9 @@ where
 impl<T, H> LookupSet<T, H>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
-    fn lookup_key<Q: ?Sized>(prefix: &[u8], value: &Q, buffer: &mut Vec<u8>) -> LookupKey
+    fn lookup_key<Q: ?Sized>(prefix: &[u8], value: &Q, buffer: &mut Vec<u8>) -> H::Digest
     where
         Q: BorshSerialize,
         T: Borrow<Q>,
@@ -90,13 +97,14 @@ where
         H::hash(buffer)
     }
 
-    fn contains_trie_element<Q: ?Sized>(prefix: &[u8], value: &Q) -> bool
+    fn contains_trie_element<Q: ?Sized>(prefix: &[u8], value: &Q, buffer: &mut Vec<u8>) -> bool
     where
         Q: BorshSerialize,
         T: Borrow<Q>,
     {
-        let lookup_key = Self::lookup_key(prefix, value, &mut Vec::new());
-        env::storage_has_key(&lookup_key)
+        buffer.clear();
+        let lookup_key = Self::lookup_key(prefix, value, buffer);
+        env::storage_has_key(lookup_key.as_ref())
     }
 
     fn get_mut_inner<Q: ?Sized>(&mut self, value: &Q) -> &mut EntryState
@@ -108,7 +116,7 @@ where
         //* ToOwned bound, which forces a clone, is required to be able to keep the value in the cache
         let entry = self.cache.get_mut(value.to_owned());
         entry.get_or_init(|| {
-            if Self::contains_trie_element(prefix, value) {
+            if Self::contains_trie_element(prefix, value, &mut Vec::new()) {
                 EntryState::Present
             } else {
                 EntryState::Absent
@@ -144,15 +152,21 @@ where
     /// [`BorshSerialize`], [`ToOwned<Owned = T>`](ToOwned) and [`Ord`] on the borrowed form *must*
     /// match those for the value type.
     pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = T> + Ord,
+    {
+        self.contains_with_buffer(value, &mut Vec::new())
+    }
+
+    fn contains_with_buffer<Q: ?Sized>(&self, value: &Q, buffer: &mut Vec<u8>) -> bool
     where
         T: Borrow<Q>,
         Q: BorshSerialize + ToOwned<Owned = T> + Ord,
     {
         let entry_cell = self.cache.get(value.to_owned());
         match entry_cell.get_or_init(|| {
-            let lookup_key = Self::lookup_key(&self.prefix, value, &mut Vec::new());
-            let contains = env::storage_has_key(&lookup_key);
-            if contains {
+            if Self::contains_trie_element(&self.prefix, value, buffer) {
                 EntryState::Present
             } else {
                 EntryState::Absent
@@ -163,6 +177,56 @@ where
         }
     }
 
+    /// Returns `true` if the set contains all of the specified values.
+    ///
+    /// This short-circuits on the first value that is not in the set, and reuses a single
+    /// buffer for serializing the values that need to be checked in storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LookupSet;
+    ///
+    /// let mut set = LookupSet::new(b"m");
+    /// set.extend([1u32, 2, 3]);
+    ///
+    /// assert!(set.contains_all(&[1, 3]));
+    /// assert!(!set.contains_all(&[1, 4]));
+    /// ```
+    pub fn contains_all<'a, I>(&self, values: I) -> bool
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: Clone + 'a,
+    {
+        let mut buffer = Vec::new();
+        values.into_iter().all(|value| self.contains_with_buffer(value, &mut buffer))
+    }
+
+    /// Returns `true` if the set contains any of the specified values.
+    ///
+    /// This short-circuits on the first value that is in the set, and reuses a single buffer
+    /// for serializing the values that need to be checked in storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LookupSet;
+    ///
+    /// let mut set = LookupSet::new(b"m");
+    /// set.extend([1u32, 2, 3]);
+    ///
+    /// assert!(set.contains_any(&[4, 3]));
+    /// assert!(!set.contains_any(&[4, 5]));
+    /// ```
+    pub fn contains_any<'a, I>(&self, values: I) -> bool
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: Clone + 'a,
+    {
+        let mut buffer = Vec::new();
+        values.into_iter().any(|value| self.contains_with_buffer(value, &mut buffer))
+    }
+
     /// Adds a value to the set.
     ///
     /// If the set did not have this value present, true is returned.
@@ -218,12 +282,44 @@ where
             EntryState::Deleted | EntryState::Absent => false,
         }
     }
+
+    /// Removes all of the given values from the set.
+    ///
+    /// Similar to [`LookupSet::put`], this function will not check if the values are present in
+    /// storage, and does not return whether any of the values were in the set. Use
+    /// [`LookupSet::remove`] if you need that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::LookupSet;
+    ///
+    /// let mut set = LookupSet::new(b"m");
+    /// set.extend(0u32..10);
+    ///
+    /// set.remove_all(0..5);
+    /// assert!(!set.contains(&4));
+    /// assert!(set.contains(&5));
+    /// ```
+    pub fn remove_all<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in values {
+            let entry_cell = self.cache.get_mut(value);
+            // Same as `put`, it is safe to mark the entry as `Deleted` even if it is absent from
+            // the trie.
+            entry_cell.get_or_init(|| EntryState::Deleted);
+            let entry = entry_cell.get_mut().unwrap_or_else(|| env::abort());
+            *entry = EntryState::Deleted;
+        }
+    }
 }
 
 impl<T, H> LookupSet<T, H>
 where
     T: BorshSerialize + Ord,
-    H: CryptoHasher<Digest = [u8; 32]>,
+    H: CryptoHasher,
 {
     /// Flushes the intermediate values of the set before this is called when the structure is
     /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
@@ -236,13 +332,13 @@ where
                     EntryState::Inserted => {
                         buf.clear();
                         let lookup_key = Self::lookup_key(&self.prefix, k, &mut buf);
-                        env::storage_write(&lookup_key, &[]);
+                        env::storage_write(lookup_key.as_ref(), &[]);
                         *entry = EntryState::Present;
                     }
                     EntryState::Deleted => {
                         buf.clear();
                         let lookup_key = Self::lookup_key(&self.prefix, k, &mut buf);
-                        env::storage_remove(&lookup_key);
+                        env::storage_remove(lookup_key.as_ref());
                         *entry = EntryState::Absent;
                     }
                     EntryState::Present | EntryState::Absent => {}
@@ -338,6 +434,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_batch_operations() {
+        let mut set = LookupSet::new(b"m");
+        set.extend(0u64..100);
+        assert!(set.contains_all(&(0..100).collect::<Vec<_>>()));
+        assert!(!set.contains_all(&[5, 100]));
+        assert!(set.contains_all(&[]));
+        assert!(set.contains_any(&[100, 5]));
+        assert!(!set.contains_any(&[]));
+
+        set.remove_all((0..100).filter(|v| v % 2 == 0));
+        assert!(set.contains_all(&[1, 3, 99]));
+        assert!(!set.contains_all(&[1, 2]));
+        assert!(!set.contains_any(&[0, 2, 100]));
+        assert!(set.contains_any([2, 3].iter()));
+
+        // Removing values that were never inserted is a no-op
+        set.remove_all(200..210);
+        drop(set);
+
+        let set = LookupSet::new(b"m");
+        assert!(set.contains_all(&(0..100).filter(|v| v % 2 != 0).collect::<Vec<u64>>()));
+        assert!((0..100).filter(|v| v % 2 == 0).all(|v| !set.contains(&v)));
+        assert!(!set.contains(&205));
+    }
+
     #[test]
     fn test_debug() {
         let set = LookupSet::<u8, Sha256>::new(b"m");
@@ -483,13 +605,13 @@ mod tests {
             let mut set = LookupSet::new(b"m");
             set.put(8u8);
         }
-        assert!(crate::env::storage_has_key(&lookup_key));
+        assert!(crate::env::storage_has_key(lookup_key.as_ref()));
         {
             let mut set = LookupSet::new(b"m");
             set.put(8u8);
             set.remove(&8);
         }
-        assert!(!crate::env::storage_has_key(&lookup_key));
+        assert!(!crate::env::storage_has_key(lookup_key.as_ref()));
         {
             let set = LookupSet::new(b"m");
             assert!(!set.contains(&8));
@@ -504,6 +626,7 @@ mod tests {
         Flush,
         Restore,
         Contains(u8),
+        RemoveAll(Vec<u8>),
     }
 
     #[test]
@@ -551,6 +674,12 @@ mod tests {
                             let r2 = hs.contains(&v);
                             assert_eq!(r1, r2)
                         }
+                        Op::RemoveAll(vs) => {
+                            for v in vs.iter() {
+                                hs.remove(v);
+                            }
+                            ls.remove_all(vs);
+                        }
                     }
                 }
             }
@@ -0,0 +1,472 @@
+//! Helpers for migrating stored data to new types: [`Migrate`] upgrades values of a collection as
+//! they are read, and [`vector`], [`unordered_map`] and [`tree_map`] move the entries of a legacy
+//! [`collections`](crate::collections) type into the corresponding [`store`](crate::store) type in
+//! bounded batches.
+
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use std::collections::HashMap;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+use super::ERR_INCONSISTENT_STATE;
+use crate::crypto_hash::CryptoHasher;
+use crate::{collections, env, store};
+
+const ERR_ELEMENT_DESERIALIZATION: &str = "Cannot deserialize element";
+const ERR_AMBIGUOUS_VALUE: &str =
+    "Value of Migrate starts with the tag but can be read as the old type";
+
+/// Tag written before values of the new type of a [`Migrate`].
+const MIGRATED_TAG: u8 = u8::MAX;
+
+/// Value wrapper for store collections which reads values that were stored as `V1` and upgrades
+/// them to `V2` through its [`From`] implementation, so that the value type of a collection can be
+/// changed without migrating every existing value at once.
+///
+/// New values are always written as `V2`, preceded by the tag `0xFF`. When reading, a value which
+/// starts with the tag is read as `V2`, and any other value as `V1`. This means the serialized
+/// form of `V1` must never start with `0xFF`, which is the case if its first field is for example a
+/// `bool`, an `Option` or an enum with fewer than 255 variants, but not if it is an integer. A
+/// value which starts with the tag but can also be read as `V1` is ambiguous and fails to
+/// deserialize, so such a `V1` makes reads fail instead of returning a value of the wrong type.
+/// Only the bytes of the value are read, so a `Migrate` can be nested within other serialized
+/// types, such as the entries of an [`UnorderedMap`](crate::store::UnorderedMap).
+///
+/// An upgraded value is only written back to storage when the entry is modified, which can be done
+/// by accessing it mutably, for example with [`LookupMap::get_mut`](crate::store::LookupMap::get_mut).
+/// Until then, the value will be upgraded again every time it is loaded. Values are not written
+/// back when they are only read, because reads go through shared references which cannot mark the
+/// entry as modified, and because view calls cannot write to storage. [`Migrate::is_upgraded`]
+/// can be used to check if a loaded value was stored as the old type.
+///
+/// # Examples
+/// ```
+/// use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+/// use near_sdk::store::{LookupMap, Migrate};
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct AccountV1 {
+///     frozen: bool,
+///     balance: u128,
+/// }
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct Account {
+///     frozen: bool,
+///     balance: u128,
+///     nonce: u64,
+/// }
+///
+/// impl From<AccountV1> for Account {
+///     fn from(old: AccountV1) -> Self {
+///         Self { frozen: old.frozen, balance: old.balance, nonce: 0 }
+///     }
+/// }
+///
+/// // Entry written by a previous version of the contract.
+/// let mut old: LookupMap<String, AccountV1> = LookupMap::new(b"a");
+/// old.insert("alice".to_string(), AccountV1 { frozen: false, balance: 5 });
+/// old.flush();
+///
+/// let mut accounts: LookupMap<String, Migrate<AccountV1, Account>> = LookupMap::new(b"a");
+/// assert_eq!(accounts["alice"].nonce, 0);
+/// assert!(accounts["alice"].is_upgraded());
+///
+/// // Modifying the entry writes it back as the new type.
+/// accounts.get_mut("alice").unwrap().nonce += 1;
+/// ```
+pub struct Migrate<V1, V2> {
+    value: V2,
+    upgraded: bool,
+    old_type: PhantomData<fn() -> V1>,
+}
+
+impl<V1, V2> Migrate<V1, V2> {
+    /// Wraps a value of the new type.
+    pub fn new(value: V2) -> Self {
+        Self { value, upgraded: false, old_type: PhantomData }
+    }
+
+    /// Returns `true` if the value was read from storage as `V1` and upgraded to `V2`.
+    pub fn is_upgraded(&self) -> bool {
+        self.upgraded
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> V2 {
+        self.value
+    }
+}
+
+impl<V1, V2> From<V2> for Migrate<V1, V2> {
+    fn from(value: V2) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<V1, V2> Deref for Migrate<V1, V2> {
+    type Target = V2;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<V1, V2> DerefMut for Migrate<V1, V2> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<V1, V2> BorshSerialize for Migrate<V1, V2>
+where
+    V2: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&MIGRATED_TAG, writer)?;
+        BorshSerialize::serialize(&self.value, writer)
+    }
+}
+
+impl<V1, V2> BorshDeserialize for Migrate<V1, V2>
+where
+    V1: BorshDeserialize,
+    V2: BorshDeserialize + From<V1>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        if let Some((&MIGRATED_TAG, rest)) = buf.split_first() {
+            if V1::deserialize(&mut &**buf).is_ok() {
+                return Err(borsh::maybestd::io::Error::new(
+                    borsh::maybestd::io::ErrorKind::InvalidData,
+                    ERR_AMBIGUOUS_VALUE,
+                ));
+            }
+            *buf = rest;
+            return Ok(Self::new(V2::deserialize(buf)?));
+        }
+        let old = V1::deserialize(buf)?;
+        Ok(Self { value: old.into(), upgraded: true, old_type: PhantomData })
+    }
+}
+
+/// The schema is that of the tagged new type, which is the only type that is written.
+impl<V1, V2> BorshSchema for Migrate<V1, V2>
+where
+    V2: BorshSchema,
+{
+    fn add_definitions_recursively(
+        definitions: &mut HashMap<borsh::schema::Declaration, borsh::schema::Definition>,
+    ) {
+        let fields = borsh::schema::Fields::NamedFields(vec![
+            ("tag".to_string(), u8::declaration()),
+            ("value".to_string(), V2::declaration()),
+        ]);
+        Self::add_definition(
+            Self::declaration(),
+            borsh::schema::Definition::Struct { fields },
+            definitions,
+        );
+        u8::add_definitions_recursively(definitions);
+        V2::add_definitions_recursively(definitions);
+    }
+
+    fn declaration() -> borsh::schema::Declaration {
+        format!("Migrate<{}>", V2::declaration())
+    }
+}
+
+impl<V1, V2> fmt::Debug for Migrate<V1, V2>
+where
+    V2: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrate")
+            .field("value", &self.value)
+            .field("upgraded", &self.upgraded)
+            .finish()
+    }
+}
+
+/// Moves up to `max_elements` elements from the front of a legacy
+/// [`collections::Vector`] to the back of `new`, returning `true` once all elements are moved.
+///
+/// The elements are moved in order, and `new` keeps track of the progress: the number of elements
+/// in `new` is the index of the next element to move. This means `new` must be empty when the
+/// migration starts and must not be modified until it is done, and elements which were already
+/// moved must not be read from `old`. Once all elements are moved, `old` is empty.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{collections, store};
+///
+/// let mut old = collections::Vector::new(b"v");
+/// old.extend(0u32..10);
+///
+/// // The new collection needs a different prefix.
+/// let mut new = store::Vector::new(b"w");
+/// while !store::migrate::vector(&mut old, &mut new, 4) {
+///     // In a contract, each batch would be run by a separate function call.
+/// }
+/// assert!(old.is_empty());
+/// assert_eq!(new.iter().copied().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+/// ```
+pub fn vector<T>(
+    old: &mut collections::Vector<T>,
+    new: &mut store::Vector<T>,
+    max_elements: u32,
+) -> bool
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    let start = u64::from(new.len());
+    let end = old.len().min(start + u64::from(max_elements));
+    for index in start..end {
+        let raw = old.take_raw(index);
+        new.push(
+            T::try_from_slice(&raw).unwrap_or_else(|_| env::panic_str(ERR_ELEMENT_DESERIALIZATION)),
+        );
+    }
+    old.is_empty()
+}
+
+/// Moves up to `max_entries` entries from a legacy [`collections::UnorderedMap`] to `new`,
+/// returning `true` once all entries are moved.
+///
+/// Entries are removed from the end of `old`, so that each entry is moved with a constant number
+/// of storage operations, and `old` stays a valid map which holds the entries that were not moved
+/// yet. The migration can be resumed at any time, and entries which are in `old` take precedence
+/// over entries with the same key in `new`.
+pub fn unordered_map<K, V, H>(
+    old: &mut collections::UnorderedMap<K, V>,
+    new: &mut store::UnorderedMap<K, V, H>,
+    max_entries: u32,
+) -> bool
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    for _ in 0..max_entries {
+        let keys = old.keys_as_vector();
+        let key = match keys.len().checked_sub(1).and_then(|last| keys.get(last)) {
+            Some(key) => key,
+            None => break,
+        };
+        let value = old.remove(&key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        new.insert(key, value);
+    }
+    old.is_empty()
+}
+
+/// Moves up to `max_entries` entries from a legacy [`collections::TreeMap`] to `new`, returning
+/// `true` once all entries are moved.
+///
+/// Entries are removed in order starting from the smallest key, and `old` stays a valid map
+/// which holds the entries that were not moved yet. The migration can be resumed at any time,
+/// and entries which are in `old` take precedence over entries with the same key in `new`.
+pub fn tree_map<K, V, H>(
+    old: &mut collections::TreeMap<K, V>,
+    new: &mut store::TreeMap<K, V, H>,
+    max_entries: u32,
+) -> bool
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    for _ in 0..max_entries {
+        let key = match old.min() {
+            Some(key) => key,
+            None => break,
+        };
+        let value = old.remove(&key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        new.insert(key, value);
+    }
+    old.is_empty()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::{Migrate, ERR_AMBIGUOUS_VALUE};
+    use crate::store::{LookupMap, UnorderedMap, Vector};
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct V1 {
+        on: bool,
+        a: u32,
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct V2 {
+        on: bool,
+        a: u32,
+        b: String,
+    }
+
+    impl From<V1> for V2 {
+        fn from(old: V1) -> Self {
+            Self { on: old.on, a: old.a, b: "upgraded".to_string() }
+        }
+    }
+
+    fn v2(a: u32, b: &str) -> V2 {
+        V2 { on: true, a, b: b.to_string() }
+    }
+
+    #[test]
+    fn ambiguous_tag() {
+        // An old value starting with the tag is not read as the new type.
+        let old = u32::MAX.try_to_vec().unwrap();
+        let err = Migrate::<u32, u64>::try_from_slice(&old).unwrap_err();
+        assert_eq!(err.to_string(), ERR_AMBIGUOUS_VALUE);
+
+        // With an old type which can start with the tag, new values cannot be read either.
+        let new = Migrate::<u32, u64>::new(5).try_to_vec().unwrap();
+        assert!(Migrate::<u32, u64>::try_from_slice(&new).is_err());
+        let old = 5u32.try_to_vec().unwrap();
+        assert_eq!(*Migrate::<u32, u64>::try_from_slice(&old).unwrap(), 5);
+
+        // A bool can never start with the tag, so the new type is read.
+        let new = Migrate::<V1, V2>::new(v2(1, "new")).try_to_vec().unwrap();
+        assert_eq!(*Migrate::<V1, V2>::try_from_slice(&new).unwrap(), v2(1, "new"));
+    }
+
+    #[test]
+    fn lookup_map_upgrade() {
+        let mut old = LookupMap::new(b"m");
+        old.insert(1u8, V1 { on: true, a: 1 });
+        old.insert(2u8, V1 { on: true, a: 2 });
+        old.flush();
+        drop(old);
+
+        {
+            let mut map: LookupMap<u8, Migrate<V1, V2>> = LookupMap::new(b"m");
+            assert_eq!(*map[&1], v2(1, "upgraded"));
+            assert!(map[&1].is_upgraded());
+            map.insert(3, v2(3, "new").into());
+            assert!(!map[&3].is_upgraded());
+
+            // Only the modified entry is written back.
+            map.get_mut(&2).unwrap().a += 10;
+        }
+
+        let map: LookupMap<u8, Migrate<V1, V2>> = LookupMap::new(b"m");
+        assert!(map[&1].is_upgraded());
+        assert!(!map[&2].is_upgraded());
+        assert_eq!(*map[&2], v2(12, "upgraded"));
+        assert_eq!(*map[&3], v2(3, "new"));
+    }
+
+    #[test]
+    fn unordered_map_upgrade() {
+        let mut old = UnorderedMap::new(b"u");
+        old.extend((0..5u8).map(|k| (k, V1 { on: false, a: k as u32 })));
+        old.flush();
+        let serialized = old.try_to_vec().unwrap();
+        drop(old);
+
+        let mut map =
+            UnorderedMap::<u8, Migrate<V1, V2>>::deserialize(&mut serialized.as_slice()).unwrap();
+        assert_eq!(map.len(), 5);
+        assert!(map.iter().all(|(k, v)| v.is_upgraded() && v.a == *k as u32));
+        map.get_mut(&1).unwrap().b = "modified".to_string();
+        map.insert(5, v2(5, "new").into());
+        map.flush();
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+
+        // The entries of an unordered map store the index of the key after the value.
+        let map =
+            UnorderedMap::<u8, Migrate<V1, V2>>::deserialize(&mut serialized.as_slice()).unwrap();
+        assert_eq!(map.len(), 6);
+        assert!(!map[&1].is_upgraded());
+        assert_eq!(map[&1].b, "modified");
+        assert!(!map[&5].is_upgraded());
+        assert_eq!(*map[&5], v2(5, "new"));
+        assert!(map[&0].is_upgraded());
+        assert_eq!(map.keys().copied().collect::<Vec<_>>(), (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn nested_round_trip() {
+        let mut vec: Vector<(Migrate<V1, V2>, u32)> = Vector::new(b"v");
+        vec.push((v2(1, "a").into(), 7));
+        vec.flush();
+        let serialized = vec.try_to_vec().unwrap();
+        drop(vec);
+
+        let vec =
+            Vector::<(Migrate<V1, V2>, u32)>::deserialize(&mut serialized.as_slice()).unwrap();
+        let (value, trailing) = &vec[0];
+        assert!(!value.is_upgraded());
+        assert_eq!(**value, v2(1, "a"));
+        assert_eq!(*trailing, 7);
+
+        let mut bytes = (V1 { on: true, a: 2 }, 7u32).try_to_vec().unwrap();
+        let (value, trailing) =
+            <(Migrate<V1, V2>, u32)>::deserialize(&mut bytes.as_slice()).unwrap();
+        assert!(value.is_upgraded());
+        assert_eq!(trailing, 7);
+
+        // A value which starts with the tag is read as the new type.
+        bytes = (Migrate::<V1, V2>::new(v2(3, "b")), 7u32).try_to_vec().unwrap();
+        assert_eq!(bytes[0], super::MIGRATED_TAG);
+        let (value, trailing) =
+            <(Migrate<V1, V2>, u32)>::deserialize(&mut bytes.as_slice()).unwrap();
+        assert_eq!(value.into_inner(), v2(3, "b"));
+        assert_eq!(trailing, 7);
+    }
+
+    #[test]
+    fn vector_batches() {
+        let mut old = crate::collections::Vector::new(b"v");
+        old.extend(0u32..25);
+        let mut new = crate::store::Vector::new(b"w");
+        let mut batches = 0;
+        while !super::vector(&mut old, &mut new, 10) {
+            batches += 1;
+            assert_eq!(new.len(), batches * 10);
+        }
+        assert_eq!(batches, 2);
+        assert!(old.is_empty());
+        assert!(super::vector(&mut old, &mut new, 10));
+        assert_eq!(new.iter().copied().collect::<Vec<_>>(), (0..25).collect::<Vec<_>>());
+
+        new.flush();
+        crate::mock::with_mocked_blockchain(|m| {
+            assert!(m.take_storage().keys().all(|k| k.starts_with(b"w")))
+        });
+    }
+
+    #[test]
+    fn map_batches() {
+        let mut old_unordered = crate::collections::UnorderedMap::new(b"u");
+        let mut old_tree = crate::collections::TreeMap::new(b"t");
+        for k in 0u32..25 {
+            old_unordered.insert(&k, &(k * 2));
+            old_tree.insert(&k, &(k * 3));
+        }
+        let mut unordered = UnorderedMap::new(b"v");
+        let mut tree = crate::store::TreeMap::new(b"s");
+        assert!(!super::unordered_map(&mut old_unordered, &mut unordered, 20));
+        assert_eq!(old_unordered.len(), 5);
+        assert!(super::unordered_map(&mut old_unordered, &mut unordered, 20));
+        assert!(!super::tree_map(&mut old_tree, &mut tree, 20));
+        assert_eq!(old_tree.min(), Some(20));
+        assert!(super::tree_map(&mut old_tree, &mut tree, 20));
+
+        assert_eq!(unordered.len(), 25);
+        assert_eq!(tree.len(), 25);
+        for k in 0u32..25 {
+            assert_eq!(unordered[&k], k * 2);
+            assert_eq!(tree[&k], k * 3);
+        }
+    }
+}
@@ -1,3 +1,69 @@
+//! Collections and types used when interacting with storage.
+//!
+//! These collections are lazily loaded and cache all loads and changes in memory, only writing
+//! modified values to storage when the collection is flushed or dropped.
+//!
+//! # Iteration
+//!
+//! Iterators over store collections borrow the collection for as long as they are alive, so the
+//! collection cannot be modified while it is being iterated over, other than through the
+//! iterator itself (for example with [`Vector::iter_mut`] or [`UnorderedMap::drain`]). This is
+//! checked at compile time, so there is no runtime cost to guard against an iterator observing a
+//! collection in an inconsistent state:
+//!
+//! ```compile_fail
+//! use near_sdk::store::Vector;
+//!
+//! let mut vec = Vector::new(b"v");
+//! vec.extend([1u8, 2, 3]);
+//!
+//! for value in vec.iter() {
+//!     // Cannot borrow `vec` as mutable because it is also borrowed as immutable
+//!     vec.push(*value);
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use near_sdk::store::UnorderedMap;
+//!
+//! let mut map = UnorderedMap::new(b"m");
+//! map.insert(1u8, 1u8);
+//!
+//! for (key, _) in map.iter() {
+//!     map.remove(key);
+//! }
+//! ```
+//!
+//! Note that this does not apply to separate instances of a collection that were created with
+//! the same storage prefix. Each instance keeps its own cache, and changes from one will not be
+//! seen by the other, so each prefix should only be used by a single collection.
+//!
+//! # Flushing
+//!
+//! Every collection writes its modified values to storage when it is dropped, which for the
+//! collections in the contract state happens at the end of each function call. Collections nested
+//! in the values of another collection are dropped, and so flushed, together with the outer
+//! collection. Unlike with [`collections`](crate::collections), a nested collection which is
+//! modified through a mutable reference does not need to be inserted again:
+//!
+//! ```
+//! use near_sdk::store::{LookupMap, Vector};
+//!
+//! let mut lists: LookupMap<u8, Vector<u32>> = LookupMap::new(b"m");
+//! lists.insert(1, Vector::new(b"v"));
+//! lists.get_mut(&1).unwrap().push(5);
+//! drop(lists);
+//!
+//! let lists: LookupMap<u8, Vector<u32>> = LookupMap::new(b"m");
+//! assert_eq!(lists[&1][0], 5);
+//! ```
+//!
+//! `flush` only needs to be called to write the changes before the collection is dropped, for
+//! example before reading the same storage with [`env::storage_read`](crate::env::storage_read).
+
+#[macro_use]
+mod schema;
+
 mod lazy;
 pub use lazy::Lazy;
 
@@ -9,6 +75,7 @@ pub use vec::Vector;
 
 pub mod lookup_map;
 pub use self::lookup_map::LookupMap;
+pub use self::lookup_map::TrackedLookupMap;
 
 mod lookup_set;
 pub use self::lookup_set::LookupSet;
@@ -16,11 +83,78 @@ pub use self::lookup_set::LookupSet;
 pub mod unordered_map;
 pub use self::unordered_map::UnorderedMap;
 
+pub mod unordered_set;
+pub use self::unordered_set::UnorderedSet;
+
+pub mod tree_map;
+pub use self::tree_map::MerkleTreeMap;
+pub use self::tree_map::TreeMap;
+
+pub mod tree_multi_map;
+pub use self::tree_multi_map::TreeMultiMap;
+
+pub mod indexed_map;
+pub use self::indexed_map::IndexedMap;
+
+pub mod binary_heap;
+pub use self::binary_heap::BinaryHeap;
+
+mod bit_set;
+pub use self::bit_set::BitSet;
+
+mod queue;
+pub use self::queue::Queue;
+
+pub mod log;
+pub use self::log::Log;
+
+pub mod prefix_trie;
+pub use self::prefix_trie::PrefixTrie;
+
+mod counter_map;
+pub use self::counter_map::CounterMap;
+
+mod namespace;
+pub use self::namespace::StorageNamespace;
+
+pub mod insertion_ordered_map;
+pub use self::insertion_ordered_map::InsertionOrderedMap;
+
+pub mod pagination;
+pub use self::pagination::Paginated;
+
+pub mod chunked_vector;
+pub use self::chunked_vector::ChunkedVector;
+
+pub mod sorted_vec;
+pub use self::sorted_vec::SortedVec;
+
+mod ring_buffer;
+pub use self::ring_buffer::RingBuffer;
+
+mod multi_set;
+pub use self::multi_set::MultiSet;
+
+mod expiring_map;
+pub use self::expiring_map::ExpiringMap;
+
+mod lazy_cell;
+pub use self::lazy_cell::LazyCell;
+
+mod versioned;
+pub use self::versioned::{NoPrevious, Upgrade, Versioned};
+
 mod index_map;
 pub(crate) use self::index_map::IndexMap;
 
-pub(crate) mod free_list;
-pub(crate) use self::free_list::FreeList;
+pub mod free_list;
+pub use self::free_list::{FreeList, FreeListIndex};
+
+mod clone_to;
+pub use self::clone_to::CloneTo;
+
+pub mod migrate;
+pub use self::migrate::Migrate;
 
 const ERR_INCONSISTENT_STATE: &str = "The collection is an inconsistent state. Did previous smart \
 										contract execution terminate unexpectedly?";
@@ -0,0 +1,324 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{UnorderedMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+const ERR_OVERFLOW: &str = "Count overflow";
+
+/// A lazily loaded, iterable multiset, which counts how many times each key was added.
+///
+/// Keys are removed from storage as soon as their count drops to `0`, so there are no entries
+/// with a zero count to clean up by hand, and the number of distinct keys as well as the total of
+/// all counts are kept up to date. The counts are stored in an [`UnorderedMap`], which can be
+/// iterated over.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::MultiSet;
+///
+/// let mut votes = MultiSet::new(b"v");
+/// votes.add("yes".to_string());
+/// votes.add("no".to_string());
+/// assert_eq!(votes.add_count("yes".to_string(), 2), 3);
+///
+/// assert_eq!(votes.count("yes"), 3);
+/// assert_eq!(votes.total(), 4);
+///
+/// assert!(votes.remove_one("no"));
+/// assert!(!votes.contains("no"));
+/// assert_eq!(votes.iter().collect::<Vec<_>>(), [(&"yes".to_string(), 3)]);
+/// ```
+pub struct MultiSet<K, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    total: u64,
+    counts: UnorderedMap<K, u32, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, H> BorshSerialize for MultiSet<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.total, writer)?;
+        BorshSerialize::serialize(&self.counts, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, H> BorshDeserialize for MultiSet<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            total: BorshDeserialize::deserialize(buf)?,
+            counts: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    MultiSet<K, H> {
+        total: u64,
+        counts: UnorderedMap<K, u32, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, H> fmt::Debug for MultiSet<K, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiSet")
+            .field("total", &self.total)
+            .field("counts", &self.counts)
+            .finish()
+    }
+}
+
+impl<K> MultiSet<K, Sha256>
+where
+    K: BorshSerialize + Ord,
+{
+    /// Create a new empty multiset. Uses the given prefix for the storage keys of the counts.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, H> MultiSet<K, H>
+where
+    K: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`MultiSet`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { total: 0, counts: UnorderedMap::with_hasher(prefix) }
+    }
+
+    /// Returns the number of distinct keys in the multiset.
+    pub fn len(&self) -> u32 {
+        self.counts.len()
+    }
+
+    /// Returns `true` if the multiset contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the sum of the counts of all keys.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the number of times `key` is in the multiset, which is `0` if it is not.
+    ///
+    /// The key may be any borrowed form of the multiset's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn count<Q: ?Sized>(&self, key: &Q) -> u32
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.counts.get(key).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if `key` is in the multiset at least once.
+    pub fn contains<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.count(key) != 0
+    }
+
+    /// Flushes the intermediate values of the multiset before this is called when the structure
+    /// is [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.counts.flush()
+    }
+}
+
+impl<K, H> MultiSet<K, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Adds `key` once and returns its new count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count of `key` overflows.
+    pub fn add(&mut self, key: K) -> u32 {
+        self.add_count(key, 1)
+    }
+
+    /// Adds `key` `n` times and returns its new count.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the count of `key` overflows.
+    pub fn add_count(&mut self, key: K, n: u32) -> u32 {
+        if n == 0 {
+            return self.count(&key);
+        }
+        self.total =
+            self.total.checked_add(u64::from(n)).unwrap_or_else(|| env::panic_str(ERR_OVERFLOW));
+        if let Some(count) = self.counts.get_mut(&key) {
+            *count = count.checked_add(n).unwrap_or_else(|| env::panic_str(ERR_OVERFLOW));
+            return *count;
+        }
+        self.counts.insert(key, n);
+        n
+    }
+
+    /// Removes `key` once, and returns `true` if it was in the multiset.
+    pub fn remove_one<Q: ?Sized>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.remove_count(key, 1) == 1
+    }
+
+    /// Removes `key` up to `n` times, and returns the number of times it was removed, which is
+    /// less than `n` if its count was less than `n`. The key is removed from storage once its
+    /// count reaches `0`.
+    pub fn remove_count<Q: ?Sized>(&mut self, key: &Q, n: u32) -> u32
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let removed = match self.counts.get_mut(key) {
+            Some(count) if *count > n => {
+                *count -= n;
+                n
+            }
+            Some(_) => {
+                self.counts.remove(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+            }
+            None => 0,
+        };
+        self.total -= u64::from(removed);
+        removed
+    }
+
+    /// Removes every occurrence of `key`, and returns its count before the removal.
+    pub fn remove_all<Q: ?Sized>(&mut self, key: &Q) -> u32
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.remove_count(key, u32::MAX)
+    }
+
+    /// Removes all keys from the multiset.
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.total = 0;
+    }
+
+    /// An iterator visiting all keys with their counts, in arbitrary order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&K, u32)> {
+        self.counts.iter().map(|(key, &count)| (key, count))
+    }
+}
+
+impl<K, H> Extend<K> for MultiSet<K, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = K>,
+    {
+        for key in iter {
+            self.add(key);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::MultiSet;
+    use crate::test_utils::test_env::setup_free;
+
+    #[test]
+    fn matches_counts() {
+        setup_free();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(5);
+        let mut set = MultiSet::new(b"m");
+        let mut baseline: BTreeMap<u8, u32> = BTreeMap::new();
+        for _ in 0..500 {
+            let key = rng.gen_range(0..20);
+            let n = rng.gen_range(0..4);
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let count = baseline.entry(key).or_default();
+                    *count += n;
+                    assert_eq!(set.add_count(key, n), *count);
+                    if *count == 0 {
+                        baseline.remove(&key);
+                    }
+                }
+                2 => {
+                    let count = baseline.get(&key).copied().unwrap_or(0);
+                    assert_eq!(set.remove_count(&key, n), count.min(n));
+                    if count <= n {
+                        baseline.remove(&key);
+                    } else {
+                        baseline.insert(key, count - n);
+                    }
+                }
+                _ => {
+                    let serialized = set.try_to_vec().unwrap();
+                    drop(set);
+                    set = MultiSet::try_from_slice(&serialized).unwrap();
+                }
+            }
+            assert_eq!(set.count(&key), baseline.get(&key).copied().unwrap_or(0));
+            assert_eq!(set.len() as usize, baseline.len());
+            assert_eq!(set.total(), baseline.values().map(|&c| u64::from(c)).sum::<u64>());
+        }
+        let counts: BTreeMap<u8, u32> = set.iter().map(|(&k, c)| (k, c)).collect();
+        assert_eq!(counts, baseline);
+
+        set.clear();
+        assert_eq!(set.total(), 0);
+        set.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
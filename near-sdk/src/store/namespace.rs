@@ -0,0 +1,136 @@
+use borsh::BorshSerialize;
+
+use crate::{env, IntoStorageKey};
+
+/// Components longer than this are replaced by their hash.
+const MAX_INLINE_LEN: usize = 32;
+
+/// Marker for a hashed component, which is distinct from any inline component length.
+const HASHED_COMPONENT: u8 = u8::MAX;
+
+/// A builder for storage prefixes of nested collections, which derives a distinct prefix for
+/// every path of components from a root prefix.
+///
+/// Concatenating prefixes by hand can produce the same prefix for different paths, for example
+/// `b"ab" + b"c"` and `b"a" + b"bc"`, and then two collections silently share their storage.
+/// Each component of a [`StorageNamespace`] is written with its length, so no path is encoded
+/// the same as another path. Components longer than 32 bytes, such as long account IDs, are
+/// replaced by their SHA-256 hash to keep the prefixes short.
+///
+/// A namespace can be passed to any collection constructor which takes a storage prefix. A
+/// namespace which is used as the prefix of a collection should not also be used as the parent
+/// of other namespaces, because the keys of the collection are appended to the same prefix.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::{LookupMap, StorageNamespace};
+///
+/// let root = StorageNamespace::new(b"r");
+/// let mut orders: LookupMap<String, LookupMap<u64, u128>> =
+///     LookupMap::new(root.child("orders").child("by_account"));
+///
+/// // Each account's nested map gets its own prefix derived from the account ID.
+/// let mut alice = LookupMap::new(root.child("orders").keyed("alice.near"));
+/// alice.insert(1, 100);
+/// orders.insert("alice.near".to_string(), alice);
+///
+/// assert_ne!(root.child("ab").child("c"), root.child("a").child("bc"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageNamespace {
+    prefix: Vec<u8>,
+}
+
+impl StorageNamespace {
+    /// Create a root namespace, whose prefix is the given prefix as is.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { prefix: prefix.into_storage_key() }
+    }
+
+    /// Returns the namespace for the component `name` inside this namespace.
+    pub fn child(&self, name: &str) -> Self {
+        self.push(name.as_bytes())
+    }
+
+    /// Returns the namespace for the Borsh serialized `key` inside this namespace, such as the
+    /// namespace of a collection nested in the value of a map under `key`.
+    pub fn keyed<K>(&self, key: &K) -> Self
+    where
+        K: BorshSerialize + ?Sized,
+    {
+        self.push(&key.try_to_vec().unwrap_or_else(|_| env::abort()))
+    }
+
+    fn push(&self, component: &[u8]) -> Self {
+        let mut prefix = Vec::with_capacity(self.prefix.len() + 1 + MAX_INLINE_LEN);
+        prefix.extend_from_slice(&self.prefix);
+        if component.len() <= MAX_INLINE_LEN {
+            prefix.push(component.len() as u8);
+            prefix.extend_from_slice(component);
+        } else {
+            prefix.push(HASHED_COMPONENT);
+            prefix.extend_from_slice(&env::sha256_array(component));
+        }
+        Self { prefix }
+    }
+
+    /// Returns the storage prefix of this namespace.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.prefix
+    }
+}
+
+impl IntoStorageKey for StorageNamespace {
+    fn into_storage_key(self) -> Vec<u8> {
+        self.prefix
+    }
+}
+
+impl IntoStorageKey for &StorageNamespace {
+    fn into_storage_key(self) -> Vec<u8> {
+        self.prefix.clone()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::StorageNamespace;
+
+    #[test]
+    fn distinct_prefixes() {
+        let root = StorageNamespace::new(b"n");
+        let long = "a".repeat(64);
+        let paths: Vec<Vec<&str>> = vec![
+            vec![],
+            vec![""],
+            vec!["", ""],
+            vec!["a"],
+            vec!["ab"],
+            vec!["ab", "c"],
+            vec!["a", "bc"],
+            vec!["abc"],
+            vec![&long],
+            vec![&long[..32]],
+            vec![&long[..33]],
+            vec![&long, "a"],
+        ];
+        let mut seen = HashMap::new();
+        for path in &paths {
+            let ns = path.iter().fold(root.clone(), |ns, name| ns.child(name));
+            assert!(ns.as_bytes().len() <= 1 + path.len() * 33);
+            if let Some(other) = seen.insert(ns.as_bytes().to_vec(), path) {
+                panic!("{:?} and {:?} have the same prefix", other, path);
+            }
+        }
+
+        // Keyed components are the serialized keys.
+        assert_eq!(root.keyed(&7u8), root.child("\u{7}"));
+        assert_ne!(root.keyed("x"), root.child("x"));
+    }
+}
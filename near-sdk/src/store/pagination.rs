@@ -0,0 +1,156 @@
+//! Pagination of store collections, so that view methods can return collections which are too
+//! large to load in one call.
+//!
+//! [`Paginated`] is implemented for references to [`Vector`](super::Vector),
+//! [`UnorderedMap`](super::UnorderedMap), [`UnorderedSet`](super::UnorderedSet) and
+//! [`TreeMap`](super::TreeMap). Each page returns a token for the next page, which can be
+//! returned to the caller and passed back in the next call:
+//!
+//! ```
+//! use near_sdk::store::pagination::Paginated;
+//! use near_sdk::store::UnorderedMap;
+//!
+//! let mut map = UnorderedMap::new(b"m");
+//! map.extend((0u32..5).map(|k| (k, k * 10)));
+//!
+//! let (page, token) = map.page(None, 3);
+//! assert_eq!(page, [(&0, &0), (&1, &10), (&2, &20)]);
+//!
+//! map.remove(&0);
+//! map.remove(&3);
+//! let (page, token) = map.page(token, 3);
+//! assert_eq!(page, [(&4, &40)]);
+//! assert!(token.is_none());
+//! ```
+//!
+//! # Changes between pages
+//!
+//! The token of a [`TreeMap`](super::TreeMap) is the key the next page starts at, and the token
+//! of an [`UnorderedMap`](super::UnorderedMap) or [`UnorderedSet`](super::UnorderedSet) is a
+//! [`PageToken`] with the last key of the page. The next page starts after that key, wherever it
+//! is stored by then, so every item which is in the collection during the whole pagination is
+//! returned exactly once. [`UnorderedMap::defrag`](super::UnorderedMap::defrag) and the
+//! compaction done by [`UnorderedMap::retain`](super::UnorderedMap::retain) and
+//! [`UnorderedSet::retain`](super::UnorderedSet::retain) move items but keep their order. Items
+//! inserted between pages reuse the positions of removed items, and are only returned if they are
+//! stored after the token.
+//!
+//! If the last key of a page is removed before the next page, the next page starts after the
+//! position the key had, which skips the items moved before that position by a compaction.
+//!
+//! The token of a [`Vector`](super::Vector) is the index the next page starts at.
+//! [`Vector::swap_remove`](super::Vector::swap_remove) moves the last element into the index of
+//! the removed one, and removing elements shifts the later ones, so these can cause elements to
+//! be skipped or repeated.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+
+/// A collection which can be read in pages of a limited number of items.
+///
+/// This is implemented for references to collections, so that the items can borrow from the
+/// collection.
+pub trait Paginated {
+    /// Type of the items of a page.
+    type Item;
+    /// Token which marks where the next page starts.
+    type Token;
+
+    /// Returns up to `limit` items, starting at the item marked by `from` or at the first item
+    /// if `from` is [`None`], and the token of the next page, which is [`None`] if there are no
+    /// more items.
+    fn page(
+        self,
+        from: Option<Self::Token>,
+        limit: usize,
+    ) -> (Vec<Self::Item>, Option<Self::Token>);
+}
+
+/// Token of the next page of an [`UnorderedMap`](super::UnorderedMap) or an
+/// [`UnorderedSet`](super::UnorderedSet).
+///
+/// The token holds the last key of a page and its position. The position is only a hint: if the
+/// key was moved since, its new position is looked up by the key.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "crate::serde")]
+pub struct PageToken<K> {
+    pub(crate) key: K,
+    pub(crate) index: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::Paginated;
+    use crate::store::{TreeMap, UnorderedMap, UnorderedSet, Vector};
+
+    #[test]
+    fn pages() {
+        let mut vec = Vector::new(b"v");
+        vec.extend(0u8..7);
+        assert_eq!(vec.page(None, 3), (vec![&0, &1, &2], Some(3)));
+        assert_eq!(vec.page(Some(6), 3), (vec![&6], None));
+        assert_eq!(vec.page(Some(9), 3), (vec![], None));
+        assert_eq!(vec.page(None, 0), (vec![], Some(0)));
+
+        let mut map = TreeMap::new(b"t");
+        map.extend((0u32..7).map(|k| (k * 10, k)));
+        assert_eq!(map.page(Some(25), 2), (vec![(&30, &3), (&40, &4)], Some(50)));
+        assert_eq!(map.page(Some(50), 2), (vec![(&50, &5), (&60, &6)], None));
+
+        let mut set = UnorderedSet::new(b"s");
+        set.extend(0u8..5);
+        let (page, token) = set.page(None, 4);
+        assert_eq!(page, [&0, &1, &2, &3]);
+        assert_eq!(set.page(token, 4), (vec![&4], None));
+    }
+
+    #[test]
+    fn compaction_between_pages() {
+        let mut map = UnorderedMap::new(b"m");
+        map.extend((0u32..40).map(|k| (k, k)));
+        let (page, mut token) = map.page(None, 10);
+        let mut seen: Vec<u32> = page.into_iter().map(|(k, _)| *k).collect();
+
+        // Removing keys before and after the token and compacting moves the last key of the page
+        // and all keys after it.
+        for k in [2, 5, 13, 17, 21, 33, 39] {
+            map.remove(&k);
+        }
+        map.defrag(u32::MAX);
+        let (page, next) = map.page(token, 10);
+        seen.extend(page.into_iter().map(|(k, _)| *k));
+        token = next;
+
+        // The same for the compaction of `retain`, which also moves the last key of the page.
+        map.retain(|k, _| k % 4 != 1 || *k > 30);
+        let (page, next) = map.page(token, 10);
+        seen.extend(page.into_iter().map(|(k, _)| *k));
+        token = next;
+
+        // A bounded defragmentation can leave gaps between pages.
+        map.remove(&0);
+        map.remove(&3);
+        assert!(!map.defrag(3));
+        while token.is_some() {
+            let (page, next) = map.page(token, 3);
+            seen.extend(page.into_iter().map(|(k, _)| *k));
+            token = next;
+        }
+
+        // Every key which was not removed is returned once, in the order of the keys.
+        let removed = |k: u32| [13, 17, 21, 33, 39].contains(&k) || (k % 4 == 1 && k <= 30);
+        let expected: Vec<u32> = (0..40).filter(|&k| k < 10 || !removed(k)).collect();
+        assert_eq!(seen, expected);
+
+        let mut set = UnorderedSet::new(b"s");
+        set.extend(0u32..20);
+        let (page, token) = set.page(None, 8);
+        let mut seen: Vec<u32> = page.into_iter().copied().collect();
+        set.retain(|v| v % 3 != 0);
+        let (page, token) = set.page(token, 20);
+        assert!(token.is_none());
+        seen.extend(page.into_iter().copied());
+        assert_eq!(seen, (0..20).filter(|&v| v < 8 || v % 3 != 0).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,270 @@
+//! A map from string keys to values, with lookups by key prefix.
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Bound;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{tree_map, TreeMap};
+use crate::crypto_hash::Sha256;
+use crate::IntoStorageKey;
+
+/// A lazily loaded storage map from string keys to values, which can find the keys starting with
+/// a prefix and the longest key which is a prefix of a string.
+///
+/// The keys are kept in a [`TreeMap`] in lexicographic order of their bytes, so all keys with
+/// the same prefix are next to each other. Iterating over the keys with a prefix only reads the
+/// entries with that prefix, and finding the longest matching prefix only needs a few ordered
+/// lookups instead of one lookup for every prefix of the string.
+///
+/// Sub-accounts of an account share a suffix instead of a prefix, so to look up accounts by
+/// their parent account, store their IDs with the order of the `.`-separated parts reversed.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::PrefixTrie;
+///
+/// let mut routes = PrefixTrie::new(b"r");
+/// routes.insert("/api", 1u32);
+/// routes.insert("/api/users", 2);
+/// routes.insert("/static", 3);
+///
+/// assert_eq!(routes.longest_prefix_match("/api/users/42"), Some(("/api/users", &2)));
+/// assert_eq!(routes.longest_prefix_match("/api/items"), Some(("/api", &1)));
+/// assert_eq!(routes.longest_prefix_match("/other"), None);
+///
+/// let api: Vec<(&str, &u32)> = routes.iter_prefix("/api").collect();
+/// assert_eq!(api, [("/api", &1), ("/api/users", &2)]);
+/// ```
+pub struct PrefixTrie<V>
+where
+    V: BorshSerialize,
+{
+    map: TreeMap<String, V>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<V> BorshSerialize for PrefixTrie<V>
+where
+    V: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.map, writer)
+    }
+}
+
+impl<V> BorshDeserialize for PrefixTrie<V>
+where
+    V: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self { map: BorshDeserialize::deserialize(buf)? })
+    }
+}
+
+impl_borsh_schema! {
+    PrefixTrie<V> {
+        map: TreeMap<String, V>,
+    }
+    where
+        V: BorshSerialize,
+}
+
+impl<V> fmt::Debug for PrefixTrie<V>
+where
+    V: BorshSerialize + BorshDeserialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixTrie").field("map", &self.map).finish()
+    }
+}
+
+impl<V> PrefixTrie<V>
+where
+    V: BorshSerialize,
+{
+    /// Create a new empty map which uses the given prefix for the storage keys of its entries.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { map: TreeMap::new(prefix) }
+    }
+
+    /// Returns the number of keys in the map.
+    pub fn len(&self) -> u32 {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.map.flush()
+    }
+}
+
+impl<V> PrefixTrie<V>
+where
+    V: BorshSerialize + BorshDeserialize,
+{
+    /// Returns a reference to the value of `key`.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Returns a mutable reference to the value of `key`.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    /// Returns `true` if the map contains a value for `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a key-value pair into the map, returning the previous value of the key if it was
+    /// in the map.
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        self.map.insert(key.to_string(), value)
+    }
+
+    /// Removes a key from the map, returning its value if it was in the map.
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        self.map.remove(key)
+    }
+
+    /// Returns the longest key in the map which is a prefix of `s`, along with its value. A key
+    /// equal to `s` is also a prefix of `s`.
+    pub fn longest_prefix_match<'a>(&'a self, s: &str) -> Option<(&'a str, &'a V)> {
+        // Any key which is a prefix of `s` is at most `s`. If the greatest key at most `s` is not
+        // a prefix of `s`, no key which is longer than their common prefix is a prefix of `s`,
+        // so the search continues with the common prefix.
+        let mut candidate = s;
+        loop {
+            let (key, value) = self.map.floor_entry(candidate)?;
+            if s.starts_with(key.as_str()) {
+                return Some((key, value));
+            }
+            let mut common = candidate.bytes().zip(key.bytes()).take_while(|(a, b)| a == b).count();
+            while !candidate.is_char_boundary(common) {
+                common -= 1;
+            }
+            candidate = &candidate[..common];
+        }
+    }
+
+    /// Returns an iterator over the keys starting with `prefix` and their values, in
+    /// lexicographic order of the keys. Only the entries with the prefix are read.
+    pub fn iter_prefix<'a>(&'a self, prefix: &'a str) -> IterPrefix<'a, V> {
+        IterPrefix {
+            inner: Some(self.map.range::<str, _>((Bound::Included(prefix), Bound::Unbounded))),
+            prefix,
+        }
+    }
+}
+
+/// An iterator over the entries of a [`PrefixTrie`] whose keys start with a prefix.
+///
+/// This `struct` is created by the [`iter_prefix`](PrefixTrie::iter_prefix) method on
+/// [`PrefixTrie`].
+pub struct IterPrefix<'a, V>
+where
+    V: BorshSerialize,
+{
+    /// Entries from the prefix onwards, until an entry without the prefix is reached.
+    inner: Option<tree_map::Range<'a, String, V, Sha256>>,
+    prefix: &'a str,
+}
+
+impl<'a, V> Iterator for IterPrefix<'a, V>
+where
+    V: BorshSerialize + BorshDeserialize,
+{
+    type Item = (&'a str, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.inner.as_mut()?.next()?;
+        if key.starts_with(self.prefix) {
+            Some((key, value))
+        } else {
+            // Keys are ordered, so no later key has the prefix.
+            self.inner = None;
+            None
+        }
+    }
+}
+
+impl<'a, V> FusedIterator for IterPrefix<'a, V> where V: BorshSerialize + BorshDeserialize {}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::PrefixTrie;
+
+    #[test]
+    fn prefix_lookups() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let alphabet = ['a', 'b', 'é', '.'];
+        let mut random_string = |max_len: usize| -> String {
+            let len = rng.gen_range(0..=max_len);
+            (0..len).map(|_| *alphabet.choose(&mut rng).unwrap()).collect()
+        };
+
+        let mut trie = PrefixTrie::new(b"t");
+        let mut baseline = BTreeMap::new();
+        for i in 0u32..200 {
+            let key = random_string(6);
+            assert_eq!(trie.insert(&key, i), baseline.insert(key, i));
+        }
+        for _ in 0..20 {
+            let key = random_string(6);
+            assert_eq!(trie.remove(&key), baseline.remove(&key));
+        }
+        assert_eq!(trie.len() as usize, baseline.len());
+
+        for _ in 0..200 {
+            let s = random_string(8);
+            let expected = baseline
+                .iter()
+                .filter(|(k, _)| s.starts_with(k.as_str()))
+                .max_by_key(|(k, _)| k.len())
+                .map(|(k, v)| (k.as_str(), v));
+            assert_eq!(trie.longest_prefix_match(&s), expected);
+
+            let expected: Vec<_> = baseline
+                .iter()
+                .filter(|(k, _)| k.starts_with(s.as_str()))
+                .map(|(k, v)| (k.as_str(), v))
+                .collect();
+            assert_eq!(trie.iter_prefix(&s).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn empty_key() {
+        let mut trie = PrefixTrie::new(b"t");
+        assert_eq!(trie.longest_prefix_match("abc"), None);
+        trie.insert("", 0u8);
+        trie.insert("abd", 1);
+        assert_eq!(trie.longest_prefix_match("abc"), Some(("", &0)));
+        assert_eq!(trie.longest_prefix_match(""), Some(("", &0)));
+        assert_eq!(trie.iter_prefix("").count(), 2);
+        assert_eq!(trie.iter_prefix("abdd").count(), 0);
+    }
+}
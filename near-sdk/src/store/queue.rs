@@ -0,0 +1,252 @@
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{IndexMap, ERR_INCONSISTENT_STATE};
+use crate::{env, IntoStorageKey};
+
+/// A lazily loaded first-in, first-out queue.
+///
+/// Each element is stored under its own key, at a position between a head and a tail counter
+/// which only increase, so adding or removing an element reads and writes a constant number of
+/// values regardless of the length of the queue. The counters wrap around, so the queue can be
+/// used indefinitely as long as it holds less than `u32::MAX` elements at a time.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::Queue;
+///
+/// let mut jobs = Queue::new(b"j");
+/// jobs.enqueue("a".to_string());
+/// jobs.enqueue("b".to_string());
+/// jobs.enqueue("c".to_string());
+///
+/// assert_eq!(jobs.dequeue(), Some("a".to_string()));
+/// assert_eq!(jobs.front(), Some(&"b".to_string()));
+/// assert_eq!(jobs.dequeue_up_to(5), ["b".to_string(), "c".to_string()]);
+/// assert!(jobs.is_empty());
+/// ```
+pub struct Queue<T>
+where
+    T: BorshSerialize,
+{
+    /// Position of the first element.
+    head: u32,
+    /// Position after the last element.
+    tail: u32,
+    elements: IndexMap<T>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T> BorshSerialize for Queue<T>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.head, writer)?;
+        BorshSerialize::serialize(&self.tail, writer)?;
+        BorshSerialize::serialize(&self.elements, writer)?;
+        Ok(())
+    }
+}
+
+impl<T> BorshDeserialize for Queue<T>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            head: BorshDeserialize::deserialize(buf)?,
+            tail: BorshDeserialize::deserialize(buf)?,
+            elements: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    Queue<T> {
+        head: u32,
+        tail: u32,
+        prefix: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+impl<T> Drop for Queue<T>
+where
+    T: BorshSerialize,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<T> fmt::Debug for Queue<T>
+where
+    T: BorshSerialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("head", &self.head)
+            .field("tail", &self.tail)
+            .field("prefix", &self.elements.prefix)
+            .finish()
+    }
+}
+
+impl<T> Queue<T>
+where
+    T: BorshSerialize,
+{
+    /// Create a new empty queue which uses the given prefix for the storage keys of its
+    /// elements.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { head: 0, tail: 0, elements: IndexMap::new(prefix) }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> u32 {
+        self.tail.wrapping_sub(self.head)
+    }
+
+    /// Returns `true` if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.head == self.tail
+    }
+
+    /// Adds an element to the back of the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queue already holds `u32::MAX` elements.
+    pub fn enqueue(&mut self, element: T) {
+        let tail = self.tail.wrapping_add(1);
+        if tail == self.head {
+            env::panic_str("Queue is full");
+        }
+        self.elements.set(self.tail, Some(element));
+        self.tail = tail;
+    }
+
+    /// Flushes the cache and writes all modified elements to storage.
+    pub fn flush(&mut self) {
+        self.elements.flush()
+    }
+}
+
+impl<T> Queue<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Returns a reference to the element at the front of the queue, which is the next element
+    /// to be dequeued, or [`None`] if the queue is empty.
+    pub fn front(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.elements.get(self.head).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Removes the element at the front of the queue and returns it, or [`None`] if the queue
+    /// is empty.
+    pub fn dequeue(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let element = self
+            .elements
+            .remove(self.head)
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        self.head = self.head.wrapping_add(1);
+        Some(element)
+    }
+
+    /// Removes up to `n` elements from the front of the queue and returns them in the order
+    /// they were added. Only the removed elements are read, so this can be used to process a
+    /// bounded number of elements in each function call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Queue;
+    ///
+    /// let mut queue = Queue::new(b"q");
+    /// for i in 0u32..10 {
+    ///     queue.enqueue(i);
+    /// }
+    ///
+    /// assert_eq!(queue.dequeue_up_to(4), [0, 1, 2, 3]);
+    /// assert_eq!(queue.len(), 6);
+    /// assert_eq!(queue.dequeue_up_to(10), [4, 5, 6, 7, 8, 9]);
+    /// assert!(queue.dequeue_up_to(10).is_empty());
+    /// ```
+    pub fn dequeue_up_to(&mut self, n: u32) -> Vec<T> {
+        let count = n.min(self.len());
+        (0..count).map(|_| self.dequeue().unwrap_or_else(|| env::abort())).collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::Queue;
+
+    #[test]
+    fn fifo() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut queue = Queue::new(b"q");
+        let mut baseline = VecDeque::new();
+        for _ in 0..1000 {
+            match rng.gen_range(0..10) {
+                0..=5 => {
+                    let value = rng.gen::<u64>();
+                    queue.enqueue(value);
+                    baseline.push_back(value);
+                }
+                6..=7 => assert_eq!(queue.dequeue(), baseline.pop_front()),
+                8 => {
+                    let n = rng.gen_range(0..5);
+                    let expected: Vec<_> =
+                        baseline.drain(..baseline.len().min(n as usize)).collect();
+                    assert_eq!(queue.dequeue_up_to(n), expected);
+                }
+                _ => {
+                    queue.flush();
+                    queue = Queue::try_from_slice(&queue.try_to_vec().unwrap()).unwrap();
+                }
+            }
+            assert_eq!(queue.len() as usize, baseline.len());
+            assert_eq!(queue.front(), baseline.front());
+        }
+    }
+
+    #[test]
+    fn wrapping_counters() {
+        let mut queue = Queue::new(b"q");
+        queue.head = u32::MAX - 2;
+        queue.tail = u32::MAX - 2;
+        for i in 0u8..6 {
+            queue.enqueue(i);
+        }
+        assert_eq!(queue.len(), 6);
+        assert_eq!(queue.tail, 3);
+        assert_eq!(queue.dequeue_up_to(6), [0, 1, 2, 3, 4, 5]);
+        assert!(queue.is_empty());
+
+        queue.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
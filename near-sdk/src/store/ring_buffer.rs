@@ -0,0 +1,285 @@
+use std::fmt;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{IndexMap, ERR_INCONSISTENT_STATE};
+use crate::{env, IntoStorageKey};
+
+/// A lazily loaded buffer of the last `capacity` elements pushed into it.
+///
+/// Elements are stored in `capacity` slots which are reused in order, so once the buffer is full,
+/// pushing an element overwrites the oldest one and the buffer never takes more storage than
+/// `capacity` elements. This can be used to keep a history of recent values, such as the last
+/// price points of an oracle or the most recent events of a contract.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::RingBuffer;
+///
+/// let mut prices = RingBuffer::new(b"p", 3);
+/// assert_eq!(prices.push(10u128), None);
+/// prices.push(11);
+/// prices.push(12);
+///
+/// // The oldest price is evicted once the buffer is full.
+/// assert_eq!(prices.push(13), Some(10));
+/// assert_eq!(prices.newest(), Some(&13));
+/// assert!(prices.iter().eq([11, 12, 13].iter()));
+/// ```
+pub struct RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    capacity: u32,
+    /// Slot of the oldest element.
+    head: u32,
+    len: u32,
+    elements: IndexMap<T>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T> BorshSerialize for RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.capacity, writer)?;
+        BorshSerialize::serialize(&self.head, writer)?;
+        BorshSerialize::serialize(&self.len, writer)?;
+        BorshSerialize::serialize(&self.elements, writer)?;
+        Ok(())
+    }
+}
+
+impl<T> BorshDeserialize for RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            capacity: BorshDeserialize::deserialize(buf)?,
+            head: BorshDeserialize::deserialize(buf)?,
+            len: BorshDeserialize::deserialize(buf)?,
+            elements: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    RingBuffer<T> {
+        capacity: u32,
+        head: u32,
+        len: u32,
+        prefix: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+impl<T> Drop for RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<T> fmt::Debug for RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingBuffer")
+            .field("capacity", &self.capacity)
+            .field("head", &self.head)
+            .field("len", &self.len)
+            .field("prefix", &self.elements.prefix)
+            .finish()
+    }
+}
+
+impl<T> RingBuffer<T>
+where
+    T: BorshSerialize,
+{
+    /// Create a new empty buffer which holds up to `capacity` elements, and uses the given
+    /// prefix for the storage keys of its elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new<S>(prefix: S, capacity: u32) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        if capacity == 0 {
+            env::panic_str("Ring buffer capacity must be greater than zero");
+        }
+        Self { capacity, head: 0, len: 0, elements: IndexMap::new(prefix) }
+    }
+
+    /// Returns the maximum number of elements in the buffer.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the buffer contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the buffer holds `capacity` elements, so that the next push evicts the
+    /// oldest element.
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+
+    /// Returns the slot of the element at `index`, counted from the oldest element.
+    fn slot(&self, index: u32) -> u32 {
+        // Computed in 64 bits, as the sum can overflow for capacities above `u32::MAX / 2`.
+        ((u64::from(self.head) + u64::from(index)) % u64::from(self.capacity)) as u32
+    }
+
+    /// Removes all elements from the buffer.
+    pub fn clear(&mut self) {
+        for index in 0..self.len {
+            self.elements.set(self.slot(index), None);
+        }
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Flushes the cache and writes all modified elements to storage.
+    pub fn flush(&mut self) {
+        self.elements.flush()
+    }
+}
+
+impl<T> RingBuffer<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Adds an element as the newest element of the buffer. If the buffer is full, the oldest
+    /// element is overwritten and returned.
+    pub fn push(&mut self, element: T) -> Option<T> {
+        if self.is_full() {
+            let evicted = self.elements.insert(self.head, element);
+            self.head = self.slot(1);
+            Some(evicted.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+        } else {
+            self.elements.set(self.slot(self.len), Some(element));
+            self.len += 1;
+            None
+        }
+    }
+
+    /// Removes the oldest element of the buffer and returns it, or [`None`] if the buffer is
+    /// empty.
+    pub fn pop_oldest(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let element = self
+            .elements
+            .remove(self.head)
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        self.head = self.slot(1);
+        self.len -= 1;
+        Some(element)
+    }
+
+    /// Returns the element at `index`, where index `0` is the oldest element, or [`None`] if
+    /// `index` is out of bounds.
+    pub fn get(&self, index: u32) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let element = self.elements.get(self.slot(index));
+        Some(element.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE)))
+    }
+
+    /// Returns the oldest element, or [`None`] if the buffer is empty.
+    pub fn oldest(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// Returns the most recently pushed element, or [`None`] if the buffer is empty.
+    pub fn newest(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// Returns an iterator over the elements from the oldest to the newest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        (0..self.len).map(move |index| {
+            self.elements
+                .get(self.slot(index))
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+        })
+    }
+}
+
+impl<T> Extend<T> for RingBuffer<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::{Rng, SeedableRng};
+
+    use super::RingBuffer;
+
+    #[test]
+    fn matches_vec_deque() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(3);
+        let mut buffer = RingBuffer::new(b"r", 7);
+        let mut baseline = VecDeque::new();
+        for _ in 0..200 {
+            match rng.gen_range(0..5) {
+                0..=2 => {
+                    let value: u32 = rng.gen();
+                    let evicted = if baseline.len() == 7 { baseline.pop_front() } else { None };
+                    baseline.push_back(value);
+                    assert_eq!(buffer.push(value), evicted);
+                }
+                3 => assert_eq!(buffer.pop_oldest(), baseline.pop_front()),
+                _ => {
+                    let serialized = buffer.try_to_vec().unwrap();
+                    drop(buffer);
+                    buffer = RingBuffer::try_from_slice(&serialized).unwrap();
+                }
+            }
+            assert_eq!(buffer.len() as usize, baseline.len());
+            assert_eq!(buffer.newest(), baseline.back());
+        }
+        assert!(buffer.iter().eq(baseline.iter()));
+        assert!(buffer.iter().rev().eq(baseline.iter().rev()));
+
+        buffer.clear();
+        buffer.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+}
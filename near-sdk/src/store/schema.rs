@@ -0,0 +1,142 @@
+//! [`BorshSchema`](borsh::BorshSchema) implementations of the store collections.
+//!
+//! The collections only serialize their storage prefixes and bookkeeping, such as the length,
+//! and not their elements, so the schema describes the serialized struct and does not depend on
+//! the types of the elements. The declarations are the names of the collections without their
+//! type parameters, because every instantiation of a collection has the same definition.
+
+/// Implements [`BorshSchema`](borsh::BorshSchema) for a collection, as a struct with the given
+/// fields in the order they are serialized. The field types only need to have the same schema as
+/// the serialized fields, so internal types which only hold a prefix are written as the prefix.
+macro_rules! impl_borsh_schema {
+    (
+        $name:ident $(<$($param:ident),*>)? {
+            $($field:ident: $field_ty:ty),* $(,)?
+        }
+        $(where $($bound:tt)*)?
+    ) => {
+        impl$(<$($param),*>)? borsh::BorshSchema for $name$(<$($param),*>)?
+        $(where $($bound)*)?
+        {
+            fn add_definitions_recursively(
+                definitions: &mut std::collections::HashMap<
+                    borsh::schema::Declaration,
+                    borsh::schema::Definition,
+                >,
+            ) {
+                let fields = borsh::schema::Fields::NamedFields(vec![
+                    $((
+                        stringify!($field).to_string(),
+                        <$field_ty as borsh::BorshSchema>::declaration(),
+                    )),*
+                ]);
+                <Self as borsh::BorshSchema>::add_definition(
+                    <Self as borsh::BorshSchema>::declaration(),
+                    borsh::schema::Definition::Struct { fields },
+                    definitions,
+                );
+                $(<$field_ty as borsh::BorshSchema>::add_definitions_recursively(definitions);)*
+            }
+
+            fn declaration() -> borsh::schema::Declaration {
+                stringify!($name).to_string()
+            }
+        }
+    };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use borsh::schema::{BorshSchemaContainer, Declaration, Definition, Fields};
+    use borsh::{BorshSchema, BorshSerialize};
+
+    use crate::store::{
+        BitSet, LookupMap, TreeMap, TreeMultiMap, UnorderedMap, UnorderedSet, Vector,
+    };
+
+    /// Reads a value of type `declaration` from `bytes` as described by the schema, and returns
+    /// the number of bytes read.
+    fn read(schema: &BorshSchemaContainer, declaration: &Declaration, bytes: &[u8]) -> usize {
+        let read_u32 = |bytes: &[u8]| u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        let size = match declaration.as_str() {
+            "nil" => Some(0),
+            "bool" | "u8" => Some(1),
+            "u32" => Some(4),
+            "u64" => Some(8),
+            "u128" => Some(16),
+            _ => None,
+        };
+        if let Some(size) = size {
+            assert!(bytes.len() >= size);
+            return size;
+        }
+        let read_all = |declarations: &mut dyn Iterator<Item = &Declaration>| {
+            declarations.fold(0, |offset, d| offset + read(schema, d, &bytes[offset..]))
+        };
+        match &schema.definitions[declaration] {
+            Definition::Sequence { elements } => {
+                let len = read_u32(bytes);
+                4 + (0..len)
+                    .fold(0, |offset, _| offset + read(schema, elements, &bytes[4 + offset..]))
+            }
+            Definition::Enum { variants } => {
+                1 + read(schema, &variants[bytes[0] as usize].1, &bytes[1..])
+            }
+            Definition::Struct { fields: Fields::NamedFields(fields) } => {
+                read_all(&mut fields.iter().map(|(_, d)| d))
+            }
+            Definition::Struct { fields: Fields::UnnamedFields(fields) } => {
+                read_all(&mut fields.iter())
+            }
+            Definition::Tuple { elements } => read_all(&mut elements.iter()),
+            definition => panic!("unexpected definition {:?}", definition),
+        }
+    }
+
+    fn assert_schema_matches<T: BorshSchema + BorshSerialize>(value: &T) {
+        let schema = T::schema_container();
+        let bytes = value.try_to_vec().unwrap();
+        assert_eq!(read(&schema, &schema.declaration, &bytes), bytes.len());
+    }
+
+    #[test]
+    fn schema_matches_serialization() {
+        let mut map: TreeMap<u32, String> = TreeMap::new(b"t");
+        map.insert(1, "a".to_string());
+        map.insert(2, "b".to_string());
+        map.remove(&1);
+        assert_schema_matches(&map);
+        assert_eq!(TreeMap::<u8, u8>::declaration(), "TreeMap");
+
+        let mut map: UnorderedMap<String, Vector<u8>> = UnorderedMap::new(b"u");
+        map.insert("a".to_string(), Vector::new(b"v"));
+        assert_schema_matches(&map);
+
+        let mut set: UnorderedSet<u64> = UnorderedSet::new(b"s");
+        set.insert(5);
+        assert_schema_matches(&set);
+
+        let mut multi: TreeMultiMap<u8, u8> = TreeMultiMap::new(b"m");
+        multi.insert(1, 2);
+        assert_schema_matches(&multi);
+
+        assert_schema_matches(&LookupMap::<u8, u8>::new(b"l"));
+        assert_schema_matches(&BitSet::new(b"b"));
+
+        // Different element types have the same definition.
+        let schema = Vector::<u8>::schema_container();
+        assert_eq!(schema.declaration, Vector::<String>::declaration());
+        assert_eq!(
+            schema.definitions["Vector"],
+            Definition::Struct {
+                fields: Fields::NamedFields(vec![
+                    ("len".to_string(), "u32".to_string()),
+                    ("prefix".to_string(), "Vec<u8>".to_string()),
+                ])
+            }
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//! A vector which keeps its elements sorted. See [`SortedVec`].
+
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+use super::{vec, Vector};
+use crate::{env, IntoStorageKey};
+
+const ERR_INDEX_OUT_OF_BOUNDS: &str = "Index out of bounds";
+
+/// How a [`SortedVec`] handles a value which is equal to an element already in the vector.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Duplicates {
+    /// Equal values are inserted after the elements equal to them.
+    Allow,
+    /// A value which is equal to an element of the vector is not inserted.
+    Reject,
+}
+
+/// A vector which keeps its elements in ascending order, stored as a [`Vector`].
+///
+/// Values are inserted at their position found by a binary search, which reads `O(log n)`
+/// elements, and the elements after the position are shifted by one, which rewrites only those
+/// elements. A [`TreeMap`](super::TreeMap) stores a node with the key, child indices and height
+/// of every entry in addition to the entry itself, so for ordered data of up to a few thousand
+/// small elements a [`SortedVec`] takes less storage, and lookups by position such as
+/// [`get`](Self::get) and [`rank`](Self::rank) only read the elements they need.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::SortedVec;
+///
+/// let mut bids = SortedVec::new(b"b");
+/// bids.extend([30u32, 10, 20, 10]);
+///
+/// assert!(bids.iter().eq([10, 10, 20, 30].iter()));
+/// assert_eq!(bids.rank(&20), 2);
+/// assert!(bids.range_by_index(1..3).eq([10, 20].iter()));
+///
+/// assert!(bids.remove(&10));
+/// assert_eq!(bids.first(), Some(&10));
+/// assert_eq!(bids.last(), Some(&30));
+/// ```
+pub struct SortedVec<T>
+where
+    T: BorshSerialize,
+{
+    duplicates: Duplicates,
+    elements: Vector<T>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T> BorshSerialize for SortedVec<T>
+where
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.duplicates, writer)?;
+        BorshSerialize::serialize(&self.elements, writer)?;
+        Ok(())
+    }
+}
+
+impl<T> BorshDeserialize for SortedVec<T>
+where
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            duplicates: BorshDeserialize::deserialize(buf)?,
+            elements: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    SortedVec<T> {
+        duplicates: Duplicates,
+        elements: Vector<T>,
+    }
+    where
+        T: BorshSerialize,
+}
+
+impl<T> fmt::Debug for SortedVec<T>
+where
+    T: BorshSerialize + BorshDeserialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SortedVec")
+            .field("duplicates", &self.duplicates)
+            .field("elements", &self.elements)
+            .finish()
+    }
+}
+
+impl<T> SortedVec<T>
+where
+    T: BorshSerialize,
+{
+    /// Create a new empty vector which allows duplicates. Prefixes storage accesss with the
+    /// prefix provided.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_duplicates(prefix, Duplicates::Allow)
+    }
+
+    /// Create a new empty vector which handles values equal to existing elements as given by
+    /// `duplicates`.
+    ///
+    /// # Examples
+    /// ```
+    /// use near_sdk::store::sorted_vec::{Duplicates, SortedVec};
+    ///
+    /// let mut vec = SortedVec::with_duplicates(b"v", Duplicates::Reject);
+    /// assert!(vec.insert(1u8));
+    /// assert!(!vec.insert(1));
+    /// assert_eq!(vec.len(), 1);
+    /// ```
+    pub fn with_duplicates<S>(prefix: S, duplicates: Duplicates) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self { duplicates, elements: Vector::new(prefix) }
+    }
+
+    /// Returns how values equal to an element of the vector are handled.
+    pub fn duplicates(&self) -> Duplicates {
+        self.duplicates
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> u32 {
+        self.elements.len()
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Removes all elements from the vector.
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    /// Flushes the cache and writes all modified elements to storage.
+    pub fn flush(&mut self) {
+        self.elements.flush();
+    }
+}
+
+impl<T> SortedVec<T>
+where
+    T: BorshSerialize + BorshDeserialize + Ord,
+{
+    /// Inserts a value at its sorted position, and returns `true` if it was inserted. The value
+    /// is not inserted if the vector rejects duplicates and contains an equal element.
+    ///
+    /// This rewrites every element after the position of the value.
+    pub fn insert(&mut self, value: T) -> bool {
+        let index = self.elements.partition_point(|e| e <= &value);
+        if self.duplicates == Duplicates::Reject && index > 0 && self.elements[index - 1] == value {
+            return false;
+        }
+        self.elements.insert(index, value);
+        true
+    }
+
+    /// Removes the first element equal to `value`, and returns `true` if there was one.
+    ///
+    /// This rewrites every element after the position of the removed element.
+    pub fn remove(&mut self, value: &T) -> bool {
+        let index = self.rank(value);
+        if self.elements.get(index) != Some(value) {
+            return false;
+        }
+        self.elements.remove(index);
+        true
+    }
+
+    /// Removes and returns the element at `index`, shifting all elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove_index(&mut self, index: u32) -> T {
+        self.elements.remove(index)
+    }
+
+    /// Returns `true` if the vector contains an element equal to `value`.
+    pub fn contains(&self, value: &T) -> bool {
+        self.elements.binary_search(value).is_ok()
+    }
+
+    /// Returns the number of elements which are less than `value`, which is the index of the
+    /// first element equal to `value` if there is one.
+    pub fn rank(&self, value: &T) -> u32 {
+        self.elements.partition_point(|e| e < value)
+    }
+
+    /// Returns the element at `index` in ascending order, or `None` if it is out of bounds.
+    pub fn get(&self, index: u32) -> Option<&T> {
+        self.elements.get(index)
+    }
+
+    /// Returns the smallest element, or `None` if the vector is empty.
+    pub fn first(&self) -> Option<&T> {
+        self.elements.get(0)
+    }
+
+    /// Returns the largest element, or `None` if the vector is empty.
+    pub fn last(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|i| self.elements.get(i))
+    }
+
+    /// Returns an iterator over the elements in ascending order.
+    pub fn iter(&self) -> vec::Iter<T> {
+        self.elements.iter()
+    }
+
+    /// Returns an iterator over the elements at the indices in `range`, in ascending order.
+    /// Only the elements in the range are read from storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, or if it starts after it ends.
+    pub fn range_by_index<R>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator
+    where
+        R: RangeBounds<u32>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => Some(i),
+            Bound::Excluded(&i) => i.checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.checked_add(1),
+            Bound::Excluded(&i) => Some(i),
+            Bound::Unbounded => Some(self.len()),
+        };
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start <= end && end <= self.len() => (start, end),
+            _ => env::panic_str(ERR_INDEX_OUT_OF_BOUNDS),
+        };
+        self.elements.iter().skip(start as usize).take((end - start) as usize)
+    }
+}
+
+impl<T> Extend<T> for SortedVec<T>
+where
+    T: BorshSerialize + BorshDeserialize + Ord,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::{Duplicates, SortedVec};
+    use crate::test_utils::test_env::setup_free;
+
+    #[test]
+    fn matches_sorted_vec() {
+        setup_free();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(7);
+        for duplicates in [Duplicates::Allow, Duplicates::Reject] {
+            let mut vec = SortedVec::with_duplicates(b"s", duplicates);
+            let mut baseline: Vec<u8> = Vec::new();
+            for _ in 0..300 {
+                let value = rng.gen_range(0..50);
+                if rng.gen_bool(0.6) {
+                    let index = baseline.partition_point(|&e| e <= value);
+                    let inserted = duplicates == Duplicates::Allow || !baseline.contains(&value);
+                    if inserted {
+                        baseline.insert(index, value);
+                    }
+                    assert_eq!(vec.insert(value), inserted);
+                } else {
+                    let index = baseline.iter().position(|&e| e == value);
+                    if let Some(index) = index {
+                        baseline.remove(index);
+                    }
+                    assert_eq!(vec.remove(&value), index.is_some());
+                }
+                assert_eq!(vec.contains(&value), baseline.contains(&value));
+                assert_eq!(vec.rank(&value) as usize, baseline.partition_point(|&e| e < value));
+            }
+            assert!(vec.iter().eq(baseline.iter()));
+            let (lo, hi) = (baseline.len() / 3, baseline.len() / 2);
+            assert!(vec.range_by_index(lo as u32..=hi as u32).eq(baseline[lo..=hi].iter()));
+            assert!(vec.range_by_index(lo as u32..).rev().eq(baseline[lo..].iter().rev()));
+            assert_eq!(vec.last(), baseline.last());
+            vec.clear();
+        }
+    }
+}
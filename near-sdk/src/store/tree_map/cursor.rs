@@ -0,0 +1,133 @@
+use std::borrow::Borrow;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{TreeMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::CryptoHasher;
+use crate::env;
+
+/// Position of a [`Cursor`] in the map.
+enum Position<'a, K> {
+    /// Before the smallest key.
+    Start,
+    /// At a key in the map.
+    At(&'a K),
+    /// After the largest key.
+    End,
+}
+
+/// A cursor over the entries of a [`TreeMap`], which can be moved in both directions and
+/// repositioned with [`seek`](Cursor::seek).
+///
+/// The cursor is positioned at an entry of the map, or before the first entry or after the last
+/// entry. It starts before the first entry, so that [`next`](Iterator::next) returns the first
+/// entry. Every move only loads the nodes of the tree on the path to the next key, and the value
+/// of the entry it returns.
+///
+/// The position is only the key of the current entry, so it can be persisted between calls, for
+/// example to paginate over the map, by storing the last key and seeking to it in the next call.
+///
+/// This `struct` is created by the `cursor` method on [`TreeMap`].
+pub struct Cursor<'a, K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    map: &'a TreeMap<K, V, H>,
+    position: Position<'a, K>,
+}
+
+impl<'a, K, V, H> Cursor<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a TreeMap<K, V, H>) -> Self {
+        Self { map, position: Position::Start }
+    }
+
+    /// Moves the cursor to the entry with the smallest key which is greater than or equal to
+    /// `key`, or after the last entry if there is no such key, and returns the entry.
+    pub fn seek<Q: ?Sized>(&mut self, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let next = self.map.tree.ceil(key, true);
+        self.move_to(next, Position::End)
+    }
+
+    /// Returns the key of the entry the cursor is positioned at.
+    pub fn key(&self) -> Option<&'a K> {
+        match self.position {
+            Position::At(key) => Some(key),
+            Position::Start | Position::End => None,
+        }
+    }
+
+    /// Returns the entry the cursor is positioned at, without moving the cursor.
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        self.key().map(|key| self.entry(key))
+    }
+
+    /// Moves the cursor to the previous entry and returns it. If the cursor is after the last
+    /// entry, it is moved to the last entry. If there is no previous entry, the cursor is moved
+    /// before the first entry and [`None`] is returned.
+    pub fn prev(&mut self) -> Option<(&'a K, &'a V)> {
+        let tree = &self.map.tree;
+        let prev = match self.position {
+            Position::Start => None,
+            Position::At(key) => tree.floor(key, false),
+            Position::End => tree.max(),
+        };
+        self.move_to(prev, Position::Start)
+    }
+
+    /// Positions the cursor at `key`, or at `otherwise` if there is no key.
+    fn move_to(
+        &mut self,
+        key: Option<&'a K>,
+        otherwise: Position<'a, K>,
+    ) -> Option<(&'a K, &'a V)> {
+        match key {
+            Some(key) => {
+                self.position = Position::At(key);
+                Some(self.entry(key))
+            }
+            None => {
+                self.position = otherwise;
+                None
+            }
+        }
+    }
+
+    fn entry(&self, key: &'a K) -> (&'a K, &'a V) {
+        let value =
+            self.map.values.get(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        (key, value)
+    }
+}
+
+impl<'a, K, V, H> Iterator for Cursor<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+
+    /// Moves the cursor to the next entry and returns it. If the cursor is before the first
+    /// entry, it is moved to the first entry. If there is no next entry, the cursor is moved
+    /// after the last entry and [`None`] is returned.
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = &self.map.tree;
+        let next = match self.position {
+            Position::Start => tree.min(),
+            Position::At(key) => tree.ceil(key, false),
+            Position::End => None,
+        };
+        self.move_to(next, Position::End)
+    }
+}
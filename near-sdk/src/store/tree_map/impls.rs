@@ -0,0 +1,63 @@
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{TreeMap, ERR_NOT_EXIST};
+use crate::store::pagination::Paginated;
+use crate::{crypto_hash::CryptoHasher, env};
+
+impl<K, V, H> Extend<(K, V)> for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, H, Q: ?Sized> core::ops::Index<&Q> for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + Clone + Borrow<Q>,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+    Q: BorshSerialize + ToOwned<Owned = K>,
+{
+    type Output = V;
+
+    /// Returns reference to value corresponding to key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key does not exist in the map
+    fn index(&self, index: &Q) -> &Self::Output {
+        self.get(index).unwrap_or_else(|| env::panic_str(ERR_NOT_EXIST))
+    }
+}
+
+impl<'a, K, V, H> Paginated for &'a TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+    /// Key of the first entry of the page.
+    type Token = K;
+
+    /// Returns up to `limit` entries in ascending order of the keys.
+    fn page(self, from: Option<K>, limit: usize) -> (Vec<Self::Item>, Option<K>) {
+        let lo = from.map_or(Bound::Unbounded, Bound::Included);
+        let mut items: Vec<_> =
+            self.range((lo, Bound::Unbounded)).take(limit.saturating_add(1)).collect();
+        let next = if items.len() > limit { items.pop().map(|(k, _)| k.clone()) } else { None };
+        (items, next)
+    }
+}
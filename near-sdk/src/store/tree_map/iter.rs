@@ -0,0 +1,736 @@
+use std::borrow::Borrow;
+use std::iter::FusedIterator;
+use std::ops::Bound;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{LookupMap, Tree, TreeMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::CryptoHasher;
+use crate::env;
+use crate::store::free_list::FreeListIndex;
+
+impl<'a, K, V, H> IntoIterator for &'a TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, H> IntoIterator for &'a mut TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// In-order traversal of the keys of a [`Tree`] within a range, from both ends.
+struct KeysRange<'a, K>
+where
+    K: BorshSerialize,
+{
+    tree: &'a Tree<K>,
+    /// Nodes which are left to visit from the front, with the next node on top. Contains the
+    /// nodes on the path from the root to the next node, which are greater than the next node.
+    front: Vec<FreeListIndex>,
+    /// Nodes which are left to visit from the back, with the next node on top. Contains the
+    /// nodes on the path from the root to the next node, which are smaller than the next node.
+    back: Vec<FreeListIndex>,
+    /// Amount of keys left to iterate, which keeps both ends from passing each other.
+    remaining: usize,
+}
+
+impl<'a, K> KeysRange<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    fn new<Q: ?Sized>(tree: &'a Tree<K>, lo: Bound<&Q>, hi: Bound<&Q>) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        // Find the path to the first key within the lower bound.
+        let mut front = Vec::new();
+        let mut at = tree.root;
+        while let Some(idx) = at {
+            let node = tree.node(idx);
+            let in_range = match lo {
+                Bound::Included(lo) => node.key.borrow() >= lo,
+                Bound::Excluded(lo) => node.key.borrow() > lo,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                front.push(idx);
+                at = node.lft;
+            } else {
+                at = node.rgt;
+            }
+        }
+
+        // Find the path to the last key within the upper bound.
+        let mut back = Vec::new();
+        let mut at = tree.root;
+        while let Some(idx) = at {
+            let node = tree.node(idx);
+            let in_range = match hi {
+                Bound::Included(hi) => node.key.borrow() <= hi,
+                Bound::Excluded(hi) => node.key.borrow() < hi,
+                Bound::Unbounded => true,
+            };
+            if in_range {
+                back.push(idx);
+                at = node.rgt;
+            } else {
+                at = node.lft;
+            }
+        }
+
+        let below_lo = match lo {
+            Bound::Included(lo) => tree.count_below(lo, false),
+            Bound::Excluded(lo) => tree.count_below(lo, true),
+            Bound::Unbounded => 0,
+        };
+        let below_hi = match hi {
+            Bound::Included(hi) => tree.count_below(hi, true),
+            Bound::Excluded(hi) => tree.count_below(hi, false),
+            Bound::Unbounded => tree.nodes.len(),
+        };
+        let remaining = below_hi.saturating_sub(below_lo) as usize;
+        Self { tree, front, back, remaining }
+    }
+}
+
+impl<'a, K> Iterator for KeysRange<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.tree.node(self.front.pop()?);
+        self.remaining -= 1;
+
+        // The next node is the smallest node of the right subtree, if any.
+        let mut at = node.rgt;
+        while let Some(idx) = at {
+            self.front.push(idx);
+            at = self.tree.node(idx).lft;
+        }
+        Some(&node.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for KeysRange<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.tree.node(self.back.pop()?);
+        self.remaining -= 1;
+
+        // The next node from the back is the largest node of the left subtree, if any.
+        let mut at = node.lft;
+        while let Some(idx) = at {
+            self.back.push(idx);
+            at = self.tree.node(idx).rgt;
+        }
+        Some(&node.key)
+    }
+}
+
+impl<'a, K> ExactSizeIterator for KeysRange<'a, K> where K: BorshSerialize + BorshDeserialize + Ord {}
+impl<'a, K> FusedIterator for KeysRange<'a, K> where K: BorshSerialize + BorshDeserialize + Ord {}
+
+/// An iterator over the keys of a [`TreeMap`], in ascending order. The values are not loaded.
+///
+/// This `struct` is created by the `keys` and `keys_range` methods on [`TreeMap`].
+pub struct Keys<'a, K>
+where
+    K: BorshSerialize,
+{
+    inner: KeysRange<'a, K>,
+}
+
+impl<'a, K> Keys<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    pub(super) fn new<Q: ?Sized, V, H>(
+        map: &'a TreeMap<K, V, H>,
+        lo: Bound<&Q>,
+        hi: Bound<&Q>,
+    ) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+    {
+        Self { inner: KeysRange::new(&map.tree, lo, hi) }
+    }
+}
+
+impl<'a, K> Iterator for Keys<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.inner.count()
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Keys<'a, K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, K> ExactSizeIterator for Keys<'a, K> where K: BorshSerialize + BorshDeserialize + Ord {}
+impl<'a, K> FusedIterator for Keys<'a, K> where K: BorshSerialize + BorshDeserialize + Ord {}
+
+/// An iterator over elements of a [`TreeMap`], in ascending order of the keys.
+///
+/// This `struct` is created by the `iter` method on [`TreeMap`].
+pub struct Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    inner: Range<'a, K, V, H>,
+}
+
+impl<'a, K, V, H> Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a TreeMap<K, V, H>) -> Self {
+        Self { inner: Range::new::<K>(map, Bound::Unbounded, Bound::Unbounded) }
+    }
+}
+
+impl<'a, K, V, H> Iterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.inner.count()
+    }
+}
+
+impl<'a, K, V, H> DoubleEndedIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// A mutable iterator over elements of a [`TreeMap`], in ascending order of the keys.
+///
+/// This `struct` is created by the `iter_mut` method on [`TreeMap`].
+pub struct IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    inner: RangeMut<'a, K, V, H>,
+}
+
+impl<'a, K, V, H> IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a mut TreeMap<K, V, H>) -> Self {
+        Self { inner: RangeMut::new::<K>(map, Bound::Unbounded, Bound::Unbounded) }
+    }
+}
+
+impl<'a, K, V, H> Iterator for IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.inner.count()
+    }
+}
+
+impl<'a, K, V, H> DoubleEndedIterator for IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for IterMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// An iterator over a range of elements of a [`TreeMap`], in ascending order of the keys.
+///
+/// This `struct` is created by the `range` method on [`TreeMap`].
+pub struct Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    keys: KeysRange<'a, K>,
+    /// Reference to underlying map to lookup values with `keys`.
+    values: &'a LookupMap<K, V, H>,
+}
+
+impl<'a, K, V, H> Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new<Q: ?Sized>(map: &'a TreeMap<K, V, H>, lo: Bound<&Q>, hi: Bound<&Q>) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        Self { keys: KeysRange::new(&map.tree, lo, hi), values: &map.values }
+    }
+
+    fn with_value(&self, key: &'a K) -> (&'a K, &'a V)
+    where
+        K: Clone,
+        V: BorshDeserialize,
+    {
+        let value = self.values.get(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        (key, value)
+    }
+}
+
+impl<'a, K, V, H> Iterator for Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        Some(self.with_value(key))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.keys.count()
+    }
+}
+
+impl<'a, K, V, H> DoubleEndedIterator for Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next_back()?;
+        Some(self.with_value(key))
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for Range<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// A mutable iterator over a range of elements of a [`TreeMap`], in ascending order of the keys.
+///
+/// This `struct` is created by the `range_mut` method on [`TreeMap`].
+pub struct RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    keys: KeysRange<'a, K>,
+    /// Exclusive reference to underlying map to lookup values with `keys`.
+    values: &'a mut LookupMap<K, V, H>,
+}
+
+impl<'a, K, V, H> RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new<Q: ?Sized>(
+        map: &'a mut TreeMap<K, V, H>,
+        lo: Bound<&Q>,
+        hi: Bound<&Q>,
+    ) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        Self { keys: KeysRange::new(&map.tree, lo, hi), values: &mut map.values }
+    }
+
+    fn value_mut(&mut self, key: &K) -> &'a mut V
+    where
+        K: Clone,
+        V: BorshDeserialize,
+    {
+        let value =
+            self.values.get_mut(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        //* SAFETY: The lifetime can be swapped here because we can assert that the iterator
+        //*         will only give out one mutable reference for every individual key in the tree
+        //*         during the iteration, and there is no overlap. The tree only contains unique
+        //*         keys, and the traversal visits every key at most once from either end.
+        unsafe { &mut *(value as *mut V) }
+    }
+}
+
+impl<'a, K, V, H> Iterator for RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        Some((key, self.value_mut(key)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.keys.count()
+    }
+}
+
+impl<'a, K, V, H> DoubleEndedIterator for RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next_back()?;
+        Some((key, self.value_mut(key)))
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for RangeMut<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// A draining iterator for [`TreeMap`], in ascending order of the keys.
+///
+/// This `struct` is created by the `drain` method on [`TreeMap`].
+pub struct Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    map: &'a mut TreeMap<K, V, H>,
+    /// Nodes which are left to visit, with the next node on top. Visited nodes are removed, so
+    /// the remaining nodes are no longer reachable from the root.
+    stack: Vec<FreeListIndex>,
+    /// Amount of elements left to remove.
+    remaining: usize,
+}
+
+impl<'a, K, V, H> Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a mut TreeMap<K, V, H>) -> Self {
+        // All elements will be removed on the drain iterator being dropped, fine to
+        // pre-emptively detach the root since a mutable reference is kept to the map.
+        let root = map.tree.root.take();
+        let remaining = map.len() as usize;
+        let mut drain = Self { map, stack: Vec::new(), remaining };
+        drain.push_min_path(root);
+        drain
+    }
+
+    /// Pushes the path from `at` to the smallest node of its subtree onto the stack.
+    fn push_min_path(&mut self, mut at: Option<FreeListIndex>) {
+        while let Some(idx) = at {
+            self.stack.push(idx);
+            at = self.map.tree.node(idx).lft;
+        }
+    }
+}
+
+impl<'a, K, V, H> Drop for Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn drop(&mut self) {
+        // Remove the values of any nodes that were not yielded by the iterator, along with the
+        // cells of all removed nodes.
+        self.map.clear();
+    }
+}
+
+impl<'a, K, V, H> Iterator for Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.stack.pop()?;
+        let node = self
+            .map
+            .tree
+            .nodes
+            .remove(idx)
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        // The left subtree was already drained, continue with the right subtree.
+        self.push_min_path(node.rgt);
+        self.remaining -= 1;
+
+        let value = self
+            .map
+            .values
+            .remove(&node.key)
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        Some((node.key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// An iterator which removes and yields the entries of a [`TreeMap`] for which a predicate
+/// returns `true`, in ascending order of the keys.
+///
+/// The entries are visited as the iterator is advanced, so entries after the last yielded entry
+/// are neither visited nor removed if the iterator is dropped early.
+///
+/// This `struct` is created by the `extract_if` method on [`TreeMap`].
+pub struct ExtractIf<'a, K, V, H, F>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    map: &'a mut TreeMap<K, V, H>,
+    /// Key of the last visited entry, which was kept in the map.
+    last: Option<K>,
+    pred: F,
+}
+
+impl<'a, K, V, H, F> ExtractIf<'a, K, V, H, F>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a mut TreeMap<K, V, H>, pred: F) -> Self {
+        Self { map, last: None, pred }
+    }
+}
+
+impl<'a, K, V, H, F> Iterator for ExtractIf<'a, K, V, H, F>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let tree = &self.map.tree;
+            let key = match &self.last {
+                Some(last) => tree.ceil(last, false),
+                None => tree.min(),
+            }?
+            .clone();
+            let value = self
+                .map
+                .values
+                .get_mut(&key)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if (self.pred)(&key, value) {
+                return self.map.remove_entry(&key);
+            }
+            self.last = Some(key);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let visited = match &self.last {
+            Some(last) => self.map.tree.count_below(last, true),
+            None => 0,
+        };
+        (0, Some((self.map.len() - visited) as usize))
+    }
+}
+
+impl<'a, K, V, H, F> FusedIterator for ExtractIf<'a, K, V, H, F>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+    F: FnMut(&K, &mut V) -> bool,
+{
+}
@@ -0,0 +1,595 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{Changes, Node, TreeMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::store::free_list::FreeListIndex;
+use crate::store::IndexMap;
+use crate::{env, IntoStorageKey};
+
+/// Hash of an empty subtree.
+const EMPTY_HASH: [u8; 32] = [0; 32];
+
+/// A [`TreeMap`] which keeps a hash of every subtree of its tree, so that it can commit to all of
+/// its entries with a single root hash and prove that an entry is part of the map.
+///
+/// The hash of a node is the hash of the concatenation of the Borsh serialized key, the hash of
+/// the Borsh serialized value, and the hashes of the left and right subtrees, where the hash of an
+/// empty subtree is 32 zero bytes. The [`commitment`](Self::commitment) is the hash of the root
+/// node, and a [`MerkleProof`] contains the nodes on the path from the node of a key to the root.
+///
+/// The hashes of a node are stored separately from the tree, under the prefix of the map followed
+/// by `h` and the index of the node, so a [`TreeMap`] does not pay for them. They are updated
+/// when the map is flushed, which only recomputes the hashes of the nodes on the paths to
+/// modified entries. All other methods of [`TreeMap`] are available through [`Deref`], and any
+/// changes made through them are included in the next commitment.
+///
+/// The hashes of a map wrapped with [`from_map`](Self::from_map) are all recomputed on the first
+/// flush after it is wrapped, which loads every node and value of the map.
+///
+/// # Examples
+/// ```
+/// use near_sdk::crypto_hash::Sha256;
+/// use near_sdk::store::MerkleTreeMap;
+///
+/// let mut balances = MerkleTreeMap::new(b"b");
+/// balances.insert("alice.near".to_string(), 100u128);
+/// balances.insert("bob.near".to_string(), 50);
+///
+/// let root = balances.commitment();
+/// let proof = balances.prove("bob.near").unwrap();
+/// assert!(proof.verify::<Sha256, _, _>(&root, "bob.near", &50u128));
+/// assert!(!proof.verify::<Sha256, _, _>(&root, "bob.near", &51u128));
+///
+/// // Changing an entry changes the commitment.
+/// *balances.get_mut("bob.near").unwrap() += 1;
+/// assert_ne!(balances.commitment(), root);
+/// ```
+pub struct MerkleTreeMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    map: TreeMap<K, V, H>,
+    /// Hashes of the nodes of the tree, at the indices of the nodes.
+    hashes: IndexMap<NodeHashes>,
+    /// Whether the stored hashes are ignored because they may be outdated, which is the case until
+    /// a map wrapped with [`from_map`](Self::from_map) is first flushed.
+    rebuild: bool,
+}
+
+/// Hashes stored for a node of a [`MerkleTreeMap`].
+#[derive(BorshSerialize, BorshDeserialize, PartialEq)]
+struct NodeHashes {
+    /// Hash of the value of the key of the node.
+    value: [u8; 32],
+    /// Hash of the subtree of the node.
+    subtree: [u8; 32],
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.map, writer)?;
+        BorshSerialize::serialize(&self.hashes, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        let mut map: TreeMap<K, V, H> = BorshDeserialize::deserialize(buf)?;
+        map.tree.changes = Some(Changes::default());
+        Ok(Self { map, hashes: BorshDeserialize::deserialize(buf)?, rebuild: false })
+    }
+}
+
+impl_borsh_schema! {
+    MerkleTreeMap<K, V, H> {
+        map: TreeMap<K, V, H>,
+        hashes: Box<[u8]>,
+    }
+    where
+        K: BorshSerialize + BorshDeserialize + Ord + Clone,
+        V: BorshSerialize + BorshDeserialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> Drop for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<K, V, H> fmt::Debug for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone + fmt::Debug,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MerkleTreeMap").field("map", &self.map).finish()
+    }
+}
+
+impl<K, V, H> Deref for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Target = TreeMap<K, V, H>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<K, V, H> DerefMut for MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.map
+    }
+}
+
+impl<K, V> MerkleTreeMap<K, V, Sha256>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+{
+    /// Create new map with zero elements. Use `prefix` as a unique identifier.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> MerkleTreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`MerkleTreeMap`] with a custom hash function, which is used both for the
+    /// storage keys of the values and for the hashes of the nodes.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let prefix = prefix.into_storage_key();
+        let hashes_key = [prefix.as_slice(), b"h"].concat();
+        let mut map = TreeMap::with_hasher(prefix);
+        map.tree.changes = Some(Changes::default());
+        Self { map, hashes: IndexMap::new(hashes_key), rebuild: true }
+    }
+
+    /// Wraps an existing [`TreeMap`], using the prefix of the map followed by `h` for the hashes.
+    /// Any hashes stored under that prefix may be outdated, for example if the map was modified
+    /// without being wrapped, so the hashes of all nodes are recomputed on the next flush.
+    pub fn from_map(mut map: TreeMap<K, V, H>) -> Self {
+        // The prefix of the nodes is the prefix of the map followed by `n`.
+        let mut hashes_key = map.tree.nodes.prefix().to_vec();
+        hashes_key.pop();
+        hashes_key.push(b'h');
+        map.tree.changes = Some(Changes::default());
+        Self { map, hashes: IndexMap::new(hashes_key), rebuild: true }
+    }
+
+    /// Returns the root hash committing to all entries of the map, or 32 zero bytes if the map
+    /// is empty.
+    ///
+    /// Only the hashes of nodes which were modified since the map was last flushed are computed,
+    /// the others are read from storage.
+    pub fn commitment(&self) -> [u8; 32] {
+        let dirty = self.dirty();
+        self.subtree_hash(self.map.tree.root, &dirty)
+    }
+
+    /// Returns a proof that the entry of the key is part of the map, which can be verified
+    /// against the [`commitment`](Self::commitment) with [`MerkleProof::verify`]. Returns
+    /// [`None`] if the key is not in the map.
+    pub fn prove<Q: ?Sized>(&self, k: &Q) -> Option<MerkleProof>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let tree = &self.map.tree;
+        // Path from the root to the node of the key, with the side each child was taken on.
+        let mut path = Vec::new();
+        let mut at = tree.root;
+        let found = loop {
+            let idx = at?;
+            let node = tree.node(idx);
+            match k.cmp(node.key.borrow()) {
+                Ordering::Equal => break idx,
+                Ordering::Less => {
+                    path.push((idx, true));
+                    at = node.lft;
+                }
+                Ordering::Greater => {
+                    path.push((idx, false));
+                    at = node.rgt;
+                }
+            }
+        };
+
+        let dirty = self.dirty();
+        let node = tree.node(found);
+        let children = [self.subtree_hash(node.lft, &dirty), self.subtree_hash(node.rgt, &dirty)];
+        let path = path
+            .into_iter()
+            .rev()
+            .map(|(idx, from_left)| {
+                let node = tree.node(idx);
+                let sibling = if from_left { node.rgt } else { node.lft };
+                ProofNode {
+                    key: serialize(&node.key),
+                    value_hash: self.value_hash(idx, node, &dirty),
+                    sibling: self.subtree_hash(sibling, &dirty),
+                    from_left,
+                }
+            })
+            .collect();
+        Some(MerkleProof { children, path })
+    }
+
+    /// Updates the hashes of the modified nodes and writes all modified nodes, values and hashes
+    /// to storage. This is called when the structure is [`Drop`]ed.
+    pub fn flush(&mut self) {
+        let dirty = self.dirty();
+        let root = self.map.tree.root;
+        self.refresh(root, &dirty);
+
+        // Remove the hashes of the cells left empty by removed nodes.
+        let changes = self.map.tree.changes.replace(Changes::default()).unwrap_or_default();
+        let emptied = |at: &FreeListIndex| self.map.tree.nodes.get(*at).is_none();
+        let emptied: Vec<_> = if self.rebuild {
+            (0..self.map.tree.nodes.capacity()).map(FreeListIndex).filter(emptied).collect()
+        } else {
+            changes.replaced.iter().copied().filter(emptied).collect()
+        };
+        for at in emptied {
+            self.hashes.set(at.0, None);
+        }
+        self.rebuild = false;
+
+        self.map.flush();
+        self.hashes.flush();
+    }
+
+    /// Returns the nodes whose stored hashes may be outdated, which are the nodes on the paths to
+    /// the values and nodes modified since the last flush.
+    fn dirty(&self) -> Dirty {
+        let tree = &self.map.tree;
+        let mut dirty = Dirty { rebuild: self.rebuild, ..Default::default() };
+        let changes =
+            tree.changes.as_ref().unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        // Nodes which now hold another key have outdated value hashes as well.
+        for &at in &changes.replaced {
+            if tree.nodes.get(at).is_some() {
+                dirty.values.insert(at);
+            }
+        }
+        let modified_nodes = changes.modified.iter().filter_map(|&at| tree.nodes.get(at));
+        let modified_keys = self.map.values.modified_keys();
+        for (key, value_modified) in modified_nodes
+            .map(|node| (&node.key, false))
+            .chain(modified_keys.iter().map(|key| (key, true)))
+        {
+            let mut at = tree.root;
+            while let Some(idx) = at {
+                dirty.nodes.insert(idx);
+                let node = tree.node(idx);
+                at = match key.cmp(&node.key) {
+                    Ordering::Equal => {
+                        if value_modified {
+                            dirty.values.insert(idx);
+                        }
+                        break;
+                    }
+                    Ordering::Less => node.lft,
+                    Ordering::Greater => node.rgt,
+                };
+            }
+        }
+        dirty
+    }
+
+    /// Returns the stored hashes of the node at `at`, unless they may be outdated.
+    fn stored(&self, at: FreeListIndex, dirty: &Dirty) -> Option<&NodeHashes> {
+        if dirty.rebuild {
+            None
+        } else {
+            self.hashes.get(at.0)
+        }
+    }
+
+    /// Returns the hash of the subtree at `at`, computing the hashes which are outdated.
+    fn subtree_hash(&self, at: Option<FreeListIndex>, dirty: &Dirty) -> [u8; 32] {
+        let at = match at {
+            Some(at) => at,
+            None => return EMPTY_HASH,
+        };
+        if !dirty.nodes.contains(&at) {
+            if let Some(hashes) = self.stored(at, dirty) {
+                return hashes.subtree;
+            }
+        }
+        let node = self.map.tree.node(at);
+        node_hash::<H>(
+            &serialize(&node.key),
+            &self.value_hash(at, node, dirty),
+            &self.subtree_hash(node.lft, dirty),
+            &self.subtree_hash(node.rgt, dirty),
+        )
+    }
+
+    /// Computes the outdated hashes of the subtree at `at` and stores them.
+    fn refresh(&mut self, at: Option<FreeListIndex>, dirty: &Dirty) -> [u8; 32] {
+        let at = match at {
+            Some(at) => at,
+            None => return EMPTY_HASH,
+        };
+        if !dirty.nodes.contains(&at) {
+            if let Some(hashes) = self.stored(at, dirty) {
+                return hashes.subtree;
+            }
+        }
+        let node = self.map.tree.node(at);
+        let (lft, rgt) = (node.lft, node.rgt);
+        let key = serialize(&node.key);
+        let value = self.value_hash(at, node, dirty);
+        let lft_hash = self.refresh(lft, dirty);
+        let rgt_hash = self.refresh(rgt, dirty);
+        let subtree = node_hash::<H>(&key, &value, &lft_hash, &rgt_hash);
+
+        // Avoid writing the hashes if they did not change.
+        let hashes = NodeHashes { value, subtree };
+        if self.hashes.get(at.0) != Some(&hashes) {
+            self.hashes.set(at.0, Some(hashes));
+        }
+        subtree
+    }
+
+    /// Returns the hash of the value of the node at `at`, reading the value if the hash is not
+    /// stored or the value was modified.
+    fn value_hash(&self, at: FreeListIndex, node: &Node<K>, dirty: &Dirty) -> [u8; 32] {
+        match self.stored(at, dirty) {
+            Some(hashes) if !dirty.values.contains(&at) => hashes.value,
+            _ => H::hash(&serialize(self.map.entry_value(&node.key))),
+        }
+    }
+}
+
+/// Nodes whose stored hashes may be outdated because of changes since the last flush.
+#[derive(Default)]
+struct Dirty {
+    /// Nodes on the paths to the modified values and nodes.
+    nodes: HashSet<FreeListIndex>,
+    /// Nodes whose values were modified or which now hold another key.
+    values: HashSet<FreeListIndex>,
+    /// Whether all stored hashes may be outdated.
+    rebuild: bool,
+}
+
+fn serialize<T: BorshSerialize + ?Sized>(value: &T) -> Vec<u8> {
+    value.try_to_vec().unwrap_or_else(|_| env::panic_str(ERR_INCONSISTENT_STATE))
+}
+
+fn node_hash<H>(key: &[u8], value_hash: &[u8; 32], lft: &[u8; 32], rgt: &[u8; 32]) -> [u8; 32]
+where
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    H::hash(&[key, value_hash, lft, rgt].concat())
+}
+
+/// Proof that an entry is part of a [`MerkleTreeMap`] with a given commitment.
+///
+/// This `struct` is created by the `prove` method on [`MerkleTreeMap`].
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct MerkleProof {
+    /// Hashes of the left and right subtrees of the node of the entry.
+    pub children: [[u8; 32]; 2],
+    /// Nodes on the path from the parent of the node of the entry to the root.
+    pub path: Vec<ProofNode>,
+}
+
+/// Node on the path of a [`MerkleProof`].
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ProofNode {
+    /// Borsh serialized key of the node.
+    pub key: Vec<u8>,
+    /// Hash of the Borsh serialized value of the node.
+    pub value_hash: [u8; 32],
+    /// Hash of the subtree of the other child of the node.
+    pub sibling: [u8; 32],
+    /// Whether the proven subtree is the left child of the node.
+    pub from_left: bool,
+}
+
+impl MerkleProof {
+    /// Returns `true` if the proof shows that the map with the commitment `root` contains the
+    /// entry of `key` and `value`. `H` is the hash function of the map.
+    pub fn verify<H, Q, V>(&self, root: &[u8; 32], key: &Q, value: &V) -> bool
+    where
+        H: CryptoHasher<Digest = [u8; 32]>,
+        Q: BorshSerialize + ?Sized,
+        V: BorshSerialize + ?Sized,
+    {
+        let [lft, rgt] = &self.children;
+        let value_hash = H::hash(&serialize(value));
+        let mut hash = node_hash::<H>(&serialize(key), &value_hash, lft, rgt);
+        for node in &self.path {
+            hash = if node.from_left {
+                node_hash::<H>(&node.key, &node.value_hash, &hash, &node.sibling)
+            } else {
+                node_hash::<H>(&node.key, &node.value_hash, &node.sibling, &hash)
+            };
+        }
+        &hash == root
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::{MerkleTreeMap, EMPTY_HASH};
+    use crate::crypto_hash::Sha256;
+    use crate::store::TreeMap;
+    use crate::test_utils::test_env::setup_free;
+    use arbitrary::{Arbitrary, Unstructured};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::RngCore;
+    use rand::SeedableRng;
+
+    impl MerkleTreeMap<u8, u8> {
+        /// Computes the commitment from the keys and values, ignoring the stored hashes.
+        fn expected_commitment(&self) -> [u8; 32] {
+            fn hash(map: &MerkleTreeMap<u8, u8>, at: Option<super::FreeListIndex>) -> [u8; 32] {
+                let at = match at {
+                    Some(at) => at,
+                    None => return EMPTY_HASH,
+                };
+                let node = map.map.tree.node(at);
+                let value = map.map.get(&node.key).unwrap();
+                let value_hash = crate::env::sha256_array(&[*value]);
+                super::node_hash::<Sha256>(
+                    &[node.key],
+                    &value_hash,
+                    &hash(map, node.lft),
+                    &hash(map, node.rgt),
+                )
+            }
+            hash(self, self.map.tree.root)
+        }
+    }
+
+    #[derive(Arbitrary, Debug)]
+    enum Op {
+        Insert(u8, u8),
+        Remove(u8),
+        Modify(u8, u8),
+        Defrag(u8),
+        Flush,
+        Restore,
+    }
+
+    #[test]
+    fn arbitrary() {
+        setup_free();
+
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut buf = vec![0; 4096];
+        for _ in 0..256 {
+            // Clear storage in-between runs
+            crate::mock::with_mocked_blockchain(|b| b.take_storage());
+            rng.fill_bytes(&mut buf);
+
+            let mut map = MerkleTreeMap::new(b"m");
+            let u = Unstructured::new(&buf);
+            if let Ok(ops) = Vec::<Op>::arbitrary_take_rest(u) {
+                for op in ops {
+                    match op {
+                        Op::Insert(k, v) => {
+                            map.insert(k, v);
+                        }
+                        Op::Remove(k) => {
+                            map.remove(&k);
+                        }
+                        Op::Modify(k, v) => {
+                            if let Some(value) = map.get_mut(&k) {
+                                *value = v;
+                            }
+                        }
+                        Op::Defrag(n) => {
+                            map.defrag(n as u32);
+                        }
+                        Op::Flush => {
+                            map.flush();
+                        }
+                        Op::Restore => {
+                            let serialized = map.try_to_vec().unwrap();
+                            map = MerkleTreeMap::deserialize(&mut serialized.as_slice()).unwrap();
+                        }
+                    }
+                    let root = map.commitment();
+                    assert_eq!(root, map.expected_commitment());
+                    for (k, v) in map.iter() {
+                        let proof = map.prove(k).unwrap();
+                        assert!(proof.verify::<Sha256, _, _>(&root, k, v));
+                        assert!(!proof.verify::<Sha256, _, _>(&root, k, &v.wrapping_add(1)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn from_map_recomputes_hashes() {
+        let mut map = MerkleTreeMap::<u8, u8>::new(b"m");
+        for k in 0..20 {
+            map.insert(k, k);
+        }
+        map.flush();
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+
+        // Modify the map without updating the stored hashes.
+        let mut plain = TreeMap::<u8, u8>::deserialize(&mut serialized.as_slice()).unwrap();
+        *plain.get_mut(&3).unwrap() = 30;
+        plain.remove(&7);
+        plain.insert(25, 25);
+        plain.defrag(u32::MAX);
+        plain.flush();
+
+        let mut map = MerkleTreeMap::from_map(plain);
+        assert_eq!(map.commitment(), map.expected_commitment());
+        map.flush();
+        let serialized = map.try_to_vec().unwrap();
+        let map = MerkleTreeMap::<u8, u8>::deserialize(&mut serialized.as_slice()).unwrap();
+        assert_eq!(map.commitment(), map.expected_commitment());
+        let proof = map.prove(&3).unwrap();
+        assert!(proof.verify::<Sha256, _, _>(&map.commitment(), &3u8, &30u8));
+    }
+
+    #[test]
+    fn empty_and_missing() {
+        let mut map = MerkleTreeMap::<u8, u8>::new(b"m");
+        assert_eq!(map.commitment(), EMPTY_HASH);
+        assert_eq!(map.prove(&1), None);
+        map.insert(1, 2);
+        assert_ne!(map.commitment(), EMPTY_HASH);
+        assert_eq!(map.prove(&2), None);
+    }
+}
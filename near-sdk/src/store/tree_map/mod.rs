@@ -0,0 +1,2686 @@
+mod cursor;
+mod impls;
+mod iter;
+mod merkle;
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::{Bound, RangeBounds, Sub};
+use std::{fmt, mem};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Serialize, Serializer};
+
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, Gas, IntoStorageKey};
+
+pub use self::cursor::Cursor;
+pub use self::iter::{Drain, ExtractIf, Iter, IterMut, Keys, Range, RangeMut};
+pub use self::merkle::{MerkleProof, MerkleTreeMap, ProofNode};
+use super::free_list::FreeListIndex;
+use super::{CloneTo, FreeList, LookupMap, ERR_INCONSISTENT_STATE};
+
+const ERR_NOT_EXIST: &str = "Key does not exist in map";
+
+/// A lazily loaded storage map that keeps its keys ordered. The keys are stored in an AVL tree,
+/// which allows iterating over the entries in order of the keys, or over only the entries within
+/// a range of keys, without loading the rest of the map.
+///
+/// The values are stored in a [`LookupMap`](crate::store::LookupMap), so looking up a value by
+/// its key does not need to traverse the tree.
+///
+/// Nodes and values are cached in memory once they are loaded, so repeated operations on the
+/// map within a function call only read and deserialize each node from storage once. Changes are
+/// written to storage when the map is [flushed](TreeMap::flush) or dropped.
+///
+/// Runtime complexity (worst case):
+/// - `get`/`contains_key`:     O(1) - LookupMap lookup
+/// - `insert`/`remove`:        O(log(N))
+/// - `range` of K elements:    O(K + log(N))
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::TreeMap;
+///
+/// // Initializes a map, the generic types can be inferred to `TreeMap<u64, String, Sha256>`
+/// // The `b"t"` parameter is a prefix for the storage keys of this data structure.
+/// let mut asks = TreeMap::new(b"t");
+///
+/// asks.insert(105u64, "carol.near".to_string());
+/// asks.insert(101, "alice.near".to_string());
+/// asks.insert(103, "bob.near".to_string());
+/// assert_eq!(asks.get(&103), Some(&"bob.near".to_string()));
+///
+/// // Entries are iterated in order of the keys.
+/// assert_eq!(asks.iter().map(|(price, _)| *price).collect::<Vec<_>>(), [101, 103, 105]);
+///
+/// // Only the entries within the range are loaded.
+/// for (price, account) in asks.range(102..=105) {
+///     println!("{} asks {}", account, price);
+/// }
+/// ```
+pub struct TreeMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    values: LookupMap<K, V, H>,
+    tree: Tree<K>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.values, writer)?;
+        BorshSerialize::serialize(&self.tree, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            values: BorshDeserialize::deserialize(buf)?,
+            tree: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    TreeMap<K, V, H> {
+        values: LookupMap<K, V, H>,
+        root: Option<FreeListIndex>,
+        nodes: FreeList<Node<K>>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+/// AVL tree of the keys of a [`TreeMap`]. Nodes are stored in a [`FreeList`] and link to their
+/// children by index.
+struct Tree<K>
+where
+    K: BorshSerialize,
+{
+    root: Option<FreeListIndex>,
+    nodes: FreeList<Node<K>>,
+    /// Changes to the nodes since they were last taken, only tracked for a [`MerkleTreeMap`].
+    changes: Option<Changes>,
+}
+
+/// Cells of the nodes of a [`Tree`] which changed since the changes were last taken.
+#[derive(Default)]
+struct Changes {
+    /// Cells whose node was modified, inserted, moved or removed.
+    modified: HashSet<FreeListIndex>,
+    /// Cells whose node was inserted, moved or removed, which now hold another key.
+    replaced: HashSet<FreeListIndex>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K> BorshSerialize for Tree<K>
+where
+    K: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.root, writer)?;
+        BorshSerialize::serialize(&self.nodes, writer)?;
+        Ok(())
+    }
+}
+
+impl<K> BorshDeserialize for Tree<K>
+where
+    K: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            root: BorshDeserialize::deserialize(buf)?,
+            nodes: BorshDeserialize::deserialize(buf)?,
+            changes: None,
+        })
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+struct Node<K> {
+    key: K,
+    /// Subtree with the keys smaller than `key`.
+    lft: Option<FreeListIndex>,
+    /// Subtree with the keys larger than `key`.
+    rgt: Option<FreeListIndex>,
+    /// Height of the subtree of this node.
+    ht: u32,
+    /// Number of nodes in the subtree of this node.
+    size: u32,
+}
+
+impl<K> Node<K> {
+    fn of(key: K) -> Self {
+        Self { key, lft: None, rgt: None, ht: 1, size: 1 }
+    }
+}
+
+impl<K, V, H> Drop for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<K, V, H> fmt::Debug for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeMap")
+            .field("root", &self.tree.root)
+            .field("nodes", &self.tree.nodes)
+            .field("values", &self.values)
+            .finish()
+    }
+}
+
+/// Serializes the entries of the map as a map in ascending order of the keys, which loads the
+/// whole map from storage. Use [`TreeMap::to_json_page`] to only serialize a range of entries.
+impl<K, V, H> Serialize for TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone + Serialize,
+    V: BorshSerialize + BorshDeserialize + Serialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Entries of a [`TreeMap`] page, serialized as a map.
+struct Entries<'a, K, V>(Vec<(&'a K, &'a V)>);
+
+impl<'a, K, V> Serialize for Entries<'a, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().copied())
+    }
+}
+
+impl<K, V> TreeMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    #[inline]
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+
+    /// Creates a new map under `prefix` with the entries of `iter`. If `iter` yields a key more
+    /// than once, the last value for the key is kept.
+    ///
+    /// The entries are sorted in memory and the tree is built balanced in a single pass, instead
+    /// of inserting and rebalancing one entry at a time. The map is flushed before it is
+    /// returned, so all entries are written to storage once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let map = TreeMap::from_iter_with_prefix(b"m", [(3u32, 30u64), (1, 10), (2, 20)]);
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), [&1, &2, &3]);
+    /// ```
+    pub fn from_iter_with_prefix<S, I>(prefix: S, iter: I) -> Self
+    where
+        K: BorshDeserialize + Clone,
+        V: BorshDeserialize,
+        S: IntoStorageKey,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = Self::new(prefix);
+        let entries: std::collections::BTreeMap<K, V> = iter.into_iter().collect();
+        let mut sorted = Vec::with_capacity(entries.len());
+        for (key, value) in entries {
+            map.values.set(key.clone(), Some(value));
+            sorted.push(map.tree.insert_node(Node::of(key)));
+        }
+        map.tree.root = map.tree.build(&sorted);
+        map.flush();
+        map
+    }
+}
+
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`TreeMap`] with a custom hash function.
+    ///
+    /// # Example
+    /// ```
+    /// use near_sdk::crypto_hash::Keccak256;
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let map = TreeMap::<String, String, Keccak256>::with_hasher(b"m");
+    /// ```
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut nodes_key = prefix.into_storage_key();
+        let values_key = [nodes_key.as_slice(), b"v"].concat();
+        nodes_key.push(b'n');
+        Self {
+            values: LookupMap::with_hasher(values_key),
+            tree: Tree { root: None, nodes: FreeList::new(nodes_key), changes: None },
+        }
+    }
+
+    /// Return the amount of elements inside of the map.
+    pub fn len(&self) -> u32 {
+        self.tree.nodes.len()
+    }
+
+    /// Returns true if there are no elements inside of the map.
+    pub fn is_empty(&self) -> bool {
+        self.tree.nodes.is_empty()
+    }
+
+    /// Returns the storage key of the value for the given key, as with
+    /// [`LookupMap::raw_key_for`] for a map with the prefix of the tree map followed by `v`.
+    ///
+    /// The key itself is also stored in a node of the tree, under the prefix of the tree map
+    /// followed by `n`. The position of the node depends on the order in which keys were inserted
+    /// and removed, so it cannot be computed from the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::env;
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map: TreeMap<u8, u8> = TreeMap::new(b"t");
+    /// map.insert(1, 10);
+    /// map.flush();
+    ///
+    /// assert_eq!(map.raw_key_for(&1), env::sha256(b"tv\x01"));
+    /// assert_eq!(env::storage_read(&map.raw_key_for(&1)), Some(vec![10]));
+    /// ```
+    pub fn raw_key_for<Q: ?Sized>(&self, k: &Q) -> Vec<u8>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize,
+    {
+        self.values.raw_key_for(k)
+    }
+
+    /// Clears the map, removing all key-value pairs. Keeps the allocated memory
+    /// for reuse.
+    pub fn clear(&mut self)
+    where
+        K: BorshDeserialize,
+    {
+        self.tree.root = None;
+        self.tree.replace_all();
+        for node in self.tree.nodes.drain() {
+            // Set instead of remove to avoid loading the value from storage.
+            self.values.set(node.key, None);
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in ascending order of the keys.
+    /// The iterator element type is `(&'a K, &'a V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert("b".to_string(), 2);
+    /// map.insert("a".to_string(), 1);
+    /// map.insert("c".to_string(), 3);
+    ///
+    /// for (key, val) in map.iter() {
+    ///     println!("key: {} val: {}", key, val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<K, V, H>
+    where
+        K: BorshDeserialize,
+    {
+        Iter::new(self)
+    }
+
+    /// An iterator visiting all key-value pairs in ascending order of the keys,
+    /// with exclusive references to the values.
+    /// The iterator element type is `(&'a K, &'a mut V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert("a".to_string(), 1);
+    /// map.insert("b".to_string(), 2);
+    /// map.insert("c".to_string(), 3);
+    ///
+    /// // Update all values
+    /// for (_, val) in map.iter_mut() {
+    ///     *val *= 2;
+    /// }
+    ///
+    /// for (key, val) in &map {
+    ///     println!("key: {} val: {}", key, val);
+    /// }
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<K, V, H>
+    where
+        K: BorshDeserialize,
+    {
+        IterMut::new(self)
+    }
+
+    /// Creates a [`Cursor`] over the entries of the map, positioned before the first entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for k in 0u32..10 {
+    ///     map.insert(k * 10, k);
+    /// }
+    ///
+    /// let mut cursor = map.cursor();
+    /// assert_eq!(cursor.seek(&35), Some((&40, &4)));
+    /// assert_eq!(cursor.next(), Some((&50, &5)));
+    /// assert_eq!(cursor.prev(), Some((&40, &4)));
+    /// assert_eq!(cursor.peek(), Some((&40, &4)));
+    ///
+    /// // Paginate by persisting the key of the last entry of a page.
+    /// let last = *cursor.key().unwrap();
+    /// let mut cursor = map.cursor();
+    /// cursor.seek(&last);
+    /// let page: Vec<_> = cursor.take(3).map(|(k, _)| *k).collect();
+    /// assert_eq!(page, [50, 60, 70]);
+    /// ```
+    pub fn cursor(&self) -> Cursor<K, V, H>
+    where
+        K: BorshDeserialize + Clone,
+        V: BorshDeserialize,
+    {
+        Cursor::new(self)
+    }
+
+    /// An iterator visiting all keys in ascending order.
+    /// The iterator element type is `&'a K`.
+    ///
+    /// Only the nodes of the tree are loaded, the values are not read from storage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert("c".to_string(), 3);
+    /// map.insert("a".to_string(), 1);
+    /// map.insert("b".to_string(), 2);
+    ///
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+    /// ```
+    pub fn keys(&self) -> Keys<K>
+    where
+        K: BorshDeserialize,
+    {
+        Keys::new::<K, _, _>(self, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    /// An iterator visiting the keys within `range` in ascending order.
+    /// The iterator element type is `&'a K`.
+    ///
+    /// Only the nodes of the tree on the path to the start of the range and within the range are
+    /// loaded, the values are not read from storage. This makes listing keys, for example to
+    /// paginate over identifiers, cheaper than [`range`](Self::range).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the start and end are
+    /// equal and both excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for id in 0u64..10 {
+    ///     map.insert(id, vec![0u8; 1000]);
+    /// }
+    ///
+    /// // Page of at most 3 ids after id 4, without reading the values.
+    /// let page: Vec<_> = map.keys_range(5..).take(3).collect();
+    /// assert_eq!(page, [&5, &6, &7]);
+    /// assert_eq!(map.keys_range(..3).rev().collect::<Vec<_>>(), [&2, &1, &0]);
+    /// ```
+    pub fn keys_range<Q: ?Sized, R>(&self, range: R) -> Keys<K>
+    where
+        K: BorshDeserialize + Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        Self::check_range(&range);
+        Keys::new(self, range.start_bound(), range.end_bound())
+    }
+
+    /// Constructs an iterator over the key-value pairs within `range`, in ascending order of the
+    /// keys. Only the nodes of the tree on the path to the start of the range and the entries
+    /// within the range are loaded from storage.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the start and end are
+    /// equal and both excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    /// use std::ops::Bound::{Excluded, Included};
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(3, "a".to_string());
+    /// map.insert(5, "b".to_string());
+    /// map.insert(8, "c".to_string());
+    ///
+    /// for (key, value) in map.range((Included(4), Included(8))) {
+    ///     println!("{}: {}", key, value);
+    /// }
+    /// assert_eq!(Some((&5, &"b".to_string())), map.range(4..).next());
+    /// assert_eq!(map.range((Excluded(3), Excluded(8))).count(), 1);
+    ///
+    /// // The range can be iterated from both ends.
+    /// assert_eq!(Some((&5, &"b".to_string())), map.range(..8).next_back());
+    /// let keys: Vec<_> = map.range(..=8).rev().map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, [8, 5, 3]);
+    ///
+    /// // Bounds can be any borrowed form of the key, without allocating owned keys.
+    /// let mut names = TreeMap::new(b"n");
+    /// names.insert("alice.near".to_string(), 1);
+    /// names.insert("bob.near".to_string(), 2);
+    /// names.insert("carol.near".to_string(), 3);
+    /// assert_eq!(names.range::<str, _>((Included("b"), Excluded("c"))).count(), 1);
+    /// assert_eq!(names.range_count::<str, _>((Excluded("alice.near"), Included("carol.near"))), 2);
+    /// ```
+    pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<K, V, H>
+    where
+        K: BorshDeserialize + Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        Self::check_range(&range);
+        Range::new(self, range.start_bound(), range.end_bound())
+    }
+
+    /// Constructs a mutable iterator over the key-value pairs within `range`, in ascending order
+    /// of the keys. The iterator element type is `(&'a K, &'a mut V)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the start and end are
+    /// equal and both excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for (k, v) in [("a", 0), ("b", 0), ("c", 0), ("d", 0)] {
+    ///     map.insert(k.to_string(), v);
+    /// }
+    ///
+    /// for (_, balance) in map.range_mut("b".to_string().."d".to_string()) {
+    ///     *balance += 100;
+    /// }
+    /// assert_eq!(map.iter().map(|(_, v)| *v).collect::<Vec<_>>(), [0, 100, 100, 0]);
+    /// ```
+    pub fn range_mut<Q: ?Sized, R>(&mut self, range: R) -> RangeMut<K, V, H>
+    where
+        K: BorshDeserialize + Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        Self::check_range(&range);
+        RangeMut::new(self, range.start_bound(), range.end_bound())
+    }
+
+    /// Returns the number of keys within `range`. Only the nodes of the tree on the paths to the
+    /// start and end of the range are loaded, and no values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the start and end are
+    /// equal and both excluded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for k in 0u32..100 {
+    ///     map.insert(k * 10, ());
+    /// }
+    /// assert_eq!(map.range_count(100..200), 10);
+    /// assert_eq!(map.range_count(100..=200), 11);
+    /// assert_eq!(map.range_count::<u32, _>(..), 100);
+    /// ```
+    pub fn range_count<Q: ?Sized, R>(&self, range: R) -> u32
+    where
+        K: BorshDeserialize + Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        Self::check_range(&range);
+        let lo = match range.start_bound() {
+            Bound::Included(k) => self.tree.count_below(k, false),
+            Bound::Excluded(k) => self.tree.count_below(k, true),
+            Bound::Unbounded => 0,
+        };
+        let hi = match range.end_bound() {
+            Bound::Included(k) => self.tree.count_below(k, true),
+            Bound::Excluded(k) => self.tree.count_below(k, false),
+            Bound::Unbounded => self.len(),
+        };
+        hi - lo
+    }
+
+    /// Clears the map, returning all key-value pairs as an iterator in ascending order of the
+    /// keys. The nodes of the tree are removed as they are yielded.
+    ///
+    /// This will clear all values, even if only some key/value pairs are yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut a = TreeMap::new(b"m");
+    /// a.insert(2, "b".to_string());
+    /// a.insert(1, "a".to_string());
+    ///
+    /// assert_eq!(a.drain().next(), Some((1, "a".to_string())));
+    /// assert!(a.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<K, V, H>
+    where
+        K: BorshDeserialize,
+    {
+        Drain::new(self)
+    }
+
+    fn check_range<Q: ?Sized, R>(range: &R)
+    where
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        let invalid = match (range.start_bound(), range.end_bound()) {
+            (Bound::Excluded(s), Bound::Excluded(e)) => s >= e,
+            (Bound::Included(s), Bound::Included(e))
+            | (Bound::Included(s), Bound::Excluded(e))
+            | (Bound::Excluded(s), Bound::Included(e)) => s > e,
+            _ => false,
+        };
+        if invalid {
+            env::panic_str("Invalid range.")
+        }
+    }
+}
+
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.values.get(k)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.values.get_mut(k)
+    }
+
+    /// Returns the key-value pair corresponding to the key, with the key as it is stored in the
+    /// map. Only the nodes of the tree on the path to the key are loaded.
+    ///
+    /// This is useful when keys which compare equal can differ, for example when the key type
+    /// carries data which is not part of its [`Ord`] implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert("alice.near".to_string(), 1u32);
+    /// assert_eq!(map.get_key_value("alice.near"), Some((&"alice.near".to_string(), &1)));
+    /// assert_eq!(map.get_key_value("bob.near"), None);
+    /// ```
+    pub fn get_key_value<Q: ?Sized>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + BorshDeserialize + Clone,
+        Q: Ord,
+    {
+        self.tree
+            .ceil(k, true)
+            .filter(|key| (*key).borrow() == k)
+            .map(|key| (key, self.entry_value(key)))
+    }
+
+    /// Returns the smallest key in the map, or [`None`] if the map is empty. Only the nodes of
+    /// the tree on the path to the key are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// assert_eq!(map.min_key(), None);
+    /// map.insert(2, "b".to_string());
+    /// map.insert(1, "a".to_string());
+    /// assert_eq!(map.min_key(), Some(&1));
+    /// ```
+    pub fn min_key(&self) -> Option<&K>
+    where
+        K: BorshDeserialize,
+    {
+        self.tree.min()
+    }
+
+    /// Returns the largest key in the map, or [`None`] if the map is empty. Only the nodes of
+    /// the tree on the path to the key are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// assert_eq!(map.max_key(), None);
+    /// map.insert(2, "b".to_string());
+    /// map.insert(1, "a".to_string());
+    /// assert_eq!(map.max_key(), Some(&2));
+    /// ```
+    pub fn max_key(&self) -> Option<&K>
+    where
+        K: BorshDeserialize,
+    {
+        self.tree.max()
+    }
+
+    /// Returns the entry with the smallest key in the map, or [`None`] if the map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// // Asks keyed by price, the best ask is the smallest price.
+    /// let mut asks = TreeMap::new(b"m");
+    /// asks.insert(105u64, "carol.near".to_string());
+    /// asks.insert(101, "alice.near".to_string());
+    /// assert_eq!(asks.first_key_value(), Some((&101, &"alice.near".to_string())));
+    /// ```
+    pub fn first_key_value(&self) -> Option<(&K, &V)>
+    where
+        K: BorshDeserialize + Clone,
+    {
+        self.tree.min().map(|key| (key, self.entry_value(key)))
+    }
+
+    /// Returns the entry with the largest key in the map, or [`None`] if the map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// // Bids keyed by price, the best bid is the largest price.
+    /// let mut bids = TreeMap::new(b"m");
+    /// bids.insert(99u64, "carol.near".to_string());
+    /// bids.insert(97, "alice.near".to_string());
+    /// assert_eq!(bids.last_key_value(), Some((&99, &"carol.near".to_string())));
+    /// ```
+    pub fn last_key_value(&self) -> Option<(&K, &V)>
+    where
+        K: BorshDeserialize + Clone,
+    {
+        self.tree.max().map(|key| (key, self.entry_value(key)))
+    }
+
+    /// Returns the largest key in the map which is less than or equal to `k`, or [`None`] if
+    /// there is no such key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering on the borrowed
+    /// form *must* match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(10, "a".to_string());
+    /// map.insert(20, "b".to_string());
+    /// assert_eq!(map.floor_key(&5), None);
+    /// assert_eq!(map.floor_key(&10), Some(&10));
+    /// assert_eq!(map.floor_key(&15), Some(&10));
+    /// ```
+    pub fn floor_key<Q: ?Sized>(&self, k: &Q) -> Option<&K>
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: Ord,
+    {
+        self.tree.floor(k, true)
+    }
+
+    /// Returns the smallest key in the map which is greater than or equal to `k`, or [`None`] if
+    /// there is no such key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering on the borrowed
+    /// form *must* match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(10, "a".to_string());
+    /// map.insert(20, "b".to_string());
+    /// assert_eq!(map.ceil_key(&5), Some(&10));
+    /// assert_eq!(map.ceil_key(&20), Some(&20));
+    /// assert_eq!(map.ceil_key(&25), None);
+    /// ```
+    pub fn ceil_key<Q: ?Sized>(&self, k: &Q) -> Option<&K>
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: Ord,
+    {
+        self.tree.ceil(k, true)
+    }
+
+    /// Returns the key in the map which is closest to `k`, or [`None`] if the map is empty. If
+    /// two keys are equally close, the smaller one is returned. Only the nodes of the tree on the
+    /// paths to the closest smaller and larger keys are loaded.
+    ///
+    /// The distance between keys is their difference, so this is meant for keys such as
+    /// timestamps or prices.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut prices = TreeMap::new(b"m");
+    /// for (timestamp, price) in [(100u64, 5u32), (200, 7), (400, 6)] {
+    ///     prices.insert(timestamp, price);
+    /// }
+    /// assert_eq!(prices.nearest_key(&160), Some(&200));
+    /// assert_eq!(prices.nearest_key(&300), Some(&200));
+    /// assert_eq!(prices.nearest_key(&1000), Some(&400));
+    /// ```
+    pub fn nearest_key(&self, k: &K) -> Option<&K>
+    where
+        K: BorshDeserialize + Clone + Sub<Output = K>,
+    {
+        match (self.tree.floor(k, true), self.tree.ceil(k, true)) {
+            (Some(floor), Some(ceil)) => {
+                if k.clone() - floor.clone() <= ceil.clone() - k.clone() {
+                    Some(floor)
+                } else {
+                    Some(ceil)
+                }
+            }
+            (floor, ceil) => floor.or(ceil),
+        }
+    }
+
+    /// Returns the number of keys in the map which are smaller than `k`, which is the position of
+    /// `k` in ascending order of the keys if it is in the map. Only the nodes of the tree on the
+    /// path to the key are loaded.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering on the borrowed
+    /// form *must* match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// // Leaderboard keyed by (negated score, account), so the best score comes first.
+    /// let mut board = TreeMap::new(b"m");
+    /// board.insert((-30i64, "alice.near".to_string()), ());
+    /// board.insert((-50, "bob.near".to_string()), ());
+    /// board.insert((-40, "carol.near".to_string()), ());
+    /// assert_eq!(board.rank(&(-30, "alice.near".to_string())), 2);
+    /// assert_eq!(board.rank(&(-45, String::new())), 1);
+    /// ```
+    pub fn rank<Q: ?Sized>(&self, k: &Q) -> u32
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: Ord,
+    {
+        self.tree.count_below(k, false)
+    }
+
+    /// Returns the key at position `n` in ascending order of the keys, starting at zero, or
+    /// [`None`] if `n` is not less than the length of the map. Only the nodes of the tree on the
+    /// path to the key are loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for k in [30u32, 10, 20] {
+    ///     map.insert(k, ());
+    /// }
+    /// assert_eq!(map.nth_key(0), Some(&10));
+    /// assert_eq!(map.nth_key(2), Some(&30));
+    /// assert_eq!(map.nth_key(3), None);
+    /// ```
+    pub fn nth_key(&self, n: u32) -> Option<&K>
+    where
+        K: BorshDeserialize,
+    {
+        self.tree.nth(n)
+    }
+
+    /// Returns the entry with the largest key in the map which is less than or equal to `k`, or
+    /// [`None`] if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// // Fee in basis points, keyed by the minimum amount it applies to.
+    /// let mut fees = TreeMap::new(b"m");
+    /// fees.insert(0u128, 30u16);
+    /// fees.insert(1_000, 20);
+    /// fees.insert(10_000, 10);
+    /// assert_eq!(fees.floor_entry(&5_000), Some((&1_000, &20)));
+    /// assert_eq!(fees.floor_entry(&10_000), Some((&10_000, &10)));
+    /// ```
+    pub fn floor_entry<Q: ?Sized>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + BorshDeserialize + Clone,
+        Q: Ord,
+    {
+        self.tree.floor(k, true).map(|key| (key, self.entry_value(key)))
+    }
+
+    /// Returns the entry with the smallest key in the map which is greater than or equal to `k`,
+    /// or [`None`] if there is no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(10, "a".to_string());
+    /// map.insert(20, "b".to_string());
+    /// assert_eq!(map.ceil_entry(&11), Some((&20, &"b".to_string())));
+    /// assert_eq!(map.ceil_entry(&21), None);
+    /// ```
+    pub fn ceil_entry<Q: ?Sized>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + BorshDeserialize + Clone,
+        Q: Ord,
+    {
+        self.tree.ceil(k, true).map(|key| (key, self.entry_value(key)))
+    }
+
+    /// Returns the value of a key which is in the tree.
+    fn entry_value(&self, key: &K) -> &V
+    where
+        K: Clone,
+    {
+        self.values.get(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.values.contains_key(k)
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned. The key is not updated, though; this matters for
+    /// types that can be `==` without being identical.
+    pub fn insert(&mut self, k: K, value: V) -> Option<V>
+    where
+        K: Clone + BorshDeserialize,
+    {
+        // Check if value is in map to replace first
+        let entry = self.values.get_mut_inner(&k);
+        if let Some(existing) = entry.value_mut() {
+            return Some(mem::replace(existing, value));
+        }
+
+        // At this point, we know that the key doesn't exist in the map, add key to the tree.
+        self.tree.insert(k.clone());
+        entry.replace(Some(value));
+        None
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.remove_entry(k).map(|(_, v)| v)
+    }
+
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(1, "a".to_string());
+    /// assert_eq!(map.remove(&1), Some("a".to_string()));
+    /// assert_eq!(map.remove(&1), None);
+    /// ```
+    pub fn remove_entry<Q: ?Sized>(&mut self, k: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        // Remove value
+        let old_value = self.values.remove(k)?;
+
+        // Remove key from the tree if value exists
+        let key = self.tree.remove(k).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+
+        // Return removed value
+        Some((key, old_value))
+    }
+
+    /// Removes a key from the map without reading its value from storage, returning `true` if the
+    /// key was previously in the map.
+    ///
+    /// Only the nodes of the tree are read, which avoids the cost of reading and deserializing a
+    /// large value which is only discarded. Use [`remove`](Self::remove) if you need the value.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`BorshSerialize`] and [`ToOwned<Owned = K>`](ToOwned) on the borrowed form *must* match
+    /// those for the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// map.insert(1, vec![0u8; 1024]);
+    /// assert!(map.remove_no_return(&1));
+    /// assert!(!map.remove_no_return(&1));
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn remove_no_return<Q: ?Sized>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q> + BorshDeserialize,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        match self.tree.remove(k) {
+            Some(key) => {
+                self.values.set(key, None);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)` returns `false`.
+    /// The elements are visited in ascending order of the keys. Every node of the tree is read
+    /// once, and if any elements were removed, the tree is rebuilt from the remaining nodes so
+    /// that it is balanced again, only writing the nodes whose links changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut auctions = TreeMap::new(b"m");
+    /// for (ends_at, id) in [(30u64, 1u32), (10, 2), (20, 3), (40, 4)] {
+    ///     auctions.insert(ends_at, id);
+    /// }
+    ///
+    /// // Remove expired auctions
+    /// auctions.retain(|&ends_at, _| ends_at > 25);
+    /// assert_eq!(auctions.keys().collect::<Vec<_>>(), [&30, &40]);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        K: BorshDeserialize + Clone,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let len = self.tree.nodes.len() as usize;
+        // In-order traversal, keeping the indices of the retained nodes in order.
+        let mut retained = Vec::new();
+        let mut stack = Vec::new();
+        let mut at = self.tree.root;
+        loop {
+            while let Some(idx) = at {
+                stack.push(idx);
+                at = self.tree.node(idx).lft;
+            }
+            let idx = match stack.pop() {
+                Some(idx) => idx,
+                None => break,
+            };
+            let node = self.tree.node(idx);
+            // The left subtree was already visited, continue with the right subtree.
+            at = node.rgt;
+
+            let value = self
+                .values
+                .get_mut(&node.key)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if f(&node.key, value) {
+                retained.push(idx);
+            } else {
+                let node = self.tree.remove_node(idx);
+                // Value is already loaded, set to avoid the extra return value.
+                self.values.set(node.key, None);
+            }
+        }
+
+        if retained.len() != len {
+            self.tree.root = self.tree.build(&retained);
+        }
+    }
+
+    /// Creates an iterator which removes and yields the entries for which `pred` returns `true`,
+    /// in ascending order of the keys.
+    ///
+    /// Unlike [`retain`](Self::retain), the entries are visited lazily as the iterator is
+    /// advanced, and each removal rebalances the tree. When the iterator is dropped, the entries
+    /// which were not visited yet are kept, so the work done in a call can be bounded by only
+    /// taking some of the matching entries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut auctions = TreeMap::new(b"m");
+    /// for (id, ends_at) in [(1u32, 30u64), (2, 10), (3, 20), (4, 40), (5, 5)] {
+    ///     auctions.insert(id, ends_at);
+    /// }
+    ///
+    /// // Remove at most two expired auctions per call.
+    /// let expired: Vec<_> = auctions.extract_if(|_, ends_at| *ends_at < 25).take(2).collect();
+    /// assert_eq!(expired, [(2, 10), (3, 20)]);
+    /// assert_eq!(auctions.keys().collect::<Vec<_>>(), [&1, &4, &5]);
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<K, V, H, F>
+    where
+        K: BorshDeserialize + Clone,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf::new(self, pred)
+    }
+
+    /// Splits the map in two at the given key, moving all entries with keys greater than or equal
+    /// to `key` to a new map stored under `prefix`, which is returned.
+    ///
+    /// The nodes of the tree are read once, and both trees are rebuilt balanced from their nodes.
+    /// Moved entries are removed from the storage of this map and written to the new one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut rewards = TreeMap::new(b"r");
+    /// for epoch in 1u64..=6 {
+    ///     rewards.insert(epoch, epoch * 100);
+    /// }
+    ///
+    /// // Move the current epochs to a new map, leaving the old ones in `rewards`.
+    /// let current = rewards.split_off(&4, b"c");
+    /// assert_eq!(rewards.keys().collect::<Vec<_>>(), [&1, &2, &3]);
+    /// assert_eq!(current.keys().collect::<Vec<_>>(), [&4, &5, &6]);
+    /// ```
+    pub fn split_off<Q: ?Sized, S>(&mut self, key: &Q, prefix: S) -> Self
+    where
+        K: Borrow<Q> + BorshDeserialize + Clone,
+        Q: Ord,
+        S: IntoStorageKey,
+        V: BorshDeserialize,
+    {
+        let mut other = Self::with_hasher(prefix);
+        let mut sorted = self.tree.in_order();
+        let split = sorted.partition_point(|&idx| self.tree.node(idx).key.borrow() < key);
+        if split == sorted.len() {
+            return other;
+        }
+
+        let mut moved = Vec::with_capacity(sorted.len() - split);
+        for idx in sorted.drain(split..) {
+            let node = self.tree.remove_node(idx);
+            let value = self
+                .values
+                .remove::<K>(&node.key)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            other.values.set(node.key.clone(), Some(value));
+            moved.push(other.tree.insert_node(Node::of(node.key)));
+        }
+        self.tree.root = self.tree.build(&sorted);
+        other.tree.root = other.tree.build(&moved);
+        other
+    }
+
+    /// Moves all entries from `other` into `self`, leaving `other` empty. If a key from `other`
+    /// is already present in `self`, the value from `other` replaces it.
+    ///
+    /// The nodes of both trees are read once and merged in order, and the tree is rebuilt balanced
+    /// from its existing and moved nodes. The nodes of `other` are freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut a = TreeMap::new(b"a");
+    /// a.insert(1u64, "a".to_string());
+    /// a.insert(2, "b".to_string());
+    ///
+    /// let mut b = TreeMap::new(b"b");
+    /// b.insert(2, "c".to_string());
+    /// b.insert(3, "d".to_string());
+    ///
+    /// a.append(&mut b);
+    /// assert!(b.is_empty());
+    /// assert_eq!(a.len(), 3);
+    /// assert_eq!(a.get(&2), Some(&"c".to_string()));
+    /// ```
+    pub fn append(&mut self, other: &mut Self)
+    where
+        K: BorshDeserialize + Clone,
+        V: BorshDeserialize,
+    {
+        let theirs = other.tree.in_order();
+        if theirs.is_empty() {
+            return;
+        }
+        let ours = self.tree.in_order();
+        let mut merged = Vec::with_capacity(ours.len() + theirs.len());
+        let mut ours = ours.into_iter().peekable();
+        for idx in theirs {
+            let node = other
+                .tree
+                .nodes
+                .remove(idx)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            let value = other
+                .values
+                .remove::<K>(&node.key)
+                .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+
+            // Keep the nodes with keys up to the moved key before it, reusing an equal node.
+            let mut exists = false;
+            while let Some(&at) = ours.peek() {
+                match self.tree.node(at).key.cmp(&node.key) {
+                    Ordering::Greater => break,
+                    ord => {
+                        merged.push(at);
+                        ours.next();
+                        if ord == Ordering::Equal {
+                            exists = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            if exists {
+                self.values.set(node.key, Some(value));
+            } else {
+                self.values.set(node.key.clone(), Some(value));
+                merged.push(self.tree.insert_node(Node::of(node.key)));
+            }
+        }
+        merged.extend(ours);
+        other.tree.root = None;
+        self.tree.root = self.tree.build(&merged);
+    }
+
+    /// Moves up to `max_nodes` nodes of the tree from the back of its storage into the cells left
+    /// by removed nodes, and removes the empty cells at the back, to reduce the storage used by
+    /// the map after many removals. Returns `true` if the nodes are stored without gaps, otherwise
+    /// the compaction can be continued in later calls, to bound the gas used per call.
+    ///
+    /// The links to every moved node are updated, which loads the nodes on the path to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for k in 0u32..100 {
+    ///     map.insert(k, k);
+    /// }
+    /// map.retain(|k, _| k % 10 == 0);
+    ///
+    /// // Continue the compaction over multiple calls, moving at most 4 nodes per call.
+    /// while !map.defrag(4) {}
+    /// assert_eq!(map.len(), 10);
+    /// assert_eq!(map.range(45..).next(), Some((&50, &50)));
+    /// ```
+    pub fn defrag(&mut self, max_nodes: u32) -> bool
+    where
+        K: BorshDeserialize,
+    {
+        let mut moves = Vec::new();
+        let done = self.tree.nodes.defrag_bounded(max_nodes, |_, from, to| moves.push((from, to)));
+        if moves.is_empty() {
+            return done;
+        }
+
+        // Nodes are moved from the back, sort them by their old index to look up moved links.
+        moves.reverse();
+        let moved = |at: FreeListIndex| match moves.binary_search_by_key(&at.0, |(from, _)| from.0)
+        {
+            Ok(i) => moves[i].1,
+            Err(_) => at,
+        };
+        let root = self.tree.root.map(moved);
+        self.tree.root = root;
+        for &(from, to) in &moves {
+            self.tree.replace(from);
+            self.tree.replace(to);
+            if root == Some(to) {
+                continue;
+            }
+            // Find the parent of the moved node, following the links as they are after the move.
+            let key = &self.tree.node(to).key;
+            let mut at = root.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            let (parent, is_lft) = loop {
+                let node = self.tree.node(at);
+                let is_lft = key < &node.key;
+                let child = if is_lft { node.lft } else { node.rgt };
+                match child {
+                    Some(child) if child == from => break (at, is_lft),
+                    Some(child) => at = moved(child),
+                    None => env::panic_str(ERR_INCONSISTENT_STATE),
+                }
+            };
+            self.tree.set_child(parent, is_lft, to);
+        }
+        done
+    }
+}
+
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Starts a copy of this map into a new map under the given storage `prefix`, for example to
+    /// take a snapshot of the map. Entries are copied by calling [`CloneTo::resume`], which can be
+    /// continued over multiple function calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::TreeMap;
+    /// use near_sdk::Gas;
+    ///
+    /// let mut balances = TreeMap::new(b"b");
+    /// balances.insert("alice.near".to_string(), 100u128);
+    ///
+    /// let mut snapshot = balances.clone_to(b"s");
+    /// assert!(snapshot.resume(&balances, Gas::ONE_TERA * 100));
+    /// assert_eq!(snapshot.into_inner().get("alice.near"), Some(&100));
+    /// ```
+    pub fn clone_to<S>(&self, prefix: S) -> CloneTo<TreeMap<K, V, H>>
+    where
+        S: IntoStorageKey,
+    {
+        CloneTo::new(TreeMap::with_hasher(prefix))
+    }
+}
+
+impl<K, V, H> CloneTo<TreeMap<K, V, H>>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Continues copying entries from `source` until either all entries are copied or
+    /// [`env::used_gas`] exceeds `gas_limit`. At least one entry is copied on every call.
+    /// Returns `true` if the copy is complete.
+    pub fn resume(&mut self, source: &TreeMap<K, V, H>, gas_limit: Gas) -> bool {
+        self.copy_until(source.tree.nodes.capacity(), gas_limit, |target, i| {
+            if let Some(node) = source.tree.nodes.get(FreeListIndex(i)) {
+                let value = source.entry_value(&node.key);
+                target.insert(node.key.clone(), value.clone());
+            }
+        })
+    }
+}
+
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone + Serialize,
+    V: BorshSerialize + BorshDeserialize + Serialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Serializes up to `limit` entries with keys greater than or equal to `from`, or from the
+    /// first entry if `from` is [`None`], as a JSON object in ascending order of the keys. Only
+    /// the returned entries and the nodes of the tree on the path to them are loaded, which
+    /// allows view methods to return the map in pages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::serde_json::json;
+    /// use near_sdk::store::TreeMap;
+    ///
+    /// let mut map = TreeMap::new(b"m");
+    /// for (k, v) in [("a", 1u8), ("b", 2), ("c", 3), ("d", 4)] {
+    ///     map.insert(k.to_string(), v);
+    /// }
+    ///
+    /// assert_eq!(map.to_json_page(None, 2), json!({ "a": 1, "b": 2 }));
+    /// assert_eq!(map.to_json_page(Some(&"c".to_string()), 10), json!({ "c": 3, "d": 4 }));
+    /// assert_eq!(near_sdk::serde_json::to_value(&map).unwrap()["d"], 4);
+    /// ```
+    pub fn to_json_page(&self, from: Option<&K>, limit: usize) -> serde_json::Value {
+        let lo = from.map_or(Bound::Unbounded, |k| Bound::Included(k.clone()));
+        let entries = Entries(self.range((lo, Bound::Unbounded)).take(limit).collect());
+        serde_json::to_value(entries)
+            .unwrap_or_else(|_| env::panic_str("Cannot serialize the entries as JSON"))
+    }
+}
+
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.tree.nodes.flush();
+        self.values.flush();
+    }
+}
+
+#[cfg(feature = "expensive-debug")]
+impl<K, V, H> TreeMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Checks the structure of the tree and its agreement with the stored values, aborting with a
+    /// message describing the first violation found.
+    ///
+    /// Every node reachable from the root is loaded, and the value of every key, so this is only
+    /// meant for tests and debugging, for example after a state migration. The following is
+    /// checked:
+    /// - the keys are in strictly ascending order in the tree;
+    /// - the stored height and subtree size of every node match its children, and the heights of
+    ///   the subtrees of every node differ by at most one;
+    /// - every node is linked from exactly one parent, and every stored node is reachable from
+    ///   the root;
+    /// - every key in the tree has a value.
+    pub fn check_invariants(&self) {
+        let tree = &self.tree;
+        // Depth-first traversal with the bounds each subtree must be within.
+        let mut stack = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+        if let Some(root) = tree.root {
+            stack.push((root, None, None));
+        }
+        while let Some((at, lo, hi)) = stack.pop() {
+            if !visited.insert(at) {
+                env::panic_str(&format!("TreeMap: node {} is linked more than once", at.0));
+            }
+            let node = tree.nodes.get(at).unwrap_or_else(|| {
+                env::panic_str(&format!("TreeMap: linked node {} does not exist", at.0))
+            });
+            let below_lo = matches!(lo, Some(lo) if &node.key <= lo);
+            if below_lo || matches!(hi, Some(hi) if &node.key >= hi) {
+                env::panic_str(&format!("TreeMap: key of node {} is out of order", at.0));
+            }
+
+            let (lht, rht) = (tree.height(node.lft), tree.height(node.rgt));
+            if node.ht != 1 + std::cmp::max(lht, rht) {
+                env::panic_str(&format!("TreeMap: height of node {} is {}", at.0, node.ht));
+            }
+            if (lht as i64 - rht as i64).abs() > 1 {
+                env::panic_str(&format!("TreeMap: node {} is unbalanced", at.0));
+            }
+            if node.size != 1 + tree.size(node.lft) + tree.size(node.rgt) {
+                env::panic_str(&format!("TreeMap: size of node {} is {}", at.0, node.size));
+            }
+            if self.values.get(&node.key).is_none() {
+                env::panic_str(&format!("TreeMap: key of node {} has no value", at.0));
+            }
+
+            if let Some(lft) = node.lft {
+                stack.push((lft, lo, Some(&node.key)));
+            }
+            if let Some(rgt) = node.rgt {
+                stack.push((rgt, Some(&node.key), hi));
+            }
+        }
+        if visited.len() != tree.nodes.len() as usize {
+            env::panic_str(&format!(
+                "TreeMap: {} nodes are stored but {} are reachable",
+                tree.nodes.len(),
+                visited.len()
+            ));
+        }
+    }
+}
+
+impl<K> Tree<K>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+{
+    fn node(&self, at: FreeListIndex) -> &Node<K> {
+        self.nodes.get(at).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+    }
+
+    fn node_mut(&mut self, at: FreeListIndex) -> &mut Node<K> {
+        if let Some(changes) = &mut self.changes {
+            changes.modified.insert(at);
+        }
+        self.nodes.get_mut(at).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+    }
+
+    fn insert_node(&mut self, node: Node<K>) -> FreeListIndex {
+        let at = self.nodes.insert(node);
+        self.replace(at);
+        at
+    }
+
+    fn remove_node(&mut self, at: FreeListIndex) -> Node<K> {
+        self.replace(at);
+        self.nodes.remove(at).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+    }
+
+    /// Records that the node in the cell at `at` was replaced, if changes are tracked.
+    fn replace(&mut self, at: FreeListIndex) {
+        if let Some(changes) = &mut self.changes {
+            changes.modified.insert(at);
+            changes.replaced.insert(at);
+        }
+    }
+
+    /// Records that the nodes in all cells were replaced, if changes are tracked.
+    fn replace_all(&mut self) {
+        for at in 0..self.nodes.capacity() {
+            self.replace(FreeListIndex(at));
+        }
+    }
+
+    /// Returns the number of keys in the tree which are smaller than `key`, or smaller than or
+    /// equal to `key` if `inclusive`.
+    fn count_below<Q: ?Sized>(&self, key: &Q, inclusive: bool) -> u32
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let mut count = 0;
+        let mut at = self.root;
+        while let Some(idx) = at {
+            let node = self.node(idx);
+            match node.key.borrow().cmp(key) {
+                Ordering::Less => {
+                    count += self.size(node.lft) + 1;
+                    at = node.rgt;
+                }
+                Ordering::Equal => return count + self.size(node.lft) + inclusive as u32,
+                Ordering::Greater => at = node.lft,
+            }
+        }
+        count
+    }
+
+    /// Returns the key at position `n` in ascending order of the keys.
+    fn nth(&self, mut n: u32) -> Option<&K> {
+        let mut at = self.root;
+        while let Some(idx) = at {
+            let node = self.node(idx);
+            let lft_size = self.size(node.lft);
+            match n.cmp(&lft_size) {
+                Ordering::Less => at = node.lft,
+                Ordering::Equal => return Some(&node.key),
+                Ordering::Greater => {
+                    n -= lft_size + 1;
+                    at = node.rgt;
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the indices of all nodes, in ascending order of their keys.
+    fn in_order(&self) -> Vec<FreeListIndex> {
+        let mut sorted = Vec::with_capacity(self.nodes.len() as usize);
+        let mut stack = Vec::new();
+        let mut at = self.root;
+        loop {
+            while let Some(idx) = at {
+                stack.push(idx);
+                at = self.node(idx).lft;
+            }
+            match stack.pop() {
+                Some(idx) => {
+                    sorted.push(idx);
+                    at = self.node(idx).rgt;
+                }
+                None => return sorted,
+            }
+        }
+    }
+
+    /// Returns the smallest key in the tree.
+    fn min(&self) -> Option<&K> {
+        let mut node = self.node(self.root?);
+        while let Some(lft) = node.lft {
+            node = self.node(lft);
+        }
+        Some(&node.key)
+    }
+
+    /// Returns the largest key in the tree.
+    fn max(&self) -> Option<&K> {
+        let mut node = self.node(self.root?);
+        while let Some(rgt) = node.rgt {
+            node = self.node(rgt);
+        }
+        Some(&node.key)
+    }
+
+    /// Returns the largest key in the tree which is less than or equal to `key`, or less than
+    /// `key` if not `inclusive`.
+    fn floor<Q: ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let mut floor = None;
+        let mut at = self.root;
+        while let Some(idx) = at {
+            let node = self.node(idx);
+            match node.key.borrow().cmp(key) {
+                Ordering::Equal if inclusive => return Some(&node.key),
+                Ordering::Less => {
+                    floor = Some(&node.key);
+                    at = node.rgt;
+                }
+                Ordering::Equal | Ordering::Greater => at = node.lft,
+            }
+        }
+        floor
+    }
+
+    /// Returns the smallest key in the tree which is greater than or equal to `key`, or greater
+    /// than `key` if not `inclusive`.
+    fn ceil<Q: ?Sized>(&self, key: &Q, inclusive: bool) -> Option<&K>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let mut ceil = None;
+        let mut at = self.root;
+        while let Some(idx) = at {
+            let node = self.node(idx);
+            match node.key.borrow().cmp(key) {
+                Ordering::Equal if inclusive => return Some(&node.key),
+                Ordering::Greater => {
+                    ceil = Some(&node.key);
+                    at = node.lft;
+                }
+                Ordering::Equal | Ordering::Less => at = node.rgt,
+            }
+        }
+        ceil
+    }
+
+    /// Inserts a key which is not yet in the tree.
+    fn insert(&mut self, key: K) {
+        // Path from the root to the parent of the new node, with whether the left child was taken.
+        let mut path = Vec::new();
+        let mut at = self.root;
+        while let Some(idx) = at {
+            let node = self.node(idx);
+            match key.cmp(&node.key) {
+                Ordering::Less => {
+                    path.push((idx, true));
+                    at = node.lft;
+                }
+                Ordering::Greater => {
+                    path.push((idx, false));
+                    at = node.rgt;
+                }
+                // Key is already in the tree, nothing to do.
+                Ordering::Equal => return,
+            }
+        }
+
+        // Link the new subtree to its parent and rebalance up the path, until a subtree keeps
+        // both its root and its height, in which case the rest of the path is only one node
+        // larger.
+        let mut child = self.insert_node(Node::of(key));
+        while let Some((at, is_lft)) = path.pop() {
+            self.set_child(at, is_lft, child);
+            let ht = self.node(at).ht;
+            child = self.rebalance(at);
+            if child == at && self.node(at).ht == ht {
+                for (at, _) in path {
+                    self.node_mut(at).size += 1;
+                }
+                return;
+            }
+        }
+        self.root = Some(child);
+    }
+
+    /// Sets the left or right child of the node at `at`, only marking the node as modified if
+    /// the link changes.
+    fn set_child(&mut self, at: FreeListIndex, is_lft: bool, child: FreeListIndex) {
+        let node = self.node(at);
+        let link = if is_lft { node.lft } else { node.rgt };
+        if link != Some(child) {
+            let node = self.node_mut(at);
+            if is_lft {
+                node.lft = Some(child);
+            } else {
+                node.rgt = Some(child);
+            }
+        }
+    }
+
+    /// Removes `key` from the tree, returning the key stored in the removed node.
+    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let (root, removed) = self.remove_at(self.root, key);
+        self.root = root;
+        removed
+    }
+
+    /// Removes `key` from the subtree at `at`, returning the new root of the subtree and the
+    /// removed key.
+    fn remove_at<Q: ?Sized>(
+        &mut self,
+        at: Option<FreeListIndex>,
+        key: &Q,
+    ) -> (Option<FreeListIndex>, Option<K>)
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        let at = match at {
+            Some(at) => at,
+            None => return (None, None),
+        };
+        let node = self.node(at);
+        let removed = match key.cmp(node.key.borrow()) {
+            Ordering::Less => {
+                let lft = node.lft;
+                let (lft, removed) = self.remove_at(lft, key);
+                if removed.is_some() {
+                    self.node_mut(at).lft = lft;
+                }
+                removed
+            }
+            Ordering::Greater => {
+                let rgt = node.rgt;
+                let (rgt, removed) = self.remove_at(rgt, key);
+                if removed.is_some() {
+                    self.node_mut(at).rgt = rgt;
+                }
+                removed
+            }
+            Ordering::Equal => {
+                let (lft, rgt) = (node.lft, node.rgt);
+                let substitute = match (lft, rgt) {
+                    (None, None) => None,
+                    (Some(child), None) | (None, Some(child)) => Some(child),
+                    (Some(_), Some(rgt)) => {
+                        // Replace the node with the smallest node of its right subtree.
+                        let (rgt, min) = self.detach_min(rgt);
+                        let substitute = self.node_mut(min);
+                        substitute.lft = lft;
+                        substitute.rgt = rgt;
+                        Some(self.rebalance(min))
+                    }
+                };
+                let node = self.remove_node(at);
+                return (substitute, Some(node.key));
+            }
+        };
+        if removed.is_some() {
+            (Some(self.rebalance(at)), removed)
+        } else {
+            (Some(at), None)
+        }
+    }
+
+    /// Links the nodes at `sorted`, which are in ascending order of their keys, into a balanced
+    /// subtree, returning the root of the subtree.
+    fn build(&mut self, sorted: &[FreeListIndex]) -> Option<FreeListIndex> {
+        if sorted.is_empty() {
+            return None;
+        }
+        let mid = sorted.len() / 2;
+        let lft = self.build(&sorted[..mid]);
+        let rgt = self.build(&sorted[mid + 1..]);
+        let at = sorted[mid];
+        let ht = 1 + std::cmp::max(self.height(lft), self.height(rgt));
+        let size = 1 + self.size(lft) + self.size(rgt);
+
+        // Avoid marking the node as modified if its links did not change.
+        let node = self.node(at);
+        if node.lft != lft || node.rgt != rgt || node.ht != ht || node.size != size {
+            let node = self.node_mut(at);
+            node.lft = lft;
+            node.rgt = rgt;
+            node.ht = ht;
+            node.size = size;
+        }
+        Some(at)
+    }
+
+    /// Detaches the smallest node of the subtree at `at` from the tree, returning the new root of
+    /// the subtree and the detached node.
+    fn detach_min(&mut self, at: FreeListIndex) -> (Option<FreeListIndex>, FreeListIndex) {
+        let node = self.node(at);
+        match node.lft {
+            None => (node.rgt, at),
+            Some(lft) => {
+                let (lft, min) = self.detach_min(lft);
+                self.node_mut(at).lft = lft;
+                (Some(self.rebalance(at)), min)
+            }
+        }
+    }
+
+    fn height(&self, at: Option<FreeListIndex>) -> u32 {
+        at.map_or(0, |at| self.node(at).ht)
+    }
+
+    fn size(&self, at: Option<FreeListIndex>) -> u32 {
+        at.map_or(0, |at| self.node(at).size)
+    }
+
+    // Calculate and save the height and size of a subtree at node `at`:
+    // height[at] = 1 + max(height[at.L], height[at.R])
+    // size[at] = 1 + size[at.L] + size[at.R]
+    fn update_height(&mut self, at: FreeListIndex) {
+        let node = self.node(at);
+        let ht = 1 + std::cmp::max(self.height(node.lft), self.height(node.rgt));
+        let size = 1 + self.size(node.lft) + self.size(node.rgt);
+        // Avoid marking the node as modified if the height and size did not change.
+        if node.ht != ht || node.size != size {
+            let node = self.node_mut(at);
+            node.ht = ht;
+            node.size = size;
+        }
+    }
+
+    // Balance = difference in heights between left and right subtrees at given node.
+    fn balance(&self, at: FreeListIndex) -> i64 {
+        let node = self.node(at);
+        self.height(node.lft) as i64 - self.height(node.rgt) as i64
+    }
+
+    // Right rotation of the subtree at node `at`, the left child becomes the root of the subtree.
+    // New root of subtree is returned, caller is responsible for updating proper link from parent.
+    fn rotate_right(&mut self, at: FreeListIndex) -> FreeListIndex {
+        let lft = self.node(at).lft.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        let lft_rgt = self.node(lft).rgt;
+
+        // at.L = at.L.R
+        self.node_mut(at).lft = lft_rgt;
+        self.update_height(at);
+
+        // at.L.R = at
+        self.node_mut(lft).rgt = Some(at);
+        self.update_height(lft);
+
+        lft
+    }
+
+    // Left rotation of the subtree at node `at`, the right child becomes the root of the subtree.
+    // New root of subtree is returned, caller is responsible for updating proper link from parent.
+    fn rotate_left(&mut self, at: FreeListIndex) -> FreeListIndex {
+        let rgt = self.node(at).rgt.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        let rgt_lft = self.node(rgt).lft;
+
+        // at.R = at.R.L
+        self.node_mut(at).rgt = rgt_lft;
+        self.update_height(at);
+
+        // at.R.L = at
+        self.node_mut(rgt).lft = Some(at);
+        self.update_height(rgt);
+
+        rgt
+    }
+
+    // Update the height and size of the node at `at` and enforce its balance with rotations if necessary,
+    // returning the new root of the subtree.
+    fn rebalance(&mut self, at: FreeListIndex) -> FreeListIndex {
+        self.update_height(at);
+        let balance = self.balance(at);
+        if balance > 1 {
+            let lft = self.node(at).lft.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if self.balance(lft) < 0 {
+                let rotated = self.rotate_left(lft);
+                self.node_mut(at).lft = Some(rotated);
+            }
+            self.rotate_right(at)
+        } else if balance < -1 {
+            let rgt = self.node(at).rgt.unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if self.balance(rgt) > 0 {
+                let rotated = self.rotate_right(rgt);
+                self.node_mut(at).rgt = Some(rotated);
+            }
+            self.rotate_left(at)
+        } else {
+            at
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::TreeMap;
+    use crate::store::LookupMap;
+    use crate::test_utils::test_env::setup_free;
+    use arbitrary::{Arbitrary, Unstructured};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::RngCore;
+    use rand::SeedableRng;
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+
+    impl<K, V> TreeMap<K, V>
+    where
+        K: BorshSerialize + BorshDeserialize + Ord + Clone,
+        V: BorshSerialize + BorshDeserialize,
+    {
+        /// Checks the ordering, heights and balance of every node of the tree.
+        fn assert_valid(&self) {
+            fn check<K>(tree: &super::Tree<K>, at: Option<super::FreeListIndex>) -> (u32, u32)
+            where
+                K: BorshSerialize + BorshDeserialize + Ord,
+            {
+                let at = match at {
+                    Some(at) => at,
+                    None => return (0, 0),
+                };
+                let node = tree.node(at);
+                let (lht, lcount) = check(tree, node.lft);
+                let (rht, rcount) = check(tree, node.rgt);
+                assert_eq!(node.ht, 1 + lht.max(rht));
+                assert!((lht as i64 - rht as i64).abs() <= 1);
+                assert_eq!(node.size, lcount + rcount + 1);
+                (node.ht, node.size)
+            }
+            let (_, count) = check(&self.tree, self.tree.root);
+            assert_eq!(count, self.len());
+            let keys: Vec<_> = self.iter().map(|(k, _)| k.clone()).collect();
+            assert!(keys.windows(2).all(|w| w[0] < w[1]));
+            #[cfg(feature = "expensive-debug")]
+            self.check_invariants();
+        }
+    }
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = TreeMap::new(b"b");
+        assert!(map.is_empty());
+        assert!(map.insert("test".to_string(), 5u8).is_none());
+        assert_eq!(map.get("test"), Some(&5));
+        assert_eq!(map.len(), 1);
+
+        *map.get_mut("test").unwrap() = 6;
+        assert_eq!(map.insert("test".to_string(), 7), Some(6));
+        assert_eq!(map.len(), 1);
+
+        map.insert("a".to_string(), 1);
+        map.insert("z".to_string(), 26);
+        assert_eq!(map.min_key().map(String::as_str), Some("a"));
+        assert_eq!(map.max_key().map(String::as_str), Some("z"));
+        assert_eq!(map.first_key_value(), Some((&"a".to_string(), &1)));
+        assert_eq!(map.last_key_value(), Some((&"z".to_string(), &26)));
+        map.remove("a");
+        map.remove("z");
+        assert_eq!(map.first_key_value(), map.last_key_value());
+
+        assert_eq!(map.remove("test"), Some(7));
+        assert_eq!(map.remove("test"), None);
+        assert_eq!(map.len(), 0);
+        assert_eq!(map.min_key(), None);
+        assert_eq!(map.last_key_value(), None);
+    }
+
+    #[test]
+    fn insert_remove_balanced() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        // Insert in an order which causes all rotation cases.
+        for k in (0u32..200).map(|k| (k * 37) % 200) {
+            map.insert(k, k * 2);
+            baseline.insert(k, k * 2);
+            map.assert_valid();
+        }
+        assert!(Iterator::eq(map.iter(), baseline.iter()));
+
+        for k in (0u32..200).map(|k| (k * 73) % 200).filter(|k| k % 3 != 0) {
+            assert_eq!(map.remove(&k), baseline.remove(&k));
+            map.assert_valid();
+        }
+        assert!(Iterator::eq(map.iter(), baseline.iter()));
+        assert!(Iterator::eq(map.keys(), baseline.keys()));
+        assert_eq!(map.keys().len(), baseline.len());
+        assert_eq!(map.tree.nodes.len(), map.len());
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&3), None);
+        assert_eq!(map.iter().next(), None);
+    }
+
+    #[test]
+    fn remove_no_return() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..100).map(|k| (k * 37) % 100) {
+            map.insert(k, vec![k as u8; 64]);
+            baseline.insert(k, vec![k as u8; 64]);
+        }
+        map.flush();
+
+        for k in (0u32..120).map(|k| (k * 73) % 120).filter(|k| k % 3 != 0) {
+            assert_eq!(map.remove_no_return(&k), baseline.remove(&k).is_some());
+            map.assert_valid();
+        }
+        assert!(Iterator::eq(map.iter(), baseline.iter()));
+
+        for k in 0u32..100 {
+            map.remove_no_return(&k);
+        }
+        assert!(map.is_empty());
+        map.flush();
+        // Only the empty slots of the nodes remain, all values are removed from storage.
+        let storage = crate::mock::with_mocked_blockchain(|m| m.take_storage());
+        assert!(storage.keys().all(|k| k.starts_with(b"bn")));
+    }
+
+    #[test]
+    fn insert_sequential() {
+        let mut map = TreeMap::new(b"b");
+        for k in 1u32..=255 {
+            map.insert(k, ());
+        }
+        map.assert_valid();
+        // Ascending inserts into an AVL tree produce a perfect tree for 2^n - 1 keys.
+        assert_eq!(map.tree.height(map.tree.root), 8);
+        assert!(map.insert(128, ()).is_some());
+        assert_eq!(map.len(), 255);
+    }
+
+    #[test]
+    fn rank_nth() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..200).map(|k| (k * 37) % 200) {
+            map.insert(k * 2, ());
+            baseline.insert(k * 2, ());
+        }
+        for k in (0u32..200).map(|k| (k * 73) % 200).filter(|k| k % 3 != 0) {
+            map.remove(&(k * 2));
+            baseline.remove(&(k * 2));
+        }
+        map.assert_valid();
+
+        for k in 0..401 {
+            assert_eq!(map.rank(&k), baseline.range(..k).count() as u32);
+        }
+        for (n, k) in baseline.keys().enumerate() {
+            assert_eq!(map.nth_key(n as u32), Some(k));
+        }
+        assert_eq!(map.nth_key(map.len()), None);
+    }
+
+    #[test]
+    fn drain() {
+        let mut map = TreeMap::new(b"b");
+        for k in (0u8..20).rev() {
+            map.insert(k, k * 2);
+        }
+        map.remove(&7);
+
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 19);
+        assert_eq!(drain.next(), Some((0, 0)));
+        assert_eq!(drain.nth(6), Some((8, 16)));
+        assert_eq!(drain.len(), 11);
+        drop(drain);
+        assert!(map.is_empty());
+        assert_eq!(map.get(&19), None);
+
+        for k in 0u8..10 {
+            map.insert(k, k);
+        }
+        assert!(Iterator::eq(map.drain(), (0u8..10).map(|k| (k, k))));
+        assert!(map.is_empty());
+        map.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+
+    #[test]
+    fn retain() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..100).map(|k| (k * 37) % 100) {
+            map.insert(k, k);
+            baseline.insert(k, k);
+        }
+
+        map.retain(|k, v| {
+            *v += 1;
+            k % 3 != 0
+        });
+        baseline.retain(|k, v| {
+            *v += 1;
+            k % 3 != 0
+        });
+        map.assert_valid();
+        assert!(Iterator::eq(map.iter(), baseline.iter()));
+
+        // The removed nodes are reused by following inserts.
+        let capacity = map.tree.nodes.capacity();
+        for k in (0u32..100).filter(|k| k % 3 == 0) {
+            map.insert(k, 0);
+        }
+        map.assert_valid();
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.tree.nodes.capacity(), capacity);
+
+        map.retain(|_, _| false);
+        assert!(map.is_empty());
+        assert_eq!(map.iter().next(), None);
+        assert_eq!(map.get(&1), None);
+    }
+
+    #[test]
+    fn range() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..100).map(|k| k * 2) {
+            map.insert(k, k);
+            baseline.insert(k, k);
+        }
+
+        let bounds = |k| [Bound::Included(k), Bound::Excluded(k), Bound::Unbounded];
+        for lo in (0..10).map(|k| k * 23).flat_map(bounds) {
+            for hi in (0..10).map(|k| k * 23).flat_map(bounds) {
+                let valid = match (lo, hi) {
+                    (Bound::Excluded(s), Bound::Excluded(e)) => s < e,
+                    (
+                        Bound::Included(s) | Bound::Excluded(s),
+                        Bound::Included(e) | Bound::Excluded(e),
+                    ) => s <= e,
+                    _ => true,
+                };
+                if valid {
+                    assert!(Iterator::eq(map.range((lo, hi)), baseline.range((lo, hi))));
+                    assert!(Iterator::eq(
+                        map.range((lo, hi)).rev(),
+                        baseline.range((lo, hi)).rev()
+                    ));
+                    assert_eq!(map.range((lo, hi)).len(), baseline.range((lo, hi)).count());
+                    assert!(Iterator::eq(
+                        map.keys_range((lo, hi)),
+                        baseline.range((lo, hi)).map(|(k, _)| k)
+                    ));
+                    assert_eq!(
+                        map.range_count((lo, hi)) as usize,
+                        baseline.range((lo, hi)).count()
+                    );
+                }
+            }
+        }
+        assert_eq!(map.range(7..7).next(), None);
+        assert_eq!(map.range_count(8..8), 0);
+        assert_eq!(map.range::<u32, _>(..).count(), 100);
+
+        let mut names = TreeMap::new(b"s");
+        for k in 0u32..30 {
+            names.insert(format!("{:02}", k), k);
+        }
+        let keys: Vec<_> = names
+            .range::<str, _>((Bound::Included("05"), Bound::Excluded("15")))
+            .map(|(_, v)| *v)
+            .collect();
+        assert_eq!(keys, (5..15).collect::<Vec<_>>());
+        for (_, v) in names.range_mut::<str, _>((Bound::Excluded("27"), Bound::Unbounded)) {
+            *v = 0;
+        }
+        assert_eq!(names.iter().map(|(_, v)| *v).sum::<u32>(), (0..28).sum::<u32>());
+    }
+
+    #[test]
+    fn floor_ceil() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        assert_eq!(map.floor_key(&0), None);
+        assert_eq!(map.ceil_entry(&0), None);
+        for k in (1u32..50).map(|k| k * 3) {
+            map.insert(k, k * 10);
+            baseline.insert(k, k * 10);
+        }
+
+        for k in 0..160 {
+            let floor = baseline.range(..=k).next_back();
+            let ceil = baseline.range(k..).next();
+            assert_eq!(map.floor_entry(&k), floor);
+            assert_eq!(map.ceil_entry(&k), ceil);
+            assert_eq!(map.floor_key(&k), floor.map(|(k, _)| k));
+            assert_eq!(map.ceil_key(&k), ceil.map(|(k, _)| k));
+            let nearest = match (floor, ceil) {
+                (Some((f, _)), Some((c, _))) => Some(if k - f <= c - k { f } else { c }),
+                (floor, ceil) => floor.or(ceil).map(|(k, _)| k),
+            };
+            assert_eq!(map.nearest_key(&k), nearest);
+        }
+    }
+
+    #[test]
+    fn get_key_value() {
+        let mut map = TreeMap::new(b"b");
+        for k in (0u32..20).map(|k| k * 2) {
+            map.insert(k.to_string(), k);
+        }
+        for k in 0u32..40 {
+            let key = k.to_string();
+            let expected = if k % 2 == 0 { Some((&key, &k)) } else { None };
+            assert_eq!(map.get_key_value(key.as_str()), expected);
+        }
+        assert_eq!(map["16"], 16);
+        assert_eq!(map[&"38".to_string()], 38);
+    }
+
+    #[test]
+    fn extract_if() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..100).map(|k| k * 7 % 100) {
+            map.insert(k, k);
+            baseline.insert(k, k);
+        }
+
+        // Only the visited entries are removed when the iterator is dropped early.
+        {
+            let mut extracted = map.extract_if(|k, _| k % 3 == 0);
+            assert_eq!(extracted.size_hint(), (0, Some(100)));
+            assert_eq!(
+                extracted.by_ref().take(5).collect::<Vec<_>>(),
+                [(0, 0), (3, 3), (6, 6), (9, 9), (12, 12)]
+            );
+            assert_eq!(extracted.size_hint(), (0, Some(87)));
+        }
+        baseline.retain(|k, _| k % 3 != 0 || *k > 12);
+        map.assert_valid();
+        assert!(map.iter().eq(baseline.iter()));
+
+        let extracted: Vec<_> = map
+            .extract_if(|k, v| {
+                *v += 1;
+                k % 2 == 0
+            })
+            .collect();
+        let expected: Vec<_> =
+            baseline.iter().filter(|(k, _)| *k % 2 == 0).map(|(k, v)| (*k, v + 1)).collect();
+        assert_eq!(extracted, expected);
+        baseline.retain(|k, v| {
+            *v += 1;
+            k % 2 != 0
+        });
+        map.assert_valid();
+        assert!(map.iter().eq(baseline.iter()));
+    }
+
+    #[test]
+    fn from_iter_extend() {
+        let entries: Vec<_> = (0u32..100).map(|k| (k * 37 % 50, k)).collect();
+        let baseline: BTreeMap<_, _> = entries.iter().copied().collect();
+
+        let map = TreeMap::from_iter_with_prefix(b"b", entries.iter().copied());
+        map.assert_valid();
+        assert!(map.iter().map(|(k, v)| (*k, *v)).eq(baseline.clone()));
+
+        let mut map = TreeMap::new(b"e");
+        map.extend(entries);
+        map.assert_valid();
+        assert!(map.iter().map(|(k, v)| (*k, *v)).eq(baseline));
+    }
+
+    #[test]
+    fn clone_to() {
+        let mut map = TreeMap::new(b"b");
+        map.extend((0u8..10).map(|k| (k, k)));
+        map.remove(&3);
+
+        let mut copy = map.clone_to(b"c");
+        while !copy.resume(&map, crate::Gas(0)) {}
+        let shadow = copy.into_inner();
+        shadow.assert_valid();
+        assert_eq!(shadow.len(), 9);
+        assert!(Iterator::eq(shadow.iter(), map.iter()));
+    }
+
+    #[test]
+    fn iter_mut() {
+        let mut map = TreeMap::new(b"b");
+        for k in [5u8, 1, 4, 2, 3] {
+            map.insert(k, k);
+        }
+
+        for (_, v) in map.range_mut(2..4) {
+            *v *= 10;
+        }
+        for (k, v) in &mut map {
+            *v += k;
+        }
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [(&1, &2), (&2, &22), (&3, &33), (&4, &8), (&5, &10)]
+        );
+
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+        let map = TreeMap::<u8, u8>::try_from_slice(&serialized).unwrap();
+        assert_eq!(map.iter().len(), 5);
+        assert_eq!(map.range(3..).map(|(k, _)| *k).collect::<Vec<_>>(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn cursor() {
+        let mut map = TreeMap::new(b"b");
+        let mut cursor = map.cursor();
+        assert_eq!(cursor.next(), None);
+        assert_eq!(cursor.prev(), None);
+        assert_eq!(cursor.seek(&0), None);
+
+        for k in (0u32..50).map(|k| (k * 37) % 50) {
+            map.insert(k * 2, k);
+        }
+        let mut cursor = map.cursor();
+        assert_eq!(cursor.peek(), None);
+        assert_eq!(cursor.prev(), None);
+        assert!(Iterator::eq(cursor.by_ref().map(|(k, _)| *k), (0..50).map(|k| k * 2)));
+        assert_eq!(cursor.key(), None);
+        assert_eq!(cursor.next(), None);
+        for k in (0..50).rev() {
+            assert_eq!(cursor.prev(), Some((&(k * 2), &k)));
+            assert_eq!(cursor.peek(), Some((&(k * 2), &k)));
+        }
+        assert_eq!(cursor.prev(), None);
+        assert_eq!(cursor.next(), Some((&0, &0)));
+
+        assert_eq!(cursor.seek(&51), Some((&52, &26)));
+        assert_eq!(cursor.seek(&52), Some((&52, &26)));
+        assert_eq!(cursor.next(), Some((&54, &27)));
+        assert_eq!(cursor.seek(&99), None);
+        assert_eq!(cursor.prev(), Some((&98, &49)));
+    }
+
+    #[test]
+    fn double_ended() {
+        let mut map = TreeMap::new(b"b");
+        for k in (0u32..50).map(|k| (k * 37) % 50) {
+            map.insert(k, k);
+        }
+
+        // Both ends meet without yielding any key twice.
+        let mut range = map.range(10..20);
+        assert_eq!(range.next(), Some((&10, &10)));
+        assert_eq!(range.next_back(), Some((&19, &19)));
+        assert_eq!(range.len(), 8);
+        let rest: Vec<_> = range.by_ref().rev().map(|(k, _)| *k).collect();
+        assert_eq!(rest, (11..19).rev().collect::<Vec<_>>());
+        assert_eq!(range.next(), None);
+        assert_eq!(range.next_back(), None);
+
+        let mut iter = map.iter_mut();
+        while let (Some((a, va)), Some((b, vb))) = (iter.next(), iter.next_back()) {
+            *va = *b;
+            *vb = *a;
+        }
+        assert!(Iterator::eq(map.iter().map(|(_, v)| *v), (0..50).rev()));
+        assert!(Iterator::eq(map.keys().rev().copied(), (0..50).rev()));
+        for (_, v) in map.range_mut(..5).rev() {
+            *v = 0;
+        }
+        assert_eq!(map.iter().take(6).map(|(_, v)| *v).collect::<Vec<_>>(), [0, 0, 0, 0, 0, 44]);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..100).map(|k| (k * 37) % 100) {
+            map.insert(k, k * 2);
+            baseline.insert(k, k * 2);
+        }
+
+        for (i, &key) in [150, 80, 79, 33, 0].iter().enumerate() {
+            let mut other = map.split_off(&key, vec![b'c', i as u8]);
+            let mut other_baseline = baseline.split_off(&key);
+            map.assert_valid();
+            other.assert_valid();
+            assert!(Iterator::eq(map.iter(), baseline.iter()));
+            assert!(Iterator::eq(other.iter(), other_baseline.iter()));
+            assert_eq!(map.tree.nodes.len(), map.len());
+
+            // The new map is independent of the original one.
+            if let Some((&k, _)) = other_baseline.iter().next() {
+                assert_eq!(other.remove(&k), other_baseline.remove(&k));
+                assert_eq!(map.get(&k), None);
+            }
+        }
+        assert!(map.is_empty());
+
+        // Moved values are removed from the storage of the original map.
+        let mut map = TreeMap::new(b"s");
+        map.insert(1u8, 1u8);
+        let other = map.split_off(&0, b"c");
+        map.flush();
+        drop(other);
+        assert_eq!(LookupMap::<u8, u8>::new(b"sv".to_vec()).get(&1), None);
+        assert_eq!(LookupMap::<u8, u8>::new(b"cv".to_vec()).get(&1), Some(&1));
+    }
+
+    #[test]
+    fn append() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for (i, keys) in [(0u32..0), (10..40), (0..10), (30..60), (5..55)].iter().enumerate() {
+            let mut other = TreeMap::new(vec![b'c', i as u8]);
+            let mut other_baseline = BTreeMap::new();
+            for k in keys.clone().rev() {
+                other.insert(k, k + i as u32);
+                other_baseline.insert(k, k + i as u32);
+            }
+
+            map.append(&mut other);
+            baseline.append(&mut other_baseline);
+            map.assert_valid();
+            other.assert_valid();
+            assert!(other.is_empty());
+            assert_eq!(other.tree.nodes.len(), 0);
+            assert!(Iterator::eq(map.iter(), baseline.iter()));
+            assert_eq!(map.tree.nodes.len(), map.len());
+        }
+
+        // Moved values are removed from the storage of the other map.
+        let mut other = TreeMap::new(b"s");
+        other.insert(1u8, 1u8);
+        let mut map = TreeMap::new(b"m");
+        map.append(&mut other);
+        drop(other);
+        drop(map);
+        assert_eq!(LookupMap::<u8, u8>::new(b"sv".to_vec()).get(&1), None);
+        assert_eq!(LookupMap::<u8, u8>::new(b"mv".to_vec()).get(&1), Some(&1));
+    }
+
+    #[test]
+    fn defrag() {
+        let mut map = TreeMap::new(b"b");
+        let mut baseline = BTreeMap::new();
+        for k in (0u32..200).map(|k| (k * 37) % 200) {
+            map.insert(k, k);
+            baseline.insert(k, k);
+        }
+        for k in (0u32..200).map(|k| (k * 73) % 200).filter(|k| k % 5 != 0) {
+            map.remove(&k);
+            baseline.remove(&k);
+        }
+        assert!(map.tree.nodes.capacity() > map.len());
+
+        let mut calls = 0;
+        while !map.defrag(7) {
+            calls += 1;
+            map.assert_valid();
+            assert!(Iterator::eq(map.iter(), baseline.iter()));
+        }
+        assert!(calls > 1);
+        assert_eq!(map.tree.nodes.capacity(), map.len());
+        assert!(map.defrag(7));
+
+        // Moved nodes are persisted with their links.
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+        let map = TreeMap::<u32, u32>::try_from_slice(&serialized).unwrap();
+        map.assert_valid();
+        assert!(Iterator::eq(map.iter(), baseline.iter()));
+    }
+
+    #[test]
+    fn serialize_json() {
+        let mut map = TreeMap::new(b"b");
+        assert_eq!(serde_json::to_string(&map).unwrap(), "{}");
+        for k in (0u32..20).rev() {
+            map.insert(k, k.to_string());
+        }
+        let json = serde_json::to_string(&map).unwrap();
+        assert!(json.starts_with(r#"{"0":"0","1":"1","2":"2","#));
+        let all: BTreeMap<String, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(all.len(), 20);
+
+        assert_eq!(map.to_json_page(Some(&18), 5), serde_json::json!({ "18": "18", "19": "19" }));
+        assert_eq!(map.to_json_page(Some(&3), 2), serde_json::json!({ "3": "3", "4": "4" }));
+        assert_eq!(map.to_json_page(Some(&20), 2), serde_json::json!({}));
+        assert_eq!(map.to_json_page(None, 0), serde_json::json!({}));
+    }
+
+    #[test]
+    fn nodes_cached() {
+        let mut map = TreeMap::new(b"b");
+        for k in 0u32..100 {
+            map.insert(k, k);
+        }
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+
+        // Only the first traversal loads the nodes on the path from storage, measured against
+        // the gas of reading the used gas itself.
+        let map = TreeMap::<u32, u32>::try_from_slice(&serialized).unwrap();
+        let gas_of = |f: &dyn Fn()| {
+            let gas = crate::env::used_gas();
+            f();
+            crate::env::used_gas() - gas
+        };
+        let base = gas_of(&|| {});
+        assert!(gas_of(&|| assert_eq!(map.floor_key(&50), Some(&50))) > base);
+        assert_eq!(gas_of(&|| assert_eq!(map.floor_key(&50), Some(&50))), base);
+        assert_eq!(gas_of(&|| assert_eq!(map.ceil_key(&50), Some(&50))), base);
+    }
+
+    #[derive(Arbitrary, Debug)]
+    enum Op {
+        Insert(u8, u8),
+        Remove(u8),
+        Flush,
+        Restore,
+        Get(u8),
+    }
+
+    #[test]
+    fn arbitrary() {
+        setup_free();
+
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut buf = vec![0; 4096];
+        for _ in 0..512 {
+            // Clear storage in-between runs
+            crate::mock::with_mocked_blockchain(|b| b.take_storage());
+            rng.fill_bytes(&mut buf);
+
+            let mut tm = TreeMap::new(b"l");
+            let mut bm = BTreeMap::new();
+            let mut max_len = 0;
+            let u = Unstructured::new(&buf);
+            if let Ok(ops) = Vec::<Op>::arbitrary_take_rest(u) {
+                for op in ops {
+                    match op {
+                        Op::Insert(k, v) => {
+                            let r1 = tm.insert(k, v);
+                            let r2 = bm.insert(k, v);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Remove(k) => {
+                            let r1 = tm.remove(&k);
+                            let r2 = bm.remove(&k);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Flush => {
+                            tm.flush();
+                        }
+                        Op::Restore => {
+                            let serialized = tm.try_to_vec().unwrap();
+                            tm = TreeMap::deserialize(&mut serialized.as_slice()).unwrap();
+                        }
+                        Op::Get(k) => {
+                            let r1 = tm.get(&k);
+                            let r2 = bm.get(&k);
+                            assert_eq!(r1, r2)
+                        }
+                    }
+                    max_len = max_len.max(bm.len() as u32);
+                }
+            }
+            tm.assert_valid();
+            assert!(Iterator::eq(tm.iter(), bm.iter()));
+            // Slots of removed nodes are reused by later inserts.
+            assert_eq!(tm.tree.nodes.len(), tm.len());
+            assert!(tm.tree.nodes.capacity() <= max_len);
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::RangeBounds;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{tree_map, LookupMap, TreeMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, IntoStorageKey};
+
+/// Positions of the values of a key in a [`TreeMultiMap`], in the order they were inserted.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy)]
+struct Group {
+    /// Position of the first value.
+    head: u32,
+    /// Position after the last value.
+    tail: u32,
+}
+
+impl Group {
+    fn len(&self) -> u32 {
+        self.tail - self.head
+    }
+}
+
+/// A lazily loaded storage map which keeps its keys ordered, like [`TreeMap`], and can hold
+/// multiple values for each key.
+///
+/// The values of a key are kept in the order they were inserted, and [`remove_one`] removes the
+/// value which was inserted first, so that for example orders at the same price level are
+/// matched in the order they were placed.
+///
+/// The keys are stored in a [`TreeMap`] with the positions of their values, and each value is
+/// stored separately in a [`LookupMap`], so adding or removing a value does not load the other
+/// values of its key.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::TreeMultiMap;
+///
+/// // Orders keyed by price, with multiple orders per price level.
+/// let mut asks = TreeMultiMap::new(b"a");
+/// asks.insert(101u64, "alice.near".to_string());
+/// asks.insert(100, "bob.near".to_string());
+/// asks.insert(101, "carol.near".to_string());
+/// assert_eq!(asks.len(), 3);
+///
+/// let levels: Vec<(u64, Vec<String>)> =
+///     asks.iter().map(|(price, orders)| (*price, orders.cloned().collect())).collect();
+/// assert_eq!(levels[1], (101, vec!["alice.near".to_string(), "carol.near".to_string()]));
+///
+/// // Orders at the same price are removed in the order they were inserted.
+/// assert_eq!(asks.remove_one(&101), Some("alice.near".to_string()));
+/// assert_eq!(asks.remove_all(&101), ["carol.near".to_string()]);
+/// assert!(!asks.contains_key(&101));
+/// ```
+///
+/// [`remove_one`]: Self::remove_one
+pub struct TreeMultiMap<K, V, H = Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Number of values in the map.
+    len: u32,
+    groups: TreeMap<K, Group, H>,
+    values: LookupMap<(K, u32), V, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, V, H> BorshSerialize for TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.len, writer)?;
+        BorshSerialize::serialize(&self.groups, writer)?;
+        BorshSerialize::serialize(&self.values, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, V, H> BorshDeserialize for TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            len: BorshDeserialize::deserialize(buf)?,
+            groups: BorshDeserialize::deserialize(buf)?,
+            values: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    TreeMultiMap<K, V, H> {
+        len: u32,
+        groups: TreeMap<K, Group, H>,
+        values: LookupMap<(K, u32), V, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<K, V, H> fmt::Debug for TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeMultiMap")
+            .field("len", &self.len)
+            .field("groups", &self.groups)
+            .field("values", &self.values)
+            .finish()
+    }
+}
+
+impl<K, V> TreeMultiMap<K, V, Sha256>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+{
+    /// Create new map with zero elements. Use `prefix` as a unique identifier.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<K, V, H> TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`TreeMultiMap`] with a custom hash function.
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut groups_key = prefix.into_storage_key();
+        let values_key = [groups_key.as_slice(), b"v"].concat();
+        groups_key.push(b'g');
+        Self {
+            len: 0,
+            groups: TreeMap::with_hasher(groups_key),
+            values: LookupMap::with_hasher(values_key),
+        }
+    }
+
+    /// Returns the number of values in the map, counting every value of each key.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the map contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of distinct keys in the map.
+    pub fn keys_len(&self) -> u32 {
+        self.groups.len()
+    }
+
+    /// Flushes the intermediate values of the map before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.groups.flush();
+        self.values.flush();
+    }
+}
+
+impl<K, V, H> TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns `true` if the map contains any value for the key.
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        self.groups.contains_key(k)
+    }
+
+    /// Returns the number of values of the key.
+    pub fn count<Q: ?Sized>(&self, k: &Q) -> u32
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        self.groups.get(k).map_or(0, Group::len)
+    }
+
+    /// Returns an iterator over the values of the key, in the order they were inserted.
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Values<K, V, H>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let group = self.groups.get(k).copied().unwrap_or(Group { head: 0, tail: 0 });
+        Values { key: k.to_owned(), next: group.head, tail: group.tail, values: &self.values }
+    }
+
+    /// Adds a value to the key, after any values the key already has.
+    pub fn insert(&mut self, k: K, v: V) {
+        let group = match self.groups.get_mut(&k) {
+            Some(group) => group,
+            None => {
+                self.groups.insert(k.clone(), Group { head: 0, tail: 0 });
+                self.groups.get_mut(&k).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+            }
+        };
+        let at = group.tail;
+        group.tail += 1;
+        self.values.set((k, at), Some(v));
+        self.len += 1;
+    }
+
+    /// Removes the value of the key which was inserted first, and returns it. Returns [`None`]
+    /// if the key has no values.
+    pub fn remove_one<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        let group = self.groups.get_mut(k)?;
+        let at = group.head;
+        group.head += 1;
+        if group.len() == 0 {
+            self.groups.remove(k);
+        }
+        self.len -= 1;
+        let value = self
+            .values
+            .remove(&(k.to_owned(), at))
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        Some(value)
+    }
+
+    /// Removes all values of the key, and returns them in the order they were inserted.
+    pub fn remove_all<Q: ?Sized>(&mut self, k: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K> + Ord,
+    {
+        let (key, group) = match self.groups.remove_entry(k) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        self.len -= group.len();
+        let mut key = (key, 0);
+        (group.head..group.tail)
+            .map(|at| {
+                key.1 = at;
+                self.values.remove(&key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE))
+            })
+            .collect()
+    }
+
+    /// An iterator visiting the keys in ascending order, with an iterator over the values of
+    /// each key in the order they were inserted.
+    pub fn iter(&self) -> Iter<K, V, H> {
+        Iter { groups: self.groups.range::<K, _>(..), values: &self.values }
+    }
+
+    /// An iterator over the keys within `range` in ascending order, with an iterator over the
+    /// values of each key in the order they were inserted. Only the keys within the range are
+    /// loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the start and end are
+    /// equal and both excluded.
+    pub fn range<R>(&self, range: R) -> Iter<K, V, H>
+    where
+        R: RangeBounds<K>,
+    {
+        Iter { groups: self.groups.range(range), values: &self.values }
+    }
+}
+
+impl<K, V, H> Extend<(K, V)> for TreeMultiMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// An iterator over the keys of a [`TreeMultiMap`] in ascending order, with the values of each
+/// key.
+///
+/// This `struct` is created by the `iter` and `range` methods on [`TreeMultiMap`].
+pub struct Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    groups: tree_map::Range<'a, K, Group, H>,
+    values: &'a LookupMap<(K, u32), V, H>,
+}
+
+impl<'a, K, V, H> Iterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, Values<'a, K, V, H>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, group) = self.groups.next()?;
+        let values =
+            Values { key: key.clone(), next: group.head, tail: group.tail, values: self.values };
+        Some((key, values))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.groups.size_hint()
+    }
+}
+
+impl<'a, K, V, H> DoubleEndedIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (key, group) = self.groups.next_back()?;
+        let values =
+            Values { key: key.clone(), next: group.head, tail: group.tail, values: self.values };
+        Some((key, values))
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for Iter<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+/// An iterator over the values of a key of a [`TreeMultiMap`], in the order they were inserted.
+/// The values are loaded as they are iterated.
+///
+/// This `struct` is created by the `get` method on [`TreeMultiMap`], and by its iterators.
+pub struct Values<'a, K, V, H>
+where
+    K: BorshSerialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    key: K,
+    next: u32,
+    tail: u32,
+    values: &'a LookupMap<(K, u32), V, H>,
+}
+
+impl<'a, K, V, H> Iterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.tail {
+            return None;
+        }
+        let value = self
+            .values
+            .get(&(self.key.clone(), self.next))
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+        self.next += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.tail - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for Values<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::TreeMultiMap;
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use std::collections::{BTreeMap, VecDeque};
+
+    #[test]
+    fn basic_functionality() {
+        let mut map = TreeMultiMap::new(b"m");
+        let mut baseline: BTreeMap<u8, VecDeque<u32>> = BTreeMap::new();
+        for v in 0u32..100 {
+            let k = (v * 37 % 10) as u8;
+            map.insert(k, v);
+            baseline.entry(k).or_default().push_back(v);
+        }
+        for k in [3u8, 3, 5, 7, 3] {
+            assert_eq!(map.remove_one(&k), baseline.get_mut(&k).unwrap().pop_front());
+        }
+        assert_eq!(map.remove_all(&8), Vec::from(baseline.remove(&8).unwrap()));
+        assert_eq!(map.remove_all(&8), Vec::<u32>::new());
+        assert_eq!(map.remove_one(&8), None);
+
+        let serialized = map.try_to_vec().unwrap();
+        drop(map);
+        let map = TreeMultiMap::<u8, u32>::try_from_slice(&serialized).unwrap();
+        assert_eq!(map.len(), 85);
+        assert_eq!(map.keys_len(), 9);
+        assert_eq!(map.count(&3), 7);
+        assert!(!map.contains_key(&8));
+        assert!(Iterator::eq(map.get(&5), baseline[&5].iter()));
+        assert_eq!(map.get(&8).len(), 0);
+
+        for ((k, values), (bk, bvalues)) in map.iter().zip(baseline.iter()) {
+            assert_eq!(k, bk);
+            assert!(Iterator::eq(values, bvalues.iter()));
+        }
+        let keys: Vec<_> = map.range(2..6).rev().map(|(k, _)| *k).collect();
+        assert_eq!(keys, [5, 4, 3, 2]);
+    }
+
+    #[test]
+    fn remove_all_values() {
+        let mut map = TreeMultiMap::new(b"m");
+        map.insert("a".to_string(), 1u8);
+        map.insert("a".to_string(), 2);
+        assert_eq!(map.remove_one("a"), Some(1));
+        assert_eq!(map.remove_one("a"), Some(2));
+        assert_eq!(map.remove_one("a"), None);
+        assert!(map.is_empty());
+
+        // A key which lost all its values can be inserted again, and removing it leaves no values
+        // in storage, only the freed slot of its tree node.
+        map.insert("a".to_string(), 3);
+        assert_eq!(map.get("a").collect::<Vec<_>>(), [&3]);
+        map.remove_all("a");
+        map.flush();
+        let storage = crate::mock::with_mocked_blockchain(|m| m.take_storage());
+        assert!(storage.keys().all(|k| k.starts_with(b"mg")));
+    }
+}
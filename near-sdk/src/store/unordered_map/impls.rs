@@ -3,6 +3,8 @@ use std::borrow::Borrow;
 use borsh::{BorshDeserialize, BorshSerialize};
 
 use super::{UnorderedMap, ERR_NOT_EXIST};
+use crate::store::free_list::FreeListIndex;
+use crate::store::pagination::{PageToken, Paginated};
 use crate::{crypto_hash::CryptoHasher, env};
 
 impl<K, V, H> Extend<(K, V)> for UnorderedMap<K, V, H>
@@ -39,3 +41,31 @@ where
         self.get(index).unwrap_or_else(|| env::panic_str(ERR_NOT_EXIST))
     }
 }
+
+impl<'a, K, V, H> Paginated for &'a UnorderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (&'a K, &'a V);
+    type Token = PageToken<K>;
+
+    /// Returns up to `limit` entries in the order of [`UnorderedMap::iter`]. If `limit` is 0, no
+    /// entries are returned and `from` is returned unchanged.
+    fn page(
+        self,
+        from: Option<PageToken<K>>,
+        limit: usize,
+    ) -> (Vec<Self::Item>, Option<PageToken<K>>) {
+        if limit == 0 {
+            return (Vec::new(), from);
+        }
+        let start = from.map_or(0, |token| match self.keys.get(FreeListIndex(token.index)) {
+            Some(key) if *key == token.key => token.index + 1,
+            // The key was moved or removed.
+            _ => self.values.get(&token.key).map_or(token.index, |v| v.key_index.0) + 1,
+        });
+        self.page_at(start, limit)
+    }
+}
@@ -120,6 +120,74 @@ where
     }
 }
 
+/// An iterator over copies of the elements of a [`UnorderedMap`], which are not cached.
+///
+/// This `struct` is created by the `iter_uncached` method on [`UnorderedMap`].
+pub struct IterUncached<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Keys iterator which skips the empty cells.
+    keys: free_list::IterUncached<'a, K>,
+    /// Reference to underlying map to lookup values with `keys`.
+    values: &'a LookupMap<K, ValueAndIndex<V>, H>,
+}
+
+impl<'a, K, V, H> IterUncached<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    pub(super) fn new(map: &'a UnorderedMap<K, V, H>) -> Self {
+        Self { keys: map.keys.iter_uncached(), values: &map.values }
+    }
+}
+
+impl<'a, K, V, H> Iterator for IterUncached<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = self.keys.next()?;
+        let entry = self
+            .values
+            .get_uncached(&key)
+            .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+
+        Some((key, entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.keys.count()
+    }
+}
+
+impl<'a, K, V, H> ExactSizeIterator for IterUncached<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+impl<'a, K, V, H> FusedIterator for IterUncached<'a, K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+}
+
 /// A mutable iterator over elements of a [`UnorderedMap`].
 ///
 /// This `struct` is created by the `iter_mut` method on [`UnorderedMap`].
@@ -471,6 +539,20 @@ where
     }
 }
 
+impl<'a, K, V, H> Drop for Drain<'a, K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    V: BorshSerialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn drop(&mut self) {
+        // Remove the values of any keys that were not yielded by the iterator.
+        for key in self.keys.by_ref() {
+            self.values.set(key, None);
+        }
+    }
+}
+
 impl<'a, K, V, H> Iterator for Drain<'a, K, V, H>
 where
     K: BorshSerialize + BorshDeserialize + Ord + Clone,
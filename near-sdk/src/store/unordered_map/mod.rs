@@ -6,15 +6,18 @@ use std::borrow::Borrow;
 use std::{fmt, mem};
 
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde-collections")]
+use serde::{Serialize, Serializer};
 
 use crate::crypto_hash::{CryptoHasher, Sha256};
-use crate::{env, IntoStorageKey};
+use crate::{env, Gas, IntoStorageKey};
 
 pub use entry::{Entry, OccupiedEntry, VacantEntry};
 
-pub use self::iter::{Drain, Iter, IterMut, Keys, Values, ValuesMut};
+pub use self::iter::{Drain, Iter, IterMut, IterUncached, Keys, Values, ValuesMut};
 use super::free_list::FreeListIndex;
-use super::{FreeList, LookupMap, ERR_INCONSISTENT_STATE};
+use super::pagination::PageToken;
+use super::{CloneTo, FreeList, LookupMap, ERR_INCONSISTENT_STATE};
 
 const ERR_NOT_EXIST: &str = "Key does not exist in map";
 
@@ -87,7 +90,7 @@ where
     values: LookupMap<K, ValueAndIndex<V>, H>,
 }
 
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Clone)]
 struct ValueAndIndex<V> {
     value: V,
     key_index: FreeListIndex,
@@ -125,6 +128,17 @@ where
     }
 }
 
+impl_borsh_schema! {
+    UnorderedMap<K, V, H> {
+        keys: FreeList<K>,
+        values: LookupMap<K, ValueAndIndex<V>, H>,
+    }
+    where
+        K: BorshSerialize + Ord,
+        V: BorshSerialize,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
 impl<K, V, H> Drop for UnorderedMap<K, V, H>
 where
     K: BorshSerialize + Ord,
@@ -150,6 +164,23 @@ where
     }
 }
 
+/// Serializes the entries of the map as a map in the order of [`UnorderedMap::iter`], which loads
+/// the whole map from storage, so this should only be used for small maps.
+#[cfg(feature = "serde-collections")]
+impl<K, V, H> Serialize for UnorderedMap<K, V, H>
+where
+    K: BorshSerialize + Ord + BorshDeserialize + Clone + Serialize,
+    V: BorshSerialize + BorshDeserialize + Serialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
 impl<K, V> UnorderedMap<K, V, Sha256>
 where
     K: BorshSerialize + Ord,
@@ -237,6 +268,33 @@ where
         Iter::new(self)
     }
 
+    /// An iterator visiting all key-value pairs in arbitrary order, yielding copies of the keys
+    /// and values which are read from storage without being cached. The memory used by
+    /// [`iter`](Self::iter) grows with every entry it loads, so this is meant for iterating over
+    /// large maps in a single call. Entries which are already cached, including modified entries
+    /// which are not flushed yet, are cloned from the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// map.insert("a".to_string(), 1u32);
+    /// map.insert("b".to_string(), 2);
+    /// map.flush();
+    ///
+    /// let total: u32 = map.iter_uncached().map(|(_, val)| val).sum();
+    /// assert_eq!(total, 3);
+    /// ```
+    pub fn iter_uncached(&self) -> IterUncached<K, V, H>
+    where
+        K: BorshDeserialize + Clone,
+        V: Clone,
+    {
+        IterUncached::new(self)
+    }
+
     /// An iterator visiting all key-value pairs in arbitrary order,
     /// with exclusive references to the values.
     /// The iterator element type is `(&'a K, &'a mut V)`.
@@ -510,6 +568,226 @@ where
     {
         Entry::new(self.values.entry(key), &mut self.keys)
     }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)` returns `false`.
+    /// The keys of the removed pairs are compacted afterwards, which moves the remaining keys
+    /// into the freed slots so that storage does not keep growing with removed entries. The
+    /// remaining keys keep their iteration order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// map.extend((0..8).map(|x| (x, x * 10)));
+    ///
+    /// map.retain(|&k, _| k % 2 == 0);
+    /// assert_eq!(map.len(), 4);
+    /// assert_eq!(map.get(&2), Some(&20));
+    /// assert_eq!(map.get(&3), None);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        K: BorshDeserialize + Clone,
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let values = &mut self.values;
+        self.keys.retain(|key| {
+            let entry =
+                values.get_mut(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            if f(key, &mut entry.value) {
+                true
+            } else {
+                // Value is already loaded, set to avoid the extra return value.
+                values.set(key.clone(), None);
+                false
+            }
+        });
+        self.keys.compact(u32::MAX, |key, _, new_index| {
+            let entry =
+                values.get_mut(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            entry.key_index = new_index;
+        });
+    }
+
+    /// Moves up to `max_entries` keys towards the front of the map into the positions left empty
+    /// by removed keys, and removes the empty positions at the back, to reduce the storage used
+    /// by the map after many removals. Returns `true` if the keys are stored without gaps,
+    /// otherwise the compaction can be continued in later calls, to bound the gas used per call.
+    ///
+    /// The keys keep their iteration order, so every key after the first empty position is
+    /// moved. The positions of removed keys are otherwise only reused by later insertions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// map.extend((0u32..100).map(|k| (k, k)));
+    /// for k in 0..90 {
+    ///     map.remove(&k);
+    /// }
+    ///
+    /// // Continue the compaction over multiple calls, moving at most 4 keys per call.
+    /// while !map.defrag(4) {}
+    /// assert_eq!(map.len(), 10);
+    /// assert_eq!(map.to_page(0, 10).len(), 10);
+    /// ```
+    pub fn defrag(&mut self, max_entries: u32) -> bool
+    where
+        K: BorshDeserialize + Clone,
+    {
+        let values = &mut self.values;
+        self.keys.compact(max_entries, |key, _, new_index| {
+            let entry =
+                values.get_mut(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            entry.key_index = new_index;
+        })
+    }
+}
+
+impl<K, V, H> UnorderedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns up to `limit` entries stored at or after position `from_index`, and the token of
+    /// the next page if there are entries after them.
+    fn page_at(&self, from_index: u32, limit: usize) -> (Vec<(&K, &V)>, Option<PageToken<K>>) {
+        let mut items = Vec::new();
+        let mut last = from_index;
+        for index in from_index..self.keys.capacity() {
+            let key = match self.keys.get(FreeListIndex(index)) {
+                Some(key) => key,
+                None => continue,
+            };
+            if items.len() == limit {
+                let token = items
+                    .last()
+                    .map(|&(key, _): &(&K, _)| PageToken { key: key.clone(), index: last });
+                return (items, token);
+            }
+            let value =
+                self.values.get(key).unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+            items.push((key, &value.value));
+            last = index;
+        }
+        (items, None)
+    }
+}
+
+impl<K, V, H> UnorderedMap<K, V, H>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Returns up to `limit` entries stored at or after position `from_index` of the map, in
+    /// the order they are stored. Only the entries of the page are read, starting directly at
+    /// `from_index` instead of iterating over the entries before it.
+    ///
+    /// The positions of removed entries are reused by later insertions and are skipped, so after
+    /// removals a page can start at a position which is not a multiple of `limit`. Use
+    /// [`to_page_after`](Self::to_page_after) or [`Paginated::page`](crate::store::Paginated::page)
+    /// to continue from the last key of a page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// for k in 0u8..10 {
+    ///     map.insert(k, k * 10);
+    /// }
+    /// assert_eq!(map.to_page(4, 3), [(4, 40), (5, 50), (6, 60)]);
+    /// assert_eq!(map.to_page(8, 3), [(8, 80), (9, 90)]);
+    /// ```
+    pub fn to_page(&self, from_index: u32, limit: u32) -> Vec<(K, V)> {
+        let (page, _) = self.page_at(from_index, limit as usize);
+        page.into_iter().map(|(key, value)| (key.clone(), value.clone())).collect()
+    }
+
+    /// Returns up to `limit` entries stored after the entry of `key`, in the order they are
+    /// stored, or [`None`] if `key` is not in the map. Passing the last key of a page returns the
+    /// next page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// for k in 0u8..10 {
+    ///     map.insert(k, k * 10);
+    /// }
+    /// map.remove(&4);
+    ///
+    /// let page = map.to_page(0, 4);
+    /// assert_eq!(page, [(0, 0), (1, 10), (2, 20), (3, 30)]);
+    /// let (last, _) = page.last().unwrap();
+    /// assert_eq!(map.to_page_after(last, 2), Some(vec![(5, 50), (6, 60)]));
+    /// assert_eq!(map.to_page_after(&4, 2), None);
+    /// ```
+    pub fn to_page_after<Q: ?Sized>(&self, key: &Q, limit: u32) -> Option<Vec<(K, V)>>
+    where
+        K: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = K>,
+    {
+        let from_index = self.values.get(key)?.key_index.0 + 1;
+        Some(self.to_page(from_index, limit))
+    }
+
+    /// Starts a copy of this map into a new map under the given storage `prefix`. Entries
+    /// are copied by calling [`CloneTo::resume`], which can be continued over multiple function
+    /// calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedMap;
+    /// use near_sdk::Gas;
+    ///
+    /// let mut map = UnorderedMap::new(b"m");
+    /// map.insert("a".to_string(), 1u8);
+    ///
+    /// let mut copy = map.clone_to(b"c");
+    /// assert!(copy.resume(&map, Gas::ONE_TERA * 100));
+    /// assert_eq!(copy.into_inner().get("a"), Some(&1));
+    /// ```
+    pub fn clone_to<S>(&self, prefix: S) -> CloneTo<UnorderedMap<K, V, H>>
+    where
+        S: IntoStorageKey,
+    {
+        CloneTo::new(UnorderedMap::with_hasher(prefix))
+    }
+}
+
+impl<K, V, H> CloneTo<UnorderedMap<K, V, H>>
+where
+    K: BorshSerialize + BorshDeserialize + Ord + Clone,
+    V: BorshSerialize + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Continues copying entries from `source` until either all entries are copied or
+    /// [`env::used_gas`] exceeds `gas_limit`. At least one entry is copied on every call.
+    /// Returns `true` if the copy is complete.
+    pub fn resume(&mut self, source: &UnorderedMap<K, V, H>, gas_limit: Gas) -> bool {
+        self.copy_until(source.keys.capacity(), gas_limit, |target, i| {
+            if let Some(key) = source.keys.get(FreeListIndex(i)) {
+                let entry = source
+                    .values
+                    .get(key)
+                    .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+                target.insert(key.clone(), entry.value.clone());
+            }
+        })
+    }
 }
 
 impl<K, V, H> UnorderedMap<K, V, H>
@@ -553,6 +831,24 @@ mod tests {
         assert_eq!(map.len(), 0);
     }
 
+    #[test]
+    fn iter_uncached() {
+        let mut map = UnorderedMap::new(b"m");
+        map.extend((0u32..10).map(|k| (k, k * 10)));
+        map.flush();
+        let mut map = UnorderedMap::<u32, u32>::try_from_slice(&map.try_to_vec().unwrap()).unwrap();
+
+        // Changes which are not flushed are read from the cache.
+        map.remove(&4);
+        map.insert(7, 0);
+        map.insert(10, 100);
+        let entries: Vec<_> = map.iter_uncached().collect();
+        assert_eq!(entries.len(), map.iter_uncached().len());
+        assert_eq!(entries, map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+        assert_eq!(entries[7], (7, 0));
+        assert_eq!(entries.len(), 10);
+    }
+
     #[test]
     fn entry_api() {
         let mut map = UnorderedMap::new(b"b");
@@ -600,6 +896,72 @@ mod tests {
         assert_eq!(map.keys().collect::<Vec<_>>(), [&0, &2, &3]);
     }
 
+    #[test]
+    fn clone_to() {
+        let mut map = UnorderedMap::new(b"b");
+        map.extend((0u8..10).map(|k| (k, k)));
+        map.remove(&3);
+
+        let mut copy = map.clone_to(b"c");
+        while !copy.resume(&map, crate::Gas(0)) {}
+        let shadow = copy.into_inner();
+        assert_eq!(shadow.len(), 9);
+        assert!(Iterator::eq(shadow.iter(), map.iter()));
+    }
+
+    #[test]
+    fn to_page() {
+        let mut map = UnorderedMap::new(b"b");
+        map.extend((0u8..20).map(|k| (k, k)));
+        for k in [3, 7, 8, 19] {
+            map.remove(&k);
+        }
+
+        let mut paged = map.to_page(0, 5);
+        while let Some((last, _)) = paged.last() {
+            let page = map.to_page_after(last, 5).unwrap();
+            if page.is_empty() {
+                break;
+            }
+            paged.extend(page);
+        }
+        assert_eq!(paged, map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+        assert!(map.to_page(20, 5).is_empty());
+        assert_eq!(map.to_page_after(&3, 5), None);
+    }
+
+    #[test]
+    fn partial_drain() {
+        let mut map = UnorderedMap::new(b"b");
+        map.extend((0u8..10).map(|k| (k, k)));
+
+        assert_eq!(map.drain().take(3).count(), 3);
+        assert!(map.is_empty());
+        for k in 0..10 {
+            assert!(!map.contains_key(&k));
+        }
+    }
+
+    #[test]
+    fn retain() {
+        let mut map = UnorderedMap::new(b"b");
+        map.extend((0u8..10).map(|k| (k, k)));
+
+        map.retain(|k, v| {
+            *v += 1;
+            k % 3 != 0
+        });
+        assert_eq!(map.len(), 6);
+        for k in 0..10 {
+            assert_eq!(map.get(&k).copied(), if k % 3 != 0 { Some(k + 1) } else { None });
+        }
+
+        // Moved keys must still be removable through their updated index.
+        assert_eq!(map.remove(&8), Some(9));
+        assert_eq!(map.remove(&1), Some(2));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [(&2, &3), (&4, &5), (&5, &6), (&7, &8)]);
+    }
+
     #[derive(Arbitrary, Debug)]
     enum Op {
         Insert(u8, u8),
@@ -609,6 +971,40 @@ mod tests {
         Get(u8),
     }
 
+    #[test]
+    fn defrag() {
+        let mut map = UnorderedMap::new(b"b");
+        let mut baseline = HashMap::new();
+        for k in 0u32..200 {
+            map.insert(k, k * 2);
+            baseline.insert(k, k * 2);
+        }
+        for k in (0u32..200).map(|k| (k * 73) % 200).filter(|k| k % 5 != 0) {
+            map.remove(&k);
+            baseline.remove(&k);
+        }
+        assert!(map.keys.capacity() > map.len());
+        let order: Vec<u32> = map.keys().copied().collect();
+
+        let mut calls = 0;
+        while !map.defrag(7) {
+            calls += 1;
+            assert_eq!(map.iter().map(|(k, v)| (*k, *v)).collect::<HashMap<_, _>>(), baseline);
+            assert!(map.keys().copied().eq(order.iter().copied()));
+        }
+        assert!(calls > 1);
+        assert_eq!(map.keys.capacity(), map.len());
+        assert!(map.defrag(7));
+
+        // Moved keys are persisted with their new positions.
+        map.flush();
+        let mut map = UnorderedMap::<u32, u32>::try_from_slice(&map.try_to_vec().unwrap()).unwrap();
+        for (k, v) in &baseline {
+            assert_eq!(map.remove(k), Some(*v));
+        }
+        assert!(map.is_empty());
+    }
+
     #[test]
     fn arbitrary() {
         setup_free();
@@ -653,4 +1049,16 @@ mod tests {
             }
         }
     }
+
+    #[cfg(feature = "serde-collections")]
+    #[test]
+    fn serialize_json() {
+        let mut map = UnorderedMap::new(b"m");
+        assert_eq!(serde_json::to_string(&map).unwrap(), "{}");
+        map.insert("a".to_string(), 1u8);
+        map.insert("b".to_string(), 2);
+        map.insert("c".to_string(), 3);
+        map.remove("a");
+        assert_eq!(serde_json::to_value(&map).unwrap(), serde_json::json!({ "b": 2, "c": 3 }));
+    }
 }
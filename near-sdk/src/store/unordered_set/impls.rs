@@ -0,0 +1,48 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::UnorderedSet;
+use crate::crypto_hash::CryptoHasher;
+use crate::store::free_list::FreeListIndex;
+use crate::store::pagination::{PageToken, Paginated};
+
+impl<T, H> Extend<T> for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T, H> Paginated for &'a UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord + BorshDeserialize + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    type Item = &'a T;
+    type Token = PageToken<T>;
+
+    /// Returns up to `limit` elements in the order of [`UnorderedSet::iter`]. If `limit` is 0, no
+    /// elements are returned and `from` is returned unchanged.
+    fn page(
+        self,
+        from: Option<PageToken<T>>,
+        limit: usize,
+    ) -> (Vec<Self::Item>, Option<PageToken<T>>) {
+        if limit == 0 {
+            return (Vec::new(), from);
+        }
+        let start = from.map_or(0, |token| match self.elements.get(FreeListIndex(token.index)) {
+            Some(element) if *element == token.key => token.index + 1,
+            // The element was moved or removed.
+            _ => self.index.get(&token.key).map_or(token.index, |i| i.0) + 1,
+        });
+        self.page_at(start, limit)
+    }
+}
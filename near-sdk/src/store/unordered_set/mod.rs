@@ -0,0 +1,948 @@
+mod impls;
+mod iter;
+
+pub use self::iter::{Difference, Drain, Intersection, Iter, SymmetricDifference, Union};
+use super::free_list::FreeListIndex;
+use super::pagination::PageToken;
+use super::{CloneTo, FreeList, LookupMap, ERR_INCONSISTENT_STATE};
+use crate::crypto_hash::{CryptoHasher, Sha256};
+use crate::{env, Gas, IntoStorageKey};
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde-collections")]
+use serde::{Serialize, Serializer};
+use std::borrow::Borrow;
+use std::fmt;
+
+/// A lazily loaded storage set that stores its content directly on the storage trie.
+/// This structure is similar to [`near_sdk::store::LookupSet`](crate::store::LookupSet), except
+/// that it stores the values so that [`UnorderedSet`] can be iterable.
+///
+/// This set stores the index of each value under a hash of the set's `prefix` and
+/// [`BorshSerialize`] of the value using the set's [`CryptoHasher`] implementation.
+///
+/// The default hash function for [`UnorderedSet`] is [`Sha256`] which uses a syscall
+/// (or host function) built into the NEAR runtime to hash the value. To use a custom function,
+/// use [`with_hasher`]. Alternative builtin hash functions can be found at
+/// [`near_sdk::crypto_hash`](crate::crypto_hash).
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::UnorderedSet;
+///
+/// // Initializes a set, the generic types can be inferred to `UnorderedSet<String, Sha256>`
+/// // The `b"a"` parameter is a prefix for the storage keys of this data structure.
+/// let mut set = UnorderedSet::new(b"a");
+///
+/// assert!(set.insert("test".to_string()));
+/// assert!(set.contains("test"));
+/// assert!(set.remove("test"));
+/// assert!(set.is_empty());
+/// ```
+///
+/// [`with_hasher`]: Self::with_hasher
+pub struct UnorderedSet<T, H = Sha256>
+where
+    T: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    elements: FreeList<T>,
+    index: LookupMap<T, FreeListIndex, H>,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<T, H> BorshSerialize for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.elements, writer)?;
+        BorshSerialize::serialize(&self.index, writer)?;
+        Ok(())
+    }
+}
+
+impl<T, H> BorshDeserialize for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            elements: BorshDeserialize::deserialize(buf)?,
+            index: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl_borsh_schema! {
+    UnorderedSet<T, H> {
+        elements: FreeList<T>,
+        index: LookupMap<T, FreeListIndex, H>,
+    }
+    where
+        T: BorshSerialize + Ord,
+        H: CryptoHasher<Digest = [u8; 32]>,
+}
+
+impl<T, H> Drop for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+impl<T, H> fmt::Debug for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord + BorshDeserialize + fmt::Debug,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnorderedSet")
+            .field("elements", &self.elements)
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// Serializes the elements of the set as a sequence in the order of [`UnorderedSet::iter`],
+/// which loads the whole set from storage, so this should only be used for small sets.
+#[cfg(feature = "serde-collections")]
+impl<T, H> Serialize for UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord + BorshDeserialize + Serialize,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<T> UnorderedSet<T, Sha256>
+where
+    T: BorshSerialize + Ord,
+{
+    #[inline]
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        Self::with_hasher(prefix)
+    }
+}
+
+impl<T, H> UnorderedSet<T, H>
+where
+    T: BorshSerialize + Ord,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Initialize a [`UnorderedSet`] with a custom hash function.
+    ///
+    /// # Example
+    /// ```
+    /// use near_sdk::crypto_hash::Keccak256;
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let set = UnorderedSet::<String, Keccak256>::with_hasher(b"m");
+    /// ```
+    pub fn with_hasher<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut vec_key = prefix.into_storage_key();
+        let map_key = [vec_key.as_slice(), b"m"].concat();
+        vec_key.push(b'v');
+        Self { elements: FreeList::new(vec_key), index: LookupMap::with_hasher(map_key) }
+    }
+
+    /// Returns the number of elements in the set.
+    pub fn len(&self) -> u32 {
+        self.elements.len()
+    }
+
+    /// Returns true if the set contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Clears the set, removing all values.
+    pub fn clear(&mut self)
+    where
+        T: BorshDeserialize + Clone,
+    {
+        for e in self.elements.drain() {
+            self.index.set(e, None);
+        }
+    }
+
+    /// An iterator visiting all elements in arbitrary order.
+    /// The iterator element type is `&'a T`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"m");
+    /// set.insert("a".to_string());
+    /// set.insert("b".to_string());
+    /// set.insert("c".to_string());
+    ///
+    /// for val in set.iter() {
+    ///     println!("val: {}", val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<T>
+    where
+        T: BorshDeserialize,
+    {
+        Iter::new(self)
+    }
+
+    /// Clears the set, returning all elements in an iterator.
+    ///
+    /// This will clear all values, even if only some are yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut a = UnorderedSet::new(b"m");
+    /// a.insert(1);
+    /// a.insert(2);
+    ///
+    /// for v in a.drain().take(1) {
+    ///     assert!(v == 1 || v == 2);
+    /// }
+    ///
+    /// assert!(a.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<T, H>
+    where
+        T: BorshDeserialize,
+    {
+        Drain::new(self)
+    }
+
+    /// Returns `true` if the set contains the specified value.
+    ///
+    /// The value may be any borrowed form of the set's value type, but
+    /// [`BorshSerialize`], [`ToOwned<Owned = T>`](ToOwned) and [`Ord`] on the borrowed form *must*
+    /// match those for the value type.
+    pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = T> + Ord,
+    {
+        self.index.contains_key(value)
+    }
+
+    /// Returns `true` if the set contains all of the specified values.
+    ///
+    /// This short-circuits on the first value that is not in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"m");
+    /// set.extend([1u32, 2, 3]);
+    ///
+    /// assert!(set.contains_all(&[1, 3]));
+    /// assert!(!set.contains_all(&[1, 4]));
+    /// ```
+    pub fn contains_all<'a, I>(&self, values: I) -> bool
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: Clone + 'a,
+    {
+        values.into_iter().all(|value| self.index.contains_key(value))
+    }
+
+    /// Returns `true` if the set contains any of the specified values.
+    ///
+    /// This short-circuits on the first value that is in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"m");
+    /// set.extend([1u32, 2, 3]);
+    ///
+    /// assert!(set.contains_any(&[4, 3]));
+    /// assert!(!set.contains_any(&[4, 5]));
+    /// ```
+    pub fn contains_any<'a, I>(&self, values: I) -> bool
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: Clone + 'a,
+    {
+        values.into_iter().any(|value| self.index.contains_key(value))
+    }
+
+    /// Adds a value to the set.
+    ///
+    /// If the set did not have this value present, true is returned.
+    ///
+    /// If the set did have this value present, false is returned.
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Clone + BorshDeserialize,
+    {
+        let entry = self.index.get_mut_inner(&value);
+        if entry.value().is_some() {
+            false
+        } else {
+            let element_index = self.elements.insert(value);
+            entry.replace(Some(element_index));
+            true
+        }
+    }
+
+    /// Removes a value from the set. Returns whether the value was present in the set.
+    ///
+    /// The value may be any borrowed form of the set's value type, but
+    /// [`BorshSerialize`], [`ToOwned<Owned = T>`](ToOwned) and [`Ord`] on the borrowed form *must*
+    /// match those for the value type.
+    pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q> + BorshDeserialize,
+        Q: BorshSerialize + ToOwned<Owned = T> + Ord,
+    {
+        match self.index.remove(value) {
+            Some(element_index) => {
+                self.elements
+                    .remove(element_index)
+                    .unwrap_or_else(|| env::panic_str(ERR_INCONSISTENT_STATE));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` such that `f(&e)` returns `false`.
+    /// The removed elements are compacted afterwards, which moves the remaining elements
+    /// into the freed slots so that storage does not keep growing with removed entries. The
+    /// remaining values keep their iteration order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"m");
+    /// set.extend(0..8);
+    ///
+    /// set.retain(|&v| v % 2 == 0);
+    /// assert_eq!(set.len(), 4);
+    /// assert!(set.contains(&2));
+    /// assert!(!set.contains(&3));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        T: BorshDeserialize + Clone,
+        F: FnMut(&T) -> bool,
+    {
+        let index = &mut self.index;
+        self.elements.retain(|value| {
+            if f(value) {
+                true
+            } else {
+                index.set(value.clone(), None);
+                false
+            }
+        });
+        self.elements.compact(u32::MAX, |value, _, new_index| {
+            index.insert(value.clone(), new_index);
+        });
+    }
+
+    /// Flushes the intermediate values of the set before this is called when the structure is
+    /// [`Drop`]ed. This will write all modified values to storage but keep all cached values
+    /// in memory.
+    pub fn flush(&mut self) {
+        self.elements.flush();
+        self.index.flush();
+    }
+}
+
+impl<T, H> UnorderedSet<T, H>
+where
+    T: BorshSerialize + BorshDeserialize + Ord + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Visits the values representing the difference, i.e., the values that are in `self` but
+    /// not in `other`. Every value of `self` is read, and looked up in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set1 = UnorderedSet::new(b"m");
+    /// set1.insert("a".to_string());
+    /// set1.insert("b".to_string());
+    /// set1.insert("c".to_string());
+    ///
+    /// let mut set2 = UnorderedSet::new(b"n");
+    /// set2.insert("b".to_string());
+    /// set2.insert("c".to_string());
+    /// set2.insert("d".to_string());
+    ///
+    /// // Can be seen as `set1 - set2`.
+    /// for x in set1.difference(&set2) {
+    ///     println!("{}", x); // Prints "a"
+    /// }
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a UnorderedSet<T, H>) -> Difference<'a, T, H> {
+        Difference::new(self, other)
+    }
+
+    /// Visits the values representing the symmetric difference, i.e., the values that are in
+    /// `self` or in `other` but not in both. Every value of both sets is read, and looked up in
+    /// the other set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set1 = UnorderedSet::new(b"m");
+    /// set1.insert("a".to_string());
+    /// set1.insert("b".to_string());
+    /// set1.insert("c".to_string());
+    ///
+    /// let mut set2 = UnorderedSet::new(b"n");
+    /// set2.insert("b".to_string());
+    /// set2.insert("c".to_string());
+    /// set2.insert("d".to_string());
+    ///
+    /// // Prints "a", "d" in arbitrary order.
+    /// for x in set1.symmetric_difference(&set2) {
+    ///     println!("{}", x);
+    /// }
+    /// ```
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a UnorderedSet<T, H>,
+    ) -> SymmetricDifference<'a, T, H> {
+        SymmetricDifference::new(self, other)
+    }
+
+    /// Visits the values representing the intersection, i.e., the values that are both in `self`
+    /// and `other`. The values of the smaller set are read, and looked up in the larger set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set1 = UnorderedSet::new(b"m");
+    /// set1.insert("a".to_string());
+    /// set1.insert("b".to_string());
+    /// set1.insert("c".to_string());
+    ///
+    /// let mut set2 = UnorderedSet::new(b"n");
+    /// set2.insert("b".to_string());
+    /// set2.insert("c".to_string());
+    /// set2.insert("d".to_string());
+    ///
+    /// // Prints "b", "c" in arbitrary order.
+    /// for x in set1.intersection(&set2) {
+    ///     println!("{}", x);
+    /// }
+    /// ```
+    pub fn intersection<'a>(&'a self, other: &'a UnorderedSet<T, H>) -> Intersection<'a, T, H> {
+        if self.len() <= other.len() {
+            Intersection::new(self, other)
+        } else {
+            Intersection::new(other, self)
+        }
+    }
+
+    /// Visits the values representing the union, i.e., all the values in `self` or `other`,
+    /// without duplicates. The values of the larger set are read first, followed by the values
+    /// of the smaller set which are not in the larger set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set1 = UnorderedSet::new(b"m");
+    /// set1.insert("a".to_string());
+    /// set1.insert("b".to_string());
+    /// set1.insert("c".to_string());
+    ///
+    /// let mut set2 = UnorderedSet::new(b"n");
+    /// set2.insert("b".to_string());
+    /// set2.insert("c".to_string());
+    /// set2.insert("d".to_string());
+    ///
+    /// // Prints "a", "b", "c", "d" in arbitrary order.
+    /// for x in set1.union(&set2) {
+    ///     println!("{}", x);
+    /// }
+    /// ```
+    pub fn union<'a>(&'a self, other: &'a UnorderedSet<T, H>) -> Union<'a, T, H> {
+        if self.len() >= other.len() {
+            Union::new(self, other)
+        } else {
+            Union::new(other, self)
+        }
+    }
+
+    /// Returns `true` if `self` has no elements in common with `other`. This is equivalent to
+    /// checking for an empty intersection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set1 = UnorderedSet::new(b"m");
+    /// set1.insert("a".to_string());
+    /// set1.insert("b".to_string());
+    /// set1.insert("c".to_string());
+    ///
+    /// let mut set2 = UnorderedSet::new(b"n");
+    ///
+    /// assert_eq!(set1.is_disjoint(&set2), true);
+    /// set2.insert("d".to_string());
+    /// assert_eq!(set1.is_disjoint(&set2), true);
+    /// set2.insert("a".to_string());
+    /// assert_eq!(set1.is_disjoint(&set2), false);
+    /// ```
+    pub fn is_disjoint(&self, other: &UnorderedSet<T, H>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Returns `true` if the set is a subset of another, i.e., `other` contains at least all the
+    /// values in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut sup = UnorderedSet::new(b"m");
+    /// sup.insert("a".to_string());
+    /// sup.insert("b".to_string());
+    /// sup.insert("c".to_string());
+    ///
+    /// let mut set = UnorderedSet::new(b"n");
+    ///
+    /// assert_eq!(set.is_subset(&sup), true);
+    /// set.insert("b".to_string());
+    /// assert_eq!(set.is_subset(&sup), true);
+    /// set.insert("d".to_string());
+    /// assert_eq!(set.is_subset(&sup), false);
+    /// ```
+    pub fn is_subset(&self, other: &UnorderedSet<T, H>) -> bool {
+        self.len() <= other.len() && self.iter().all(|v| other.contains(v))
+    }
+
+    /// Returns `true` if the set is a superset of another, i.e., `self` contains at least all the
+    /// values in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut sub = UnorderedSet::new(b"m");
+    /// sub.insert("a".to_string());
+    /// sub.insert("b".to_string());
+    ///
+    /// let mut set = UnorderedSet::new(b"n");
+    ///
+    /// assert_eq!(set.is_superset(&sub), false);
+    ///
+    /// set.insert("a".to_string());
+    /// set.insert("b".to_string());
+    /// assert_eq!(set.is_superset(&sub), true);
+    ///
+    /// set.insert("c".to_string());
+    /// assert_eq!(set.is_superset(&sub), true);
+    /// ```
+    pub fn is_superset(&self, other: &UnorderedSet<T, H>) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns up to `limit` elements stored at or after position `from_index`, and the token of
+    /// the next page if there are elements after them.
+    fn page_at(&self, from_index: u32, limit: usize) -> (Vec<&T>, Option<PageToken<T>>) {
+        let mut items = Vec::new();
+        let mut last = from_index;
+        for index in from_index..self.elements.capacity() {
+            let element = match self.elements.get(FreeListIndex(index)) {
+                Some(element) => element,
+                None => continue,
+            };
+            if items.len() == limit {
+                let token =
+                    items.last().map(|&key: &&T| PageToken { key: key.clone(), index: last });
+                return (items, token);
+            }
+            items.push(element);
+            last = index;
+        }
+        (items, None)
+    }
+
+    /// Returns up to `limit` elements stored at or after position `from_index` of the set, in
+    /// the order they are stored. Only the elements of the page are read, starting directly at
+    /// `from_index` instead of iterating over the elements before it.
+    ///
+    /// The positions of removed elements are reused by later insertions and are skipped, so after
+    /// removals a page can start at a position which is not a multiple of `limit`. Use
+    /// [`to_page_after`](Self::to_page_after) or [`Paginated::page`](crate::store::Paginated::page)
+    /// to continue from the last element of a page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"s");
+    /// for v in 0u8..10 {
+    ///     set.insert(v);
+    /// }
+    /// assert_eq!(set.to_page(4, 3), [4, 5, 6]);
+    /// assert_eq!(set.to_page(8, 3), [8, 9]);
+    /// ```
+    pub fn to_page(&self, from_index: u32, limit: u32) -> Vec<T> {
+        let (page, _) = self.page_at(from_index, limit as usize);
+        page.into_iter().cloned().collect()
+    }
+
+    /// Returns up to `limit` elements stored after `value`, in the order they are stored, or
+    /// [`None`] if `value` is not in the set. Passing the last element of a page returns the
+    /// next page.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    ///
+    /// let mut set = UnorderedSet::new(b"s");
+    /// for v in 0u8..10 {
+    ///     set.insert(v);
+    /// }
+    /// set.remove(&4);
+    ///
+    /// let page = set.to_page(0, 4);
+    /// assert_eq!(page, [0, 1, 2, 3]);
+    /// assert_eq!(set.to_page_after(page.last().unwrap(), 2), Some(vec![5, 6]));
+    /// assert_eq!(set.to_page_after(&4, 2), None);
+    /// ```
+    pub fn to_page_after<Q: ?Sized>(&self, value: &Q, limit: u32) -> Option<Vec<T>>
+    where
+        T: Borrow<Q>,
+        Q: BorshSerialize + ToOwned<Owned = T>,
+    {
+        let from_index = self.index.get(value)?.0 + 1;
+        Some(self.to_page(from_index, limit))
+    }
+
+    /// Starts a copy of this set into a new set under the given storage `prefix`. Elements
+    /// are copied by calling [`CloneTo::resume`], which can be continued over multiple function
+    /// calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::UnorderedSet;
+    /// use near_sdk::Gas;
+    ///
+    /// let mut set = UnorderedSet::new(b"s");
+    /// set.insert("a".to_string());
+    ///
+    /// let mut copy = set.clone_to(b"c");
+    /// assert!(copy.resume(&set, Gas::ONE_TERA * 100));
+    /// assert!(copy.into_inner().contains("a"));
+    /// ```
+    pub fn clone_to<S>(&self, prefix: S) -> CloneTo<UnorderedSet<T, H>>
+    where
+        S: IntoStorageKey,
+    {
+        CloneTo::new(UnorderedSet::with_hasher(prefix))
+    }
+}
+
+impl<T, H> CloneTo<UnorderedSet<T, H>>
+where
+    T: BorshSerialize + BorshDeserialize + Ord + Clone,
+    H: CryptoHasher<Digest = [u8; 32]>,
+{
+    /// Continues copying elements from `source` until either all elements are copied or
+    /// [`env::used_gas`] exceeds `gas_limit`. At least one element is copied on every call.
+    /// Returns `true` if the copy is complete.
+    pub fn resume(&mut self, source: &UnorderedSet<T, H>, gas_limit: Gas) -> bool {
+        self.copy_until(source.elements.capacity(), gas_limit, |target, i| {
+            if let Some(value) = source.elements.get(FreeListIndex(i)) {
+                target.insert(value.clone());
+            }
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::UnorderedSet;
+    use crate::test_utils::test_env::setup_free;
+    use arbitrary::{Arbitrary, Unstructured};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use rand::SeedableRng;
+    use rand::{Rng, RngCore};
+    use std::collections::HashSet;
+
+    #[test]
+    fn basic_functionality() {
+        let mut set = UnorderedSet::new(b"b");
+        assert!(set.is_empty());
+        assert!(set.insert("test".to_string()));
+        assert!(!set.insert("test".to_string()));
+        assert!(set.contains("test"));
+        assert_eq!(set.len(), 1);
+
+        assert!(set.remove("test"));
+        assert!(!set.remove("test"));
+        assert_eq!(set.len(), 0);
+    }
+
+    #[test]
+    fn contains_all_any() {
+        let mut set = UnorderedSet::new(b"b");
+        set.extend(["alice".to_string(), "bob".to_string()]);
+        let accounts = ["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        assert!(set.contains_all(&accounts[..2]));
+        assert!(!set.contains_all(&accounts));
+        assert!(set.contains_any(&accounts[1..]));
+        assert!(!set.contains_any(&accounts[2..]));
+        assert!(set.contains_all(&[]));
+        assert!(!set.contains_any(&[]));
+    }
+
+    #[test]
+    fn set_iterator() {
+        let mut set = UnorderedSet::new(b"b");
+
+        set.insert(0u8);
+        set.insert(1);
+        set.insert(2);
+        set.insert(3);
+        set.remove(&1);
+        let iter = set.iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.collect::<Vec<_>>(), [&0, &2, &3]);
+
+        let mut iter = set.iter().rev();
+        assert_eq!(iter.nth(1), Some(&2));
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn drain() {
+        let mut set = UnorderedSet::new(b"b");
+        set.extend(0u8..10);
+        set.remove(&4);
+
+        let drained: HashSet<_> = set.drain().collect();
+        assert_eq!(drained, (0..10).filter(|&v| v != 4).collect());
+        assert!(set.is_empty());
+
+        // Partially consumed drain still removes all elements
+        set.extend(0u8..10);
+        assert_eq!(set.drain().take(3).count(), 3);
+        assert!(set.is_empty());
+        for v in 0..10 {
+            assert!(!set.contains(&v));
+        }
+
+        set.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+
+    #[test]
+    fn clone_to() {
+        let mut set = UnorderedSet::new(b"b");
+        set.extend(0u8..10);
+        set.remove(&3);
+
+        let mut copy = set.clone_to(b"c");
+        while !copy.resume(&set, crate::Gas(0)) {}
+        let shadow = copy.into_inner();
+        assert!(Iterator::eq(shadow.iter(), set.iter()));
+    }
+
+    #[test]
+    fn to_page() {
+        let mut set = UnorderedSet::new(b"b");
+        set.extend(0u8..20);
+        for v in [3, 7, 8, 19] {
+            set.remove(&v);
+        }
+
+        let mut paged = set.to_page(0, 5);
+        while let Some(last) = paged.last() {
+            let page = set.to_page_after(last, 5).unwrap();
+            if page.is_empty() {
+                break;
+            }
+            paged.extend(page);
+        }
+        assert_eq!(paged, set.iter().copied().collect::<Vec<_>>());
+        assert!(set.to_page(20, 5).is_empty());
+        assert_eq!(set.to_page_after(&3, 5), None);
+    }
+
+    #[test]
+    fn set_algebra() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(7);
+        let mut set1 = UnorderedSet::new(b"a");
+        let mut set2 = UnorderedSet::new(b"b");
+        let mut base1 = HashSet::new();
+        let mut base2 = HashSet::new();
+        for _ in 0..100 {
+            let v = rng.gen::<u8>() % 120;
+            set1.insert(v);
+            base1.insert(v);
+            let v = rng.gen::<u8>() % 80;
+            set2.insert(v);
+            base2.insert(v);
+        }
+        let sorted = |iter: &mut dyn Iterator<Item = &u8>| {
+            let mut values: Vec<u8> = iter.copied().collect();
+            values.sort_unstable();
+            values
+        };
+
+        for (a, b, base_a, base_b) in
+            [(&set1, &set2, &base1, &base2), (&set2, &set1, &base2, &base1)]
+        {
+            assert_eq!(sorted(&mut a.difference(b)), sorted(&mut base_a.difference(base_b)));
+            assert_eq!(sorted(&mut a.intersection(b)), sorted(&mut base_a.intersection(base_b)));
+            assert_eq!(sorted(&mut a.union(b)), sorted(&mut base_a.union(base_b)));
+            assert_eq!(
+                sorted(&mut a.symmetric_difference(b)),
+                sorted(&mut base_a.symmetric_difference(base_b))
+            );
+            assert_eq!(a.is_disjoint(b), base_a.is_disjoint(base_b));
+            assert_eq!(a.is_subset(b), base_a.is_subset(base_b));
+            assert_eq!(a.is_superset(b), base_a.is_superset(base_b));
+        }
+
+        let mut sub = UnorderedSet::new(b"c");
+        sub.extend(set1.iter().copied().filter(|v| v % 2 == 0));
+        assert!(sub.is_subset(&set1));
+        assert!(set1.is_superset(&sub));
+        let empty = UnorderedSet::new(b"d");
+        assert!(empty.is_disjoint(&set1));
+        assert!(empty.is_subset(&set1));
+    }
+
+    #[test]
+    fn retain() {
+        let mut set = UnorderedSet::new(b"b");
+        set.extend(0u8..10);
+
+        set.retain(|v| v % 3 != 0);
+        assert_eq!(set.len(), 6);
+        for v in 0..10 {
+            assert_eq!(set.contains(&v), v % 3 != 0);
+        }
+
+        // Moved elements must still be removable through their updated index.
+        assert!(set.remove(&8));
+        assert!(set.remove(&1));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [&2, &4, &5, &7]);
+    }
+
+    #[derive(Arbitrary, Debug)]
+    enum Op {
+        Insert(u8),
+        Remove(u8),
+        Flush,
+        Restore,
+        Contains(u8),
+        Retain(u8),
+    }
+
+    #[test]
+    fn arbitrary() {
+        setup_free();
+
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let mut buf = vec![0; 4096];
+        for _ in 0..512 {
+            // Clear storage in-between runs
+            crate::mock::with_mocked_blockchain(|b| b.take_storage());
+            rng.fill_bytes(&mut buf);
+
+            let mut us = UnorderedSet::new(b"l");
+            let mut hs = HashSet::new();
+            let u = Unstructured::new(&buf);
+            if let Ok(ops) = Vec::<Op>::arbitrary_take_rest(u) {
+                for op in ops {
+                    match op {
+                        Op::Insert(v) => {
+                            let r1 = us.insert(v);
+                            let r2 = hs.insert(v);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Remove(v) => {
+                            let r1 = us.remove(&v);
+                            let r2 = hs.remove(&v);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Flush => {
+                            us.flush();
+                        }
+                        Op::Restore => {
+                            let serialized = us.try_to_vec().unwrap();
+                            us = UnorderedSet::deserialize(&mut serialized.as_slice()).unwrap();
+                        }
+                        Op::Contains(v) => {
+                            let r1 = us.contains(&v);
+                            let r2 = hs.contains(&v);
+                            assert_eq!(r1, r2)
+                        }
+                        Op::Retain(m) => {
+                            let m = m.max(1);
+                            us.retain(|v| v % m == 0);
+                            hs.retain(|v| v % m == 0);
+                        }
+                    }
+                    assert_eq!(us.len() as usize, hs.len());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "serde-collections")]
+    #[test]
+    fn serialize_json() {
+        let mut set = UnorderedSet::new(b"s");
+        set.extend([3u8, 1, 2]);
+        assert_eq!(serde_json::to_string(&set).unwrap(), "[3,1,2]");
+    }
+}
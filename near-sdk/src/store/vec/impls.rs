@@ -1,8 +1,9 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 
-use super::iter::{Iter, IterMut};
+use super::iter::{IntoIter, Iter, IterMut};
 use super::{Vector, ERR_INDEX_OUT_OF_BOUNDS};
 use crate::env;
+use crate::store::pagination::Paginated;
 
 impl<T> Drop for Vector<T>
 where
@@ -13,6 +14,20 @@ where
     }
 }
 
+impl<T> IntoIterator for Vector<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    /// Creates a consuming iterator that moves each element out of the vector, removing it from
+    /// storage. Any elements not iterated over are also removed when the iterator is dropped.
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self)
+    }
+}
+
 impl<'a, T> IntoIterator for &'a Vector<T>
 where
     T: BorshSerialize + BorshDeserialize,
@@ -70,3 +85,20 @@ where
         self.get_mut(index).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS))
     }
 }
+
+impl<'a, T> Paginated for &'a Vector<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = &'a T;
+    /// Index of the first element of the page.
+    type Token = u32;
+
+    fn page(self, from: Option<u32>, limit: usize) -> (Vec<Self::Item>, Option<u32>) {
+        let start = from.unwrap_or(0).min(self.len());
+        let end =
+            if limit < (self.len() - start) as usize { start + limit as u32 } else { self.len() };
+        let items = (start..end).map(|i| &self[i]).collect();
+        (items, if end < self.len() { Some(end) } else { None })
+    }
+}
@@ -72,6 +72,79 @@ where
     }
 }
 
+/// An iterator over copies of the elements of a stored vector, which are not cached.
+///
+/// This `struct` is created by the `iter_uncached` method on [`Vector`].
+#[derive(Debug)]
+pub struct IterUncached<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Underlying vector to iterate through
+    vec: &'a Vector<T>,
+    /// Range of indices to iterate.
+    range: Range<u32>,
+}
+
+impl<'a, T> IterUncached<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    pub(super) fn new(vec: &'a Vector<T>) -> Self {
+        Self { vec, range: Range { start: 0, end: vec.len() } }
+    }
+
+    /// Returns number of elements left to iterate.
+    fn remaining(&self) -> usize {
+        self.range.len()
+    }
+}
+
+impl<'a, T> Iterator for IterUncached<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize + Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        <Self as Iterator>::nth(self, 0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let idx = self.range.nth(n)?;
+        Some(self.vec.get_uncached(idx).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS)))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterUncached<'a, T> where
+    T: BorshSerialize + BorshDeserialize + Clone
+{
+}
+impl<'a, T> FusedIterator for IterUncached<'a, T> where T: BorshSerialize + BorshDeserialize + Clone {}
+
+impl<'a, T> DoubleEndedIterator for IterUncached<'a, T>
+where
+    T: BorshSerialize + BorshDeserialize + Clone,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        <Self as DoubleEndedIterator>::nth_back(self, 0)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        let idx = self.range.nth_back(n)?;
+        Some(self.vec.get_uncached(idx).unwrap_or_else(|| env::panic_str(ERR_INDEX_OUT_OF_BOUNDS)))
+    }
+}
+
 /// An iterator over exclusive references to each element of a stored vector.
 #[derive(Debug)]
 pub struct IterMut<'a, T>
@@ -199,11 +272,8 @@ where
     T: BorshSerialize + BorshDeserialize,
 {
     fn drop(&mut self) {
-        let delete_indices = (self.delete_range.start..self.range.start)
-            .chain(self.range.end..self.delete_range.end);
-
-        // Delete any non-deleted elements from iterator (not loading from storage)
-        for i in delete_indices {
+        // Delete any elements which were not yielded by the iterator (not loading from storage)
+        for i in self.range.clone() {
             self.vec.values.set(i, None);
         }
 
@@ -277,3 +347,109 @@ where
         self.next_back()
     }
 }
+
+/// An owning iterator over the elements of a [`Vector<T>`].
+///
+/// This `struct` is created by the `into_iter` method on [`Vector`]. Every element yielded is
+/// removed from storage, along with any elements left when the iterator is dropped.
+#[derive(Debug)]
+pub struct IntoIter<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Vector being consumed by the iterator.
+    vec: Vector<T>,
+    /// Range of indices to iterate.
+    range: Range<u32>,
+}
+
+impl<T> IntoIter<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    /// Creates a new iterator which consumes the given storage vector.
+    pub(crate) fn new(vec: Vector<T>) -> Self {
+        let end = vec.len();
+        Self { vec, range: Range { start: 0, end } }
+    }
+
+    /// Returns the amount of remaining elements to yield by the iterator.
+    fn remaining(&self) -> usize {
+        self.range.len()
+    }
+
+    fn remove(&mut self, index: u32) -> T {
+        self.vec
+            .values
+            .get_mut_inner(index)
+            .replace(None)
+            // Element must exist within bounds of vector
+            .unwrap_or_else(|| env::abort())
+    }
+}
+
+impl<T> Drop for IntoIter<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn drop(&mut self) {
+        // Delete any elements not yielded by the iterator (not loading from storage).
+        for i in self.range.clone() {
+            self.vec.values.set(i, None);
+        }
+        // Vector is flushed when dropped after this, which will clear the storage.
+        self.vec.len = 0;
+    }
+}
+
+impl<T> Iterator for IntoIter<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next()?;
+        Some(self.remove(idx))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            let next = self.range.next()?;
+            // Delete skipped values without loading and deserializing them.
+            self.vec.values.set(next, None);
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining();
+        (remaining, Some(remaining))
+    }
+
+    fn count(self) -> usize {
+        self.remaining()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> where T: BorshSerialize + BorshDeserialize {}
+impl<T> FusedIterator for IntoIter<T> where T: BorshSerialize + BorshDeserialize {}
+
+impl<T> DoubleEndedIterator for IntoIter<T>
+where
+    T: BorshSerialize + BorshDeserialize,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let idx = self.range.next_back()?;
+        Some(self.remove(idx))
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            let next = self.range.next_back()?;
+            // Delete skipped values without loading and deserializing them.
+            self.vec.values.set(next, None);
+        }
+        self.next_back()
+    }
+}
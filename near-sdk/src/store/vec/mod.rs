@@ -1,16 +1,21 @@
 mod impls;
 mod iter;
+mod sort;
 
 use std::{
+    cmp::Ordering,
     fmt,
     ops::{Bound, Range, RangeBounds},
 };
 
 use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde-collections")]
+use serde::{Serialize, Serializer};
 
-pub use self::iter::{Drain, Iter, IterMut};
-use super::ERR_INCONSISTENT_STATE;
-use crate::{env, IntoStorageKey};
+pub use self::iter::{Drain, IntoIter, Iter, IterMut, IterUncached};
+pub use self::sort::ExternalSort;
+use super::{CloneTo, ERR_INCONSISTENT_STATE};
+use crate::{env, Gas, IntoStorageKey};
 
 use super::IndexMap;
 
@@ -55,7 +60,10 @@ fn expect_consistent_state<T>(val: Option<T>) -> T {
 /// assert_eq!(vec[0], 7);
 ///
 /// vec.extend([1, 2, 3].iter().copied());
-/// assert!(Iterator::eq(vec.into_iter(), [7, 1, 2, 3].iter()));
+/// assert!(Iterator::eq(vec.iter(), [7, 1, 2, 3].iter()));
+///
+/// // Consuming the vector removes all of its elements from storage
+/// assert!(Iterator::eq(vec.into_iter(), [7, 1, 2, 3]));
 /// ```
 pub struct Vector<T>
 where
@@ -93,6 +101,15 @@ where
     }
 }
 
+impl_borsh_schema! {
+    Vector<T> {
+        len: u32,
+        prefix: Box<[u8]>,
+    }
+    where
+        T: BorshSerialize,
+}
+
 impl<T> Vector<T>
 where
     T: BorshSerialize,
@@ -131,6 +148,34 @@ where
         self.values.flush();
     }
 
+    /// Returns the storage key of the element at `index`, which is the prefix of the vector
+    /// followed by the index as little-endian bytes. This allows tools which read the state of
+    /// the contract, such as state viewers and indexers, to find the elements of the vector.
+    ///
+    /// The index is not checked against the length of the vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::env;
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([7u8, 8]);
+    /// vec.flush();
+    ///
+    /// assert_eq!(vec.element_key(1), b"v\x01\0\0\0");
+    /// assert_eq!(env::storage_read(&vec.element_key(1)), Some(vec![8]));
+    /// ```
+    pub fn element_key(&self, index: u32) -> Vec<u8> {
+        self.values.element_key(index)
+    }
+
+    /// Returns the prefix of the storage keys of the elements.
+    pub(crate) fn prefix(&self) -> &[u8] {
+        &self.values.prefix
+    }
+
     /// Sets a value at a given index to the value provided. This does not shift values after the
     /// index to the right.
     ///
@@ -178,7 +223,35 @@ where
         self.values.get_mut(index)
     }
 
-    fn swap(&mut self, a: u32, b: u32) {
+    /// Returns a copy of the element at `index` without caching it, or `None` if it is not
+    /// present.
+    pub(crate) fn get_uncached(&self, index: u32) -> Option<T>
+    where
+        T: Clone,
+    {
+        if index >= self.len() {
+            return None;
+        }
+        self.values.get_uncached(index)
+    }
+
+    /// Swaps two elements in the vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` are out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend(["a", "b", "c", "d"].iter().map(|s| s.to_string()));
+    /// vec.swap(1, 3);
+    /// assert_eq!(vec.iter().collect::<Vec<_>>(), ["a", "d", "c", "b"]);
+    /// ```
+    pub fn swap(&mut self, a: u32, b: u32) {
         if a >= self.len() || b >= self.len() {
             env::panic_str(ERR_INDEX_OUT_OF_BOUNDS);
         }
@@ -202,6 +275,72 @@ where
         expect_consistent_state(self.pop())
     }
 
+    /// Inserts an element at position `index` within the vector, shifting all elements after it
+    /// to the right.
+    ///
+    /// This reads and rewrites every element after `index`, which is `O(n)` in storage
+    /// operations and can be very expensive for large vectors. Use [`push`](Self::push) when the
+    /// position of the element does not matter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([1u32, 2, 3]);
+    ///
+    /// vec.insert(1, 4);
+    /// assert!(Iterator::eq(vec.iter(), [1, 4, 2, 3].iter()));
+    /// vec.insert(4, 5);
+    /// assert!(Iterator::eq(vec.iter(), [1, 4, 2, 3, 5].iter()));
+    /// ```
+    pub fn insert(&mut self, index: u32, element: T) {
+        if index > self.len {
+            env::panic_str(ERR_INDEX_OUT_OF_BOUNDS);
+        }
+        self.push(element);
+        for i in (index + 1..self.len).rev() {
+            self.values.swap(i - 1, i);
+        }
+    }
+
+    /// Removes and returns the element at position `index` within the vector, shifting all
+    /// elements after it to the left.
+    ///
+    /// This reads and rewrites every element after `index`, which is `O(n)` in storage
+    /// operations and can be very expensive for large vectors. Use
+    /// [`swap_remove`](Self::swap_remove) when the order of the elements does not matter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([1u32, 2, 3, 4]);
+    ///
+    /// assert_eq!(vec.remove(1), 2);
+    /// assert!(Iterator::eq(vec.iter(), [1, 3, 4].iter()));
+    /// ```
+    pub fn remove(&mut self, index: u32) -> T {
+        if index >= self.len {
+            env::panic_str(ERR_INDEX_OUT_OF_BOUNDS);
+        }
+        for i in index + 1..self.len {
+            self.values.swap(i - 1, i);
+        }
+        expect_consistent_state(self.pop())
+    }
+
     /// Removes the last element from a vector and returns it, or `None` if it is empty.
     pub fn pop(&mut self) -> Option<T> {
         let new_idx = self.len.checked_sub(1)?;
@@ -223,12 +362,74 @@ where
         self.values.insert(index, element).unwrap()
     }
 
+    /// Retains only the elements specified by the predicate, preserving the order of the retained
+    /// elements.
+    ///
+    /// In other words, remove all elements `e` such that `f(&mut e)` returns `false`. Every
+    /// element is visited once, and retained elements are moved down over the removed ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend(1u32..=6);
+    ///
+    /// vec.retain(|x| {
+    ///     *x *= 10;
+    ///     *x % 20 != 0
+    /// });
+    /// assert!(Iterator::eq(vec.iter(), [10, 30, 50].iter()));
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(expect_consistent_state(self.values.get_mut(i))) {
+                self.values.swap(kept, i);
+                kept += 1;
+            }
+        }
+        // Removed elements have been moved to the end of the vector.
+        for i in kept..self.len {
+            self.values.set(i, None);
+        }
+        self.len = kept;
+    }
+
     /// Returns an iterator over the vector. This iterator will lazily load any values iterated
     /// over from storage.
     pub fn iter(&self) -> Iter<T> {
         Iter::new(self)
     }
 
+    /// Returns an iterator over copies of the elements of the vector, which are read from storage
+    /// without being cached. The memory used by [`iter`](Self::iter) grows with every element it
+    /// loads, so this is meant for iterating over large vectors in a single call. Elements which
+    /// are already cached, including modified elements which are not flushed yet, are cloned from
+    /// the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend(0u32..5);
+    /// vec.flush();
+    ///
+    /// assert_eq!(vec.iter_uncached().sum::<u32>(), 10);
+    /// ```
+    pub fn iter_uncached(&self) -> IterUncached<T>
+    where
+        T: Clone,
+    {
+        IterUncached::new(self)
+    }
+
     /// Returns an iterator over the [`Vector`] that allows modifying each value. This iterator
     /// will lazily load any values iterated over from storage.
     pub fn iter_mut(&mut self) -> IterMut<T> {
@@ -268,6 +469,182 @@ where
         // This will also cap the max length at the length of the vector.
         Drain::new(self, Range { start, end: core::cmp::min(end, self.len()) })
     }
+
+    /// Sorts the vector with a key extraction function, preserving the initial order of equal
+    /// elements. The key function is called only once per element.
+    ///
+    /// All elements are loaded into memory to be sorted, and only elements that change position
+    /// are written back to storage. For vectors too large to be sorted within a single function
+    /// call, use [`ExternalSort`] which can be resumed across calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([-5i32, 4, 32, -3, 2]);
+    ///
+    /// vec.sort_by_cached_key(|k| k.to_string());
+    /// assert!(Iterator::eq(vec.iter(), [-3, -5, 2, 32, 4].iter()));
+    /// ```
+    pub fn sort_by_cached_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        let mut indices: Vec<(K, u32)> =
+            (0..self.len).map(|i| (f(expect_consistent_state(self.values.get(i))), i)).collect();
+        // Indices are unique, so an unstable sort is stable with respect to the keys.
+        indices.sort_unstable();
+
+        // Take all elements that are moved before writing any, since the sources of moved
+        // elements are also the destinations of other moved elements.
+        let moved: Vec<(u32, T)> = indices
+            .into_iter()
+            .zip(0..)
+            .filter(|((_, from), to)| from != to)
+            .map(|((_, from), to)| {
+                (to, expect_consistent_state(self.values.get_mut_inner(from).replace(None)))
+            })
+            .collect();
+        for (to, value) in moved {
+            self.values.set(to, Some(value));
+        }
+    }
+
+    /// Binary searches this sorted vector for a given element, reading `O(log n)` elements from
+    /// storage. If the vector is not sorted, the returned result is unspecified and meaningless.
+    ///
+    /// If the value is found then [`Result::Ok`] is returned, containing the index of the
+    /// matching element. If there are multiple matches, then any one of the matches could be
+    /// returned. If the value is not found then [`Result::Err`] is returned, containing the index
+    /// where a matching element could be inserted while maintaining sorted order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([1u32, 3, 5, 7, 9]);
+    ///
+    /// assert_eq!(vec.binary_search(&7), Ok(3));
+    /// assert_eq!(vec.binary_search(&4), Err(2));
+    /// assert_eq!(vec.binary_search(&10), Err(5));
+    /// ```
+    pub fn binary_search(&self, x: &T) -> Result<u32, u32>
+    where
+        T: Ord,
+    {
+        self.binary_search_by(|p| p.cmp(x))
+    }
+
+    /// Binary searches this sorted vector with a comparator function, reading `O(log n)`
+    /// elements from storage.
+    ///
+    /// The comparator function should return whether its argument is [`Less`](Ordering::Less),
+    /// [`Equal`](Ordering::Equal) or [`Greater`](Ordering::Greater) than the desired target, and
+    /// the vector must be sorted consistently with it. The result is the same as for
+    /// [`binary_search`](Self::binary_search).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([(1u32, "a".to_string()), (4, "b".to_string()), (9, "c".to_string())]);
+    ///
+    /// assert_eq!(vec.binary_search_by(|(k, _)| k.cmp(&4)), Ok(1));
+    /// assert_eq!(vec.binary_search_by(|(k, _)| k.cmp(&5)), Err(2));
+    /// ```
+    pub fn binary_search_by<F>(&self, mut f: F) -> Result<u32, u32>
+    where
+        F: FnMut(&T) -> Ordering,
+    {
+        let mut left = 0;
+        let mut right = self.len;
+        while left < right {
+            let mid = left + (right - left) / 2;
+            match f(expect_consistent_state(self.values.get(mid))) {
+                Ordering::Less => left = mid + 1,
+                Ordering::Greater => right = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(left)
+    }
+
+    /// Returns the index of the partition point of this vector according to the given
+    /// predicate, which is the index of the first element for which the predicate returns
+    /// `false`. Reads `O(log n)` elements from storage.
+    ///
+    /// The vector must be partitioned by the predicate, so that all elements for which it
+    /// returns `true` are at the start of the vector. Otherwise the returned index is
+    /// unspecified and meaningless.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([1u32, 2, 3, 3, 5, 6, 7]);
+    ///
+    /// assert_eq!(vec.partition_point(|&x| x < 5), 4);
+    /// assert_eq!(vec.partition_point(|&x| x < 10), 7);
+    /// ```
+    pub fn partition_point<P>(&self, mut pred: P) -> u32
+    where
+        P: FnMut(&T) -> bool,
+    {
+        self.binary_search_by(|x| if pred(x) { Ordering::Less } else { Ordering::Greater })
+            .unwrap_or_else(|i| i)
+    }
+}
+
+impl<T> Vector<T>
+where
+    T: BorshSerialize + BorshDeserialize + Clone,
+{
+    /// Starts a copy of this vector into a new vector under the given storage `prefix`. Elements
+    /// are copied by calling [`CloneTo::resume`], which can be continued over multiple function
+    /// calls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use near_sdk::store::Vector;
+    /// use near_sdk::Gas;
+    ///
+    /// let mut vec = Vector::new(b"v");
+    /// vec.extend([1u8, 2, 3]);
+    ///
+    /// let mut copy = vec.clone_to(b"c");
+    /// assert!(copy.resume(&vec, Gas::ONE_TERA * 100));
+    /// assert!(Iterator::eq(copy.into_inner().iter(), [1, 2, 3].iter()));
+    /// ```
+    pub fn clone_to<S>(&self, prefix: S) -> CloneTo<Vector<T>>
+    where
+        S: IntoStorageKey,
+    {
+        CloneTo::new(Vector::new(prefix))
+    }
+}
+
+impl<T> CloneTo<Vector<T>>
+where
+    T: BorshSerialize + BorshDeserialize + Clone,
+{
+    /// Continues copying elements from `source` until either all elements are copied or
+    /// [`env::used_gas`] exceeds `gas_limit`. At least one element is copied on every call.
+    /// Returns `true` if the copy is complete.
+    pub fn resume(&mut self, source: &Vector<T>, gas_limit: Gas) -> bool {
+        self.copy_until(source.len(), gas_limit, |target, i| {
+            target.push(expect_consistent_state(source.get(i)).clone())
+        })
+    }
 }
 
 impl<T> fmt::Debug for Vector<T>
@@ -286,6 +663,21 @@ where
     }
 }
 
+/// Serializes the elements of the vector as a sequence, which loads the whole vector from
+/// storage, so this should only be used for small vectors.
+#[cfg(feature = "serde-collections")]
+impl<T> Serialize for Vector<T>
+where
+    T: BorshSerialize + BorshDeserialize + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod tests {
@@ -362,6 +754,100 @@ mod tests {
         assert_eq!(actual, baseline);
     }
 
+    #[test]
+    pub fn test_swap() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(3);
+        let mut vec = Vector::new(b"v".to_vec());
+        let mut baseline = vec![];
+        for _ in 0..100 {
+            let value = rng.gen::<u64>();
+            vec.push(value);
+            baseline.push(value);
+        }
+        vec.flush();
+        for _ in 0..100 {
+            let a = rng.gen::<u32>() % vec.len();
+            let b = rng.gen::<u32>() % vec.len();
+            vec.swap(a, b);
+            baseline.swap(a as usize, b as usize);
+        }
+        vec.flush();
+        let vec = Vector::<u64>::deserialize(&mut vec.try_to_vec().unwrap().as_slice()).unwrap();
+        let actual: Vec<_> = vec.iter().cloned().collect();
+        assert_eq!(actual, baseline);
+    }
+
+    #[test]
+    fn binary_search() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(4);
+        let mut baseline: Vec<u8> = (0..200).map(|_| rng.gen()).collect();
+        baseline.sort_unstable();
+        let mut vec = Vector::new(b"v".to_vec());
+        vec.extend(baseline.iter().copied());
+
+        for x in 0..=u8::MAX {
+            match vec.binary_search(&x) {
+                Ok(i) => assert_eq!(vec[i], x),
+                Err(i) => assert_eq!(i as usize, baseline.partition_point(|&v| v < x)),
+            }
+            assert_eq!(baseline.binary_search(&x).is_ok(), vec.binary_search(&x).is_ok());
+            assert_eq!(
+                vec.partition_point(|&v| v <= x) as usize,
+                baseline.partition_point(|&v| v <= x)
+            );
+        }
+
+        let empty = Vector::<u8>::new(b"e".to_vec());
+        assert_eq!(empty.binary_search(&0), Err(0));
+        assert_eq!(empty.partition_point(|_| true), 0);
+    }
+
+    #[test]
+    fn retain() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(5);
+        let mut baseline: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+        let mut vec = Vector::new(b"v".to_vec());
+        vec.extend(baseline.iter().copied());
+        vec.flush();
+
+        vec.retain(|v| {
+            *v = v.wrapping_add(1);
+            *v % 3 != 0
+        });
+        baseline.retain(|v| v.wrapping_add(1) % 3 != 0);
+        baseline.iter_mut().for_each(|v| *v = v.wrapping_add(1));
+        assert_eq!(vec.len() as usize, baseline.len());
+
+        vec.flush();
+        let vec = Vector::<u8>::deserialize(&mut vec.try_to_vec().unwrap().as_slice()).unwrap();
+        assert!(Iterator::eq(vec.iter(), baseline.iter()));
+        assert_eq!(vec.values.get(vec.len()), None);
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(6);
+        let mut vec = Vector::new(b"v".to_vec());
+        let mut baseline = vec![];
+        for _ in 0..200 {
+            if baseline.is_empty() || rng.gen_bool(0.6) {
+                let index = rng.gen_range(0..=vec.len());
+                let value = rng.gen::<u64>();
+                vec.insert(index, value);
+                baseline.insert(index as usize, value);
+            } else {
+                let index = rng.gen_range(0..vec.len());
+                assert_eq!(vec.remove(index), baseline.remove(index as usize));
+            }
+            if rng.gen_bool(0.1) {
+                vec.flush();
+            }
+        }
+        vec.flush();
+        let vec = Vector::<u64>::deserialize(&mut vec.try_to_vec().unwrap().as_slice()).unwrap();
+        assert!(Iterator::eq(vec.iter(), baseline.iter()));
+    }
+
     #[test]
     pub fn test_clear() {
         let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(3);
@@ -502,6 +988,9 @@ mod tests {
 
         // Drain rest
         assert!(Iterator::eq(vec.drain(..), baseline.drain(..)));
+        // Elements not yielded by incomplete drains must be removed from storage
+        vec.flush();
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
 
         // Test double ended iterator functions
         let mut vec = Vector::new(b"v");
@@ -523,6 +1012,97 @@ mod tests {
         crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
     }
 
+    #[test]
+    fn sort_by_cached_key() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(5);
+        let mut vec = Vector::new(b"v");
+        let mut baseline: Vec<(u8, u32)> = (0..200).map(|i| (rng.gen::<u8>() % 16, i)).collect();
+        vec.extend(baseline.iter().copied());
+        vec.flush();
+
+        let mut calls = 0;
+        vec.sort_by_cached_key(|v| {
+            calls += 1;
+            v.0
+        });
+        baseline.sort_by_cached_key(|v| v.0);
+        assert_eq!(calls, 200);
+        assert!(Iterator::eq(vec.iter(), baseline.iter()));
+
+        // Sorting an already sorted vector does not modify any elements
+        vec.flush();
+        vec.sort_by_cached_key(|v| v.0);
+        assert!(vec.values.cache.inner().values().all(|v| v.get().unwrap().is_cached()));
+    }
+
+    #[test]
+    fn iter_uncached() {
+        let mut vec = Vector::new(b"v");
+        vec.extend(0u32..10);
+        vec.flush();
+        let mut vec = Vector::<u32>::try_from_slice(&vec.try_to_vec().unwrap()).unwrap();
+
+        // Modified elements are read from the cache, the others from storage.
+        vec.set(3, 30);
+        let expected = [0, 1, 2, 30, 4, 5, 6, 7, 8, 9];
+        assert!(vec.iter_uncached().eq(expected.iter().copied()));
+        assert!(vec.iter_uncached().rev().eq(expected.iter().rev().copied()));
+        assert_eq!(vec.iter_uncached().nth(8), Some(8));
+        assert_eq!(vec.values.cache.inner().len(), 1);
+    }
+
+    #[test]
+    fn clone_to() {
+        let mut vec = Vector::new(b"v");
+        vec.extend(0u32..20);
+
+        let mut copy = vec.clone_to(b"c");
+        let mut calls = 0;
+        while !copy.resume(&vec, crate::Gas(0)) {
+            calls += 1;
+        }
+        assert_eq!(calls, 19);
+        assert!(copy.is_complete());
+
+        let shadow = copy.into_inner();
+        assert!(Iterator::eq(shadow.iter(), vec.iter()));
+        drop(shadow);
+        drop(vec);
+
+        // Both vectors are persisted under their own prefixes
+        let shadow = Vector::<u32> { len: 20, values: IndexMap::new(b"c") };
+        assert!(Iterator::eq(shadow.iter(), (0..20).collect::<Vec<_>>().iter()));
+    }
+
+    #[test]
+    fn into_iterator() {
+        let mut vec = Vector::new(b"v");
+        let baseline = vec![0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        vec.extend(baseline.clone());
+        vec.flush();
+
+        let mut vec_iter = vec.into_iter();
+        let mut bl_iter = baseline.clone().into_iter();
+        assert_eq!(vec_iter.len(), bl_iter.len());
+        assert_eq!(vec_iter.next(), bl_iter.next());
+        assert_eq!(vec_iter.next_back(), bl_iter.next_back());
+        assert_eq!(vec_iter.nth(2), bl_iter.nth(2));
+        assert_eq!(vec_iter.nth_back(1), bl_iter.nth_back(1));
+        assert_eq!(vec_iter.len(), bl_iter.len());
+        // Drop partially consumed iterator, which must remove the remaining values
+        drop(vec_iter);
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+
+        let mut vec = Vector::new(b"v");
+        vec.extend(baseline.clone());
+        let mut consumed = Vec::new();
+        for item in vec {
+            consumed.push(item);
+        }
+        assert_eq!(consumed, baseline);
+        crate::mock::with_mocked_blockchain(|m| assert!(m.take_storage().is_empty()));
+    }
+
     #[derive(Arbitrary, Debug)]
     enum Op {
         Push(u8),
@@ -631,4 +1211,13 @@ mod tests {
         let vec = Vector::<String>::deserialize(&mut serialized.as_slice()).unwrap();
         assert_eq!(vec[0], "Some data");
     }
+
+    #[cfg(feature = "serde-collections")]
+    #[test]
+    fn serialize_json() {
+        let mut vec = Vector::new(b"v");
+        assert_eq!(serde_json::to_string(&vec).unwrap(), "[]");
+        vec.extend(["a".to_string(), "b".to_string()]);
+        assert_eq!(serde_json::to_string(&vec).unwrap(), r#"["a","b"]"#);
+    }
 }
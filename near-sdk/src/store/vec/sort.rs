@@ -0,0 +1,272 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use super::{expect_consistent_state, Vector};
+use crate::{env, Gas, IntoStorageKey};
+
+/// Resumable merge sort of a [`Vector`], for vectors that are too large to be sorted within the
+/// gas limit of a single function call.
+///
+/// Sorting is done in bounded steps through [`ExternalSort::resume`], which can be continued
+/// over multiple function calls by keeping this type in the contract state in between calls.
+/// The key of each element is only computed once and is stored alongside a copy of the element
+/// in scratch storage under the prefix given to [`ExternalSort::new`].
+///
+/// The vector must not be modified until the sort is complete. Elements of the vector are only
+/// overwritten in the last phase of the sort.
+///
+/// # Examples
+/// ```
+/// use near_sdk::store::vec::ExternalSort;
+/// use near_sdk::store::Vector;
+/// use near_sdk::Gas;
+///
+/// let mut vec = Vector::new(b"v");
+/// vec.extend([5u32, 1, 4, 2, 3]);
+///
+/// let mut sort = ExternalSort::new(b"s");
+/// // Stops sorting once 10 TGas has been used, and can be resumed in a later function call.
+/// while !sort.resume(&mut vec, |v| *v, Gas(10_000_000_000_000)) {}
+///
+/// assert!(Iterator::eq(vec.iter(), [1, 2, 3, 4, 5].iter()));
+/// ```
+pub struct ExternalSort<K, T>
+where
+    K: BorshSerialize,
+    T: BorshSerialize,
+{
+    phase: Phase,
+    /// Buffer that the current phase reads elements from.
+    front: Vector<(K, T)>,
+    /// Buffer that merge passes write elements into.
+    back: Vector<(K, T)>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Copying elements of the vector along with their keys into the front buffer.
+    Keys {
+        next: u32,
+    },
+    /// Merging pairs of sorted runs of length `width` from the front buffer into the back buffer.
+    /// The current pair of runs begins at `start`, and `left` and `right` are the next indices
+    /// to merge from each run.
+    Merge {
+        width: u32,
+        start: u32,
+        left: u32,
+        right: u32,
+    },
+    /// Moving the sorted elements from the front buffer back into the vector.
+    WriteBack {
+        next: u32,
+    },
+    Complete,
+}
+
+//? Manual implementations needed only because borsh derive is leaking field types
+// https://github.com/near/borsh-rs/issues/41
+impl<K, T> BorshSerialize for ExternalSort<K, T>
+where
+    K: BorshSerialize,
+    T: BorshSerialize,
+{
+    fn serialize<W: borsh::maybestd::io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), borsh::maybestd::io::Error> {
+        BorshSerialize::serialize(&self.phase, writer)?;
+        BorshSerialize::serialize(&self.front, writer)?;
+        BorshSerialize::serialize(&self.back, writer)?;
+        Ok(())
+    }
+}
+
+impl<K, T> BorshDeserialize for ExternalSort<K, T>
+where
+    K: BorshSerialize,
+    T: BorshSerialize,
+{
+    fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
+        Ok(Self {
+            phase: BorshDeserialize::deserialize(buf)?,
+            front: BorshDeserialize::deserialize(buf)?,
+            back: BorshDeserialize::deserialize(buf)?,
+        })
+    }
+}
+
+impl<K, T> ExternalSort<K, T>
+where
+    K: BorshSerialize,
+    T: BorshSerialize,
+{
+    /// Create a new sort which uses the given prefix for the scratch storage of the elements
+    /// being sorted.
+    pub fn new<S>(prefix: S) -> Self
+    where
+        S: IntoStorageKey,
+    {
+        let mut front_key = prefix.into_storage_key();
+        let back_key = [front_key.as_slice(), b"b"].concat();
+        front_key.push(b'f');
+        Self {
+            phase: Phase::Keys { next: 0 },
+            front: Vector::new(front_key),
+            back: Vector::new(back_key),
+        }
+    }
+
+    /// Returns `true` if the vector has been completely sorted.
+    pub fn is_complete(&self) -> bool {
+        self.phase == Phase::Complete
+    }
+}
+
+impl<K, T> ExternalSort<K, T>
+where
+    K: BorshSerialize + BorshDeserialize + Ord,
+    T: BorshSerialize + BorshDeserialize + Clone,
+{
+    /// Continues sorting `vec` by the key returned from `f` until either the sort completes or
+    /// [`env::used_gas`] exceeds `gas_limit`. At least one element is processed on every call.
+    /// Returns `true` if the vector is sorted.
+    ///
+    /// The sort is stable, and `f` must return the same key for an element on every call.
+    pub fn resume<F>(&mut self, vec: &mut Vector<T>, mut f: F, gas_limit: Gas) -> bool
+    where
+        F: FnMut(&T) -> K,
+    {
+        while !self.is_complete() {
+            self.step(vec, &mut f);
+            if env::used_gas() >= gas_limit {
+                break;
+            }
+        }
+        self.is_complete()
+    }
+
+    fn step<F>(&mut self, vec: &mut Vector<T>, f: &mut F)
+    where
+        F: FnMut(&T) -> K,
+    {
+        match self.phase {
+            Phase::Keys { next } => {
+                if let Some(value) = vec.get(next) {
+                    self.front.push((f(value), value.clone()));
+                    self.phase = Phase::Keys { next: next + 1 };
+                } else {
+                    self.phase = self.pass_phase(1);
+                }
+            }
+            Phase::Merge { width, start, mut left, mut right } => {
+                let len = self.front.len();
+                let mid = start.saturating_add(width).min(len);
+                let end = mid.saturating_add(width).min(len);
+
+                let take_left = left < mid
+                    && (right >= end
+                        || expect_consistent_state(self.front.get(left)).0
+                            <= expect_consistent_state(self.front.get(right)).0);
+                let index = if take_left {
+                    left += 1;
+                    left - 1
+                } else {
+                    right += 1;
+                    right - 1
+                };
+                let element = self.take_front(index);
+                self.back.push(element);
+
+                self.phase = if left < mid || right < end {
+                    Phase::Merge { width, start, left, right }
+                } else if end < len {
+                    // Move on to the next pair of runs in this pass.
+                    let right = end.saturating_add(width).min(len);
+                    Phase::Merge { width, start: end, left: end, right }
+                } else {
+                    // All elements have been moved to the back buffer, swap for the next pass.
+                    self.front.len = 0;
+                    core::mem::swap(&mut self.front, &mut self.back);
+                    self.pass_phase(width.saturating_mul(2))
+                };
+            }
+            Phase::WriteBack { next } => {
+                let (_, value) = self.take_front(next);
+                vec.set(next, value);
+                if next + 1 < self.front.len() {
+                    self.phase = Phase::WriteBack { next: next + 1 };
+                } else {
+                    self.front.len = 0;
+                    self.phase = Phase::Complete;
+                }
+            }
+            Phase::Complete => {}
+        }
+    }
+
+    /// Returns the phase to start a pass merging runs of `width` length, or the phase to write
+    /// back the elements if the front buffer is already sorted.
+    fn pass_phase(&self, width: u32) -> Phase {
+        let len = self.front.len();
+        if width < len {
+            Phase::Merge { width, start: 0, left: 0, right: width }
+        } else if len > 0 {
+            Phase::WriteBack { next: 0 }
+        } else {
+            Phase::Complete
+        }
+    }
+
+    /// Removes and returns the element of the front buffer at the given index, without shifting
+    /// any other elements.
+    fn take_front(&mut self, index: u32) -> (K, T) {
+        expect_consistent_state(self.front.values.get_mut_inner(index).replace(None))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::ExternalSort;
+    use crate::store::Vector;
+    use crate::test_utils::test_env::setup_free;
+    use crate::Gas;
+
+    #[test]
+    fn sort_single_step_calls() {
+        setup_free();
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        for len in [0u32, 1, 2, 7, 64, 100] {
+            crate::mock::with_mocked_blockchain(|b| b.take_storage());
+            let mut vec = Vector::new(b"v");
+            let mut baseline: Vec<(u8, u32)> = (0..len).map(|i| (rng.gen::<u8>() % 8, i)).collect();
+            vec.extend(baseline.iter().copied());
+
+            let mut sort = ExternalSort::new(b"s");
+            let mut calls = 0;
+            // A zero gas limit processes a single element per call.
+            while !sort.resume(&mut vec, |v| v.0, Gas(0)) {
+                // Simulate state being persisted and loaded between function calls.
+                vec.flush();
+                let serialized = borsh::BorshSerialize::try_to_vec(&sort).unwrap();
+                drop(sort);
+                sort = borsh::BorshDeserialize::try_from_slice(&serialized).unwrap();
+                calls += 1;
+            }
+            assert!(len < 2 || calls > len);
+
+            // Stable sort by key only
+            baseline.sort_by_key(|v| v.0);
+            assert!(Iterator::eq(vec.iter(), baseline.iter()));
+
+            // Scratch storage is cleaned up after sorting.
+            drop(sort);
+            vec.flush();
+            crate::mock::with_mocked_blockchain(|m| {
+                assert_eq!(m.take_storage().len(), len as usize);
+            });
+        }
+    }
+}
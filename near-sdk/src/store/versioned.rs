@@ -0,0 +1,336 @@
+use core::ops::{Deref, DerefMut};
+use std::collections::HashMap;
+use std::fmt;
+
+use borsh::maybestd::io;
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+
+/// A version of a type stored in a [`Versioned`] wrapper, which can be upgraded from the
+/// previous version of the type.
+///
+/// The versions of a type form a chain: each version names the version before it as
+/// [`Previous`](Self::Previous), and the first version names [`NoPrevious`].
+pub trait Upgrade: BorshDeserialize {
+    /// Tag written before values of this version. Must be greater than the version of
+    /// [`Previous`](Self::Previous), unless this is the first version.
+    const VERSION: u8;
+
+    /// The version this version is upgraded from, or [`NoPrevious`] if this is the first version.
+    type Previous: Upgrade;
+
+    /// Upgrades a value of the previous version.
+    fn upgrade(previous: Self::Previous) -> Self;
+}
+
+/// Marks the first version of a type in the chain of [`Upgrade`] implementations. It has no
+/// values, so the [`Upgrade::upgrade`] of a first version can be implemented as
+/// `match previous {}`.
+#[derive(Debug)]
+pub enum NoPrevious {}
+
+impl BorshDeserialize for NoPrevious {
+    fn deserialize(_buf: &mut &[u8]) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown version"))
+    }
+}
+
+impl Upgrade for NoPrevious {
+    const VERSION: u8 = 0;
+    type Previous = NoPrevious;
+
+    fn upgrade(previous: Self::Previous) -> Self {
+        previous
+    }
+}
+
+/// Reads a value stored as `version`, and upgrades it through the chain of versions to `T`.
+fn read_version<T: Upgrade>(version: u8, buf: &mut &[u8]) -> io::Result<T> {
+    if version == T::VERSION {
+        T::deserialize(buf)
+    } else if version < T::VERSION {
+        read_version::<T::Previous>(version, buf).map(T::upgrade)
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown version"))
+    }
+}
+
+/// Value wrapper for store collections which stores the version of the value before it, and
+/// upgrades values stored as an older version through the chain of [`Upgrade`] implementations
+/// when they are read.
+///
+/// This allows changing the value type of a [`LookupMap`](super::LookupMap) or a
+/// [`TreeMap`](super::TreeMap) without migrating every existing entry at once. New values are
+/// always written as the latest version, and an upgraded value is only written back to storage
+/// when the entry is modified, for example by accessing it with
+/// [`LookupMap::get_mut`](super::LookupMap::get_mut). [`Versioned::stored_version`] returns the
+/// version a loaded value was stored as.
+///
+/// Values are serialized as the version tag as a `u8` followed by the value, which is the
+/// serialization of a Borsh enum with a variant for each version, if the versions are the
+/// indices of the variants. Unlike [`Migrate`](super::Migrate), any number of versions can be
+/// read, and the values of every version are tagged.
+///
+/// # Examples
+/// ```
+/// use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+/// use near_sdk::store::{LookupMap, NoPrevious, Upgrade, Versioned};
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct AccountV0 {
+///     balance: u128,
+/// }
+///
+/// impl Upgrade for AccountV0 {
+///     const VERSION: u8 = 0;
+///     type Previous = NoPrevious;
+///
+///     fn upgrade(previous: NoPrevious) -> Self {
+///         match previous {}
+///     }
+/// }
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct Account {
+///     balance: u128,
+///     nonce: u64,
+/// }
+///
+/// impl Upgrade for Account {
+///     const VERSION: u8 = 1;
+///     type Previous = AccountV0;
+///
+///     fn upgrade(previous: AccountV0) -> Self {
+///         Self { balance: previous.balance, nonce: 0 }
+///     }
+/// }
+///
+/// // Entry written by a previous version of the contract.
+/// let mut old = LookupMap::new(b"a");
+/// old.insert("alice".to_string(), Versioned::new(AccountV0 { balance: 5 }));
+/// old.flush();
+///
+/// let mut accounts: LookupMap<String, Versioned<Account>> = LookupMap::new(b"a");
+/// assert_eq!(accounts["alice"].nonce, 0);
+/// assert_eq!(accounts["alice"].stored_version(), 0);
+///
+/// // Modifying the entry writes it back as the latest version.
+/// accounts.get_mut("alice").unwrap().nonce += 1;
+/// ```
+pub struct Versioned<T> {
+    value: T,
+    stored_version: u8,
+}
+
+impl<T> Versioned<T>
+where
+    T: Upgrade,
+{
+    /// Wraps a value of the latest version.
+    pub fn new(value: T) -> Self {
+        Self { value, stored_version: T::VERSION }
+    }
+
+    /// Returns the version the value was stored as, which is the latest version for values that
+    /// were not read from storage.
+    pub fn stored_version(&self) -> u8 {
+        self.stored_version
+    }
+
+    /// Returns `true` if the value was read from storage as an older version and upgraded.
+    pub fn is_upgraded(&self) -> bool {
+        self.stored_version != T::VERSION
+    }
+}
+
+impl<T> Versioned<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> From<T> for Versioned<T>
+where
+    T: Upgrade,
+{
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Versioned<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<T> BorshSerialize for Versioned<T>
+where
+    T: Upgrade + BorshSerialize,
+{
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        BorshSerialize::serialize(&T::VERSION, writer)?;
+        BorshSerialize::serialize(&self.value, writer)
+    }
+}
+
+impl<T> BorshDeserialize for Versioned<T>
+where
+    T: Upgrade,
+{
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        let stored_version = u8::deserialize(buf)?;
+        Ok(Self { value: read_version(stored_version, buf)?, stored_version })
+    }
+}
+
+/// The schema is that of the latest version, which is the only version that is written.
+impl<T> BorshSchema for Versioned<T>
+where
+    T: BorshSchema,
+{
+    fn add_definitions_recursively(
+        definitions: &mut HashMap<borsh::schema::Declaration, borsh::schema::Definition>,
+    ) {
+        let fields = borsh::schema::Fields::NamedFields(vec![
+            ("version".to_string(), u8::declaration()),
+            ("value".to_string(), T::declaration()),
+        ]);
+        Self::add_definition(
+            Self::declaration(),
+            borsh::schema::Definition::Struct { fields },
+            definitions,
+        );
+        u8::add_definitions_recursively(definitions);
+        T::add_definitions_recursively(definitions);
+    }
+
+    fn declaration() -> borsh::schema::Declaration {
+        format!("Versioned<{}>", T::declaration())
+    }
+}
+
+impl<T> fmt::Debug for Versioned<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Versioned")
+            .field("value", &self.value)
+            .field("stored_version", &self.stored_version)
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use super::{NoPrevious, Upgrade, Versioned};
+    use crate::store::LookupMap;
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct V0 {
+        a: u32,
+    }
+
+    impl Upgrade for V0 {
+        const VERSION: u8 = 0;
+        type Previous = NoPrevious;
+
+        fn upgrade(previous: NoPrevious) -> Self {
+            match previous {}
+        }
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct V1 {
+        a: u64,
+    }
+
+    impl Upgrade for V1 {
+        const VERSION: u8 = 1;
+        type Previous = V0;
+
+        fn upgrade(previous: V0) -> Self {
+            Self { a: u64::from(previous.a) * 10 }
+        }
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+    struct V3 {
+        a: u64,
+        b: String,
+    }
+
+    impl Upgrade for V3 {
+        const VERSION: u8 = 3;
+        type Previous = V1;
+
+        fn upgrade(previous: V1) -> Self {
+            Self { a: previous.a + 1, b: "upgraded".to_string() }
+        }
+    }
+
+    /// Borsh enum with the same serialization as the versions.
+    #[derive(BorshSerialize, BorshDeserialize)]
+    enum Stored {
+        V0(V0),
+        V1(V1),
+        _V2,
+        V3(V3),
+    }
+
+    #[test]
+    fn upgrades_through_versions() {
+        let mut old = LookupMap::new(b"t");
+        old.insert(0u8, Stored::V0(V0 { a: 1 }));
+        old.insert(1, Stored::V1(V1 { a: 2 }));
+        old.insert(3, Stored::V3(V3 { a: 3, b: "new".to_string() }));
+        old.flush();
+
+        let mut map: LookupMap<u8, Versioned<V3>> = LookupMap::new(b"t");
+        assert_eq!(*map[&0], V3 { a: 11, b: "upgraded".to_string() });
+        assert_eq!(*map[&1], V3 { a: 3, b: "upgraded".to_string() });
+        assert_eq!(*map[&3], V3 { a: 3, b: "new".to_string() });
+        let versions: Vec<_> = [0, 1, 3].iter().map(|k| map[k].stored_version()).collect();
+        assert_eq!(versions, [0, 1, 3]);
+
+        map.get_mut(&0).unwrap().a += 1;
+        map.flush();
+        let map: LookupMap<u8, Versioned<V3>> = LookupMap::new(b"t");
+        assert!(!map[&0].is_upgraded());
+        assert_eq!(map[&0].a, 12);
+        assert!(map[&1].is_upgraded());
+    }
+
+    #[test]
+    fn nested_and_unknown_versions() {
+        let value = (Versioned::new(V1 { a: 7 }), 9u8);
+        let serialized = value.try_to_vec().unwrap();
+        assert_eq!(
+            serialized,
+            Stored::V1(V1 { a: 7 })
+                .try_to_vec()
+                .unwrap()
+                .into_iter()
+                .chain([9])
+                .collect::<Vec<_>>()
+        );
+        let (nested, next) = <(Versioned<V3>, u8)>::try_from_slice(&serialized).unwrap();
+        assert_eq!((nested.into_inner().a, next), (8, 9));
+
+        // Versions which are newer than the latest or are not in the chain can't be read.
+        assert!(Versioned::<V1>::try_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(Versioned::<V3>::try_from_slice(&[2, 0, 0, 0, 0]).is_err());
+    }
+}
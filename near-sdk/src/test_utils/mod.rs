@@ -2,10 +2,16 @@
 pub mod test_env;
 
 mod context;
-use crate::mock::Receipt;
+use crate::mock::{Receipt, YieldedPromise};
 #[allow(deprecated)]
 pub use context::{accounts, testing_env_with_promise_results, VMContextBuilder};
 
+mod simulation;
+pub use simulation::{CallResult, Simulation};
+
+mod state_snapshot;
+pub use state_snapshot::StateSnapshot;
+
 /// Initializes a testing environment to mock interactions which would otherwise go through a
 /// validator node. This macro will initialize or overwrite the [`MockedBlockchain`]
 /// instance for interactions from a smart contract.
@@ -99,6 +105,12 @@ pub fn get_created_receipts() -> Vec<Receipt> {
     crate::mock::with_mocked_blockchain(|b| b.created_receipts().clone())
 }
 
+/// Returns the promises created with `env::promise_yield_create` in the current test, with the
+/// payloads they were resumed with. Only available in unit tests.
+pub fn get_yielded_promises() -> Vec<YieldedPromise> {
+    crate::mock::with_yielded_promises(|promises| promises.clone())
+}
+
 /// Objects stored on the trie directly should have identifiers. If identifier is not provided
 /// explicitly than `Default` trait would use this index to generate an id.
 #[allow(dead_code)]
@@ -0,0 +1,571 @@
+use crate::mock::{MockedBlockchain, VmAction};
+use crate::test_utils::VMContextBuilder;
+use crate::{AccountId, Balance, BlockHeight, Gas, PromiseResult, RuntimeFeesConfig, VMConfig};
+use borsh::BorshDeserialize;
+use near_vm_logic::types::ReturnData;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Prepaid gas for the function calls made through [`Simulation::call`].
+const DEFAULT_GAS: Gas = Gas(300_000_000_000_000);
+
+/// Time between the blocks in which the receipts are executed, in nanoseconds.
+const BLOCK_PROD_TIME: u64 = 1_000_000_000;
+
+/// Predecessor of the refunds of failed receipts.
+const SYSTEM_ACCOUNT: &str = "system";
+
+type Storage = HashMap<Vec<u8>, Vec<u8>>;
+
+/// Contract code which is run natively. It is called with the name of the method, and the rest of
+/// the call, such as the input and the attached deposit, is read from [`env`](crate::env).
+type Contract = Box<dyn FnMut(&str)>;
+
+/// Native simulation of multiple contracts, for testing cross-contract calls without compiling
+/// the contracts to Wasm and running them in a runtime.
+///
+/// Contracts are registered as functions that dispatch a method name to the contract code, in the
+/// same way the code generated by `#[near_bindgen]` does within the contract Wasm. Each account
+/// has its own storage and balance, and the receipts created by a call, including callbacks and
+/// transfers, are executed in order until none are left.
+///
+/// Each receipt is executed in a new block. If a function call panics, the changes to the storage
+/// and balance of the receiver are reverted, the attached deposits are refunded to the
+/// predecessor and the receipt fails. Refunds which fail themselves, for example because the
+/// signer account does not exist, are burnt.
+///
+/// Only panics that unwind fail the receipt, such as failed assertions and `require!` in debug
+/// builds. [`env::panic_str`](crate::env::panic_str) panics inside a host function of the mocked
+/// blockchain, which unwinds on toolchains before Rust 1.81 but aborts the test on later ones,
+/// because panics can no longer unwind out of `extern "C"` functions.
+///
+/// Gas is not accounted for, every function call has the gas attached to it available.
+///
+/// # Examples
+/// ```
+/// use near_sdk::test_utils::Simulation;
+/// use near_sdk::{env, AccountId, Promise};
+///
+/// let counter: AccountId = "counter.near".parse().unwrap();
+/// let proxy: AccountId = "proxy.near".parse().unwrap();
+/// let alice: AccountId = "alice.near".parse().unwrap();
+///
+/// let mut sim = Simulation::new();
+/// sim.create_account(alice.clone(), 100);
+/// sim.deploy(counter.clone(), 0, |method| match method {
+///     "increment" => {
+///         let count = env::state_read::<u64>().unwrap_or_default() + 1;
+///         env::state_write(&count);
+///         env::value_return(&count.to_le_bytes());
+///     }
+///     _ => panic!("Method not found"),
+/// });
+/// let target = counter.clone();
+/// sim.deploy(proxy.clone(), 0, move |method| match method {
+///     "increment" => {
+///         Promise::new(target.clone())
+///             .function_call("increment".to_string(), vec![], 0, env::prepaid_gas() / 3)
+///             .as_return();
+///     }
+///     _ => panic!("Method not found"),
+/// });
+///
+/// sim.call(&alice, &proxy, "increment", &[], 10).assert_success();
+/// let res = sim.call(&alice, &proxy, "increment", &[], 0);
+/// assert_eq!(res.unwrap(), 2u64.to_le_bytes());
+/// assert_eq!(sim.balance(&alice), Some(90));
+/// assert_eq!(sim.balance(&proxy), Some(10));
+/// ```
+pub struct Simulation {
+    accounts: HashMap<AccountId, Account>,
+    pending: VecDeque<PendingReceipt>,
+    outcomes: Vec<Option<Outcome>>,
+    block_index: BlockHeight,
+    block_timestamp: u64,
+}
+
+#[derive(Default)]
+struct Account {
+    balance: Balance,
+    storage: Storage,
+    contract: Option<Contract>,
+}
+
+struct PendingReceipt {
+    id: usize,
+    signer_id: AccountId,
+    predecessor_id: AccountId,
+    receiver_id: AccountId,
+    dependencies: Vec<usize>,
+    actions: Vec<VmAction>,
+}
+
+enum Outcome {
+    Value(Vec<u8>),
+    Failed,
+    /// The result is the result of another receipt, returned from the function call.
+    Forward(usize),
+}
+
+/// Result of a call made through the [`Simulation`].
+#[derive(Debug, PartialEq)]
+pub struct CallResult {
+    /// Result of the call, after all of the receipts it created are executed. This is
+    /// [`PromiseResult::Failed`] if the called method or a promise it returned failed.
+    pub result: PromiseResult,
+    /// Logs of all receipts executed during the call, in order.
+    pub logs: Vec<String>,
+}
+
+impl CallResult {
+    /// Returns `true` if the call succeeded.
+    pub fn is_ok(&self) -> bool {
+        matches!(self.result, PromiseResult::Successful(_))
+    }
+
+    /// Panics if the call failed.
+    pub fn assert_success(&self) {
+        assert!(self.is_ok(), "Expected the call to succeed, logs: {:?}", self.logs);
+    }
+
+    /// Returns the value returned from the call, panicking if the call failed.
+    pub fn unwrap(self) -> Vec<u8> {
+        match self.result {
+            PromiseResult::Successful(value) => value,
+            _ => panic!("Expected the call to succeed, logs: {:?}", self.logs),
+        }
+    }
+
+    /// Deserializes the value returned from the call from JSON.
+    pub fn unwrap_json<T: DeserializeOwned>(self) -> T {
+        serde_json::from_slice(&self.unwrap()).expect("Failed to deserialize the result as JSON")
+    }
+
+    /// Deserializes the value returned from the call from Borsh.
+    pub fn unwrap_borsh<T: BorshDeserialize>(self) -> T {
+        T::try_from_slice(&self.unwrap()).expect("Failed to deserialize the result as Borsh")
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulation {
+    /// Creates a simulation without any accounts.
+    pub fn new() -> Self {
+        Self {
+            accounts: HashMap::new(),
+            pending: VecDeque::new(),
+            outcomes: Vec::new(),
+            block_index: 0,
+            block_timestamp: 0,
+        }
+    }
+
+    /// Creates an account without a contract, which can be used to make calls.
+    pub fn create_account(&mut self, account_id: AccountId, balance: Balance) {
+        self.accounts.insert(account_id, Account { balance, ..Default::default() });
+    }
+
+    /// Creates an account with the given contract deployed. The contract is called with the name
+    /// of the method for every function call to the account, and has to panic if the method does
+    /// not exist.
+    ///
+    /// The contract state should be read from and written to storage on every call, as done by
+    /// `#[near_bindgen]`, because the storage of the contract is only available during its calls.
+    pub fn deploy<F>(&mut self, account_id: AccountId, balance: Balance, contract: F)
+    where
+        F: FnMut(&str) + 'static,
+    {
+        self.accounts.insert(
+            account_id,
+            Account { balance, storage: Storage::new(), contract: Some(Box::new(contract)) },
+        );
+    }
+
+    /// Returns the balance of the account, or [`None`] if it does not exist.
+    pub fn balance(&self, account_id: &AccountId) -> Option<Balance> {
+        self.accounts.get(account_id).map(|account| account.balance)
+    }
+
+    /// Returns the storage of the account, or [`None`] if it does not exist.
+    pub fn storage(&self, account_id: &AccountId) -> Option<&HashMap<Vec<u8>, Vec<u8>>> {
+        self.accounts.get(account_id).map(|account| &account.storage)
+    }
+
+    /// Returns the height of the last block in which a receipt was executed.
+    pub fn block_index(&self) -> BlockHeight {
+        self.block_index
+    }
+
+    /// Calls `method` on `receiver_id` from `signer_id`, attaching `deposit`, and executes all of
+    /// the receipts created by the call.
+    ///
+    /// The deposit is taken from the balance of the signer, which is not checked so that accounts
+    /// only need to be created for the contracts and the signers can be any account.
+    pub fn call(
+        &mut self,
+        signer_id: &AccountId,
+        receiver_id: &AccountId,
+        method: &str,
+        args: &[u8],
+        deposit: Balance,
+    ) -> CallResult {
+        if let Some(signer) = self.accounts.get_mut(signer_id) {
+            signer.balance = signer.balance.saturating_sub(deposit);
+        }
+        let id = self.push_receipt(
+            signer_id.clone(),
+            signer_id.clone(),
+            receiver_id.clone(),
+            vec![],
+            vec![VmAction::FunctionCall {
+                function_name: method.to_string(),
+                args: args.to_vec(),
+                gas: DEFAULT_GAS,
+                deposit,
+            }],
+        );
+
+        let mut logs = Vec::new();
+        while let Some(position) = self
+            .pending
+            .iter()
+            .position(|receipt| receipt.dependencies.iter().all(|&d| self.is_ready(d)))
+        {
+            let receipt = self.pending.remove(position).unwrap();
+            logs.extend(self.execute(receipt));
+        }
+        assert!(
+            self.pending.is_empty(),
+            "Receipts are waiting on results that will never be ready"
+        );
+
+        CallResult { result: self.result(id), logs }
+    }
+
+    /// Calls `method` on `account_id` without persisting any changes, for reading the state of a
+    /// contract. Any receipts created by the call are dropped.
+    pub fn view(&mut self, account_id: &AccountId, method: &str, args: &[u8]) -> CallResult {
+        let account = self.accounts.get_mut(account_id).expect("Account does not exist");
+        let mut contract = account.contract.take().expect("Account has no contract deployed");
+        let mut context = VMContextBuilder::new();
+        context
+            .current_account_id(account_id.clone())
+            .signer_account_id(account_id.clone())
+            .predecessor_account_id(account_id.clone())
+            .account_balance(account.balance)
+            .block_index(self.block_index)
+            .block_timestamp(self.block_timestamp);
+        context.context.input = args.to_vec();
+        crate::env::set_blockchain_interface(MockedBlockchain::new(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            vec![],
+            account.storage.clone(),
+            Default::default(),
+            None,
+        ));
+        let success = panic::catch_unwind(AssertUnwindSafe(|| contract(method))).is_ok();
+        account.contract = Some(contract);
+
+        let outcome = crate::mock::with_mocked_blockchain(|b| b.outcome());
+        let result = match outcome.return_data {
+            ReturnData::Value(value) if success => PromiseResult::Successful(value),
+            ReturnData::None if success => PromiseResult::Successful(vec![]),
+            _ => PromiseResult::Failed,
+        };
+        CallResult { result, logs: outcome.logs }
+    }
+
+    fn push_receipt(
+        &mut self,
+        signer_id: AccountId,
+        predecessor_id: AccountId,
+        receiver_id: AccountId,
+        dependencies: Vec<usize>,
+        actions: Vec<VmAction>,
+    ) -> usize {
+        let id = self.outcomes.len();
+        self.outcomes.push(None);
+        self.pending.push_back(PendingReceipt {
+            id,
+            signer_id,
+            predecessor_id,
+            receiver_id,
+            dependencies,
+            actions,
+        });
+        id
+    }
+
+    /// Follows the returned promises of the receipt to the receipt which has the result.
+    fn resolve(&self, mut id: usize) -> Option<&Outcome> {
+        loop {
+            match self.outcomes[id].as_ref()? {
+                Outcome::Forward(next) => id = *next,
+                outcome => return Some(outcome),
+            }
+        }
+    }
+
+    fn is_ready(&self, id: usize) -> bool {
+        self.resolve(id).is_some()
+    }
+
+    fn result(&self, id: usize) -> PromiseResult {
+        match self.resolve(id) {
+            Some(Outcome::Value(value)) => PromiseResult::Successful(value.clone()),
+            Some(Outcome::Failed) => PromiseResult::Failed,
+            _ => PromiseResult::NotReady,
+        }
+    }
+
+    /// Executes the receipt, returning its logs.
+    fn execute(&mut self, receipt: PendingReceipt) -> Vec<String> {
+        self.block_index += 1;
+        self.block_timestamp += BLOCK_PROD_TIME;
+
+        let PendingReceipt { id, signer_id, predecessor_id, receiver_id, dependencies, actions } =
+            receipt;
+        let deposit: Balance = actions
+            .iter()
+            .map(|action| match action {
+                VmAction::FunctionCall { deposit, .. } | VmAction::Transfer { deposit } => *deposit,
+                _ => 0,
+            })
+            .sum();
+
+        // Account and storage changes are reverted if any of the actions fail.
+        let snapshot = self
+            .accounts
+            .get(&receiver_id)
+            .map(|account| (account.balance, account.storage.clone()));
+        let mut logs = Vec::new();
+        let mut outcome = Some(Outcome::Value(vec![]));
+        for action in actions {
+            outcome = match action {
+                VmAction::CreateAccount if !self.accounts.contains_key(&receiver_id) => {
+                    self.accounts.insert(receiver_id.clone(), Account::default());
+                    Some(Outcome::Value(vec![]))
+                }
+                VmAction::Transfer { deposit } => self.accounts.get_mut(&receiver_id).map(|a| {
+                    a.balance += deposit;
+                    Outcome::Value(vec![])
+                }),
+                VmAction::FunctionCall { function_name, args, gas, deposit } => self.function_call(
+                    &signer_id,
+                    &predecessor_id,
+                    &receiver_id,
+                    &function_name,
+                    args,
+                    gas,
+                    deposit,
+                    &dependencies,
+                    &mut logs,
+                ),
+                VmAction::DeleteAccount { beneficiary_id } => {
+                    self.accounts.remove(&receiver_id).map(|account| {
+                        self.push_receipt(
+                            signer_id.clone(),
+                            receiver_id.clone(),
+                            beneficiary_id,
+                            vec![],
+                            vec![VmAction::Transfer { deposit: account.balance }],
+                        );
+                        Outcome::Value(vec![])
+                    })
+                }
+                // Creating an account which exists fails, the other actions are not simulated.
+                VmAction::CreateAccount => None,
+                _ => Some(Outcome::Value(vec![])),
+            };
+            if outcome.is_none() {
+                break;
+            }
+        }
+
+        if outcome.is_none() {
+            match snapshot {
+                Some((balance, storage)) => {
+                    let account = self.accounts.entry(receiver_id).or_default();
+                    account.balance = balance;
+                    account.storage = storage;
+                }
+                None => {
+                    self.accounts.remove(&receiver_id);
+                }
+            }
+            // Refunds are not refunded again if they fail, their deposit is burnt.
+            if deposit > 0 && predecessor_id.as_str() != SYSTEM_ACCOUNT {
+                self.push_receipt(
+                    signer_id,
+                    AccountId::new_unchecked(SYSTEM_ACCOUNT.to_string()),
+                    predecessor_id,
+                    vec![],
+                    vec![VmAction::Transfer { deposit }],
+                );
+            }
+        }
+        self.outcomes[id] = Some(outcome.unwrap_or(Outcome::Failed));
+        logs
+    }
+
+    /// Runs the contract of the receiver, and schedules the receipts it created. Returns [`None`]
+    /// if the call failed.
+    #[allow(clippy::too_many_arguments)]
+    fn function_call(
+        &mut self,
+        signer_id: &AccountId,
+        predecessor_id: &AccountId,
+        receiver_id: &AccountId,
+        method: &str,
+        args: Vec<u8>,
+        gas: Gas,
+        deposit: Balance,
+        dependencies: &[usize],
+        logs: &mut Vec<String>,
+    ) -> Option<Outcome> {
+        let promise_results = dependencies.iter().map(|&d| self.result(d)).collect();
+        let account = self.accounts.get_mut(receiver_id)?;
+        let mut contract = account.contract.take()?;
+
+        let mut context = VMContextBuilder::new();
+        context
+            .current_account_id(receiver_id.clone())
+            .signer_account_id(signer_id.clone())
+            .predecessor_account_id(predecessor_id.clone())
+            .account_balance(account.balance)
+            .attached_deposit(deposit)
+            .prepaid_gas(gas)
+            .block_index(self.block_index)
+            .block_timestamp(self.block_timestamp);
+        context.context.input = args;
+        crate::env::set_blockchain_interface(MockedBlockchain::new(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            promise_results,
+            std::mem::take(&mut account.storage),
+            Default::default(),
+            None,
+        ));
+        let success = panic::catch_unwind(AssertUnwindSafe(|| contract(method))).is_ok();
+        account.contract = Some(contract);
+
+        let (storage, receipts, outcome) = crate::mock::with_mocked_blockchain(|b| {
+            (b.take_storage(), b.created_receipts().clone(), b.outcome())
+        });
+        logs.extend(outcome.logs);
+        if !success {
+            return None;
+        }
+        account.storage = storage;
+        account.balance = outcome.balance;
+
+        // Receipt indices of the call are mapped to the ids of the scheduled receipts.
+        let mut ids = Vec::with_capacity(receipts.len());
+        for receipt in receipts {
+            let dependencies = receipt.receipt_indices.iter().map(|&i| ids[i as usize]).collect();
+            ids.push(self.push_receipt(
+                signer_id.clone(),
+                receiver_id.clone(),
+                receipt.receiver_id,
+                dependencies,
+                receipt.actions,
+            ));
+        }
+        Some(match outcome.return_data {
+            ReturnData::Value(value) => Outcome::Value(value),
+            ReturnData::ReceiptIndex(index) => Outcome::Forward(ids[index as usize]),
+            ReturnData::None => Outcome::Value(vec![]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Simulation;
+    use crate::{env, AccountId, Promise, PromiseResult};
+
+    fn account(id: &str) -> AccountId {
+        id.parse().unwrap()
+    }
+
+    #[test]
+    fn callback_with_failed_promise() {
+        let mut sim = Simulation::new();
+        sim.create_account(account("alice"), 1000);
+        sim.deploy(account("failing"), 0, |method| match method {
+            "fail" => panic!("always fails"),
+            _ => panic!("Method not found"),
+        });
+        sim.deploy(account("caller"), 0, |method| match method {
+            "call" => {
+                Promise::new(account("failing"))
+                    .function_call("fail".to_string(), vec![], 100, env::prepaid_gas() / 3)
+                    .then(Promise::new(env::current_account_id()).function_call(
+                        "on_result".to_string(),
+                        vec![],
+                        0,
+                        env::prepaid_gas() / 3,
+                    ))
+                    .as_return();
+            }
+            "on_result" => {
+                assert_eq!(env::predecessor_account_id(), env::current_account_id());
+                let failed = matches!(env::promise_result(0), PromiseResult::Failed);
+                env::log_str(&format!("failed: {}", failed));
+                env::value_return(&[failed as u8]);
+            }
+            _ => panic!("Method not found"),
+        });
+
+        let res = sim.call(&account("alice"), &account("caller"), "call", &[], 500);
+        assert_eq!(res.logs, ["failed: true"]);
+        assert_eq!(res.unwrap(), [1]);
+        // The deposit to the failed call is refunded to the caller.
+        assert_eq!(sim.balance(&account("alice")), Some(500));
+        assert_eq!(sim.balance(&account("caller")), Some(500));
+        assert_eq!(sim.balance(&account("failing")), Some(0));
+    }
+
+    #[test]
+    fn refund_to_missing_signer() {
+        let mut sim = Simulation::new();
+        sim.deploy(account("failing"), 0, |_| panic!("always fails"));
+
+        // The refund to the signer fails because the signer does not exist, and is burnt.
+        let res = sim.call(&account("ghost"), &account("failing"), "fail", &[], 100);
+        assert!(!res.is_ok());
+        assert_eq!(sim.balance(&account("failing")), Some(0));
+        assert_eq!(sim.balance(&account("ghost")), None);
+        assert_eq!(sim.balance(&account("system")), None);
+    }
+
+    #[test]
+    fn failed_call_reverts_state() {
+        let mut sim = Simulation::new();
+        sim.create_account(account("alice"), 100);
+        sim.deploy(account("counter"), 0, |method| {
+            let count = env::state_read::<u64>().unwrap_or_default() + 1;
+            env::state_write(&count);
+            env::log_str(&count.to_string());
+            assert_eq!(method, "increment");
+        });
+
+        assert!(sim.call(&account("alice"), &account("counter"), "increment", &[], 0).is_ok());
+        let res = sim.call(&account("alice"), &account("counter"), "other", &[], 10);
+        assert!(!res.is_ok());
+        assert_eq!(res.logs, ["2"]);
+        assert_eq!(sim.balance(&account("alice")), Some(100));
+        assert_eq!(sim.balance(&account("counter")), Some(0));
+        assert_eq!(sim.view(&account("counter"), "increment", &[]).logs, ["2"]);
+        assert_eq!(sim.view(&account("counter"), "increment", &[]).logs, ["2"]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::json_types::Base64VecU8;
+use borsh::BorshDeserialize;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+
+/// Key of the contract state which is read and written by `#[near_bindgen]`.
+const STATE_KEY: &[u8] = b"STATE";
+
+/// Storage of a deployed contract, loaded from the response of the `view_state` RPC query, which
+/// can be used to run unit tests and migration dry runs against production data.
+///
+/// The snapshot can be fetched with
+/// ```text
+/// curl https://rpc.mainnet.near.org -H 'Content-Type: application/json' -d '{
+///   "jsonrpc": "2.0", "id": "dontcare", "method": "query",
+///   "params": {"request_type": "view_state", "finality": "final",
+///              "account_id": "contract.near", "prefix_base64": ""}
+/// }' > state.json
+/// ```
+/// Note that RPC nodes only return the state of contracts with less than 50kb of storage.
+///
+/// # Examples
+/// ```
+/// use near_sdk::test_utils::StateSnapshot;
+/// use near_sdk::env;
+///
+/// let snapshot = StateSnapshot::from_json(r#"{
+///     "jsonrpc": "2.0",
+///     "result": {
+///         "values": [{ "key": "U1RBVEU=", "value": "AQAAAA==", "proof": [] }],
+///         "proof": []
+///     },
+///     "id": "dontcare"
+/// }"#).unwrap();
+/// assert_eq!(snapshot.root_state::<u32>(), Some(1));
+///
+/// // Contract methods now run against the state of the snapshot.
+/// snapshot.load();
+/// assert_eq!(env::state_read::<u32>(), Some(1));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    storage: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "crate::serde", untagged)]
+enum ViewStateResponse {
+    Rpc { result: ViewStateResult },
+    Result(ViewStateResult),
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "crate::serde")]
+struct ViewStateResult {
+    values: Vec<StateItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "crate::serde")]
+struct StateItem {
+    key: Base64VecU8,
+    value: Base64VecU8,
+}
+
+impl StateSnapshot {
+    /// Parses a `view_state` RPC response, either the full JSON-RPC response or only its
+    /// `result`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let result = match serde_json::from_str(json)? {
+            ViewStateResponse::Rpc { result } | ViewStateResponse::Result(result) => result,
+        };
+        let storage = result.values.into_iter().map(|item| (item.key.0, item.value.0)).collect();
+        Ok(Self { storage })
+    }
+
+    /// Reads and parses a `view_state` RPC response from the file at `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Returns the key-value pairs of the snapshot.
+    pub fn storage(&self) -> &HashMap<Vec<u8>, Vec<u8>> {
+        &self.storage
+    }
+
+    /// Returns the key-value pairs of the snapshot, to be passed to
+    /// [`MockedBlockchain::new`](crate::MockedBlockchain::new).
+    pub fn into_storage(self) -> HashMap<Vec<u8>, Vec<u8>> {
+        self.storage
+    }
+
+    /// Deserializes the contract state, which `#[near_bindgen]` stores under the `STATE` key.
+    /// Returns [`None`] if the snapshot has no contract state or it is not a valid `T`.
+    pub fn root_state<T: BorshDeserialize>(&self) -> Option<T> {
+        self.storage.get(STATE_KEY).and_then(|state| T::try_from_slice(state).ok())
+    }
+
+    /// Replaces the storage of the mocked blockchain with the snapshot. The storage is kept when
+    /// the context is changed with [`testing_env!`](crate::testing_env).
+    pub fn load(self) {
+        crate::mock::with_mocked_blockchain(|b| b.set_storage(self.storage));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::LookupMap;
+    use crate::test_utils::VMContextBuilder;
+    use crate::{env, testing_env};
+
+    #[test]
+    fn load_view_state_result() {
+        let mut map = LookupMap::new(b"m");
+        map.insert(&"alice.near".to_string(), &5u64);
+        env::state_write(&7u8);
+        let storage = crate::mock::with_mocked_blockchain(|b| b.take_storage());
+        let values: Vec<_> = storage
+            .iter()
+            .map(|(k, v)| {
+                serde_json::json!({ "key": base64::encode(k), "value": base64::encode(v), "proof": [] })
+            })
+            .collect();
+        let json = serde_json::json!({ "values": values, "proof": [] }).to_string();
+
+        let snapshot = StateSnapshot::from_json(&json).unwrap();
+        assert_eq!(snapshot.storage(), &storage);
+        assert_eq!(snapshot.root_state::<u8>(), Some(7));
+        assert_eq!(snapshot.root_state::<String>(), None);
+        assert!(!env::storage_has_key(STATE_KEY));
+
+        snapshot.load();
+        testing_env!(VMContextBuilder::new().build());
+        let map: LookupMap<String, u64> = LookupMap::new(b"m");
+        assert_eq!(map.get(&"alice.near".to_string()), Some(5));
+        assert_eq!(env::state_read::<u8>(), Some(7));
+
+        assert!(StateSnapshot::from_json(r#"{"jsonrpc": "2.0", "error": {}}"#).is_err());
+    }
+}
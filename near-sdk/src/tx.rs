@@ -0,0 +1,326 @@
+use crate::json_types::{Base64VecU8, U128};
+use crate::{env, AccountId, Gas, Promise, PublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Sequence of actions on a single account, executed in one receipt, which is built with the
+/// same actions and naming as wallets and `near-api-js` use for transactions.
+///
+/// The actions can be inspected, logged or returned as JSON before they are scheduled, which
+/// makes complex sequences of actions easier to audit than chained [`Promise`] calls. A `Tx` is
+/// scheduled by converting it into a [`Promise`] with [`Tx::into_promise`].
+///
+/// # Examples
+/// ```
+/// use near_sdk::serde_json::json;
+/// use near_sdk::{Gas, PublicKey, Tx, ONE_NEAR};
+///
+/// let key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+/// let tx = Tx::to("sub.alice.near".parse().unwrap())
+///     .create_account()
+///     .transfer(5 * ONE_NEAR)
+///     .add_full_access_key(key)
+///     .function_call("init", &json!({ "owner_id": "alice.near" }), 0, Gas(20_000_000_000_000));
+///
+/// assert_eq!(tx.actions().len(), 4);
+/// near_sdk::log!("Scheduling {}", near_sdk::serde_json::to_string(&tx).unwrap());
+/// tx.into_promise();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub struct Tx {
+    receiver_id: AccountId,
+    actions: Vec<TxAction>,
+}
+
+/// Action of a [`Tx`], serialized in the format used by wallets, such as
+/// `{"type": "Transfer", "params": {"deposit": "1"}}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde", tag = "type", content = "params")]
+pub enum TxAction {
+    CreateAccount,
+    #[serde(rename_all = "camelCase")]
+    DeployContract {
+        code: Base64VecU8,
+    },
+    #[serde(rename_all = "camelCase")]
+    FunctionCall {
+        method_name: String,
+        args: Base64VecU8,
+        gas: Gas,
+        deposit: U128,
+    },
+    #[serde(rename_all = "camelCase")]
+    Transfer {
+        deposit: U128,
+    },
+    #[serde(rename_all = "camelCase")]
+    Stake {
+        stake: U128,
+        public_key: PublicKey,
+    },
+    #[serde(rename_all = "camelCase")]
+    AddKey {
+        public_key: PublicKey,
+        access_key: TxAccessKey,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeleteKey {
+        public_key: PublicKey,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeleteAccount {
+        beneficiary_id: AccountId,
+    },
+}
+
+/// Access key added by a [`TxAction::AddKey`] action.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde", rename_all = "camelCase")]
+pub struct TxAccessKey {
+    pub nonce: u64,
+    pub permission: TxPermission,
+}
+
+/// Permission of a [`TxAccessKey`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(crate = "crate::serde")]
+pub enum TxPermission {
+    FullAccess,
+    /// Key which can only call `method_names` on `receiver_id`, or any method if it is empty,
+    /// and spend up to `allowance` on gas, or any amount if it is [`None`]. An allowance of zero
+    /// cannot be scheduled, see [`Tx::into_promise`].
+    #[serde(rename_all = "camelCase")]
+    FunctionCall {
+        receiver_id: AccountId,
+        allowance: Option<U128>,
+        method_names: Vec<String>,
+    },
+}
+
+impl Tx {
+    /// Creates a transaction without actions on `receiver_id`.
+    pub fn to(receiver_id: AccountId) -> Self {
+        Self { receiver_id, actions: Vec::new() }
+    }
+
+    /// Returns the account the actions are executed on.
+    pub fn receiver_id(&self) -> &AccountId {
+        &self.receiver_id
+    }
+
+    /// Returns the actions in the order they are executed.
+    pub fn actions(&self) -> &[TxAction] {
+        &self.actions
+    }
+
+    /// Adds `action` to the end of the transaction.
+    pub fn action(mut self, action: TxAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// Creates the receiver account.
+    pub fn create_account(self) -> Self {
+        self.action(TxAction::CreateAccount)
+    }
+
+    /// Deploys `code` to the receiver account.
+    pub fn deploy_contract(self, code: Vec<u8>) -> Self {
+        self.action(TxAction::DeployContract { code: code.into() })
+    }
+
+    /// Calls `method_name` with `args` serialized as JSON.
+    pub fn function_call<A: Serialize + ?Sized>(
+        self,
+        method_name: &str,
+        args: &A,
+        deposit: u128,
+        gas: Gas,
+    ) -> Self {
+        let args = serde_json::to_vec(args)
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize the function call arguments"));
+        self.function_call_raw(method_name, args, deposit, gas)
+    }
+
+    /// Calls `method_name` with `args` as the raw input.
+    pub fn function_call_raw(
+        self,
+        method_name: &str,
+        args: Vec<u8>,
+        deposit: u128,
+        gas: Gas,
+    ) -> Self {
+        self.action(TxAction::FunctionCall {
+            method_name: method_name.to_string(),
+            args: args.into(),
+            gas,
+            deposit: deposit.into(),
+        })
+    }
+
+    /// Transfers `deposit` to the receiver account.
+    pub fn transfer(self, deposit: u128) -> Self {
+        self.action(TxAction::Transfer { deposit: deposit.into() })
+    }
+
+    /// Stakes `stake` from the receiver account with the validator key `public_key`.
+    pub fn stake(self, stake: u128, public_key: PublicKey) -> Self {
+        self.action(TxAction::Stake { stake: stake.into(), public_key })
+    }
+
+    /// Adds a full access key to the receiver account.
+    pub fn add_full_access_key(self, public_key: PublicKey) -> Self {
+        self.action(TxAction::AddKey {
+            public_key,
+            access_key: TxAccessKey { nonce: 0, permission: TxPermission::FullAccess },
+        })
+    }
+
+    /// Adds a key to the receiver account which can only call `method_names` on `receiver_id`,
+    /// or any method if it is empty, and spend up to `allowance` on gas, or any amount if it is
+    /// [`None`]. An allowance of zero cannot be scheduled, see [`Tx::into_promise`].
+    pub fn add_function_call_key(
+        self,
+        public_key: PublicKey,
+        receiver_id: AccountId,
+        method_names: &[&str],
+        allowance: Option<u128>,
+    ) -> Self {
+        self.action(TxAction::AddKey {
+            public_key,
+            access_key: TxAccessKey {
+                nonce: 0,
+                permission: TxPermission::FunctionCall {
+                    receiver_id,
+                    allowance: allowance.map(U128),
+                    method_names: method_names.iter().map(|m| m.to_string()).collect(),
+                },
+            },
+        })
+    }
+
+    /// Deletes `public_key` from the receiver account.
+    pub fn delete_key(self, public_key: PublicKey) -> Self {
+        self.action(TxAction::DeleteKey { public_key })
+    }
+
+    /// Deletes the receiver account and transfers its balance to `beneficiary_id`.
+    pub fn delete_account(self, beneficiary_id: AccountId) -> Self {
+        self.action(TxAction::DeleteAccount { beneficiary_id })
+    }
+
+    /// Converts the transaction into a [`Promise`] with the same actions, which schedules them
+    /// when it is dropped and can be chained with other promises.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a function call key has an allowance of zero. The promise API treats an
+    /// allowance of zero as unlimited, so such a key would be added without any limit.
+    pub fn into_promise(self) -> Promise {
+        self.actions.into_iter().fold(Promise::new(self.receiver_id), |promise, action| {
+            match action {
+                TxAction::CreateAccount => promise.create_account(),
+                TxAction::DeployContract { code } => promise.deploy_contract(code.into()),
+                TxAction::FunctionCall { method_name, args, gas, deposit } => {
+                    promise.function_call(method_name, args.into(), deposit.0, gas)
+                }
+                TxAction::Transfer { deposit } => promise.transfer(deposit.0),
+                TxAction::Stake { stake, public_key } => promise.stake(stake.0, public_key),
+                TxAction::AddKey { public_key, access_key } => match access_key.permission {
+                    TxPermission::FullAccess => {
+                        promise.add_full_access_key_with_nonce(public_key, access_key.nonce)
+                    }
+                    TxPermission::FunctionCall { receiver_id, allowance, method_names } => {
+                        // An allowance of zero is an unlimited allowance in the promise API.
+                        crate::require!(
+                            allowance != Some(U128(0)),
+                            "Allowance of a function call key cannot be zero, use None for an \
+                            unlimited allowance"
+                        );
+                        promise.add_access_key_with_nonce(
+                            public_key,
+                            allowance.map_or(0, |a| a.0),
+                            receiver_id,
+                            method_names.join(","),
+                            access_key.nonce,
+                        )
+                    }
+                },
+                TxAction::DeleteKey { public_key } => promise.delete_key(public_key),
+                TxAction::DeleteAccount { beneficiary_id } => {
+                    promise.delete_account(beneficiary_id)
+                }
+            }
+        })
+    }
+}
+
+impl From<Tx> for Promise {
+    fn from(tx: Tx) -> Self {
+        tx.into_promise()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::VmAction;
+    use crate::test_utils::get_created_receipts;
+    use crate::test_utils::test_env::{alice, bob};
+
+    #[test]
+    fn into_promise() {
+        let pk: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+        let tx = Tx::to(bob())
+            .create_account()
+            .transfer(100)
+            .add_function_call_key(pk.clone(), alice(), &["a", "b"], None)
+            .function_call("init", &serde_json::json!({ "x": 1 }), 5, Gas(10));
+        tx.clone().into_promise();
+
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, bob());
+        assert_eq!(
+            receipts[0].actions,
+            [
+                VmAction::CreateAccount,
+                VmAction::Transfer { deposit: 100 },
+                VmAction::AddKeyWithFunctionCall {
+                    public_key: pk,
+                    nonce: 0,
+                    allowance: None,
+                    receiver_id: alice(),
+                    function_names: vec!["a".to_string(), "b".to_string()],
+                },
+                VmAction::FunctionCall {
+                    function_name: "init".to_string(),
+                    args: br#"{"x":1}"#.to_vec(),
+                    gas: Gas(10),
+                    deposit: 5,
+                },
+            ]
+        );
+
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["receiverId"], "bob.near");
+        assert_eq!(json["actions"][0], serde_json::json!({ "type": "CreateAccount" }));
+        assert_eq!(
+            json["actions"][1],
+            serde_json::json!({ "type": "Transfer", "params": { "deposit": "100" } })
+        );
+        assert_eq!(
+            json["actions"][2]["params"]["accessKey"]["permission"]["FunctionCall"]["methodNames"],
+            serde_json::json!(["a", "b"])
+        );
+        assert_eq!(serde_json::from_value::<Tx>(json).unwrap(), tx);
+    }
+
+    #[test]
+    #[should_panic(expected = "Allowance of a function call key cannot be zero")]
+    fn zero_allowance() {
+        let pk: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+        Tx::to(bob()).add_function_call_key(pk, alice(), &[], Some(0)).into_promise();
+    }
+}
@@ -4,6 +4,7 @@ use std::convert::TryFrom;
 use std::fmt;
 
 use crate::env::is_valid_account_id;
+use crate::{CurveType, PublicKey};
 
 /// Account identifier. This is the human readable utf8 string which is used internally to index
 /// accounts on the network and their respective state.
@@ -54,6 +55,51 @@ impl AccountId {
         debug_assert!(is_valid_account_id(id.as_bytes()));
         Self(id)
     }
+
+    /// Returns the implicit account ID of an ED25519 public key, which is the lowercase hex
+    /// encoding of the key. Returns [`None`] if the key is not an ED25519 key.
+    ///
+    /// # Examples
+    /// ```
+    /// use near_sdk::{AccountId, PublicKey};
+    ///
+    /// let pk: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+    /// assert_eq!(
+    ///     AccountId::from_ed25519_pubkey(&pk).unwrap().as_str(),
+    ///     "4da7e0f4096aaf2ce55e371657cd3089ba1e9f59f4d6e27bd02e472a16a61dc1"
+    /// );
+    /// assert_eq!(AccountId::from_secp256k1_pubkey(&pk), None);
+    /// ```
+    pub fn from_ed25519_pubkey(public_key: &PublicKey) -> Option<Self> {
+        match public_key.curve_type() {
+            CurveType::ED25519 => Some(Self(hex_encode(&public_key.as_bytes()[1..]))),
+            CurveType::SECP256K1 => None,
+        }
+    }
+
+    /// Returns the Ethereum-compatible implicit account ID of a SECP256K1 public key, which is
+    /// `0x` followed by the lowercase hex encoding of the last 20 bytes of the keccak256 hash of
+    /// the key, the same as the Ethereum address of the key. Returns [`None`] if the key is not a
+    /// SECP256K1 key.
+    pub fn from_secp256k1_pubkey(public_key: &PublicKey) -> Option<Self> {
+        match public_key.curve_type() {
+            CurveType::SECP256K1 => {
+                let hash = crate::env::keccak256_array(&public_key.as_bytes()[1..]);
+                Some(Self(format!("0x{}", hex_encode(&hash[12..]))))
+            }
+            CurveType::ED25519 => None,
+        }
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        s.push(HEX[(byte >> 4) as usize] as char);
+        s.push(HEX[(byte & 0xf) as usize] as char);
+    }
+    s
 }
 
 impl fmt::Display for AccountId {
@@ -164,4 +210,31 @@ mod tests {
         // Test to make sure the account ID is serialized as a string through borsh
         assert_eq!(str::try_to_vec(id).unwrap(), account_id.try_to_vec().unwrap());
     }
+
+    #[test]
+    fn implicit_account_ids() {
+        let ed25519 = PublicKey::try_from([vec![0], (0..32).collect()].concat()).unwrap();
+        assert_eq!(
+            AccountId::from_ed25519_pubkey(&ed25519).unwrap().as_str(),
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        );
+        assert_eq!(AccountId::from_secp256k1_pubkey(&ed25519), None);
+
+        // Public key of the secret key `1`, the generator point.
+        let secp256k1 = PublicKey::try_from(
+            [
+                vec![1],
+                hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                    .unwrap(),
+                hex::decode("483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8")
+                    .unwrap(),
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let account_id = AccountId::from_secp256k1_pubkey(&secp256k1).unwrap();
+        assert_eq!(account_id.as_str(), "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf");
+        assert!(is_valid_account_id(account_id.as_bytes()));
+        assert_eq!(AccountId::from_ed25519_pubkey(&secp256k1), None);
+    }
 }
@@ -0,0 +1,115 @@
+use borsh::maybestd::io;
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::convert::{TryFrom, TryInto};
+
+/// A point of the G1 group of the BLS12-381 curve, encoded as the big-endian `x` and `y`
+/// coordinates, as used by the BLS12-381 functions of [`env`](crate::env).
+///
+/// The point at infinity is encoded with the second most significant bit of the first byte set
+/// and the rest of the bits unset. Points are not validated when they are created, invalid
+/// points are rejected by the host functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bls12381G1Point([u8; 96]);
+
+/// A point of the G2 group of the BLS12-381 curve, encoded as the big-endian `x` and `y`
+/// coordinates, where each coordinate is an element of Fp2 encoded as its `c1` and `c0`
+/// components, as used by the BLS12-381 functions of [`env`](crate::env).
+///
+/// The point at infinity is encoded like for [`Bls12381G1Point`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bls12381G2Point([u8; 192]);
+
+macro_rules! impl_bls12381_point {
+    ($name:ident, $len:expr) => {
+        impl $name {
+            /// Length of an encoded point.
+            pub const LEN: usize = $len;
+
+            /// Returns the encoding of the point.
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl From<$name> for [u8; $len] {
+            fn from(point: $name) -> Self {
+                point.0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = std::array::TryFromSliceError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                bytes.try_into().map(Self)
+            }
+        }
+
+        //? Manual implementations needed only because borsh doesn't implement arrays of this
+        //? length.
+        impl BorshSerialize for $name {
+            fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.0)
+            }
+        }
+
+        impl BorshDeserialize for $name {
+            fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+                if buf.len() < $len {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Unexpected length of input",
+                    ));
+                }
+                let (point, rest) = buf.split_at($len);
+                *buf = rest;
+                Self::try_from(point).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+            }
+        }
+    };
+}
+
+impl_bls12381_point!(Bls12381G1Point, 96);
+impl_bls12381_point!(Bls12381G2Point, 192);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let mut bytes = [0; 96];
+        bytes[0] = 0x40;
+        let infinity = Bls12381G1Point::from(bytes);
+        assert_eq!(infinity.as_bytes(), &bytes);
+        assert_eq!(infinity.as_ref(), &bytes[..]);
+        assert_eq!(<[u8; 96]>::from(infinity), bytes);
+        assert_eq!(Bls12381G1Point::try_from(&bytes[..]).unwrap(), infinity);
+        assert!(Bls12381G1Point::try_from(&bytes[1..]).is_err());
+        assert!(Bls12381G2Point::try_from(&[0; 96][..]).is_err());
+        assert_eq!(Bls12381G2Point::LEN, 2 * Bls12381G1Point::LEN);
+    }
+
+    #[test]
+    fn borsh_as_bytes() {
+        let point = Bls12381G2Point::from([3; 192]);
+        let serialized = (point, 1u8).try_to_vec().unwrap();
+        assert_eq!(serialized.len(), 193);
+        assert_eq!(&serialized[..192], point.as_bytes());
+        assert_eq!(<(Bls12381G2Point, u8)>::try_from_slice(&serialized).unwrap(), (point, 1));
+        assert!(Bls12381G1Point::try_from_slice(&[0; 95]).is_err());
+        assert!(Bls12381G1Point::try_from(&[0; 97][..]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use bs58::decode::Error as B58Error;
+use std::convert::{TryFrom, TryInto};
+
+use crate::types::{CurveType, PublicKey};
+
+/// An ed25519 public key, which can be used to verify signatures with
+/// [`env::ed25519_verify`](crate::env::ed25519_verify).
+///
+/// Like [`PublicKey`], it is serialized to JSON as a base58 string prefixed with `ed25519:`,
+/// and the prefix is optional when parsing. A [`PublicKey`] of the ed25519 curve can be converted
+/// to an [`Ed25519PublicKey`] with [`TryFrom`].
+///
+/// # Example
+/// ```
+/// use near_sdk::{Ed25519PublicKey, PublicKey};
+/// use std::convert::TryFrom;
+///
+/// let key: PublicKey = "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp".parse().unwrap();
+/// let ed25519 = Ed25519PublicKey::try_from(&key).unwrap();
+/// assert_eq!(PublicKey::from(ed25519), key);
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize,
+)]
+pub struct Ed25519PublicKey([u8; 32]);
+
+/// An ed25519 signature, which can be verified with
+/// [`env::ed25519_verify`](crate::env::ed25519_verify).
+///
+/// It is serialized to JSON as a base58 string prefixed with `ed25519:`, and the prefix is
+/// optional when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, BorshSerialize, BorshDeserialize)]
+pub struct Ed25519Signature([u8; 64]);
+
+/// An uncompressed secp256k1 public key without the leading `0x04` byte, as returned by
+/// [`env::ecrecover_typed`](crate::env::ecrecover_typed).
+///
+/// Like [`PublicKey`], it is serialized to JSON as a base58 string prefixed with `secp256k1:`,
+/// and the prefix is optional when parsing. [`eth_address`](Self::eth_address) derives the
+/// Ethereum address of the key.
+///
+/// # Example
+/// ```
+/// use near_sdk::{PublicKey, Secp256k1PublicKey};
+/// use std::convert::TryFrom;
+///
+/// let key = Secp256k1PublicKey::from([1; 64]);
+/// assert_eq!(Secp256k1PublicKey::try_from(&PublicKey::from(key)).unwrap(), key);
+/// assert_eq!(key.eth_address_hex().len(), 42);
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize,
+)]
+pub struct Secp256k1PublicKey([u8; 64]);
+
+macro_rules! impl_curve_key_type {
+    ($name:ident, $len:expr, $prefix:literal) => {
+        impl $name {
+            /// Returns the bytes of the key or signature.
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl From<$name> for [u8; $len] {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = ParseCurveKeyError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+                bytes.try_into().map(Self).map_err(|_| ParseCurveKeyError {
+                    kind: ParseCurveKeyErrorKind::InvalidLength(bytes.len()),
+                })
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = ParseCurveKeyError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                let data = value.strip_prefix(concat!($prefix, ":")).unwrap_or(value);
+                Self::try_from(bs58::decode(data).into_vec()?.as_slice())
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, concat!($prefix, ":{}"), bs58::encode(&self.0).into_string())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let s: String = serde::Deserialize::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+macro_rules! impl_public_key_conversions {
+    ($name:ident, $curve:expr) => {
+        impl TryFrom<&PublicKey> for $name {
+            type Error = ParseCurveKeyError;
+
+            fn try_from(key: &PublicKey) -> Result<Self, Self::Error> {
+                if key.curve_type() != $curve {
+                    return Err(ParseCurveKeyError {
+                        kind: ParseCurveKeyErrorKind::UnsupportedCurve,
+                    });
+                }
+                Self::try_from(&key.as_bytes()[1..])
+            }
+        }
+
+        impl From<$name> for PublicKey {
+            fn from(key: $name) -> Self {
+                let data = [&[$curve as u8], &key.0[..]].concat();
+                PublicKey::try_from(data).unwrap_or_else(|_| crate::env::abort())
+            }
+        }
+    };
+}
+
+impl_curve_key_type!(Ed25519PublicKey, 32, "ed25519");
+impl_curve_key_type!(Ed25519Signature, 64, "ed25519");
+impl_curve_key_type!(Secp256k1PublicKey, 64, "secp256k1");
+impl_public_key_conversions!(Ed25519PublicKey, CurveType::ED25519);
+impl_public_key_conversions!(Secp256k1PublicKey, CurveType::SECP256K1);
+
+impl Secp256k1PublicKey {
+    /// Returns the Ethereum address of the key, which is the last 20 bytes of the keccak256
+    /// hash of the key.
+    pub fn eth_address(&self) -> [u8; 20] {
+        let hash = crate::env::keccak256_array(&self.0);
+        let mut address = [0; 20];
+        address.copy_from_slice(&hash[12..]);
+        address
+    }
+
+    /// Returns the Ethereum address of the key as `0x` followed by lowercase hex, which is
+    /// also the Ethereum-compatible implicit account ID of the key.
+    pub fn eth_address_hex(&self) -> String {
+        let mut s = String::with_capacity(42);
+        s.push_str("0x");
+        for byte in self.eth_address().iter() {
+            s.push_str(&format!("{:02x}", byte));
+        }
+        s
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseCurveKeyError {
+    kind: ParseCurveKeyErrorKind,
+}
+
+#[derive(Debug)]
+enum ParseCurveKeyErrorKind {
+    InvalidLength(usize),
+    Base58(B58Error),
+    UnsupportedCurve,
+}
+
+impl std::fmt::Display for ParseCurveKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseCurveKeyErrorKind::InvalidLength(l) => write!(f, "invalid length {}", l),
+            ParseCurveKeyErrorKind::Base58(e) => write!(f, "base58 decoding error: {}", e),
+            ParseCurveKeyErrorKind::UnsupportedCurve => {
+                write!(f, "the key is of a different curve")
+            }
+        }
+    }
+}
+
+impl From<B58Error> for ParseCurveKeyError {
+    fn from(e: B58Error) -> Self {
+        Self { kind: ParseCurveKeyErrorKind::Base58(e) }
+    }
+}
+
+impl std::error::Error for ParseCurveKeyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_and_public_key_conversions() {
+        let key: Ed25519PublicKey =
+            serde_json::from_str("\"ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp\"")
+                .unwrap();
+        let unprefixed: Ed25519PublicKey =
+            serde_json::from_str("\"6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp\"").unwrap();
+        assert_eq!(key, unprefixed);
+        assert_eq!(
+            serde_json::to_string(&key).unwrap(),
+            "\"ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp\""
+        );
+        assert_eq!(Ed25519PublicKey::try_from(&PublicKey::from(key)).unwrap(), key);
+
+        let secp256k1 = PublicKey::try_from([vec![1], vec![0; 64]].concat()).unwrap();
+        assert!(Ed25519PublicKey::try_from(&secp256k1).is_err());
+        assert!("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEt"
+            .parse::<Ed25519Signature>()
+            .is_err());
+
+        let signature = Ed25519Signature::from([7; 64]);
+        assert_eq!(signature.to_string().parse::<Ed25519Signature>().unwrap(), signature);
+        assert_eq!(signature.try_to_vec().unwrap(), [7; 64]);
+
+        let secp256k1_key = Secp256k1PublicKey::try_from(&secp256k1).unwrap();
+        assert_eq!(secp256k1_key.to_string(), String::from(&secp256k1));
+        assert_eq!(secp256k1_key.to_string().parse::<PublicKey>().unwrap(), secp256k1);
+        assert!(Secp256k1PublicKey::try_from(&PublicKey::from(key)).is_err());
+    }
+}
@@ -4,6 +4,16 @@ pub use self::vm_types::*;
 mod public_key;
 pub use self::public_key::{CurveType, PublicKey};
 
+mod curve_key;
+pub use self::curve_key::{
+    Ed25519PublicKey, Ed25519Signature, ParseCurveKeyError, Secp256k1PublicKey,
+};
+
+#[cfg(feature = "bls12381")]
+mod bls12381;
+#[cfg(feature = "bls12381")]
+pub use self::bls12381::{Bls12381G1Point, Bls12381G2Point};
+
 mod primitives;
 pub use self::primitives::*;
 
@@ -13,6 +23,9 @@ pub use self::account_id::{AccountId, ParseAccountIdError};
 mod gas;
 pub use self::gas::Gas;
 
+mod near_amount;
+pub use self::near_amount::{format_near, parse_near, ParseNearError};
+
 /// Raw type for duration in nanoseconds
 pub type Duration = u64;
 
@@ -22,6 +35,9 @@ pub type Timestamp = u64;
 /// Raw type for 32 bytes of the hash.
 pub type CryptoHash = [u8; 32];
 
+/// ID of the data a yielded promise waits for, which is passed to resume it.
+pub type DataId = CryptoHash;
+
 /// Balance of one Yocto NEAR, which is the smallest denomination. This value is 10^-24 of one NEAR.
 pub const ONE_YOCTO: Balance = 1;
 
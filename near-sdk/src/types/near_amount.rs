@@ -0,0 +1,130 @@
+use crate::{Balance, ONE_NEAR};
+
+/// Number of decimal places of yoctoNEAR in one NEAR.
+const NEAR_DECIMALS: usize = 24;
+
+/// Formats a yoctoNEAR amount in NEAR with `precision` decimal places, for logs and events.
+///
+/// Digits beyond the precision are truncated rather than rounded, so the formatted amount is
+/// never larger than the actual amount. The precision is capped at the 24 decimal places of
+/// yoctoNEAR.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{format_near, ONE_NEAR};
+///
+/// assert_eq!(format_near(ONE_NEAR / 4, 2), "0.25 N");
+/// assert_eq!(format_near(2 * ONE_NEAR - 1, 3), "1.999 N");
+/// assert_eq!(format_near(5 * ONE_NEAR, 0), "5 N");
+/// ```
+pub fn format_near(amount: Balance, precision: usize) -> String {
+    let whole = amount / ONE_NEAR;
+    let precision = precision.min(NEAR_DECIMALS);
+    if precision == 0 {
+        return format!("{} N", whole);
+    }
+    let fraction = format!("{:0width$}", amount % ONE_NEAR, width = NEAR_DECIMALS);
+    format!("{}.{} N", whole, &fraction[..precision])
+}
+
+/// Parses an amount of NEAR, such as `"0.25 N"`, into yoctoNEAR. The unit can be `N` or `NEAR`,
+/// or can be left out, and the amount can have up to 24 decimal places.
+///
+/// # Examples
+/// ```
+/// use near_sdk::{parse_near, ONE_NEAR};
+///
+/// assert_eq!(parse_near("0.25 N").unwrap(), ONE_NEAR / 4);
+/// assert_eq!(parse_near("3NEAR").unwrap(), 3 * ONE_NEAR);
+/// assert_eq!(parse_near("0.000000000000000000000001").unwrap(), 1);
+/// assert!(parse_near("1.5 yN").is_err());
+/// ```
+pub fn parse_near(s: &str) -> Result<Balance, ParseNearError> {
+    let s = s.trim();
+    let amount =
+        s.strip_suffix("NEAR").or_else(|| s.strip_suffix('N')).map(str::trim_end).unwrap_or(s);
+    let (whole, fraction) = match amount.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (amount, ""),
+    };
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+        return Err(ParseNearError { kind: ParseNearErrorKind::InvalidNumber });
+    }
+    if fraction.len() > NEAR_DECIMALS {
+        return Err(ParseNearError { kind: ParseNearErrorKind::TooManyDecimals(fraction.len()) });
+    }
+
+    let overflow = ParseNearError { kind: ParseNearErrorKind::Overflow };
+    let whole: Balance = whole.parse().map_err(|_| overflow.clone())?;
+    // Fraction has at most 24 digits, which always fits, and is scaled up to yoctoNEAR.
+    let fraction: Balance =
+        format!("{:0<width$}", fraction, width = NEAR_DECIMALS).parse().unwrap();
+    whole.checked_mul(ONE_NEAR).and_then(|whole| whole.checked_add(fraction)).ok_or(overflow)
+}
+
+/// Error returned by [`parse_near`] when the string is not a valid amount of NEAR, has more than
+/// 24 decimal places, or the amount does not fit into a [`Balance`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNearError {
+    kind: ParseNearErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseNearErrorKind {
+    InvalidNumber,
+    TooManyDecimals(usize),
+    Overflow,
+}
+
+impl std::fmt::Display for ParseNearError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseNearErrorKind::InvalidNumber => write!(f, "invalid NEAR amount"),
+            ParseNearErrorKind::TooManyDecimals(d) => {
+                write!(f, "NEAR amount has {} decimal places, at most 24 are allowed", d)
+            }
+            ParseNearErrorKind::Overflow => write!(f, "NEAR amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ParseNearError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_and_parse() {
+        for (amount, precision, formatted) in [
+            (0, 2, "0.00 N"),
+            (1, 24, "0.000000000000000000000001 N"),
+            (1, 30, "0.000000000000000000000001 N"),
+            (ONE_NEAR * 3 / 2, 1, "1.5 N"),
+            (ONE_NEAR * 1234 + ONE_NEAR / 100 * 99, 5, "1234.99000 N"),
+            (u128::MAX, 0, "340282366920938 N"),
+        ] {
+            assert_eq!(format_near(amount, precision), formatted);
+        }
+
+        for (s, amount) in [
+            ("0", 0),
+            ("1.5", ONE_NEAR * 3 / 2),
+            (" 1.5 N ", ONE_NEAR * 3 / 2),
+            ("2. NEAR", ONE_NEAR * 2),
+            ("0.000000000000000000000001N", 1),
+            ("340282366920938.463463374607431768211455", u128::MAX),
+        ] {
+            assert_eq!(parse_near(s), Ok(amount), "{}", s);
+        }
+
+        for s in ["", "N", ".5", "1.2.3", "-1", "1e3", "1 yN", "0.0000000000000000000000001"] {
+            assert!(parse_near(s).is_err(), "{}", s);
+        }
+        assert_eq!(
+            parse_near("340282366920938.463463374607431768211456").unwrap_err().to_string(),
+            "NEAR amount is too large"
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{env, EpochHeight};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Bookkeeping for work that should be done at most once per epoch, such as distributing rewards
+/// or updating stake in staking contracts.
+///
+/// The schedule stores the last epoch in which the work was done and should be kept in the
+/// contract state, so that [`should_run`](Self::should_run) is `true` only for the first call in
+/// each new epoch.
+///
+/// # Examples
+/// ```
+/// use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+/// use near_sdk::EpochSchedule;
+///
+/// #[derive(BorshSerialize, BorshDeserialize)]
+/// struct Pool {
+///     distribution: EpochSchedule,
+///     rewards_per_epoch: u128,
+///     total_rewards: u128,
+/// }
+///
+/// impl Pool {
+///     fn ping(&mut self) {
+///         // Rewards of epochs without a ping are distributed on the next one.
+///         let epochs = self.distribution.epochs_since_last_run().unwrap_or(1);
+///         if self.distribution.try_run() {
+///             self.total_rewards += self.rewards_per_epoch * epochs as u128;
+///         }
+///     }
+/// }
+///
+/// let mut pool =
+///     Pool { distribution: EpochSchedule::new(), rewards_per_epoch: 10, total_rewards: 0 };
+/// pool.ping();
+/// pool.ping();
+/// assert_eq!(pool.total_rewards, 10);
+/// ```
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EpochSchedule {
+    last_run: Option<EpochHeight>,
+}
+
+impl EpochSchedule {
+    /// Creates a schedule which has not run yet.
+    pub fn new() -> Self {
+        Self { last_run: None }
+    }
+
+    /// Returns the epoch in which the schedule last ran.
+    pub fn last_run(&self) -> Option<EpochHeight> {
+        self.last_run
+    }
+
+    /// Returns `true` if the schedule has not run in the current epoch.
+    pub fn should_run(&self) -> bool {
+        match self.last_run {
+            Some(last) => last < env::epoch_height(),
+            None => true,
+        }
+    }
+
+    /// Returns the number of epochs since the schedule last ran, or [`None`] if it has not run
+    /// yet.
+    pub fn epochs_since_last_run(&self) -> Option<EpochHeight> {
+        self.last_run.map(|last| env::epoch_height().saturating_sub(last))
+    }
+
+    /// Records that the schedule ran in the current epoch.
+    pub fn mark_run(&mut self) {
+        self.last_run = Some(env::epoch_height());
+    }
+
+    /// Records that the schedule ran in the current epoch if it [`should_run`](Self::should_run),
+    /// returning `true` if it did.
+    pub fn try_run(&mut self) -> bool {
+        let should_run = self.should_run();
+        if should_run {
+            self.mark_run();
+        }
+        should_run
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(test)]
+mod tests {
+    use super::EpochSchedule;
+    use crate::test_utils::VMContextBuilder;
+    use crate::testing_env;
+
+    #[test]
+    fn once_per_epoch() {
+        let mut schedule = EpochSchedule::new();
+        assert!(schedule.should_run());
+        assert_eq!(schedule.epochs_since_last_run(), None);
+
+        testing_env!(VMContextBuilder::new().epoch_height(5).build());
+        assert!(schedule.try_run());
+        assert!(!schedule.try_run());
+        assert_eq!(schedule.last_run(), Some(5));
+        assert_eq!(schedule.epochs_since_last_run(), Some(0));
+
+        testing_env!(VMContextBuilder::new().epoch_height(8).build());
+        assert!(schedule.should_run());
+        assert_eq!(schedule.epochs_since_last_run(), Some(3));
+        schedule.mark_run();
+        assert!(!schedule.should_run());
+        assert_eq!(schedule.last_run(), Some(8));
+    }
+}
@@ -1,5 +1,11 @@
 pub(crate) mod storage_key_impl;
 
+mod serialized_size;
+pub use self::serialized_size::*;
+
+mod epoch_schedule;
+pub use self::epoch_schedule::EpochSchedule;
+
 #[cfg(feature = "unstable")]
 mod stable_map;
 #[cfg(feature = "unstable")]
@@ -49,6 +55,11 @@ macro_rules! log {
 /// This macro can be used similarly to [`assert!`] but will reduce code size by not including
 /// file and rust specific data in the panic message.
 ///
+/// The message can also be given as a format string with arguments, like [`std::format`], in
+/// which case the message is only formatted if the assertion fails. An error code can be added
+/// before the message with `code = <expr>`, which prefixes the panic message with the code
+/// followed by a colon, so that callers can match on the code of the error.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -58,6 +69,9 @@ macro_rules! log {
 /// let a = 2;
 /// require!(a > 0);
 /// require!("test" != "other", "Some custom error message if false");
+/// require!(a < 10, "Value {} must be less than {}", a, 10);
+/// // Panics with "E_AMOUNT_TOO_LOW: Amount 2 is too low" if false
+/// require!(a > 1, code = "E_AMOUNT_TOO_LOW", "Amount {} is too low", a);
 /// # }
 /// ```
 #[macro_export]
@@ -69,13 +83,25 @@ macro_rules! require {
             $crate::env::panic_str("require! assertion failed");
         }
     };
-    ($cond:expr, $message:expr $(,)?) => {
+    ($cond:expr, code = $code:expr, $($arg:tt)+) => {
+        $crate::require!($cond, "{}: {}", $code, format_args!($($arg)+))
+    };
+    ($cond:expr, $fmt:literal, $($arg:tt)+) => {
         if cfg!(debug_assertions) {
-            // Error message must be &str to match panic_str signature
-            let msg: &str = &$message;
-            assert!($cond, "{}", msg)
+            assert!($cond, $fmt, $($arg)+)
         } else if !$cond {
-            $crate::env::panic_str(&$message)
+            $crate::env::panic_str(&format!($fmt, $($arg)+))
+        }
+    };
+    ($cond:expr, $message:expr $(,)?) => {
+        if !$cond {
+            if cfg!(debug_assertions) {
+                // Error message must be &str to match panic_str signature
+                let msg: &str = &$message;
+                panic!("{}", msg)
+            } else {
+                $crate::env::panic_str(&$message)
+            }
         }
     };
 }
@@ -172,4 +198,30 @@ mod tests {
 
         assert_eq!(get_logs(), vec!["hello user_name (25)".to_string()]);
     }
+
+    #[test]
+    fn test_require_lazy_message() {
+        let mut formatted = 0;
+        let mut message = || {
+            formatted += 1;
+            "message".to_string()
+        };
+        require!(true, message());
+        require!(true, "value {}", message());
+        require!(true, code = 1, "value {}", message());
+        assert_eq!(formatted, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "value 5 is too high")]
+    fn test_require_format() {
+        require!(1 + 1 == 2, "unused {}", 1);
+        require!(5 < 3, "value {} is too {}", 5, "high");
+    }
+
+    #[test]
+    #[should_panic(expected = "E_LIMIT: value 5 is too high")]
+    fn test_require_code() {
+        require!(5 < 3, code = "E_LIMIT", "value {} is too high", 5);
+    }
 }
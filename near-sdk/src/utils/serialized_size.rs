@@ -0,0 +1,271 @@
+use crate::json_types::{I128, I64, U128, U64};
+use crate::{AccountId, Gas, Promise, PromiseOrValue};
+
+/// Maximum length in bytes of the input arguments of a function call, from the runtime config.
+pub const MAX_ARGUMENTS_LENGTH: usize = 4 * 1024 * 1024;
+
+/// Maximum length in bytes of the value returned from a function call, from the runtime config.
+pub const MAX_RETURN_DATA_LENGTH: usize = 4 * 1024 * 1024;
+
+/// Upper bound of the serialized size of a type, which is used by the `#[check_size]` attribute
+/// of `#[near_bindgen]` methods to check at compile time that the arguments and result of a
+/// method always fit within [`MAX_ARGUMENTS_LENGTH`] and [`MAX_RETURN_DATA_LENGTH`]. A failed
+/// check is reported as a `deprecated` warning naming the method, which can be turned into an
+/// error with `#![deny(deprecated)]`.
+///
+/// A size of [`None`] means that the serialized size is not bounded, such as for [`Vec`] and
+/// [`String`], which will always fail the check.
+///
+/// # Examples
+/// ```
+/// use near_sdk::serde::Serialize;
+/// use near_sdk::{max_json_object_size, max_size_sum, MaxSerializedSize};
+///
+/// #[derive(Serialize)]
+/// #[serde(crate = "near_sdk::serde")]
+/// struct Position {
+///     x: u32,
+///     y: u32,
+/// }
+///
+/// impl MaxSerializedSize for Position {
+///     const MAX_BORSH_SIZE: Option<usize> =
+///         max_size_sum(&[u32::MAX_BORSH_SIZE, u32::MAX_BORSH_SIZE]);
+///     const MAX_JSON_SIZE: Option<usize> =
+///         max_json_object_size(&[("x", u32::MAX_JSON_SIZE), ("y", u32::MAX_JSON_SIZE)]);
+/// }
+///
+/// assert_eq!(Position::MAX_BORSH_SIZE, Some(8));
+/// assert_eq!(
+///     Position::MAX_JSON_SIZE,
+///     Some(near_sdk::serde_json::to_string(&Position { x: u32::MAX, y: u32::MAX }).unwrap().len())
+/// );
+/// ```
+pub trait MaxSerializedSize {
+    /// Maximum length of the value serialized with Borsh.
+    const MAX_BORSH_SIZE: Option<usize>;
+    /// Maximum length of the value serialized as JSON without whitespace.
+    const MAX_JSON_SIZE: Option<usize>;
+}
+
+/// Sum of the given sizes, or [`None`] if any of the sizes are unbounded.
+pub const fn max_size_sum(sizes: &[Option<usize>]) -> Option<usize> {
+    let mut total: usize = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        total = match sizes[i] {
+            Some(size) => match total.checked_add(size) {
+                Some(total) => total,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+    Some(total)
+}
+
+/// Largest of the given sizes, or [`None`] if any of the sizes are unbounded.
+pub const fn max_size_of(sizes: &[Option<usize>]) -> Option<usize> {
+    let mut max: usize = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        match sizes[i] {
+            Some(size) if size > max => max = size,
+            Some(_) => {}
+            None => return None,
+        }
+        i += 1;
+    }
+    Some(max)
+}
+
+/// Maximum size of a JSON object with the given field names and maximum value sizes.
+pub const fn max_json_object_size(fields: &[(&str, Option<usize>)]) -> Option<usize> {
+    // Braces around the object and commas between fields.
+    let mut total = 2 + fields.len().saturating_sub(1);
+    let mut i = 0;
+    while i < fields.len() {
+        let (name, size) = fields[i];
+        // Quotes around the name and a colon before the value.
+        total = match size {
+            Some(size) => match total.checked_add(name.len() + 3 + size) {
+                Some(total) => total,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+    Some(total)
+}
+
+/// Maximum size of a JSON array with elements of the given maximum sizes.
+pub const fn max_json_array_size(elements: &[Option<usize>]) -> Option<usize> {
+    match max_size_sum(elements) {
+        // Brackets around the array and commas between elements.
+        Some(size) => size.checked_add(2 + elements.len().saturating_sub(1)),
+        None => None,
+    }
+}
+
+/// Returns `true` if `size` is unbounded or larger than `limit`.
+pub const fn exceeds_limit(size: Option<usize>, limit: usize) -> bool {
+    match size {
+        Some(size) => size > limit,
+        None => true,
+    }
+}
+
+macro_rules! impl_max_size {
+    ($($ty:ty => ($borsh:expr, $json:expr)),* $(,)?) => {
+        $(impl MaxSerializedSize for $ty {
+            const MAX_BORSH_SIZE: Option<usize> = $borsh;
+            const MAX_JSON_SIZE: Option<usize> = $json;
+        })*
+    };
+}
+
+impl_max_size!(
+    () => (Some(0), Some(4)),
+    bool => (Some(1), Some(5)),
+    u8 => (Some(1), Some(3)),
+    u16 => (Some(2), Some(5)),
+    u32 => (Some(4), Some(10)),
+    u64 => (Some(8), Some(20)),
+    u128 => (Some(16), Some(39)),
+    i8 => (Some(1), Some(4)),
+    i16 => (Some(2), Some(6)),
+    i32 => (Some(4), Some(11)),
+    i64 => (Some(8), Some(20)),
+    i128 => (Some(16), Some(40)),
+    // Integer JSON types and gas are serialized as strings.
+    U64 => (Some(8), Some(22)),
+    U128 => (Some(16), Some(41)),
+    I64 => (Some(8), Some(22)),
+    I128 => (Some(16), Some(42)),
+    Gas => (Some(8), Some(22)),
+    // Account IDs are at most 64 characters, and do not need to be escaped in JSON.
+    AccountId => (Some(4 + 64), Some(2 + 64)),
+    String => (None, None),
+    // Promises are not serialized, the result of the promise is returned instead.
+    Promise => (Some(0), Some(4)),
+);
+
+impl<T: MaxSerializedSize> MaxSerializedSize for Option<T> {
+    const MAX_BORSH_SIZE: Option<usize> = max_size_sum(&[Some(1), T::MAX_BORSH_SIZE]);
+    const MAX_JSON_SIZE: Option<usize> = max_size_of(&[Some(4), T::MAX_JSON_SIZE]);
+}
+
+impl<T: MaxSerializedSize> MaxSerializedSize for PromiseOrValue<T> {
+    const MAX_BORSH_SIZE: Option<usize> = T::MAX_BORSH_SIZE;
+    const MAX_JSON_SIZE: Option<usize> = max_size_of(&[Some(4), T::MAX_JSON_SIZE]);
+}
+
+impl<T: MaxSerializedSize> MaxSerializedSize for Vec<T> {
+    const MAX_BORSH_SIZE: Option<usize> = None;
+    const MAX_JSON_SIZE: Option<usize> = None;
+}
+
+impl<T: MaxSerializedSize + ?Sized> MaxSerializedSize for Box<T> {
+    const MAX_BORSH_SIZE: Option<usize> = T::MAX_BORSH_SIZE;
+    const MAX_JSON_SIZE: Option<usize> = T::MAX_JSON_SIZE;
+}
+
+impl<T: MaxSerializedSize + ?Sized> MaxSerializedSize for &T {
+    const MAX_BORSH_SIZE: Option<usize> = T::MAX_BORSH_SIZE;
+    const MAX_JSON_SIZE: Option<usize> = T::MAX_JSON_SIZE;
+}
+
+impl<T: MaxSerializedSize, const N: usize> MaxSerializedSize for [T; N] {
+    const MAX_BORSH_SIZE: Option<usize> = match T::MAX_BORSH_SIZE {
+        Some(size) => size.checked_mul(N),
+        None => None,
+    };
+    const MAX_JSON_SIZE: Option<usize> = match T::MAX_JSON_SIZE {
+        // Brackets around the array and commas between elements.
+        Some(size) => match size.checked_add(1) {
+            Some(element) => match element.checked_mul(N) {
+                Some(total) => Some(total + 1 + (N == 0) as usize),
+                None => None,
+            },
+            None => None,
+        },
+        None => None,
+    };
+}
+
+macro_rules! impl_max_size_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: MaxSerializedSize),+> MaxSerializedSize for ($($name,)+) {
+            const MAX_BORSH_SIZE: Option<usize> = max_size_sum(&[$($name::MAX_BORSH_SIZE),+]);
+            const MAX_JSON_SIZE: Option<usize> = max_json_array_size(&[$($name::MAX_JSON_SIZE),+]);
+        }
+    };
+}
+
+impl_max_size_tuple!(T0);
+impl_max_size_tuple!(T0, T1);
+impl_max_size_tuple!(T0, T1, T2);
+impl_max_size_tuple!(T0, T1, T2, T3);
+impl_max_size_tuple!(T0, T1, T2, T3, T4);
+impl_max_size_tuple!(T0, T1, T2, T3, T4, T5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    fn assert_max_size<T: MaxSerializedSize + BorshSerialize + serde::Serialize>(max: T) {
+        assert_eq!(T::MAX_BORSH_SIZE, Some(max.try_to_vec().unwrap().len()));
+        assert_eq!(T::MAX_JSON_SIZE, Some(serde_json::to_vec(&max).unwrap().len()));
+    }
+
+    #[test]
+    fn max_sizes() {
+        assert_max_size(false);
+        assert_max_size(u8::MAX);
+        assert_max_size(u16::MAX);
+        assert_max_size(u32::MAX);
+        assert_max_size(u64::MAX);
+        assert_max_size(u128::MAX);
+        assert_max_size(i8::MIN);
+        assert_max_size(i16::MIN);
+        assert_max_size(i32::MIN);
+        assert_max_size(i64::MIN);
+        assert_max_size(i128::MIN);
+        assert_max_size(U64(u64::MAX));
+        assert_max_size(U128(u128::MAX));
+        assert_max_size(I64(i64::MIN));
+        assert_max_size(I128(i128::MIN));
+        assert_max_size(Gas(u64::MAX));
+        assert_max_size(AccountId::new_unchecked("a".repeat(64)));
+        assert_max_size(Some(u128::MAX));
+        assert_max_size([u32::MAX; 3]);
+        assert_max_size((u8::MAX, Some(i16::MIN), [false; 2]));
+        assert_eq!(<[u8; 0]>::MAX_JSON_SIZE, Some(2));
+        assert_eq!(<Option<[u8; 0]>>::MAX_JSON_SIZE, Some(4));
+
+        assert_eq!(String::MAX_BORSH_SIZE, None);
+        assert_eq!(<(u8, Vec<u8>)>::MAX_JSON_SIZE, None);
+    }
+
+    #[test]
+    fn json_object_size() {
+        #[derive(serde::Serialize)]
+        struct Args {
+            amount: U128,
+            receiver_id: AccountId,
+        }
+        let max =
+            Args { amount: U128(u128::MAX), receiver_id: AccountId::new_unchecked("a".repeat(64)) };
+        assert_eq!(
+            max_json_object_size(&[
+                ("amount", U128::MAX_JSON_SIZE),
+                ("receiver_id", AccountId::MAX_JSON_SIZE)
+            ]),
+            Some(serde_json::to_vec(&max).unwrap().len())
+        );
+        assert_eq!(max_json_object_size(&[]), Some(2));
+    }
+}
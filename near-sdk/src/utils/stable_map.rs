@@ -41,6 +41,14 @@ impl<K, V> StableMap<K, V> {
     pub(crate) fn inner(&mut self) -> &mut BTreeMap<K, Box<V>> {
         self.map.get_mut()
     }
+    /// Returns the keys of the entries whose values match the predicate.
+    pub(crate) fn keys_where<F>(&self, mut f: F) -> Vec<K>
+    where
+        K: Clone,
+        F: FnMut(&V) -> bool,
+    {
+        self.map.borrow().iter().filter(|(_, v)| f(v)).map(|(k, _)| k.clone()).collect()
+    }
     pub(crate) fn map_value_ref<Q: ?Sized, F, T>(&self, k: &Q, f: F) -> Option<T>
     where
         K: Borrow<Q> + Ord,